@@ -0,0 +1,18 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.1.0
+-- Description: Version history columns for compositions (export.include_versions)
+
+-- ============================================================================
+-- Composition Version Columns
+-- ============================================================================
+-- Populated when the full version history of each composition is exported.
+-- Each version is stored as its own row (the id already includes the version),
+-- so these columns are NULL for rows exported without version history.
+
+ALTER TABLE compositions ADD COLUMN IF NOT EXISTS version_number INTEGER;
+ALTER TABLE compositions ADD COLUMN IF NOT EXISTS lifecycle_state TEXT;
+ALTER TABLE compositions ADD COLUMN IF NOT EXISTS change_type TEXT;
+
+-- Index for listing the versions of a composition
+CREATE INDEX IF NOT EXISTS idx_compositions_version
+    ON compositions(ehr_id, template_id, version_number);
@@ -0,0 +1,16 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.2.0
+-- Description: Soft-delete marker for compositions deleted in openEHR (export.deletion_policy)
+
+-- ============================================================================
+-- Composition Deletion Marker
+-- ============================================================================
+-- Set when export.deletion_policy = "soft_delete" and the composition no longer
+-- exists in openEHR. NULL for compositions that are still live.
+
+ALTER TABLE compositions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- Partial index for listing the live compositions of an EHR and template
+CREATE INDEX IF NOT EXISTS idx_compositions_live
+    ON compositions(ehr_id, template_id)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,12 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.3.0
+-- Description: Composition content format column (openehr.composition_format)
+
+-- ============================================================================
+-- Composition Format Column
+-- ============================================================================
+-- Records the format the content was retrieved in: flat, structured,
+-- canonical_json or canonical_xml. Canonical XML is stored as a JSON string.
+-- Rows exported before this migration were always retrieved as FLAT.
+
+ALTER TABLE compositions ADD COLUMN IF NOT EXISTS composition_format VARCHAR(20) NOT NULL DEFAULT 'flat';
@@ -0,0 +1,43 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.4.0
+-- Description: EHR metadata and EHR_STATUS table (export.include_ehr_status)
+
+-- ============================================================================
+-- EHRs Table
+-- ============================================================================
+-- One row per exported EHR with its metadata and current EHR_STATUS. The
+-- subject columns hold the external reference of the EHR_STATUS subject and
+-- link exported compositions to a master patient index.
+
+CREATE TABLE IF NOT EXISTS ehrs (
+    -- Primary key: EHR identifier (joins with compositions.ehr_id)
+    ehr_id TEXT PRIMARY KEY,
+
+    -- EHR metadata
+    system_id TEXT,
+    time_created TIMESTAMPTZ NOT NULL,
+
+    -- EHR_STATUS version UID
+    ehr_status_uid TEXT,
+
+    -- EHR_STATUS subject external reference (PARTY_REF)
+    subject_id TEXT,
+    subject_id_scheme TEXT,
+    subject_namespace TEXT,
+    subject_type TEXT,
+
+    -- EHR_STATUS flags
+    is_queryable BOOLEAN,
+    is_modifiable BOOLEAN,
+
+    -- EHR_STATUS other_details in canonical JSON
+    other_details JSONB,
+
+    -- Atlas metadata
+    exported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atlas_version TEXT NOT NULL
+);
+
+-- Index for looking up EHRs by subject
+CREATE INDEX IF NOT EXISTS idx_ehrs_subject
+    ON ehrs(subject_namespace, subject_id);
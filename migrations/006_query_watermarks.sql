@@ -0,0 +1,28 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.5.0
+-- Description: Watermarks of named AQL query exports (export.aql_queries)
+
+-- ============================================================================
+-- Query Watermarks Table
+-- ============================================================================
+-- One row per named AQL query. The watermark value is the highest value of
+-- the query's watermark column exported so far and is bound to the
+-- $watermark parameter of the next incremental run. The result sets
+-- themselves are stored in one table per query, created on first export.
+
+CREATE TABLE IF NOT EXISTS query_watermarks (
+    -- Primary key: query_{query_name}
+    id TEXT PRIMARY KEY,
+
+    -- Name of the AQL query (export.aql_queries.name)
+    query_name TEXT NOT NULL UNIQUE,
+
+    -- Highest watermark column value exported (JSON number or string)
+    watermark_value JSONB,
+
+    -- Export progress
+    rows_exported_count BIGINT NOT NULL DEFAULT 0,
+    last_export_started_at TIMESTAMPTZ NOT NULL,
+    last_export_completed_at TIMESTAMPTZ,
+    last_export_status TEXT NOT NULL
+);
@@ -0,0 +1,35 @@
+-- Atlas PostgreSQL Schema
+-- Version: 1.6.0
+-- Description: Dead letters of compositions that failed to export
+
+-- ============================================================================
+-- Dead Letters Table
+-- ============================================================================
+-- One row per composition that could not be fetched from openEHR or stored
+-- in the compositions table. The watermark advances past these compositions,
+-- so `atlas retry-failed` uses this table to reprocess them. Rows are deleted
+-- once the composition has been exported.
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+    -- Primary key: dead_letter_{composition_uid}
+    id TEXT PRIMARY KEY,
+
+    -- Composition identity
+    composition_uid TEXT NOT NULL,
+    template_id TEXT NOT NULL,
+    ehr_id TEXT NOT NULL,
+    time_committed TIMESTAMPTZ NOT NULL,
+
+    -- Version information (only set for entries of a version history)
+    version JSONB,
+
+    -- Last failure: stage is version_history, fetch or insert
+    stage TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 1,
+    first_failed_at TIMESTAMPTZ NOT NULL,
+    last_failed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letters_template_ehr
+    ON dead_letters (template_id, ehr_id);
@@ -0,0 +1,147 @@
+-- Atlas SQLite Schema
+-- Version: 1.0.0
+-- Description: SQLite equivalent of the PostgreSQL schema (migrations 001-007)
+--
+-- Timestamps are stored as text ("YYYY-MM-DD HH:MM:SS.ffffff+00:00"), which
+-- sorts chronologically and is understood by SQLite's date and time functions.
+-- JSON columns hold text checked with json_valid() and are queried with the
+-- JSON1 functions (json_extract, ->, ->>).
+
+-- ============================================================================
+-- Compositions Table
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS compositions (
+    id TEXT PRIMARY KEY,
+    ehr_id TEXT NOT NULL,
+    composition_uid TEXT NOT NULL,
+    template_id TEXT NOT NULL,
+    time_committed TEXT NOT NULL,
+    content TEXT NOT NULL CHECK (json_valid(content)),
+    export_mode TEXT NOT NULL,
+    exported_at TEXT NOT NULL,
+    atlas_version TEXT NOT NULL,
+    checksum TEXT,
+
+    -- Version history (export.include_versions)
+    version_number INTEGER,
+    lifecycle_state TEXT,
+    change_type TEXT,
+
+    -- Soft-delete marker (export.deletion_policy = "soft_delete")
+    deleted_at TEXT,
+
+    -- Format the content was retrieved in (openehr.composition_format)
+    composition_format TEXT NOT NULL DEFAULT 'flat'
+);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_ehr_id
+    ON compositions(ehr_id);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_template_id
+    ON compositions(template_id);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_time_committed
+    ON compositions(time_committed);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_ehr_template
+    ON compositions(ehr_id, template_id, time_committed);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_version
+    ON compositions(ehr_id, template_id, version_number);
+
+CREATE INDEX IF NOT EXISTS idx_compositions_live
+    ON compositions(ehr_id, template_id)
+    WHERE deleted_at IS NULL;
+
+-- ============================================================================
+-- Watermarks Table
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS watermarks (
+    id TEXT PRIMARY KEY,
+    template_id TEXT NOT NULL,
+    ehr_id TEXT NOT NULL,
+    last_exported_timestamp TEXT NOT NULL,
+    last_exported_composition_uid TEXT,
+    compositions_exported_count INTEGER NOT NULL DEFAULT 0,
+    last_export_started_at TEXT NOT NULL,
+    last_export_completed_at TEXT,
+    last_export_status TEXT NOT NULL CHECK (
+        last_export_status IN ('in_progress', 'completed', 'failed', 'not_started')
+    ),
+    UNIQUE (template_id, ehr_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_watermarks_ehr_id
+    ON watermarks(ehr_id);
+
+CREATE INDEX IF NOT EXISTS idx_watermarks_status
+    ON watermarks(last_export_status);
+
+-- ============================================================================
+-- EHRs Table
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS ehrs (
+    ehr_id TEXT PRIMARY KEY,
+    system_id TEXT,
+    time_created TEXT NOT NULL,
+    ehr_status_uid TEXT,
+    subject_id TEXT,
+    subject_id_scheme TEXT,
+    subject_namespace TEXT,
+    subject_type TEXT,
+    is_queryable INTEGER,
+    is_modifiable INTEGER,
+    other_details TEXT CHECK (other_details IS NULL OR json_valid(other_details)),
+    exported_at TEXT NOT NULL,
+    atlas_version TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ehrs_subject
+    ON ehrs(subject_namespace, subject_id);
+
+-- ============================================================================
+-- Query Watermarks Table
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS query_watermarks (
+    id TEXT PRIMARY KEY,
+    query_name TEXT NOT NULL UNIQUE,
+    watermark_value TEXT CHECK (watermark_value IS NULL OR json_valid(watermark_value)),
+    rows_exported_count INTEGER NOT NULL DEFAULT 0,
+    last_export_started_at TEXT NOT NULL,
+    last_export_completed_at TEXT,
+    last_export_status TEXT NOT NULL
+);
+
+-- ============================================================================
+-- Dead Letters Table
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id TEXT PRIMARY KEY,
+    composition_uid TEXT NOT NULL,
+    template_id TEXT NOT NULL,
+    ehr_id TEXT NOT NULL,
+    time_committed TEXT NOT NULL,
+    version TEXT CHECK (version IS NULL OR json_valid(version)),
+    stage TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 1,
+    first_failed_at TEXT NOT NULL,
+    last_failed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letters_template_ehr
+    ON dead_letters (template_id, ehr_id);
+
+-- ============================================================================
+-- Sample Queries (for reference)
+-- ============================================================================
+
+-- Query compositions with JSON field filtering (preserved FLAT mode)
+-- SELECT id, ehr_id, content ->> '$."ctx/language"' AS language
+-- FROM compositions
+-- WHERE template_id = 'IDCR - Vital Signs.v1';
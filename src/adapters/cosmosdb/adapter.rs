@@ -5,20 +5,27 @@
 
 use crate::adapters::cosmosdb::bulk::{
     bulk_insert_compositions as cosmos_bulk_insert,
-    bulk_insert_compositions_flattened as cosmos_bulk_insert_flattened,
+    bulk_insert_compositions_flattened as cosmos_bulk_insert_flattened, write_error,
 };
 use crate::adapters::cosmosdb::client::CosmosDbClient;
-use crate::adapters::cosmosdb::models::{CosmosComposition, CosmosCompositionFlattened};
+use crate::adapters::cosmosdb::models::{
+    CosmosComposition, CosmosCompositionFlattened, CosmosEhr, CosmosQueryRow,
+};
 use crate::adapters::database::traits::{
-    BulkInsertFailure, BulkInsertResult, DatabaseClient, StateStorage,
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
 };
-use crate::core::state::watermark::Watermark;
+use crate::adapters::retry::RetryPolicy;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
 use crate::core::transform::{flatten::flatten_composition, preserve::preserve_composition};
 use crate::domain::composition::Composition;
-use crate::domain::ids::{EhrId, TemplateId};
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use crate::domain::{AtlasError, CosmosDbError, Result};
 use async_trait::async_trait;
 use azure_data_cosmos::PartitionKey;
+use futures::stream::StreamExt;
 use std::any::Any;
 use std::sync::Arc;
 
@@ -27,19 +34,32 @@ use std::sync::Arc;
 /// This wraps the CosmosDbClient and implements the DatabaseClient and StateStorage traits.
 pub struct CosmosDbAdapter {
     client: Arc<CosmosDbClient>,
+    retry: RetryPolicy,
 }
 
 impl CosmosDbAdapter {
     /// Create a new CosmosDB adapter
     pub fn new(client: CosmosDbClient) -> Self {
-        Self {
-            client: Arc::new(client),
-        }
+        Self::new_with_arc(Arc::new(client))
     }
 
     /// Create a new CosmosDB adapter with an Arc-wrapped client
     pub fn new_with_arc(client: Arc<CosmosDbClient>) -> Self {
-        Self { client }
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the retry policy for throttled and otherwise transient writes
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Retry policy limited to the retries requested by the caller
+    fn retry_policy(&self, max_retries: usize) -> RetryPolicy {
+        self.retry.clone().with_max_retries(max_retries)
     }
 
     /// Get a reference to the underlying client
@@ -70,6 +90,105 @@ impl DatabaseClient for CosmosDbAdapter {
         self.client.ensure_control_container_exists().await
     }
 
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        self.client.ensure_ehr_container_exists().await
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(ehr_id = %ehr.id.as_str(), "DRY RUN: Would save EHR status");
+            return Ok(());
+        }
+
+        let container = self.client.get_ehr_container_client();
+        let document = CosmosEhr::from_domain(ehr);
+        let partition_key = PartitionKey::from(document.ehr_id.clone());
+
+        self.retry
+            .retry(|| async {
+                container
+                    .upsert_item(partition_key.clone(), &document, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| {
+                        write_error(&e, |message| {
+                            CosmosDbError::WriteFailed(format!(
+                                "Failed to save EHR {}: {message}",
+                                ehr.id
+                            ))
+                        })
+                    })
+            })
+            .await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status saved");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        self.client.ensure_query_container_exists(query_name).await
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would save {} query rows",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let container = self.client.get_query_container_client(query_name);
+        let mut success_count = 0;
+        let mut failures = Vec::new();
+
+        for row in rows {
+            let document = CosmosQueryRow::new(query_name, row);
+            let partition_key = PartitionKey::from(document.id.clone());
+
+            let result = self
+                .retry
+                .retry(|| async {
+                    container
+                        .upsert_item(partition_key.clone(), &document, None)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| write_error(&e, CosmosDbError::WriteFailed))
+                })
+                .await;
+
+            match result {
+                Ok(()) => success_count += 1,
+                Err(e) => {
+                    let error = e.to_string();
+                    failures.push(BulkInsertFailure {
+                        document_id: document.id,
+                        is_throttled: error.contains("429"),
+                        error,
+                    });
+                }
+            }
+        }
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
     async fn bulk_insert_json(
         &self,
         template_id: &TemplateId,
@@ -111,8 +230,12 @@ impl DatabaseClient for CosmosDbAdapter {
             let container = self.client.get_container_client(template_id);
 
             // Perform bulk insert
-            let result =
-                cosmos_bulk_insert_flattened(&container, cosmos_compositions, max_retries).await?;
+            let result = cosmos_bulk_insert_flattened(
+                &container,
+                cosmos_compositions,
+                &self.retry_policy(max_retries),
+            )
+            .await?;
 
             // Convert CosmosDB BulkInsertResult to trait BulkInsertResult
             Ok(BulkInsertResult {
@@ -156,7 +279,12 @@ impl DatabaseClient for CosmosDbAdapter {
             let container = self.client.get_container_client(template_id);
 
             // Perform bulk insert
-            let result = cosmos_bulk_insert(&container, cosmos_compositions, max_retries).await?;
+            let result = cosmos_bulk_insert(
+                &container,
+                cosmos_compositions,
+                &self.retry_policy(max_retries),
+            )
+            .await?;
 
             // Convert CosmosDB BulkInsertResult to trait BulkInsertResult
             Ok(BulkInsertResult {
@@ -211,7 +339,12 @@ impl DatabaseClient for CosmosDbAdapter {
         let container = self.client.get_container_client(template_id);
 
         // Perform bulk insert
-        let result = cosmos_bulk_insert(&container, cosmos_compositions, max_retries).await?;
+        let result = cosmos_bulk_insert(
+            &container,
+            cosmos_compositions,
+            &self.retry_policy(max_retries),
+        )
+        .await?;
 
         // Convert CosmosDB BulkInsertResult to trait BulkInsertResult
         Ok(BulkInsertResult {
@@ -265,8 +398,12 @@ impl DatabaseClient for CosmosDbAdapter {
         let container = self.client.get_container_client(template_id);
 
         // Perform bulk insert
-        let result =
-            cosmos_bulk_insert_flattened(&container, cosmos_compositions, max_retries).await?;
+        let result = cosmos_bulk_insert_flattened(
+            &container,
+            cosmos_compositions,
+            &self.retry_policy(max_retries),
+        )
+        .await?;
 
         // Convert CosmosDB BulkInsertResult to trait BulkInsertResult
         Ok(BulkInsertResult {
@@ -295,6 +432,64 @@ impl DatabaseClient for CosmosDbAdapter {
             .await
     }
 
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        self.client.list_composition_ids(template_id, ehr_id).await
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        for composition_id in composition_ids {
+            match policy {
+                DeletionPolicy::HardDelete => {
+                    self.client
+                        .delete_composition(template_id, ehr_id, composition_id)
+                        .await?
+                }
+                _ => {
+                    self.client
+                        .soft_delete_composition(template_id, ehr_id, composition_id)
+                        .await?
+                }
+            }
+        }
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = composition_ids.len(),
+            policy = %policy,
+            "Applied deletion policy in Cosmos DB"
+        );
+
+        Ok(composition_ids.len())
+    }
+
     fn database_name(&self) -> &str {
         self.client.database_name()
     }
@@ -405,4 +600,171 @@ impl StateStorage for CosmosDbAdapter {
         );
         Ok(Vec::new())
     }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        let container = self.client.get_control_container_client();
+        let watermark_id = QueryWatermark::generate_id(query_name);
+        let partition_key = PartitionKey::from(watermark_id.clone());
+
+        match container
+            .read_item::<QueryWatermark>(partition_key, &watermark_id, None)
+            .await
+        {
+            Ok(response) => {
+                let watermark = response.into_body().map_err(|e| {
+                    AtlasError::CosmosDb(CosmosDbError::DeserializationFailed(format!(
+                        "Failed to deserialize query watermark: {e}"
+                    )))
+                })?;
+                Ok(Some(watermark))
+            }
+            Err(e) => {
+                if e.to_string().contains("404") || e.to_string().contains("NotFound") {
+                    tracing::debug!(query = %query_name, "No query watermark found (first export)");
+                    Ok(None)
+                } else {
+                    Err(AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                        "Failed to load query watermark: {e}"
+                    ))))
+                }
+            }
+        }
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark"
+            );
+            return Ok(());
+        }
+
+        let container = self.client.get_control_container_client();
+        let partition_key = PartitionKey::from(watermark.id.clone());
+
+        container
+            .upsert_item(partition_key, watermark, None)
+            .await
+            .map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::WriteFailed(format!(
+                    "Failed to save query watermark: {e}"
+                )))
+            })?;
+
+        tracing::debug!(query = %watermark.query_name, "Query watermark saved");
+
+        Ok(())
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        let container = self.client.get_control_container_client();
+        let dead_letter_id = DeadLetter::generate_id(composition_uid);
+        let partition_key = PartitionKey::from(dead_letter_id.clone());
+
+        match container
+            .read_item::<DeadLetter>(partition_key, &dead_letter_id, None)
+            .await
+        {
+            Ok(response) => {
+                let dead_letter = response.into_body().map_err(|e| {
+                    AtlasError::CosmosDb(CosmosDbError::DeserializationFailed(format!(
+                        "Failed to deserialize dead letter: {e}"
+                    )))
+                })?;
+                Ok(Some(dead_letter))
+            }
+            Err(e) => {
+                if e.to_string().contains("404") || e.to_string().contains("NotFound") {
+                    Ok(None)
+                } else {
+                    Err(AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                        "Failed to load dead letter: {e}"
+                    ))))
+                }
+            }
+        }
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter"
+            );
+            return Ok(());
+        }
+
+        let container = self.client.get_control_container_client();
+        let partition_key = PartitionKey::from(dead_letter.id.clone());
+
+        container
+            .upsert_item(partition_key, dead_letter, None)
+            .await
+            .map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::WriteFailed(format!(
+                    "Failed to save dead letter: {e}"
+                )))
+            })?;
+
+        Ok(())
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter"
+            );
+            return Ok(());
+        }
+
+        let container = self.client.get_control_container_client();
+        let partition_key = PartitionKey::from(dead_letter.id.clone());
+
+        match container
+            .delete_item(partition_key, &dead_letter.id, None)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("404") || e.to_string().contains("NotFound") => Ok(()),
+            Err(e) => Err(AtlasError::CosmosDb(CosmosDbError::DeleteFailed(format!(
+                "Failed to delete dead letter: {e}"
+            )))),
+        }
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let container = self.client.get_control_container_client();
+
+        // The control container is partitioned by document ID, so this is a
+        // cross-partition query; the gateway supports it for plain filters
+        let mut query_response = container
+            .query_items::<DeadLetter>(
+                "SELECT * FROM c WHERE STARTSWITH(c.id, 'dead_letter_')",
+                PartitionKey::EMPTY,
+                None,
+            )
+            .map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                    "Failed to create query: {e}"
+                )))
+            })?;
+
+        let mut dead_letters = Vec::new();
+        while let Some(item) = query_response.next().await {
+            let dead_letter = item.map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                    "Failed to list dead letters: {e}"
+                )))
+            })?;
+            dead_letters.push(dead_letter);
+        }
+
+        dead_letters.sort_by_key(|dead_letter| dead_letter.first_failed_at);
+        Ok(dead_letters)
+    }
 }
@@ -1,15 +1,21 @@
 //! Bulk operations for Cosmos DB
 //!
-//! This module provides batch insert functionality with retry logic
-//! for handling throttling errors.
+//! This module provides batch insert functionality. Throttled (429) and
+//! otherwise transient writes are retried with the shared [`RetryPolicy`],
+//! honouring the delay Cosmos DB asks for in `x-ms-retry-after-ms`.
 
 use crate::adapters::cosmosdb::models::{CosmosComposition, CosmosCompositionFlattened};
+use crate::adapters::retry::RetryPolicy;
 use crate::domain::{AtlasError, CosmosDbError, Result};
+use azure_core::error::ErrorKind;
+use azure_core::http::headers::HeaderName;
+use azure_core::http::StatusCode;
 use azure_data_cosmos::clients::ContainerClient;
 use azure_data_cosmos::PartitionKey;
 use serde::Serialize;
-use std::time::Duration;
-use tokio::time::sleep;
+
+/// Header with the delay Cosmos DB asks for after throttling a request
+const RETRY_AFTER_MS: HeaderName = HeaderName::from_static("x-ms-retry-after-ms");
 
 /// Result of a bulk insert operation
 #[derive(Debug, Clone)]
@@ -39,14 +45,14 @@ pub struct BulkInsertFailure {
 
 /// Bulk insert compositions into Cosmos DB
 ///
-/// This function inserts multiple compositions in batch, with retry logic
-/// for handling throttling errors (429).
+/// This function inserts multiple compositions in batch, retrying throttled
+/// (429) and other transient failures.
 ///
 /// # Arguments
 ///
 /// * `container` - Container client to insert into
 /// * `compositions` - Compositions to insert
-/// * `max_retries` - Maximum number of retries for throttled requests
+/// * `retry` - Retry policy for transient failures
 ///
 /// # Returns
 ///
@@ -54,7 +60,7 @@ pub struct BulkInsertFailure {
 pub async fn bulk_insert_compositions(
     container: &ContainerClient,
     compositions: Vec<CosmosComposition>,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<BulkInsertResult> {
     let mut success_count = 0;
     let mut failures = Vec::new();
@@ -64,7 +70,7 @@ pub async fn bulk_insert_compositions(
         let document_id = composition.id.clone();
         let partition_key = PartitionKey::from(ehr_id);
 
-        match insert_with_retry(container, partition_key, composition, max_retries).await {
+        match insert_with_retry(container, partition_key, composition, retry).await {
             Ok(_) => {
                 success_count += 1;
             }
@@ -91,14 +97,14 @@ pub async fn bulk_insert_compositions(
 
 /// Bulk insert flattened compositions into Cosmos DB
 ///
-/// This function inserts multiple flattened compositions in batch, with retry logic
-/// for handling throttling errors (429).
+/// This function inserts multiple flattened compositions in batch, retrying
+/// throttled (429) and other transient failures.
 ///
 /// # Arguments
 ///
 /// * `container` - Container client to insert into
 /// * `compositions` - Flattened compositions to insert
-/// * `max_retries` - Maximum number of retries for throttled requests
+/// * `retry` - Retry policy for transient failures
 ///
 /// # Returns
 ///
@@ -106,7 +112,7 @@ pub async fn bulk_insert_compositions(
 pub async fn bulk_insert_compositions_flattened(
     container: &ContainerClient,
     compositions: Vec<CosmosCompositionFlattened>,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<BulkInsertResult> {
     let mut success_count = 0;
     let mut failures = Vec::new();
@@ -116,7 +122,7 @@ pub async fn bulk_insert_compositions_flattened(
         let document_id = composition.id.clone();
         let partition_key = PartitionKey::from(ehr_id);
 
-        match insert_with_retry(container, partition_key, composition, max_retries).await {
+        match insert_with_retry(container, partition_key, composition, retry).await {
             Ok(_) => {
                 success_count += 1;
             }
@@ -141,47 +147,33 @@ pub async fn bulk_insert_compositions_flattened(
     })
 }
 
-/// Insert a document with exponential backoff retry for throttling errors
+/// Insert a document, retrying transient failures
+///
+/// A conflict (409) means a document with the same ID was already exported.
+/// Document IDs are versioned composition UIDs, whose content never changes,
+/// so this is treated as success. This keeps re-exports of overlapping
+/// incremental windows idempotent.
 async fn insert_with_retry<T: Serialize + Clone>(
     container: &ContainerClient,
     partition_key: PartitionKey,
     document: T,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<()> {
-    let mut retry_count = 0;
-    let mut delay_ms = 1000; // Start with 1 second
-
-    loop {
-        match container
-            .create_item(partition_key.clone(), document.clone(), None)
-            .await
-        {
-            Ok(_) => return Ok(()),
-            Err(e) => {
-                let is_throttled = e.to_string().contains("429")
-                    || e.to_string().contains("TooManyRequests")
-                    || e.to_string().contains("Request rate is large");
-
-                if is_throttled && retry_count < max_retries {
-                    tracing::warn!(
-                        retry_count = retry_count,
-                        delay_ms = delay_ms,
-                        "Throttled by Cosmos DB, retrying after delay"
-                    );
-
-                    sleep(Duration::from_millis(delay_ms)).await;
-
-                    retry_count += 1;
-                    delay_ms *= 2; // Exponential backoff
-                    delay_ms = delay_ms.min(30000); // Cap at 30 seconds
-                } else {
-                    return Err(AtlasError::CosmosDb(CosmosDbError::InsertFailed(format!(
-                        "Failed to insert document after {retry_count} retries: {e}"
-                    ))));
+    retry
+        .retry(|| async {
+            match container
+                .create_item(partition_key.clone(), document.clone(), None)
+                .await
+            {
+                Ok(_) => Ok(()),
+                Err(e) if is_conflict(&e) => {
+                    tracing::debug!("Document already exists in Cosmos DB, skipping");
+                    Ok(())
                 }
+                Err(e) => Err(write_error(&e, CosmosDbError::InsertFailed)),
             }
-        }
-    }
+        })
+        .await
 }
 
 /// Upsert a composition into Cosmos DB
@@ -193,16 +185,16 @@ async fn insert_with_retry<T: Serialize + Clone>(
 ///
 /// * `container` - Container client to upsert into
 /// * `composition` - Composition to upsert
-/// * `max_retries` - Maximum number of retries for throttled requests
+/// * `retry` - Retry policy for transient failures
 pub async fn upsert_composition(
     container: &ContainerClient,
     composition: CosmosComposition,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<()> {
     let ehr_id = composition.ehr_id.clone();
     let partition_key = PartitionKey::from(ehr_id);
 
-    upsert_with_retry(container, partition_key, composition, max_retries).await
+    upsert_with_retry(container, partition_key, composition, retry).await
 }
 
 /// Upsert a flattened composition into Cosmos DB
@@ -214,64 +206,151 @@ pub async fn upsert_composition(
 ///
 /// * `container` - Container client to upsert into
 /// * `composition` - Flattened composition to upsert
-/// * `max_retries` - Maximum number of retries for throttled requests
+/// * `retry` - Retry policy for transient failures
 pub async fn upsert_composition_flattened(
     container: &ContainerClient,
     composition: CosmosCompositionFlattened,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<()> {
     let ehr_id = composition.ehr_id.clone();
     let partition_key = PartitionKey::from(ehr_id);
 
-    upsert_with_retry(container, partition_key, composition, max_retries).await
+    upsert_with_retry(container, partition_key, composition, retry).await
 }
 
-/// Upsert a document with exponential backoff retry for throttling errors
+/// Upsert a document, retrying transient failures
 async fn upsert_with_retry<T: Serialize + Clone>(
     container: &ContainerClient,
     partition_key: PartitionKey,
     document: T,
-    max_retries: usize,
+    retry: &RetryPolicy,
 ) -> Result<()> {
-    let mut retry_count = 0;
-    let mut delay_ms = 1000; // Start with 1 second
-
-    loop {
-        match container
-            .upsert_item(partition_key.clone(), document.clone(), None)
-            .await
-        {
-            Ok(_) => return Ok(()),
-            Err(e) => {
-                let is_throttled = e.to_string().contains("429")
-                    || e.to_string().contains("TooManyRequests")
-                    || e.to_string().contains("Request rate is large");
+    retry
+        .retry(|| async {
+            container
+                .upsert_item(partition_key.clone(), document.clone(), None)
+                .await
+                .map(|_| ())
+                .map_err(|e| write_error(&e, CosmosDbError::UpdateFailed))
+        })
+        .await
+}
 
-                if is_throttled && retry_count < max_retries {
-                    tracing::warn!(
-                        retry_count = retry_count,
-                        delay_ms = delay_ms,
-                        "Throttled by Cosmos DB, retrying after delay"
-                    );
-
-                    sleep(Duration::from_millis(delay_ms)).await;
-
-                    retry_count += 1;
-                    delay_ms *= 2; // Exponential backoff
-                    delay_ms = delay_ms.min(30000); // Cap at 30 seconds
-                } else {
-                    return Err(AtlasError::CosmosDb(CosmosDbError::UpdateFailed(format!(
-                        "Failed to upsert document after {retry_count} retries: {e}"
-                    ))));
-                }
-            }
+/// Classify a failed Cosmos DB write
+///
+/// Throttling (429) keeps the delay from `x-ms-retry-after-ms` (in seconds),
+/// request timeouts (408), "retry with" (449), unavailability (503) and I/O
+/// errors are transient. Anything else is wrapped with `failed`.
+pub(crate) fn write_error(
+    error: &azure_core::Error,
+    failed: impl FnOnce(String) -> CosmosDbError,
+) -> AtlasError {
+    let message = error.to_string();
+
+    let cosmos_error = match error.kind() {
+        ErrorKind::HttpResponse {
+            status,
+            raw_response,
+            ..
+        } => match u16::from(*status) {
+            429 => CosmosDbError::Throttled(
+                raw_response
+                    .as_ref()
+                    .and_then(|response| response.headers().get_optional_str(&RETRY_AFTER_MS))
+                    .and_then(|millis| millis.parse::<f64>().ok())
+                    .map(|millis| format!("{}", millis / 1000.0))
+                    .unwrap_or_else(|| "unspecified".to_string()),
+            ),
+            408 => CosmosDbError::Timeout(message),
+            449 | 503 => CosmosDbError::ConnectionFailed(message),
+            _ => failed(message),
+        },
+        ErrorKind::Io => CosmosDbError::ConnectionFailed(message),
+        _ => failed(message),
+    };
+
+    AtlasError::CosmosDb(cosmos_error)
+}
+
+/// Check whether a Cosmos DB error is a conflict (409)
+fn is_conflict(error: &azure_core::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::HttpResponse {
+            status: StatusCode::Conflict,
+            ..
         }
-    }
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_is_conflict() {
+        let http_error = |status| {
+            azure_core::Error::new(
+                ErrorKind::HttpResponse {
+                    status,
+                    error_code: None,
+                    raw_response: None,
+                },
+                "Request failed",
+            )
+        };
+
+        assert!(is_conflict(&http_error(StatusCode::Conflict)));
+        assert!(!is_conflict(&http_error(StatusCode::TooManyRequests)));
+
+        // A message mentioning 409 is not a conflict
+        let error = azure_core::Error::with_message(
+            ErrorKind::Io,
+            "Failed to insert c409::local::1 (Conflict resolution pending)",
+        );
+        assert!(!is_conflict(&error));
+    }
+
+    #[test]
+    fn test_write_error() {
+        use azure_core::http::headers::Headers;
+        use azure_core::http::RawResponse;
+
+        let mut headers = Headers::new();
+        headers.insert(RETRY_AFTER_MS, "1500");
+        let throttled = azure_core::Error::new(
+            ErrorKind::HttpResponse {
+                status: StatusCode::TooManyRequests,
+                error_code: None,
+                raw_response: Some(Box::new(RawResponse::from_bytes(
+                    StatusCode::TooManyRequests,
+                    headers,
+                    Vec::new(),
+                ))),
+            },
+            "Request rate is large",
+        );
+        let error = write_error(&throttled, CosmosDbError::InsertFailed);
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_after(), Some(Duration::from_millis(1500)));
+        assert!(error.to_string().contains("429"));
+
+        let bad_request = azure_core::Error::new(
+            ErrorKind::HttpResponse {
+                status: StatusCode::BadRequest,
+                error_code: None,
+                raw_response: None,
+            },
+            "Bad request",
+        );
+        let error = write_error(&bad_request, CosmosDbError::InsertFailed);
+        assert!(!error.is_retryable());
+        assert!(matches!(
+            error,
+            AtlasError::CosmosDb(CosmosDbError::InsertFailed(_))
+        ));
+    }
 
     #[test]
     fn test_bulk_insert_result_creation() {
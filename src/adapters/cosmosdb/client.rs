@@ -7,7 +7,9 @@ use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use crate::domain::{AtlasError, CosmosDbError, Result};
 use azure_core::credentials::Secret;
 use azure_data_cosmos::clients::{ContainerClient, DatabaseClient};
-use azure_data_cosmos::models::{ContainerProperties, IndexingPolicy, PartitionKeyDefinition};
+use azure_data_cosmos::models::{
+    ContainerProperties, IndexingPolicy, PartitionKeyDefinition, PatchDocument,
+};
 use azure_data_cosmos::{CosmosClient, CosmosClientOptions, PartitionKey};
 use futures::stream::StreamExt;
 use serde_json::Value;
@@ -205,6 +207,99 @@ impl CosmosDbClient {
         }
     }
 
+    /// Ensure the EHR container exists, creating it if necessary
+    ///
+    /// The EHR container stores one document per EHR with its metadata and
+    /// EHR_STATUS (`export.include_ehr_status`).
+    /// Partition key: `/ehr_id`
+    pub async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        let container_name = &self.config.ehr_container;
+        let container = self.database.container_client(container_name);
+
+        // Try to read the container first
+        match container.read(None).await {
+            Ok(_) => {
+                tracing::info!(container = %container_name, "EHR container already exists");
+                Ok(())
+            }
+            Err(_) => {
+                // Container doesn't exist, create it
+                tracing::info!(container = %container_name, "Creating EHR container");
+
+                let partition_key_def = PartitionKeyDefinition {
+                    paths: vec!["/ehr_id".to_string()],
+                    kind: azure_data_cosmos::models::PartitionKeyKind::Hash,
+                    version: None,
+                };
+
+                let properties = ContainerProperties {
+                    id: Cow::Owned(container_name.clone()),
+                    partition_key: partition_key_def,
+                    indexing_policy: Some(IndexingPolicy::default()),
+                    ..Default::default()
+                };
+
+                self.database
+                    .create_container(properties, None)
+                    .await
+                    .map_err(|e| {
+                        AtlasError::CosmosDb(CosmosDbError::ContainerCreationFailed(format!(
+                            "Failed to create EHR container {container_name}: {e}"
+                        )))
+                    })?;
+
+                tracing::info!(container = %container_name, "EHR container created successfully");
+                Ok(())
+            }
+        }
+    }
+
+    /// Ensure the container for a named AQL query exists, creating it if necessary
+    ///
+    /// The container is named after the query and stores one document per
+    /// result-set row (`export.aql_queries`).
+    /// Partition key: `/id`
+    pub async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        let container = self.database.container_client(query_name);
+
+        // Try to read the container first
+        match container.read(None).await {
+            Ok(_) => {
+                tracing::debug!(container = %query_name, "Query container already exists");
+                Ok(())
+            }
+            Err(_) => {
+                // Container doesn't exist, create it
+                tracing::info!(container = %query_name, "Creating query container");
+
+                let partition_key_def = PartitionKeyDefinition {
+                    paths: vec!["/id".to_string()],
+                    kind: azure_data_cosmos::models::PartitionKeyKind::Hash,
+                    version: None,
+                };
+
+                let properties = ContainerProperties {
+                    id: Cow::Owned(query_name.to_string()),
+                    partition_key: partition_key_def,
+                    indexing_policy: Some(IndexingPolicy::default()),
+                    ..Default::default()
+                };
+
+                self.database
+                    .create_container(properties, None)
+                    .await
+                    .map_err(|e| {
+                        AtlasError::CosmosDb(CosmosDbError::ContainerCreationFailed(format!(
+                            "Failed to create query container {query_name}: {e}"
+                        )))
+                    })?;
+
+                tracing::info!(container = %query_name, "Query container created successfully");
+                Ok(())
+            }
+        }
+    }
+
     /// Get the container name for a template
     ///
     /// Format: `{prefix}_{template_id}`
@@ -224,6 +319,16 @@ impl CosmosDbClient {
             .container_client(&self.config.control_container)
     }
 
+    /// Get the EHR container client
+    pub fn get_ehr_container_client(&self) -> ContainerClient {
+        self.database.container_client(&self.config.ehr_container)
+    }
+
+    /// Get the container client for a named AQL query
+    pub fn get_query_container_client(&self, query_name: &str) -> ContainerClient {
+        self.database.container_client(query_name)
+    }
+
     /// Check if a composition exists in the container
     ///
     /// # Arguments
@@ -329,6 +434,107 @@ impl CosmosDbClient {
         }
     }
 
+    /// List the IDs of the live (not soft-deleted) compositions of an EHR
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID (partition key)
+    pub async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let container = self.get_container_client(template_id);
+        let partition_key = PartitionKey::from(ehr_id.as_str().to_string());
+
+        let mut query_response = container
+            .query_items::<String>(
+                "SELECT VALUE c.id FROM c WHERE NOT IS_DEFINED(c.deleted_at)",
+                partition_key,
+                None,
+            )
+            .map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                    "Failed to create query: {e}"
+                )))
+            })?;
+
+        let mut ids = Vec::new();
+        while let Some(item) = query_response.next().await {
+            let id = item.map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::QueryFailed(format!(
+                    "Failed to list compositions: {e}"
+                )))
+            })?;
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+
+    /// Mark a composition document as deleted by setting `deleted_at`
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID (partition key)
+    /// * `composition_id` - Composition ID (document ID)
+    pub async fn soft_delete_composition(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_id: &str,
+    ) -> Result<()> {
+        let container = self.get_container_client(template_id);
+        let partition_key = PartitionKey::from(ehr_id.as_str().to_string());
+
+        let patch = PatchDocument::default()
+            .with_set("/deleted_at", chrono::Utc::now())
+            .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+
+        container
+            .patch_item(partition_key, composition_id, patch, None)
+            .await
+            .map_err(|e| {
+                AtlasError::CosmosDb(CosmosDbError::UpdateFailed(format!(
+                    "Failed to mark composition {composition_id} as deleted: {e}"
+                )))
+            })?;
+
+        Ok(())
+    }
+
+    /// Delete a composition document
+    ///
+    /// A document that no longer exists is treated as deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID (partition key)
+    /// * `composition_id` - Composition ID (document ID)
+    pub async fn delete_composition(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_id: &str,
+    ) -> Result<()> {
+        let container = self.get_container_client(template_id);
+        let partition_key = PartitionKey::from(ehr_id.as_str().to_string());
+
+        match container
+            .delete_item(partition_key, composition_id, None)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("404") || e.to_string().contains("NotFound") => Ok(()),
+            Err(e) => Err(AtlasError::CosmosDb(CosmosDbError::DeleteFailed(format!(
+                "Failed to delete composition {composition_id}: {e}"
+            )))),
+        }
+    }
+
     /// Get the database name
     pub fn database_name(&self) -> &str {
         &self.config.database_name
@@ -354,6 +560,7 @@ mod tests {
             database_name: "test_db".to_string(),
             data_container_prefix: "compositions".to_string(),
             control_container: "atlas_control".to_string(),
+            ehr_container: "atlas_ehrs".to_string(),
             partition_key: "/ehr_id".to_string(),
             max_concurrency: 10,
             request_timeout_seconds: 30,
@@ -3,7 +3,9 @@
 //! This module defines the document structures used when storing compositions
 //! in Azure Cosmos DB.
 
-use crate::domain::composition::Composition;
+use crate::adapters::database::traits::QueryRow;
+use crate::domain::composition::{Composition, CompositionVersion, ContentFormat};
+use crate::domain::ehr::{Ehr, EhrStatus};
 use crate::domain::ids::TemplateId;
 use crate::domain::Result;
 use chrono::{DateTime, Utc};
@@ -29,6 +31,10 @@ pub struct AtlasMetadata {
 
     /// Template ID this composition belongs to
     pub template_id: String,
+
+    /// Format the composition content was retrieved in
+    #[serde(default)]
+    pub composition_format: ContentFormat,
 }
 
 impl AtlasMetadata {
@@ -40,14 +46,22 @@ impl AtlasMetadata {
             checksum: None,
             export_mode,
             template_id: template_id.to_string(),
+            composition_format: ContentFormat::default(),
         }
     }
+
+    /// Record the format of the composition content
+    pub fn with_composition_format(mut self, composition_format: ContentFormat) -> Self {
+        self.composition_format = composition_format;
+        self
+    }
 }
 
-/// Composition document in preserved format (maintains FLAT structure)
+/// Composition document in preserved format (maintains the retrieved structure)
 ///
-/// This format preserves the exact FLAT JSON structure from openEHR,
-/// storing it as-is in the `content` field.
+/// This format preserves the exact content retrieved from openEHR (FLAT JSON
+/// unless another composition format is configured), storing it as-is in the
+/// `content` field.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CosmosComposition {
     /// Document ID (composition UID)
@@ -65,9 +79,13 @@ pub struct CosmosComposition {
     /// Time the composition was committed in openEHR
     pub time_committed: DateTime<Utc>,
 
-    /// Original FLAT JSON content from openEHR
+    /// Original content from openEHR, in the format recorded in `atlas_metadata`
     pub content: Value,
 
+    /// Version number, lifecycle state and change type (version history exports only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<CompositionVersion>,
+
     /// Atlas metadata
     pub atlas_metadata: AtlasMetadata,
 }
@@ -80,7 +98,8 @@ impl CosmosComposition {
         let composition_uid = composition.uid.to_string();
         let template_id = composition.template_id.to_string();
 
-        let atlas_metadata = AtlasMetadata::new(composition.template_id.clone(), export_mode);
+        let atlas_metadata = AtlasMetadata::new(composition.template_id.clone(), export_mode)
+            .with_composition_format(composition.content_format);
 
         Ok(Self {
             id,
@@ -89,11 +108,90 @@ impl CosmosComposition {
             template_id,
             time_committed: composition.time_committed,
             content: composition.content,
+            version: composition.version,
             atlas_metadata,
         })
     }
 }
 
+/// EHR document stored in the EHR container
+///
+/// Holds the EHR metadata and the current EHR_STATUS, keyed by EHR ID so that
+/// exported compositions can be joined with the subject reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosmosEhr {
+    /// Document ID (EHR ID)
+    pub id: String,
+
+    /// EHR ID (partition key)
+    pub ehr_id: String,
+
+    /// System ID where the EHR was created
+    pub system_id: Option<String>,
+
+    /// Time the EHR was created
+    pub time_created: DateTime<Utc>,
+
+    /// Current EHR_STATUS
+    pub ehr_status: Option<EhrStatus>,
+
+    /// When this EHR was exported to Cosmos DB
+    pub exported_at: DateTime<Utc>,
+
+    /// Version of Atlas that exported this EHR
+    pub atlas_version: String,
+}
+
+impl CosmosEhr {
+    /// Convert from domain Ehr to Cosmos document
+    pub fn from_domain(ehr: &Ehr) -> Self {
+        Self {
+            id: ehr.id.to_string(),
+            ehr_id: ehr.id.to_string(),
+            system_id: ehr.system_id.clone(),
+            time_created: ehr.time_created,
+            ehr_status: ehr.status.clone(),
+            exported_at: Utc::now(),
+            atlas_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Result-set row of a named AQL query
+///
+/// Stored in the container named after the query, keyed (and partitioned) by
+/// the row ID derived from the query's key columns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosmosQueryRow {
+    /// Document ID (row ID, partition key)
+    pub id: String,
+
+    /// Name of the AQL query
+    pub query_name: String,
+
+    /// Row values keyed by column name
+    pub data: serde_json::Map<String, Value>,
+
+    /// When this row was exported to Cosmos DB
+    pub exported_at: DateTime<Utc>,
+
+    /// Version of Atlas that exported this row
+    pub atlas_version: String,
+}
+
+impl CosmosQueryRow {
+    /// Create a document for a result-set row
+    pub fn new(query_name: &str, row: QueryRow) -> Self {
+        Self {
+            id: row.id,
+            query_name: query_name.to_string(),
+            data: row.data,
+            exported_at: Utc::now(),
+            atlas_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
 /// Composition document in flattened format
 ///
 /// This format converts the nested FLAT paths (e.g., "vital_signs/body_temperature:0|magnitude")
@@ -115,6 +213,10 @@ pub struct CosmosCompositionFlattened {
     /// Time the composition was committed in openEHR
     pub time_committed: DateTime<Utc>,
 
+    /// Version number, lifecycle state and change type (version history exports only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<CompositionVersion>,
+
     /// Flattened fields from the FLAT JSON content
     #[serde(flatten)]
     pub fields: HashMap<String, Value>,
@@ -131,7 +233,8 @@ impl CosmosCompositionFlattened {
         let composition_uid = composition.uid.to_string();
         let template_id = composition.template_id.to_string();
 
-        let atlas_metadata = AtlasMetadata::new(composition.template_id.clone(), export_mode);
+        let atlas_metadata = AtlasMetadata::new(composition.template_id.clone(), export_mode)
+            .with_composition_format(composition.content_format);
 
         // Flatten the content
         let fields = Self::flatten_content(&composition.content)?;
@@ -142,6 +245,7 @@ impl CosmosCompositionFlattened {
             composition_uid,
             template_id,
             time_committed: composition.time_committed,
+            version: composition.version,
             fields,
             atlas_metadata,
         })
@@ -211,6 +315,71 @@ mod tests {
         assert_eq!(cosmos_doc.ehr_id, "7d44b88c-4199-4bad-97dc-d78268e01398");
         assert_eq!(cosmos_doc.template_id, "vital_signs");
         assert_eq!(cosmos_doc.atlas_metadata.export_mode, "full");
+
+        let json = serde_json::to_value(&cosmos_doc).unwrap();
+        assert!(json.get("version").is_none());
+    }
+
+    #[test]
+    fn test_cosmos_composition_with_version() {
+        let composition = CompositionBuilder::new()
+            .uid(CompositionUid::new("84d7c3f5::local.ehrbase.org::2").unwrap())
+            .ehr_id(EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::new("vital_signs").unwrap())
+            .time_committed(Utc::now())
+            .content(json!({"ctx/language": "en"}))
+            .version(CompositionVersion {
+                version_number: 2,
+                lifecycle_state: Some("complete".to_string()),
+                change_type: Some("modification".to_string()),
+            })
+            .build()
+            .unwrap();
+
+        let cosmos_doc = CosmosComposition::from_domain(composition, "full".to_string()).unwrap();
+        let json = serde_json::to_value(&cosmos_doc).unwrap();
+
+        assert_eq!(json["id"], "84d7c3f5::local.ehrbase.org::2");
+        assert_eq!(json["version"]["version_number"], 2);
+        assert_eq!(json["version"]["lifecycle_state"], "complete");
+        assert_eq!(json["version"]["change_type"], "modification");
+    }
+
+    #[test]
+    fn test_cosmos_composition_records_format() {
+        let composition = CompositionBuilder::new()
+            .uid(CompositionUid::new("84d7c3f5::local.ehrbase.org::1").unwrap())
+            .ehr_id(EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::new("vital_signs").unwrap())
+            .time_committed(Utc::now())
+            .content(json!({"_type": "COMPOSITION", "archetype_node_id": "openEHR-EHR-COMPOSITION.encounter.v1"}))
+            .content_format(ContentFormat::CanonicalJson)
+            .build()
+            .unwrap();
+
+        let cosmos_doc = CosmosComposition::from_domain(composition, "full".to_string()).unwrap();
+        let json = serde_json::to_value(&cosmos_doc).unwrap();
+
+        assert_eq!(
+            json["atlas_metadata"]["composition_format"],
+            "canonical_json"
+        );
+        assert_eq!(json["content"]["_type"], "COMPOSITION");
+    }
+
+    #[test]
+    fn test_cosmos_ehr_from_domain() {
+        let ehr = Ehr::with_system_id(
+            EhrId::new("ehr-123").unwrap(),
+            Utc::now(),
+            "local.ehrbase.org".to_string(),
+        );
+
+        let doc = CosmosEhr::from_domain(&ehr);
+        assert_eq!(doc.id, "ehr-123");
+        assert_eq!(doc.ehr_id, "ehr-123");
+        assert_eq!(doc.system_id.as_deref(), Some("local.ehrbase.org"));
+        assert!(doc.ehr_status.is_none());
     }
 
     #[test]
@@ -5,8 +5,14 @@
 use crate::adapters::cosmosdb::adapter::CosmosDbAdapter;
 use crate::adapters::cosmosdb::client::CosmosDbClient;
 use crate::adapters::database::traits::{DatabaseClient, StateStorage};
+use crate::adapters::filesystem::FilesystemAdapter;
+use crate::adapters::mongodb::{MongoDbAdapter, MongoDbClient};
+use crate::adapters::parquet::ParquetAdapter;
 use crate::adapters::postgresql::adapter::PostgreSQLAdapter;
 use crate::adapters::postgresql::client::PostgreSQLClient;
+use crate::adapters::retry::RetryPolicy;
+use crate::adapters::search::{SearchAdapter, SearchClient};
+use crate::adapters::sqlite::{SQLiteAdapter, SQLiteClient};
 use crate::config::schema::{AtlasConfig, DatabaseTarget};
 use crate::domain::Result;
 use std::sync::Arc;
@@ -39,7 +45,8 @@ pub async fn create_database_client(
 
             tracing::info!("Creating CosmosDB client");
             let client = CosmosDbClient::new(cosmos_config.clone()).await?;
-            let adapter = CosmosDbAdapter::new(client);
+            let adapter = CosmosDbAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
 
             Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
         }
@@ -50,9 +57,73 @@ pub async fn create_database_client(
                 .expect("PostgreSQL config should be validated");
 
             tracing::info!("Creating PostgreSQL client");
-            let client = PostgreSQLClient::new(pg_config.clone()).await?;
+            let client = PostgreSQLClient::new(pg_config.clone())
+                .await?
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
             let adapter = PostgreSQLAdapter::new(client);
 
+            Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
+        }
+        DatabaseTarget::Filesystem => {
+            let fs_config = config
+                .filesystem
+                .as_ref()
+                .expect("Filesystem config should be validated");
+
+            tracing::info!("Creating filesystem client");
+            let adapter = FilesystemAdapter::new(fs_config.clone())?;
+
+            Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
+        }
+        DatabaseTarget::Parquet => {
+            let pq_config = config
+                .parquet
+                .as_ref()
+                .expect("Parquet config should be validated");
+
+            tracing::info!("Creating parquet client");
+            let adapter = ParquetAdapter::new(pq_config.clone(), config.openehr.query.batch_size)?;
+
+            Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
+        }
+        DatabaseTarget::SQLite => {
+            let sqlite_config = config
+                .sqlite
+                .as_ref()
+                .expect("SQLite config should be validated");
+
+            tracing::info!("Creating SQLite client");
+            let client = SQLiteClient::new(sqlite_config.clone())
+                .await?
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+            let adapter = SQLiteAdapter::new(client);
+
+            Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
+        }
+        DatabaseTarget::MongoDB => {
+            let mongo_config = config
+                .mongodb
+                .as_ref()
+                .expect("MongoDB config should be validated");
+
+            tracing::info!("Creating MongoDB client");
+            let client = MongoDbClient::new(mongo_config.clone()).await?;
+            let adapter = MongoDbAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+
+            Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
+        }
+        DatabaseTarget::Search => {
+            let search_config = config
+                .search
+                .as_ref()
+                .expect("Search config should be validated");
+
+            tracing::info!("Creating search client");
+            let client = SearchClient::new(search_config.clone()).await?;
+            let adapter = SearchAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+
             Ok(Arc::new(adapter) as Arc<dyn DatabaseClient + Send + Sync>)
         }
     }
@@ -86,7 +157,8 @@ pub async fn create_state_storage(
 
             tracing::info!("Creating CosmosDB state storage");
             let client = CosmosDbClient::new(cosmos_config.clone()).await?;
-            let adapter = CosmosDbAdapter::new(client);
+            let adapter = CosmosDbAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
 
             Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
         }
@@ -97,9 +169,73 @@ pub async fn create_state_storage(
                 .expect("PostgreSQL config should be validated");
 
             tracing::info!("Creating PostgreSQL state storage");
-            let client = PostgreSQLClient::new(pg_config.clone()).await?;
+            let client = PostgreSQLClient::new(pg_config.clone())
+                .await?
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
             let adapter = PostgreSQLAdapter::new(client);
 
+            Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
+        }
+        DatabaseTarget::Filesystem => {
+            let fs_config = config
+                .filesystem
+                .as_ref()
+                .expect("Filesystem config should be validated");
+
+            tracing::info!("Creating filesystem state storage");
+            let adapter = FilesystemAdapter::new(fs_config.clone())?;
+
+            Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
+        }
+        DatabaseTarget::Parquet => {
+            let pq_config = config
+                .parquet
+                .as_ref()
+                .expect("Parquet config should be validated");
+
+            tracing::info!("Creating parquet state storage");
+            let adapter = ParquetAdapter::new(pq_config.clone(), config.openehr.query.batch_size)?;
+
+            Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
+        }
+        DatabaseTarget::SQLite => {
+            let sqlite_config = config
+                .sqlite
+                .as_ref()
+                .expect("SQLite config should be validated");
+
+            tracing::info!("Creating SQLite state storage");
+            let client = SQLiteClient::new(sqlite_config.clone())
+                .await?
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+            let adapter = SQLiteAdapter::new(client);
+
+            Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
+        }
+        DatabaseTarget::MongoDB => {
+            let mongo_config = config
+                .mongodb
+                .as_ref()
+                .expect("MongoDB config should be validated");
+
+            tracing::info!("Creating MongoDB state storage");
+            let client = MongoDbClient::new(mongo_config.clone()).await?;
+            let adapter = MongoDbAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+
+            Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
+        }
+        DatabaseTarget::Search => {
+            let search_config = config
+                .search
+                .as_ref()
+                .expect("Search config should be validated");
+
+            tracing::info!("Creating search state storage");
+            let client = SearchClient::new(search_config.clone()).await?;
+            let adapter = SearchAdapter::new(client)
+                .with_retry_policy(RetryPolicy::from_export_config(&config.export));
+
             Ok(Arc::new(adapter) as Arc<dyn StateStorage + Send + Sync>)
         }
     }
@@ -136,7 +272,10 @@ pub async fn create_database_and_state(
 
             tracing::info!("Creating CosmosDB client and state storage");
             let client = Arc::new(CosmosDbClient::new(cosmos_config.clone()).await?);
-            let adapter = Arc::new(CosmosDbAdapter::new_with_arc(client));
+            let adapter = Arc::new(
+                CosmosDbAdapter::new_with_arc(client)
+                    .with_retry_policy(RetryPolicy::from_export_config(&config.export)),
+            );
 
             Ok((
                 adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
@@ -150,9 +289,101 @@ pub async fn create_database_and_state(
                 .expect("PostgreSQL config should be validated");
 
             tracing::info!("Creating PostgreSQL client and state storage");
-            let client = Arc::new(PostgreSQLClient::new(pg_config.clone()).await?);
+            let client = Arc::new(
+                PostgreSQLClient::new(pg_config.clone())
+                    .await?
+                    .with_retry_policy(RetryPolicy::from_export_config(&config.export)),
+            );
             let adapter = Arc::new(PostgreSQLAdapter::new_with_arc(client));
 
+            Ok((
+                adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
+                adapter as Arc<dyn StateStorage + Send + Sync>,
+            ))
+        }
+        DatabaseTarget::Filesystem => {
+            let fs_config = config
+                .filesystem
+                .as_ref()
+                .expect("Filesystem config should be validated");
+
+            // A single adapter, so that the state file has one writer
+            tracing::info!("Creating filesystem client and state storage");
+            let adapter = Arc::new(FilesystemAdapter::new(fs_config.clone())?);
+
+            Ok((
+                adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
+                adapter as Arc<dyn StateStorage + Send + Sync>,
+            ))
+        }
+        DatabaseTarget::Parquet => {
+            let pq_config = config
+                .parquet
+                .as_ref()
+                .expect("Parquet config should be validated");
+
+            // A single adapter, so that the state file has one writer
+            tracing::info!("Creating parquet client and state storage");
+            let adapter = Arc::new(ParquetAdapter::new(
+                pq_config.clone(),
+                config.openehr.query.batch_size,
+            )?);
+
+            Ok((
+                adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
+                adapter as Arc<dyn StateStorage + Send + Sync>,
+            ))
+        }
+        DatabaseTarget::SQLite => {
+            let sqlite_config = config
+                .sqlite
+                .as_ref()
+                .expect("SQLite config should be validated");
+
+            tracing::info!("Creating SQLite client and state storage");
+            let client = Arc::new(
+                SQLiteClient::new(sqlite_config.clone())
+                    .await?
+                    .with_retry_policy(RetryPolicy::from_export_config(&config.export)),
+            );
+            let adapter = Arc::new(SQLiteAdapter::new_with_arc(client));
+
+            Ok((
+                adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
+                adapter as Arc<dyn StateStorage + Send + Sync>,
+            ))
+        }
+        DatabaseTarget::MongoDB => {
+            let mongo_config = config
+                .mongodb
+                .as_ref()
+                .expect("MongoDB config should be validated");
+
+            tracing::info!("Creating MongoDB client and state storage");
+            let client = Arc::new(MongoDbClient::new(mongo_config.clone()).await?);
+            let adapter = Arc::new(
+                MongoDbAdapter::new_with_arc(client)
+                    .with_retry_policy(RetryPolicy::from_export_config(&config.export)),
+            );
+
+            Ok((
+                adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
+                adapter as Arc<dyn StateStorage + Send + Sync>,
+            ))
+        }
+        DatabaseTarget::Search => {
+            let search_config = config
+                .search
+                .as_ref()
+                .expect("Search config should be validated");
+
+            tracing::info!("Creating search client and state storage");
+            let client = Arc::new(SearchClient::new(search_config.clone()).await?);
+            let adapter = Arc::new(
+                SearchAdapter::new_with_arc(client)
+                    .with_retry_policy(RetryPolicy::from_export_config(&config.export)),
+            );
+
             Ok((
                 adapter.clone() as Arc<dyn DatabaseClient + Send + Sync>,
                 adapter as Arc<dyn StateStorage + Send + Sync>,
@@ -3,9 +3,12 @@
 //! This module defines the traits that database adapters must implement
 //! to work with Atlas.
 
-use crate::core::state::watermark::Watermark;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
 use crate::domain::composition::Composition;
-use crate::domain::ids::{EhrId, TemplateId};
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use crate::domain::Result;
 use async_trait::async_trait;
 use std::any::Any;
@@ -36,6 +39,16 @@ pub struct BulkInsertFailure {
     pub is_throttled: bool,
 }
 
+/// A result-set row of a named AQL query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRow {
+    /// Row ID, derived from the key columns of the query
+    pub id: String,
+
+    /// Column values keyed by column name
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
 /// Database client trait for composition storage
 ///
 /// This trait defines the interface that all database adapters must implement
@@ -80,6 +93,57 @@ pub trait DatabaseClient: Send + Sync {
     /// Returns an error if the control container/table cannot be created.
     async fn ensure_control_container_exists(&self) -> Result<()>;
 
+    /// Ensure the container/table for EHR metadata and EHR_STATUS exists
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the EHR container/table cannot be created.
+    async fn ensure_ehr_container_exists(&self) -> Result<()>;
+
+    /// Insert or replace the metadata and EHR_STATUS of an EHR
+    ///
+    /// # Arguments
+    ///
+    /// * `ehr` - EHR to store, keyed by its EHR ID
+    /// * `dry_run` - If true, skip actual database writes (for testing)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the EHR cannot be written.
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()>;
+
+    /// Ensure the container/table for the result set of a named AQL query exists
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - Name of the query, used as the container/table name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container/table cannot be created.
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()>;
+
+    /// Insert or replace result-set rows of a named AQL query
+    ///
+    /// Each row is stored as a document/row keyed by `row.id`, so re-exported
+    /// rows replace their earlier copies.
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - Name of the query, used as the container/table name
+    /// * `rows` - Result-set rows to store
+    /// * `dry_run` - If true, skip actual database writes (for testing)
+    ///
+    /// # Returns
+    ///
+    /// Returns a `BulkInsertResult` with success/failure counts.
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult>;
+
     /// Bulk insert pre-transformed JSON documents
     ///
     /// This is the preferred method for inserting compositions as it allows
@@ -177,6 +241,61 @@ pub trait DatabaseClient: Send + Sync {
         composition_id: &str,
     ) -> Result<bool>;
 
+    /// List the IDs of the compositions stored for an EHR and template
+    ///
+    /// Compositions that have been soft-deleted are not included.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID
+    ///
+    /// # Returns
+    ///
+    /// Returns the document IDs (versioned composition UIDs).
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>>;
+
+    /// Apply a deletion policy to compositions that were deleted in openEHR
+    ///
+    /// With `SoftDelete` the documents are kept and marked with a `deleted_at`
+    /// timestamp; with `HardDelete` they are removed. `Ignore` is a no-op.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID
+    /// * `composition_ids` - Document IDs of the deleted compositions
+    /// * `policy` - Deletion policy to apply
+    /// * `dry_run` - If true, skip the actual changes (for testing/preview)
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of compositions deleted or marked as deleted.
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize>;
+
+    /// Write out documents buffered by the client
+    ///
+    /// Called once the documents of an export have been passed to the client.
+    /// Clients that write every batch directly don't need to override this.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffered documents cannot be written.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Get the database name
     fn database_name(&self) -> &str;
 }
@@ -252,4 +371,80 @@ pub trait StateStorage: Send + Sync {
     ///
     /// Returns an error if the query fails.
     async fn get_all_watermarks(&self) -> Result<Vec<Watermark>>;
+
+    /// Load the watermark of a named AQL query
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - Name of the query (`export.aql_queries.name`)
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(QueryWatermark))` if found, `Ok(None)` if not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails for reasons other than "not found".
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>>;
+
+    /// Save the watermark of a named AQL query
+    ///
+    /// # Arguments
+    ///
+    /// * `watermark` - Query watermark to save
+    /// * `dry_run` - If true, skip actual database writes (for testing)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save operation fails.
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()>;
+
+    /// Load the dead letter of a composition
+    ///
+    /// # Arguments
+    ///
+    /// * `composition_uid` - Composition UID (including version)
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(DeadLetter))` if found, `Ok(None)` if not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails for reasons other than "not found".
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>>;
+
+    /// Save (insert or replace) a dead letter
+    ///
+    /// # Arguments
+    ///
+    /// * `dead_letter` - Dead letter to save
+    /// * `dry_run` - If true, skip actual database writes (for testing)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save operation fails.
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()>;
+
+    /// Delete a dead letter after its composition was exported
+    ///
+    /// # Arguments
+    ///
+    /// * `dead_letter` - Dead letter to delete
+    /// * `dry_run` - If true, skip actual database writes (for testing)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delete operation fails.
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()>;
+
+    /// List all dead letters, oldest failure first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>>;
 }
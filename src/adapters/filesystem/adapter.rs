@@ -0,0 +1,719 @@
+//! Filesystem adapter implementing database traits
+//!
+//! This module provides the implementation of DatabaseClient and StateStorage traits
+//! for the filesystem target.
+//!
+//! # Layout
+//!
+//! ```text
+//! <root_dir>/
+//! ├── atlas_state.json                  watermarks and dead letters
+//! ├── atlas_state.json.journal          state changes since the last compaction
+//! ├── <template>/<date>/part-N.ndjson   compositions
+//! ├── _ehrs/<date>/part-N.ndjson        EHR metadata and EHR_STATUS
+//! └── _queries/<query>/<date>/part-N.ndjson
+//! ```
+//!
+//! `<template>` is the template ID in lowercase with other characters than
+//! letters and digits replaced by underscores, and `<date>` is the UTC date
+//! of the export. Files are only ever appended to: a re-exported composition
+//! is written again, and a soft-deleted composition gets a record with its
+//! `id`, `ehr_id`, `template_id` and `deleted_at`. Readers keep the last
+//! record of each `id`.
+
+use crate::adapters::cosmosdb::models::CosmosEhr;
+use crate::adapters::database::traits::{
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
+};
+use crate::adapters::filesystem::index::{CompositionIndex, IndexCache, IndexEntry};
+use crate::adapters::filesystem::state::{Change, StateFile};
+use crate::adapters::filesystem::writer::{self, NdjsonWriter};
+use crate::config::schema::FilesystemConfig;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::core::transform::flatten::flatten_composition;
+use crate::core::transform::preserve::preserve_composition;
+use crate::domain::composition::Composition;
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use crate::domain::{AtlasError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use std::any::Any;
+use std::path::{Path, PathBuf};
+
+/// Directory of the EHR files
+const EHR_DIR: &str = "_ehrs";
+
+/// Directory of the named AQL query files
+const QUERY_DIR: &str = "_queries";
+
+/// Filesystem implementation of database traits
+///
+/// Writes newline-delimited JSON files below a root directory and keeps the
+/// export state in a local JSON file.
+pub struct FilesystemAdapter {
+    root: PathBuf,
+    writer: NdjsonWriter,
+    state: StateFile,
+    /// Composition index of each template directory, read from the files on
+    /// first use and then kept up to date by the writes of this adapter
+    indexes: IndexCache,
+}
+
+impl FilesystemAdapter {
+    /// Create a new filesystem adapter
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing state file cannot be read.
+    pub fn new(config: FilesystemConfig) -> Result<Self> {
+        let root = PathBuf::from(&config.root_dir);
+        let state = StateFile::open(root.join(&config.state_file))?;
+
+        Ok(Self {
+            writer: NdjsonWriter::new(config.compression, config.max_file_size_mb * 1024 * 1024),
+            state,
+            root,
+            indexes: IndexCache::default(),
+        })
+    }
+
+    /// Root directory of the exported files
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the compositions of a template
+    fn template_dir(&self, template_id: &TemplateId) -> PathBuf {
+        self.root.join(template_id.to_container_name(""))
+    }
+
+    /// Directory of the result set of a named AQL query
+    fn query_dir(&self, query_name: &str) -> PathBuf {
+        self.root.join(QUERY_DIR).join(query_name)
+    }
+
+    /// Append documents to today's files of a directory
+    async fn append(&self, dir: &Path, documents: &[serde_json::Value]) -> Result<PathBuf> {
+        let dir = dir.join(Utc::now().format("%Y-%m-%d").to_string());
+        self.writer.append(&dir, documents).await
+    }
+
+    /// Append documents, reporting a failed write as failures of all documents
+    async fn append_batch(
+        &self,
+        dir: &Path,
+        documents: &[serde_json::Value],
+    ) -> Result<BulkInsertResult> {
+        match self.append(dir, documents).await {
+            Ok(path) => {
+                tracing::debug!(
+                    path = %path.display(),
+                    count = documents.len(),
+                    "Documents written to filesystem"
+                );
+                Ok(BulkInsertResult {
+                    success_count: documents.len(),
+                    failure_count: 0,
+                    failures: Vec::new(),
+                })
+            }
+            Err(e) => {
+                tracing::error!(dir = %dir.display(), error = %e, "Failed to write documents");
+                let failures: Vec<BulkInsertFailure> = documents
+                    .iter()
+                    .map(|doc| BulkInsertFailure {
+                        document_id: doc
+                            .get("id")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("unknown")
+                            .to_string(),
+                        error: e.to_string(),
+                        is_throttled: false,
+                    })
+                    .collect();
+
+                Ok(BulkInsertResult {
+                    success_count: 0,
+                    failure_count: failures.len(),
+                    failures,
+                })
+            }
+        }
+    }
+
+    /// IDs of the stored compositions of an EHR that are not deleted
+    ///
+    /// The files of the template are read once, on the first call.
+    async fn live_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let dir = self.template_dir(template_id);
+
+        self.indexes
+            .live_ids(&dir, ehr_id.as_str(), || {
+                let dir = dir.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || read_index(&dir))
+                        .await
+                        .map_err(|e| {
+                            AtlasError::Io(format!("Failed to read exported files: {e}"))
+                        })?
+                }
+            })
+            .await
+    }
+}
+
+/// Read the composition index of a template directory from its files
+fn read_index(dir: &Path) -> Result<CompositionIndex> {
+    let mut index = CompositionIndex::default();
+    for path in writer::part_files(dir)? {
+        for doc in writer::read_documents(&path)? {
+            index.extend(IndexEntry::from_document(&doc));
+        }
+    }
+
+    Ok(index)
+}
+
+#[async_trait]
+impl DatabaseClient for FilesystemAdapter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+
+        if tokio::fs::metadata(&self.root)
+            .await?
+            .permissions()
+            .readonly()
+        {
+            return Err(AtlasError::Io(format!(
+                "Directory {} is not writable",
+                self.root.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_database_exists(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        Ok(())
+    }
+
+    async fn ensure_container_exists(&self, template_id: &TemplateId) -> Result<()> {
+        tokio::fs::create_dir_all(self.template_dir(template_id)).await?;
+        Ok(())
+    }
+
+    async fn ensure_control_container_exists(&self) -> Result<()> {
+        if let Some(parent) = self.state.path().parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(())
+    }
+
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        tokio::fs::create_dir_all(self.root.join(EHR_DIR)).await?;
+        Ok(())
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would write EHR status to filesystem"
+            );
+            return Ok(());
+        }
+
+        let document = serde_json::to_value(CosmosEhr::from_domain(ehr))
+            .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+        self.append(&self.root.join(EHR_DIR), &[document]).await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status written to filesystem");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        tokio::fs::create_dir_all(self.query_dir(query_name)).await?;
+        Ok(())
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would write {} query rows to filesystem",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let exported_at = Utc::now();
+        let documents: Vec<serde_json::Value> = rows
+            .into_iter()
+            .map(|row| {
+                json!({
+                    "id": row.id,
+                    "data": row.data,
+                    "exported_at": exported_at,
+                    "atlas_version": env!("CARGO_PKG_VERSION"),
+                })
+            })
+            .collect();
+
+        self.append_batch(&self.query_dir(query_name), &documents)
+            .await
+    }
+
+    async fn bulk_insert_json(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<serde_json::Value>,
+        _max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                count = documents.len(),
+                "DRY RUN: Would write {} compositions to filesystem",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let result = self
+            .append_batch(&self.template_dir(template_id), &documents)
+            .await?;
+        if result.failure_count == 0 {
+            self.indexes
+                .record(
+                    &self.template_dir(template_id),
+                    documents.iter().filter_map(IndexEntry::from_document),
+                )
+                .await;
+        }
+
+        Ok(result)
+    }
+
+    async fn bulk_insert_compositions(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        let documents = compositions
+            .into_iter()
+            .map(|composition| preserve_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.bulk_insert_json(template_id, documents, max_retries, dry_run)
+            .await
+    }
+
+    async fn bulk_insert_compositions_flattened(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        let documents = compositions
+            .into_iter()
+            .map(|composition| flatten_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.bulk_insert_json(template_id, documents, max_retries, dry_run)
+            .await
+    }
+
+    async fn check_composition_exists(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &str,
+        composition_id: &str,
+    ) -> Result<bool> {
+        let ehr_id = EhrId::new(ehr_id).map_err(AtlasError::Validation)?;
+        let ids = self.live_composition_ids(template_id, &ehr_id).await?;
+
+        Ok(ids.iter().any(|id| id == composition_id))
+    }
+
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        self.live_composition_ids(template_id, ehr_id).await
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if policy == DeletionPolicy::HardDelete {
+            return Err(AtlasError::Database(
+                "hard_delete is not supported by the filesystem target".to_string(),
+            ));
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in filesystem",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let deleted_at = Utc::now();
+        let records: Vec<serde_json::Value> = composition_ids
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "ehr_id": ehr_id.as_str(),
+                    "template_id": template_id.as_str(),
+                    "deleted_at": deleted_at,
+                })
+            })
+            .collect();
+        self.append(&self.template_dir(template_id), &records)
+            .await?;
+        self.indexes
+            .record(
+                &self.template_dir(template_id),
+                records.iter().filter_map(IndexEntry::from_document),
+            )
+            .await;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = records.len(),
+            policy = %policy,
+            "Applied deletion policy in filesystem"
+        );
+
+        Ok(records.len())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.state.compact().await
+    }
+
+    fn database_name(&self) -> &str {
+        "filesystem"
+    }
+}
+
+#[async_trait]
+impl StateStorage for FilesystemAdapter {
+    async fn load_watermark(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Option<Watermark>> {
+        let watermark_id = Watermark::generate_id(template_id, ehr_id);
+
+        Ok(self
+            .state
+            .read(|state| state.watermarks.get(&watermark_id).cloned())
+            .await)
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                template_id = %watermark.template_id.as_str(),
+                ehr_id = %watermark.ehr_id.as_str(),
+                watermark_id = %watermark.id,
+                "DRY RUN: Would save watermark to state file"
+            );
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::Watermark(watermark.clone())])
+            .await
+    }
+
+    async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
+        let mut watermarks: Vec<Watermark> = self
+            .state
+            .read(|state| state.watermarks.values().cloned().collect())
+            .await;
+        watermarks.sort_by(|a, b| {
+            (a.template_id.as_str(), a.ehr_id.as_str())
+                .cmp(&(b.template_id.as_str(), b.ehr_id.as_str()))
+        });
+
+        Ok(watermarks)
+    }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        let id = QueryWatermark::generate_id(query_name);
+
+        Ok(self
+            .state
+            .read(|state| state.query_watermarks.get(&id).cloned())
+            .await)
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to state file"
+            );
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::QueryWatermark(watermark.clone())])
+            .await
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        let id = DeadLetter::generate_id(composition_uid);
+
+        Ok(self
+            .state
+            .read(|state| state.dead_letters.get(&id).cloned())
+            .await)
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to state file"
+            );
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::DeadLetter(dead_letter.clone())])
+            .await
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from state file"
+            );
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::DeleteDeadLetter(dead_letter.id.clone())])
+            .await
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut dead_letters: Vec<DeadLetter> = self
+            .state
+            .read(|state| state.dead_letters.values().cloned().collect())
+            .await;
+        dead_letters.sort_by_key(|dead_letter| dead_letter.first_failed_at);
+
+        Ok(dead_letters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::schema::FileCompression;
+
+    fn adapter(root: &Path) -> FilesystemAdapter {
+        FilesystemAdapter::new(FilesystemConfig {
+            root_dir: root.display().to_string(),
+            compression: FileCompression::Gzip,
+            max_file_size_mb: 1,
+            state_file: "atlas_state.json".to_string(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compositions_and_soft_delete() {
+        let root = tempfile::tempdir().unwrap();
+        let adapter = adapter(root.path());
+        let template_id = TemplateId::new("IDCR - Vital Signs.v1").unwrap();
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+
+        let documents = vec![
+            json!({"id": "c1::local::1", "ehr_id": ehr_id.as_str(), "content": {}}),
+            json!({"id": "c2::local::1", "ehr_id": ehr_id.as_str(), "content": {}}),
+            json!({"id": "c3::local::1", "ehr_id": "other-ehr", "content": {}}),
+        ];
+        let result = adapter
+            .bulk_insert_json(&template_id, documents, 3, false)
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 3);
+
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        assert!(root
+            .path()
+            .join("idcr_vital_signs_v1")
+            .join(date)
+            .join("part-1.ndjson.gz")
+            .is_file());
+
+        assert_eq!(
+            adapter
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        let deleted = adapter
+            .delete_compositions(
+                &template_id,
+                &ehr_id,
+                &["c1::local::1".to_string()],
+                DeletionPolicy::SoftDelete,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert!(!adapter
+            .check_composition_exists(&template_id, ehr_id.as_str(), "c1::local::1")
+            .await
+            .unwrap());
+        assert!(adapter
+            .check_composition_exists(&template_id, ehr_id.as_str(), "c2::local::1")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_writes_after_first_read() {
+        let root = tempfile::tempdir().unwrap();
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+        let document = |id: &str| json!({"id": id, "ehr_id": ehr_id.as_str(), "content": {}});
+
+        adapter(root.path())
+            .bulk_insert_json(
+                &template_id,
+                vec![document("c1::local::1"), document("c2::local::1")],
+                3,
+                false,
+            )
+            .await
+            .unwrap();
+
+        // The index of a new adapter is read from the files of the previous run
+        let resumed = adapter(root.path());
+        assert_eq!(
+            resumed
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        resumed
+            .delete_compositions(
+                &template_id,
+                &ehr_id,
+                &["c1::local::1".to_string(), "c2::local::1".to_string()],
+                DeletionPolicy::SoftDelete,
+                false,
+            )
+            .await
+            .unwrap();
+        resumed
+            .bulk_insert_json(
+                &template_id,
+                vec![document("c1::local::1"), document("c3::local::1")],
+                3,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let expected = vec!["c1::local::1", "c3::local::1"];
+        assert_eq!(
+            resumed
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            adapter(root.path())
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_writes_nothing() {
+        let root = tempfile::tempdir().unwrap();
+        let adapter = adapter(root.path());
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+
+        let result = adapter
+            .bulk_insert_json(&template_id, vec![json!({"id": "c1"})], 3, true)
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert!(writer::part_files(&adapter.template_dir(&template_id))
+            .unwrap()
+            .is_empty());
+        assert!(!root.path().join("atlas_state.json").exists());
+    }
+}
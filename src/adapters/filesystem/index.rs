@@ -0,0 +1,181 @@
+//! In-memory index of exported composition IDs
+//!
+//! The file targets never rewrite files, so whether a composition is live is
+//! decided by its last record. Comparing the stored compositions of an EHR
+//! with openEHR would otherwise read every file of the template once per
+//! EHR. Instead, the files of a template are read into an index on first use,
+//! and the adapter records what it writes afterwards.
+
+use crate::domain::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Composition of an EHR and whether its last record is live (not deleted)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// EHR ID
+    pub ehr_id: String,
+
+    /// Composition document ID
+    pub id: String,
+
+    /// Whether the composition is not deleted
+    pub live: bool,
+}
+
+impl IndexEntry {
+    /// Entry of a composition document or deletion record
+    ///
+    /// Returns `None` for documents without `id` or `ehr_id`.
+    pub fn from_document(document: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            ehr_id: document.get("ehr_id")?.as_str()?.to_string(),
+            id: document.get("id")?.as_str()?.to_string(),
+            live: document.get("deleted_at").is_none_or(|v| v.is_null()),
+        })
+    }
+}
+
+/// Compositions of a template directory by EHR ID
+#[derive(Debug, Default)]
+pub struct CompositionIndex {
+    ehrs: HashMap<String, BTreeMap<String, bool>>,
+}
+
+impl CompositionIndex {
+    /// Record an entry, replacing the earlier record of the composition
+    pub fn insert(&mut self, entry: IndexEntry) {
+        self.ehrs
+            .entry(entry.ehr_id)
+            .or_default()
+            .insert(entry.id, entry.live);
+    }
+
+    /// IDs of the live compositions of an EHR, in ascending order
+    pub fn live_ids(&self, ehr_id: &str) -> Vec<String> {
+        self.ehrs
+            .get(ehr_id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, live)| **live)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+impl Extend<IndexEntry> for CompositionIndex {
+    fn extend<T: IntoIterator<Item = IndexEntry>>(&mut self, entries: T) {
+        for entry in entries {
+            self.insert(entry);
+        }
+    }
+}
+
+/// Composition indices of the template directories of a target
+///
+/// Each directory has its own lock, held while its index is read, so
+/// entries recorded during the read are applied once it is done.
+#[derive(Debug, Default)]
+pub struct IndexCache {
+    indexes: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<Option<CompositionIndex>>>>>,
+}
+
+impl IndexCache {
+    /// Index of a directory, which is `None` until it is read
+    fn index(&self, dir: &Path) -> Arc<Mutex<Option<CompositionIndex>>> {
+        self.indexes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(dir.to_path_buf())
+            .or_default()
+            .clone()
+    }
+
+    /// IDs of the live compositions of an EHR
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Template directory
+    /// * `ehr_id` - EHR ID
+    /// * `read` - Reads the index of the directory, called on first use only
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `read`. The index is read again on the next call.
+    pub async fn live_ids<F, Fut>(&self, dir: &Path, ehr_id: &str, read: F) -> Result<Vec<String>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<CompositionIndex>>,
+    {
+        let index = self.index(dir);
+        let mut index = index.lock().await;
+
+        let index = match index.as_mut() {
+            Some(index) => index,
+            None => index.insert(read().await?),
+        };
+
+        Ok(index.live_ids(ehr_id))
+    }
+
+    /// Record written entries in the index of a directory, if it was read
+    pub async fn record(&self, dir: &Path, entries: impl IntoIterator<Item = IndexEntry>) {
+        let index = self.index(dir);
+        let mut index = index.lock().await;
+        if let Some(index) = index.as_mut() {
+            index.extend(entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_index_cache() {
+        let cache = IndexCache::default();
+        let dir = Path::new("/data/vital_signs_v1");
+        let read = || async {
+            let mut index = CompositionIndex::default();
+            index.extend(
+                [
+                    json!({"id": "c2", "ehr_id": "e1"}),
+                    json!({"id": "c1", "ehr_id": "e1", "deleted_at": null}),
+                    json!({"id": "c3", "ehr_id": "e2"}),
+                    json!({"id": "c2", "ehr_id": "e1", "deleted_at": "2025-01-15T10:00:00Z"}),
+                ]
+                .iter()
+                .filter_map(IndexEntry::from_document),
+            );
+            Ok(index)
+        };
+
+        // Nothing is recorded before the index is read
+        cache
+            .record(
+                dir,
+                IndexEntry::from_document(&json!({"id": "c9", "ehr_id": "e1"})),
+            )
+            .await;
+        assert_eq!(cache.live_ids(dir, "e1", read).await.unwrap(), vec!["c1"]);
+
+        cache
+            .record(
+                dir,
+                IndexEntry::from_document(&json!({"id": "c2", "ehr_id": "e1"})),
+            )
+            .await;
+        let ids = cache
+            .live_ids(dir, "e1", || async { panic!("index read twice") })
+            .await
+            .unwrap();
+        assert_eq!(ids, vec!["c1", "c2"]);
+
+        assert!(IndexEntry::from_document(&json!({"id": "c1"})).is_none());
+    }
+}
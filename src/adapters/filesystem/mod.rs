@@ -0,0 +1,12 @@
+//! Local filesystem integration
+//!
+//! This module provides a zero-infrastructure target that writes openEHR
+//! compositions as newline-delimited JSON files and keeps the export state
+//! in a local JSON file.
+
+pub mod adapter;
+pub mod index;
+pub mod state;
+pub mod writer;
+
+pub use adapter::FilesystemAdapter;
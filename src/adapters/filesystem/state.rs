@@ -0,0 +1,324 @@
+//! Local JSON state file
+//!
+//! The filesystem target keeps watermarks, query watermarks and dead letters
+//! in a single JSON file. The file is loaded once, and changes are appended
+//! to a journal next to it (`<state_file>.journal`, one change per line).
+//! The journal is compacted into the state file on [`StateFile::compact`]
+//! and once it holds as many changes as the state has entries, so a long
+//! export writes each change about twice instead of rewriting the whole
+//! state for every change. The state file is replaced by writing a temporary
+//! file and renaming it over the old one, so an interrupted write never
+//! leaves a truncated state file behind.
+
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::domain::{AtlasError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Journal size below which it is never compacted
+const MIN_COMPACTION_CHANGES: usize = 1000;
+
+/// Contents of the state file, keyed by document ID
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Watermarks of {template, EHR} pairs
+    #[serde(default)]
+    pub watermarks: BTreeMap<String, Watermark>,
+
+    /// Watermarks of named AQL queries
+    #[serde(default)]
+    pub query_watermarks: BTreeMap<String, QueryWatermark>,
+
+    /// Compositions that failed to export
+    #[serde(default)]
+    pub dead_letters: BTreeMap<String, DeadLetter>,
+}
+
+impl State {
+    /// Apply a change
+    pub fn apply(&mut self, change: Change) {
+        match change {
+            Change::Watermark(watermark) => {
+                self.watermarks.insert(watermark.id.clone(), watermark);
+            }
+            Change::QueryWatermark(watermark) => {
+                self.query_watermarks
+                    .insert(watermark.id.clone(), watermark);
+            }
+            Change::DeadLetter(dead_letter) => {
+                self.dead_letters
+                    .insert(dead_letter.id.clone(), dead_letter);
+            }
+            Change::DeleteDeadLetter(id) => {
+                self.dead_letters.remove(&id);
+            }
+        }
+    }
+
+    /// Number of entries
+    fn len(&self) -> usize {
+        self.watermarks.len() + self.query_watermarks.len() + self.dead_letters.len()
+    }
+}
+
+/// Change of the state, stored as one line of the journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// Insert or replace a watermark
+    Watermark(Watermark),
+    /// Insert or replace a query watermark
+    QueryWatermark(QueryWatermark),
+    /// Insert or replace a dead letter
+    DeadLetter(DeadLetter),
+    /// Remove the dead letter with this ID
+    DeleteDeadLetter(String),
+}
+
+/// State and the number of changes in the journal
+#[derive(Debug)]
+struct Journaled {
+    state: State,
+    changes: usize,
+}
+
+/// State file shared by all export workers
+#[derive(Debug)]
+pub struct StateFile {
+    path: PathBuf,
+    journal: PathBuf,
+    inner: Mutex<Journaled>,
+}
+
+impl StateFile {
+    /// Load the state file and replay its journal, starting with an empty
+    /// state if neither exists
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed, or
+    /// if the journal cannot be read.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let journal = with_suffix(&path, ".journal");
+
+        let mut state: State = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+                AtlasError::State(format!(
+                    "Failed to parse state file {}: {e}",
+                    path.display()
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut changes = 0;
+        match std::fs::read_to_string(&journal) {
+            Ok(content) => {
+                for line in content.lines().filter(|line| !line.is_empty()) {
+                    // Only the last line can be cut short by an interrupted append
+                    match serde_json::from_str(line) {
+                        Ok(change) => {
+                            state.apply(change);
+                            changes += 1;
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                path = %journal.display(),
+                                error = %e,
+                                "Ignoring incomplete state journal entry"
+                            );
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Self {
+            path,
+            journal,
+            inner: Mutex::new(Journaled { state, changes }),
+        })
+    }
+
+    /// Path of the state file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read from the state
+    pub async fn read<R>(&self, f: impl FnOnce(&State) -> R) -> R {
+        f(&self.inner.lock().await.state)
+    }
+
+    /// Apply changes and append them to the journal
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal or state file cannot be written. The
+    /// changes are kept in memory either way.
+    pub async fn update(&self, changes: impl IntoIterator<Item = Change>) -> Result<()> {
+        let mut lines = Vec::new();
+        let mut inner = self.inner.lock().await;
+
+        for change in changes {
+            serde_json::to_writer(&mut lines, &change)
+                .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+            lines.push(b'\n');
+            inner.state.apply(change);
+            inner.changes += 1;
+        }
+
+        let mut journal = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal)
+            .await?;
+        journal.write_all(&lines).await?;
+        journal.flush().await?;
+
+        if inner.changes >= inner.state.len().max(MIN_COMPACTION_CHANGES) {
+            self.write_state(&mut inner).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the state file and empty the journal
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file cannot be written.
+    pub async fn compact(&self) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        if inner.changes > 0 {
+            self.write_state(&mut inner).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the state file with the current state and remove the journal
+    async fn write_state(&self, inner: &mut Journaled) -> Result<()> {
+        let content = serde_json::to_vec_pretty(&inner.state)
+            .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+
+        let temp = with_suffix(&self.path, ".tmp");
+        tokio::fs::write(&temp, content).await?;
+        tokio::fs::rename(&temp, &self.path).await?;
+
+        match tokio::fs::remove_file(&self.journal).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => inner.changes = 0,
+        }
+
+        Ok(())
+    }
+}
+
+/// Path with a suffix appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::watermark::WatermarkBuilder;
+    use crate::domain::ids::{EhrId, TemplateId};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_state_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("atlas_state.json");
+
+        let watermark = WatermarkBuilder::new(
+            TemplateId::from_str("vital_signs.v1").unwrap(),
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+        )
+        .build();
+
+        let state = StateFile::open(&path).unwrap();
+        assert!(state.read(|s| s.watermarks.is_empty()).await);
+        state
+            .update([Change::Watermark(watermark.clone())])
+            .await
+            .unwrap();
+
+        // The change is only in the journal until the state is compacted
+        assert!(!path.exists());
+        let reopened = StateFile::open(&path).unwrap();
+        let loaded = reopened
+            .read(|s| s.watermarks.get(&watermark.id).cloned())
+            .await
+            .unwrap();
+        assert_eq!(loaded.ehr_id, watermark.ehr_id);
+
+        state.compact().await.unwrap();
+        assert!(path.is_file());
+        assert!(!dir.path().join("atlas_state.json.journal").exists());
+        let reopened = StateFile::open(&path).unwrap();
+        assert_eq!(reopened.read(|s| s.watermarks.len()).await, 1);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(StateFile::open(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_journal_replay_and_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("atlas_state.json");
+        let journal = dir.path().join("atlas_state.json.journal");
+        let template_id = TemplateId::from_str("vital_signs.v1").unwrap();
+        let watermark = |ehr_id: &str| {
+            WatermarkBuilder::new(template_id.clone(), EhrId::from_str(ehr_id).unwrap()).build()
+        };
+
+        let state = StateFile::open(&path).unwrap();
+        for i in 0..MIN_COMPACTION_CHANGES - 1 {
+            state
+                .update([Change::Watermark(watermark(&format!("ehr-{}", i % 10)))])
+                .await
+                .unwrap();
+        }
+        assert!(!path.exists());
+
+        // An append cut short by a crash leaves an incomplete last line
+        let mut content = std::fs::read_to_string(&journal).unwrap();
+        content.push_str(r#"{"watermark": {"id"#);
+        std::fs::write(&journal, content).unwrap();
+        let reopened = StateFile::open(&path).unwrap();
+        assert_eq!(reopened.read(|s| s.watermarks.len()).await, 10);
+
+        // The journal is compacted once it holds enough changes
+        std::fs::remove_file(&journal).unwrap();
+        let state = StateFile::open(&path).unwrap();
+        for i in 0..MIN_COMPACTION_CHANGES {
+            state
+                .update([Change::Watermark(watermark(&format!("ehr-{}", i % 10)))])
+                .await
+                .unwrap();
+        }
+        assert!(path.is_file());
+        assert!(!journal.exists());
+        assert_eq!(
+            StateFile::open(&path)
+                .unwrap()
+                .read(|s| s.watermarks.len())
+                .await,
+            10
+        );
+    }
+}
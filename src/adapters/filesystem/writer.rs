@@ -0,0 +1,276 @@
+//! Rotating NDJSON part files
+//!
+//! Documents are appended to `part-N.ndjson` files (`.ndjson.gz` or
+//! `.ndjson.zst` when compressed), one JSON document per line. Each append
+//! writes a complete gzip member or zstd frame, so a file stays readable by
+//! standard tools even if Atlas stops between two batches. Once a part file
+//! has reached the configured size, the next batch starts a new part.
+
+use crate::config::schema::FileCompression;
+use crate::domain::{AtlasError, Result};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Part file currently written in a directory
+#[derive(Debug, Clone, Copy)]
+struct Part {
+    /// Part number (`part-N`)
+    number: u32,
+
+    /// Size of the part file in bytes
+    size: u64,
+}
+
+/// Appends documents to size-rotated part files
+#[derive(Debug)]
+pub struct NdjsonWriter {
+    compression: FileCompression,
+    max_file_size: u64,
+    parts: Mutex<HashMap<PathBuf, Part>>,
+}
+
+impl NdjsonWriter {
+    /// Create a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - Compression of the part files
+    /// * `max_file_size` - Size in bytes after which a new part file is started
+    pub fn new(compression: FileCompression, max_file_size: u64) -> Self {
+        Self {
+            compression,
+            max_file_size,
+            parts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Append documents to the current part file of a directory
+    ///
+    /// The directory is created if necessary. Parts left by earlier runs are
+    /// continued, so numbering never restarts.
+    ///
+    /// # Returns
+    ///
+    /// Returns the path of the part file the documents were written to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the documents cannot be serialized or written.
+    pub async fn append(&self, dir: &Path, documents: &[serde_json::Value]) -> Result<PathBuf> {
+        let data = encode(self.compression, documents)?;
+        let extension = self.compression.extension();
+
+        // Appends are serialized so that concurrent batches don't interleave
+        let mut parts = self.parts.lock().await;
+        let part = match parts.get(dir) {
+            Some(part) => *part,
+            None => {
+                tokio::fs::create_dir_all(dir).await?;
+                current_part(dir, extension)?
+            }
+        };
+
+        let part = if part.size > 0 && part.size + data.len() as u64 > self.max_file_size {
+            Part {
+                number: part.number + 1,
+                size: 0,
+            }
+        } else {
+            part
+        };
+
+        let path = dir.join(format!("part-{}.{extension}", part.number));
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(&data).await?;
+        file.sync_data().await?;
+
+        parts.insert(
+            dir.to_path_buf(),
+            Part {
+                number: part.number,
+                size: part.size + data.len() as u64,
+            },
+        );
+
+        Ok(path)
+    }
+}
+
+/// Find the part file to continue in a directory
+///
+/// This is the highest-numbered part if it has the current extension, or a
+/// new part after it otherwise.
+fn current_part(dir: &Path, extension: &str) -> Result<Part> {
+    let mut latest: Option<(u32, PathBuf)> = None;
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(number) = part_number(&path) {
+            if latest.as_ref().is_none_or(|(n, _)| number > *n) {
+                latest = Some((number, path));
+            }
+        }
+    }
+
+    Ok(match latest {
+        None => Part { number: 1, size: 0 },
+        Some((number, path)) if path.to_string_lossy().ends_with(&format!(".{extension}")) => {
+            Part {
+                number,
+                size: std::fs::metadata(&path)?.len(),
+            }
+        }
+        Some((number, _)) => Part {
+            number: number + 1,
+            size: 0,
+        },
+    })
+}
+
+/// Part number of a `part-N.*` file name
+fn part_number(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?;
+    let (number, _) = name.strip_prefix("part-")?.split_once('.')?;
+    number.parse().ok()
+}
+
+/// Serialize documents as NDJSON and compress them
+fn encode(compression: FileCompression, documents: &[serde_json::Value]) -> Result<Vec<u8>> {
+    let mut lines = Vec::new();
+    for document in documents {
+        serde_json::to_writer(&mut lines, document)
+            .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+        lines.push(b'\n');
+    }
+
+    Ok(match compression {
+        FileCompression::None => lines,
+        FileCompression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&lines)?;
+            encoder.finish()?
+        }
+        FileCompression::Zstd => zstd::encode_all(lines.as_slice(), 0)?,
+    })
+}
+
+/// List the part files below a directory, oldest first
+///
+/// Date directories are visited in name order and parts in number order.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read.
+pub fn part_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut dirs = vec![dir.to_path_buf()];
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs[1..].sort();
+
+    for dir in dirs {
+        let mut parts: Vec<(u32, PathBuf)> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .filter_map(|path| part_number(&path).map(|number| (number, path)))
+            .collect();
+        parts.sort();
+        files.extend(parts.into_iter().map(|(_, path)| path));
+    }
+
+    Ok(files)
+}
+
+/// Read the documents of a part file
+///
+/// The compression is taken from the file extension.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a line is not valid JSON.
+pub fn read_documents(path: &Path) -> Result<Vec<serde_json::Value>> {
+    let file = File::open(path)?;
+    let name = path.to_string_lossy();
+    let reader: Box<dyn Read> = if name.ends_with(".gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else if name.ends_with(".zst") {
+        Box::new(zstd::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+
+    let mut documents = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        documents.push(serde_json::from_str(&line).map_err(|e| {
+            AtlasError::Serialization(format!("Invalid line in {}: {e}", path.display()))
+        })?);
+    }
+
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_append_and_rotate() {
+        for compression in [
+            FileCompression::None,
+            FileCompression::Gzip,
+            FileCompression::Zstd,
+        ] {
+            let root = tempfile::tempdir().unwrap();
+            let dir = root.path().join("vital_signs_v1").join("2025-01-15");
+            let batch = vec![json!({"id": "a"}), json!({"id": "b"})];
+            let batch_size = encode(compression, &batch).unwrap().len() as u64;
+
+            // Two batches fit into a part, the third starts a new one
+            let writer = NdjsonWriter::new(compression, batch_size * 2);
+            let first = writer.append(&dir, &batch).await.unwrap();
+            assert_eq!(writer.append(&dir, &batch).await.unwrap(), first);
+            let second = writer.append(&dir, &batch).await.unwrap();
+            assert_ne!(first, second);
+            assert!(second
+                .to_string_lossy()
+                .ends_with(&format!("part-2.{}", compression.extension())));
+
+            assert_eq!(read_documents(&first).unwrap().len(), 4);
+            assert_eq!(read_documents(&second).unwrap(), batch);
+
+            // A new writer continues the last part
+            let writer = NdjsonWriter::new(compression, batch_size * 2);
+            assert_eq!(writer.append(&dir, &batch).await.unwrap(), second);
+            assert_eq!(
+                part_files(&root.path().join("vital_signs_v1"))
+                    .unwrap()
+                    .len(),
+                2
+            );
+        }
+    }
+}
@@ -6,6 +6,12 @@
 //! - [`database`] - Database abstraction layer (trait-based)
 //! - [`cosmosdb`] - Azure Cosmos DB implementation
 //! - [`postgresql`] - PostgreSQL implementation (coming soon)
+//! - [`mongodb`] - MongoDB implementation
+//! - [`filesystem`] - Newline-delimited JSON files on a local filesystem
+//! - [`parquet`] - Apache Parquet files with a schema inferred per template
+//! - [`sqlite`] - Embedded SQLite database file
+//! - [`search`] - Elasticsearch/OpenSearch indices for full-text search
+//! - [`retry`] - Retry policy shared by all adapters
 //!
 //! # Design Pattern
 //!
@@ -32,12 +38,22 @@
 //!     password: Some(Secret::new(SecretValue::from("pass".to_string()))),
 //!     oidc_token_url: None,
 //!     client_id: None,
+//!     client_secret: None,
+//!     oidc_grant_type: Default::default(),
 //!     tls_verify: true,
 //!     tls_verify_certificates: true,
 //!     tls_ca_cert: None,
+//!     tls_client_cert: None,
+//!     tls_client_key: None,
+//!     tls_client_pkcs12: None,
+//!     tls_client_pkcs12_password: None,
 //!     timeout_seconds: 30,
 //!     retry: Default::default(),
+//!     rate_limit: Default::default(),
 //!     query: Default::default(),
+//!     composition_format: Default::default(),
+//!     vendor_options: Default::default(),
+//!     templates: Default::default(),
 //! };
 //!
 //! let client = OpenEhrClient::new(config).await?;
@@ -61,6 +77,7 @@
 //!     key: Secret::new(SecretValue::from("key".to_string())),
 //!     database_name: "openehr_data".to_string(),
 //!     control_container: "atlas_control".to_string(),
+//!     ehr_container: "atlas_ehrs".to_string(),
 //!     data_container_prefix: "compositions".to_string(),
 //!     partition_key: "/ehr_id".to_string(),
 //!     max_concurrency: 10,
@@ -75,5 +92,11 @@
 
 pub mod cosmosdb;
 pub mod database;
+pub mod filesystem;
+pub mod mongodb;
 pub mod openehr;
+pub mod parquet;
 pub mod postgresql;
+pub mod retry;
+pub mod search;
+pub mod sqlite;
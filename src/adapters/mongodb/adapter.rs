@@ -0,0 +1,825 @@
+//! MongoDB adapter implementing database traits
+//!
+//! This module provides the implementation of DatabaseClient and StateStorage traits
+//! for MongoDB. Documents have the same shape as in Cosmos DB, with the document
+//! ID also stored as `_id`.
+
+use crate::adapters::cosmosdb::models::{CosmosEhr, CosmosQueryRow};
+use crate::adapters::database::traits::{
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
+};
+use crate::adapters::mongodb::client::{mongodb_error, MongoDbClient};
+use crate::adapters::retry::RetryPolicy;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::core::transform::{flatten::flatten_composition, preserve::preserve_composition};
+use crate::domain::composition::Composition;
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use crate::domain::{AtlasError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use mongodb::bson::{self, doc, Document};
+use mongodb::error::{ErrorKind, InsertManyError};
+use mongodb::Collection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::Any;
+use std::sync::Arc;
+
+/// Server error code of a write that violates a unique index
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// Maximum number of existing documents replaced at once
+const MAX_CONCURRENT_REPLACES: usize = 16;
+
+/// MongoDB implementation of database traits
+///
+/// This wraps the MongoDbClient and implements the DatabaseClient and StateStorage traits.
+pub struct MongoDbAdapter {
+    client: Arc<MongoDbClient>,
+    retry: RetryPolicy,
+}
+
+impl MongoDbAdapter {
+    /// Create a new MongoDB adapter
+    pub fn new(client: MongoDbClient) -> Self {
+        Self::new_with_arc(Arc::new(client))
+    }
+
+    /// Create a new MongoDB adapter with an Arc-wrapped client
+    pub fn new_with_arc(client: Arc<MongoDbClient>) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the retry policy for transient write failures
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Get a reference to the underlying client
+    pub fn client(&self) -> &Arc<MongoDbClient> {
+        &self.client
+    }
+
+    /// Upsert documents keyed by `_id`
+    ///
+    /// New documents are written with one unordered `insert_many`; documents
+    /// that already exist are then replaced one by one. Documents rejected by
+    /// the server are reported as failures; the others are still written.
+    /// Transient failures are retried.
+    async fn bulk_upsert(
+        &self,
+        collection: &Collection<Document>,
+        documents: Vec<(String, Document)>,
+        mut failures: Vec<BulkInsertFailure>,
+        retry: &RetryPolicy,
+    ) -> Result<BulkInsertResult> {
+        let write_errors = if documents.is_empty() {
+            Vec::new()
+        } else {
+            retry
+                .retry(|| async {
+                    match collection
+                        .insert_many(documents.iter().map(|(_, document)| document))
+                        .ordered(false)
+                        .await
+                    {
+                        Ok(_) => Ok(Vec::new()),
+                        Err(e) => match *e.kind {
+                            ErrorKind::InsertMany(InsertManyError {
+                                write_errors: Some(ref errors),
+                                write_concern_error: None,
+                                ..
+                            }) => Ok(errors
+                                .iter()
+                                .map(|error| (error.index, error.code, error.message.clone()))
+                                .collect()),
+                            _ => Err(mongodb_error(e)),
+                        },
+                    }
+                })
+                .await?
+        };
+
+        let mut success_count = documents.len() - write_errors.len();
+        let mut replaced = stream::iter(write_errors)
+            .map(|(index, code, message)| {
+                let (id, document) = &documents[index];
+                async move {
+                    let result = if code == DUPLICATE_KEY_CODE {
+                        retry
+                            .retry(|| async {
+                                collection
+                                    .replace_one(doc! { "_id": id.as_str() }, document)
+                                    .upsert(true)
+                                    .await
+                                    .map_err(mongodb_error)
+                            })
+                            .await
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    } else {
+                        Err(message)
+                    };
+                    (id, result)
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_REPLACES);
+
+        while let Some((id, result)) = replaced.next().await {
+            match result {
+                Ok(()) => success_count += 1,
+                Err(error) => failures.push(BulkInsertFailure {
+                    document_id: id.clone(),
+                    error: format!("Upsert failed: {error}"),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
+    /// Upsert transformed composition documents
+    async fn insert_documents(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<serde_json::Value>,
+        max_retries: usize,
+    ) -> Result<BulkInsertResult> {
+        let mut converted = Vec::new();
+        let mut failures = Vec::new();
+
+        for document in documents {
+            let doc_id = document
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            match keyed_document(&document) {
+                Ok(converted_doc) => converted.push(converted_doc),
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id: doc_id,
+                    error: format!("Failed to convert composition: {e}"),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        let collection = self.client.get_container_collection(template_id);
+        self.bulk_upsert(
+            &collection,
+            converted,
+            failures,
+            &self.retry.clone().with_max_retries(max_retries),
+        )
+        .await
+    }
+
+    /// Load a document of the control collection
+    async fn load_state<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>> {
+        let document = self
+            .client
+            .get_control_collection()
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(mongodb_error)?;
+
+        document
+            .map(|document| {
+                bson::from_document(document).map_err(|e| {
+                    AtlasError::Serialization(format!("Failed to deserialize {id}: {e}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Save a document of the control collection
+    async fn save_state<T: Serialize>(&self, id: &str, value: &T) -> Result<()> {
+        let (_, document) = keyed_document(&serde_json::to_value(value)?)?;
+        let collection = self.client.get_control_collection();
+
+        self.retry
+            .retry(|| async {
+                collection
+                    .replace_one(doc! { "_id": id }, document.clone())
+                    .upsert(true)
+                    .await
+                    .map(|_| ())
+                    .map_err(mongodb_error)
+            })
+            .await
+    }
+
+    /// Find documents of the control collection
+    async fn find_state<T: DeserializeOwned>(&self, filter: Document) -> Result<Vec<T>> {
+        let documents: Vec<Document> = self
+            .client
+            .get_control_collection()
+            .find(filter)
+            .await
+            .map_err(mongodb_error)?
+            .try_collect()
+            .await
+            .map_err(mongodb_error)?;
+
+        documents
+            .into_iter()
+            .map(|document| {
+                bson::from_document(document)
+                    .map_err(|e| AtlasError::Serialization(format!("Failed to deserialize: {e}")))
+            })
+            .collect()
+    }
+}
+
+/// Convert a JSON document with an `id` field into a BSON document keyed by `_id`
+///
+/// # Returns
+///
+/// Returns the document ID and the document.
+fn keyed_document(value: &serde_json::Value) -> Result<(String, Document)> {
+    let id = value
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| AtlasError::Serialization("Missing 'id' field".to_string()))?
+        .to_string();
+
+    let mut document = doc! { "_id": id.as_str() };
+    document
+        .extend(bson::to_document(value).map_err(|e| AtlasError::Serialization(e.to_string()))?);
+
+    Ok((id, document))
+}
+
+#[async_trait]
+impl DatabaseClient for MongoDbAdapter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.client.test_connection().await
+    }
+
+    async fn ensure_database_exists(&self) -> Result<()> {
+        self.client.ensure_database_exists().await
+    }
+
+    async fn ensure_container_exists(&self, template_id: &TemplateId) -> Result<()> {
+        self.client.ensure_container_exists(template_id).await
+    }
+
+    async fn ensure_control_container_exists(&self) -> Result<()> {
+        self.client.ensure_control_container_exists().await
+    }
+
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        self.client.ensure_ehr_container_exists().await
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would save EHR status to MongoDB"
+            );
+            return Ok(());
+        }
+
+        let (id, document) = keyed_document(&serde_json::to_value(CosmosEhr::from_domain(ehr))?)?;
+        let collection = self.client.get_ehr_collection();
+
+        self.retry
+            .retry(|| async {
+                collection
+                    .replace_one(doc! { "_id": id.as_str() }, document.clone())
+                    .upsert(true)
+                    .await
+                    .map(|_| ())
+                    .map_err(mongodb_error)
+            })
+            .await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status saved to MongoDB");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, _query_name: &str) -> Result<()> {
+        // No-op: MongoDB creates the collection on first write
+        Ok(())
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would save {} query rows to MongoDB",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let mut documents = Vec::new();
+        let mut failures = Vec::new();
+
+        for row in rows {
+            let document_id = row.id.clone();
+            let document = serde_json::to_value(CosmosQueryRow::new(query_name, row))
+                .map_err(AtlasError::from)
+                .and_then(|value| keyed_document(&value));
+
+            match document {
+                Ok(document) => documents.push(document),
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id,
+                    error: e.to_string(),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        let collection = self.client.get_query_collection(query_name);
+        self.bulk_upsert(&collection, documents, failures, &self.retry)
+            .await
+    }
+
+    async fn bulk_insert_json(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<serde_json::Value>,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would insert {} compositions into MongoDB",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn bulk_insert_compositions(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // Transform compositions using preserve_composition
+        let documents = compositions
+            .into_iter()
+            .map(|composition| preserve_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would insert {} compositions (preserved format) into MongoDB",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn bulk_insert_compositions_flattened(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // Transform compositions using flatten_composition
+        let documents = compositions
+            .into_iter()
+            .map(|composition| flatten_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would insert {} compositions (flattened format) into MongoDB",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn check_composition_exists(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &str,
+        composition_id: &str,
+    ) -> Result<bool> {
+        let count = self
+            .client
+            .get_container_collection(template_id)
+            .count_documents(doc! { "_id": composition_id, "ehr_id": ehr_id })
+            .limit(1)
+            .await
+            .map_err(mongodb_error)?;
+
+        Ok(count > 0)
+    }
+
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let documents: Vec<Document> = self
+            .client
+            .get_container_collection(template_id)
+            .find(doc! { "ehr_id": ehr_id.as_str(), "deleted_at": { "$exists": false } })
+            .projection(doc! { "_id": 1 })
+            .sort(doc! { "_id": 1 })
+            .await
+            .map_err(mongodb_error)?
+            .try_collect()
+            .await
+            .map_err(mongodb_error)?;
+
+        documents
+            .iter()
+            .map(|document| {
+                document
+                    .get_str("_id")
+                    .map(str::to_string)
+                    .map_err(|e| AtlasError::Serialization(e.to_string()))
+            })
+            .collect()
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in MongoDB",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let collection = self.client.get_container_collection(template_id);
+        let filter = doc! { "_id": { "$in": composition_ids }, "ehr_id": ehr_id.as_str() };
+
+        let affected = match policy {
+            DeletionPolicy::HardDelete => {
+                collection
+                    .delete_many(filter)
+                    .await
+                    .map_err(mongodb_error)?
+                    .deleted_count
+            }
+            _ => {
+                let mut filter = filter;
+                filter.insert("deleted_at", doc! { "$exists": false });
+                let deleted_at = bson::to_bson(&Utc::now())
+                    .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+
+                collection
+                    .update_many(filter, doc! { "$set": { "deleted_at": deleted_at } })
+                    .await
+                    .map_err(mongodb_error)?
+                    .modified_count
+            }
+        } as usize;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = affected,
+            policy = %policy,
+            "Applied deletion policy in MongoDB"
+        );
+
+        Ok(affected)
+    }
+
+    fn database_name(&self) -> &str {
+        self.client.database_name()
+    }
+}
+
+#[async_trait]
+impl StateStorage for MongoDbAdapter {
+    async fn load_watermark(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Option<Watermark>> {
+        self.load_state(&Watermark::generate_id(template_id, ehr_id))
+            .await
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark, dry_run: bool) -> Result<()> {
+        // If dry-run, skip actual write
+        if dry_run {
+            tracing::info!(
+                template_id = %watermark.template_id.as_str(),
+                ehr_id = %watermark.ehr_id.as_str(),
+                watermark_id = %watermark.id,
+                "DRY RUN: Would save watermark to MongoDB"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&watermark.id, watermark).await?;
+
+        tracing::debug!(
+            template_id = %watermark.template_id.as_str(),
+            ehr_id = %watermark.ehr_id.as_str(),
+            "Watermark saved to MongoDB"
+        );
+
+        Ok(())
+    }
+
+    async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
+        // Per-EHR watermarks are the only control documents with this field
+        let mut watermarks: Vec<Watermark> = self
+            .find_state(doc! { "last_exported_timestamp": { "$exists": true } })
+            .await?;
+
+        watermarks.sort_by(|a, b| {
+            (a.template_id.as_str(), a.ehr_id.as_str())
+                .cmp(&(b.template_id.as_str(), b.ehr_id.as_str()))
+        });
+        Ok(watermarks)
+    }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        self.load_state(&QueryWatermark::generate_id(query_name))
+            .await
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to MongoDB"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&watermark.id, watermark).await?;
+
+        tracing::debug!(query = %watermark.query_name, "Query watermark saved to MongoDB");
+
+        Ok(())
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        self.load_state(&DeadLetter::generate_id(composition_uid))
+            .await
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to MongoDB"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&dead_letter.id, dead_letter).await
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from MongoDB"
+            );
+            return Ok(());
+        }
+
+        self.client
+            .get_control_collection()
+            .delete_one(doc! { "_id": dead_letter.id.as_str() })
+            .await
+            .map_err(mongodb_error)?;
+
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut dead_letters: Vec<DeadLetter> = self
+            .find_state(doc! { "_id": { "$regex": "^dead_letter_" } })
+            .await?;
+
+        dead_letters.sort_by_key(|dead_letter| dead_letter.first_failed_at);
+        Ok(dead_letters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::watermark::WatermarkBuilder;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_keyed_document() {
+        let (id, document) = keyed_document(&json!({
+            "id": "c1::local::1",
+            "ehr_id": "7d44b88c-4199-4bad-97dc-d78268e01398",
+            "vital_signs_pulse_0_rate_magnitude": 72,
+            "atlas_metadata": {"export_mode": "full"},
+        }))
+        .unwrap();
+
+        assert_eq!(id, "c1::local::1");
+        assert_eq!(document.keys().next().unwrap(), "_id");
+        assert_eq!(document.get_str("_id").unwrap(), "c1::local::1");
+        assert_eq!(document.get_str("id").unwrap(), "c1::local::1");
+        assert_eq!(
+            document
+                .get_document("atlas_metadata")
+                .unwrap()
+                .get_str("export_mode")
+                .unwrap(),
+            "full"
+        );
+
+        assert!(keyed_document(&json!({"ehr_id": "e1"})).is_err());
+    }
+
+    #[test]
+    fn test_watermark_document_round_trip() {
+        let mut watermark = WatermarkBuilder::new(
+            TemplateId::from_str("vital_signs.v1").unwrap(),
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+        )
+        .build();
+        watermark.update_after_export(
+            CompositionUid::from_str("c1::local::1").unwrap(),
+            Utc::now(),
+        );
+
+        let (id, document) = keyed_document(&serde_json::to_value(&watermark).unwrap()).unwrap();
+        assert_eq!(id, watermark.id);
+
+        let loaded: Watermark = bson::from_document(document).unwrap();
+        assert_eq!(
+            loaded.last_exported_timestamp,
+            watermark.last_exported_timestamp
+        );
+        assert_eq!(loaded.compositions_exported_count, 1);
+    }
+
+    /// Run with `ATLAS_TEST_MONGODB_URI=mongodb://localhost:27017 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "requires a MongoDB server at ATLAS_TEST_MONGODB_URI"]
+    async fn test_upsert_and_watermarks_against_server() {
+        let uri = std::env::var("ATLAS_TEST_MONGODB_URI")
+            .expect("ATLAS_TEST_MONGODB_URI should point to a MongoDB server");
+        let config: crate::config::schema::MongoDbConfig = toml::from_str(&format!(
+            "connection_string = {uri:?}\ndatabase_name = \"atlas_test_{}\"",
+            uuid::Uuid::new_v4().simple()
+        ))
+        .unwrap();
+        let adapter = MongoDbAdapter::new(MongoDbClient::new(config).await.unwrap());
+        adapter.ensure_database_exists().await.unwrap();
+
+        let template_id = TemplateId::from_str("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+        adapter.ensure_container_exists(&template_id).await.unwrap();
+        adapter.ensure_control_container_exists().await.unwrap();
+
+        // A re-exported composition replaces the stored document
+        for magnitude in [37.0, 38.5] {
+            let result = adapter
+                .bulk_insert_json(
+                    &template_id,
+                    vec![
+                        json!({"id": "c1::local::1", "ehr_id": ehr_id.as_str(), "temp_magnitude": magnitude}),
+                        json!({"id": "c2::local::1", "ehr_id": ehr_id.as_str()}),
+                    ],
+                    3,
+                    false,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.success_count, 2);
+        }
+
+        let collection = adapter.client().get_container_collection(&template_id);
+        assert_eq!(collection.count_documents(doc! {}).await.unwrap(), 2);
+        let stored = collection
+            .find_one(doc! { "_id": "c1::local::1" })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.get_f64("temp_magnitude").unwrap(), 38.5);
+        assert_eq!(
+            adapter
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        let mut watermark = WatermarkBuilder::new(template_id.clone(), ehr_id.clone()).build();
+        watermark.update_after_export(
+            CompositionUid::from_str("c2::local::1").unwrap(),
+            Utc::now(),
+        );
+        watermark.mark_completed();
+        adapter.save_watermark(&watermark, false).await.unwrap();
+        adapter.save_watermark(&watermark, false).await.unwrap();
+
+        let loaded = adapter
+            .load_watermark(&template_id, &ehr_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            loaded.last_exported_composition_uid,
+            watermark.last_exported_composition_uid
+        );
+        assert!(loaded.is_completed());
+        assert_eq!(adapter.get_all_watermarks().await.unwrap().len(), 1);
+
+        adapter
+            .client()
+            .client()
+            .database(adapter.client().database_name())
+            .drop()
+            .await
+            .unwrap();
+    }
+}
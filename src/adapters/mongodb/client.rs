@@ -0,0 +1,201 @@
+//! MongoDB client implementation
+//!
+//! This module provides a client for MongoDB. Compositions are stored in a
+//! collection per template, named like the Cosmos DB containers.
+
+use crate::config::schema::MongoDbConfig;
+use crate::domain::ids::{container_name, TemplateId};
+use crate::domain::{AtlasError, Result};
+use mongodb::bson::{doc, Document};
+use mongodb::error::{ErrorKind, RETRYABLE_WRITE_ERROR, SYSTEM_OVERLOADED_ERROR};
+use mongodb::options::{ClientOptions, IndexOptions};
+use mongodb::{Client, Collection, Database, IndexModel};
+use secrecy::ExposeSecret;
+use std::time::Duration;
+
+/// MongoDB client
+#[derive(Clone)]
+pub struct MongoDbClient {
+    client: Client,
+    database: Database,
+    config: MongoDbConfig,
+}
+
+impl MongoDbClient {
+    /// Create a new MongoDB client
+    ///
+    /// The driver connects lazily; use [`test_connection`](Self::test_connection)
+    /// to check that the server is reachable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection string is invalid.
+    pub async fn new(config: MongoDbConfig) -> Result<Self> {
+        let mut options = ClientOptions::parse(config.connection_string.expose_secret().as_ref())
+            .await
+            .map_err(|e| {
+                AtlasError::Configuration(format!("Invalid MongoDB connection string: {e}"))
+            })?;
+        options.app_name = Some(format!("atlas/{}", env!("CARGO_PKG_VERSION")));
+        options.max_pool_size = Some(config.max_pool_size);
+        options.server_selection_timeout =
+            Some(Duration::from_secs(config.server_selection_timeout_seconds));
+
+        let client = Client::with_options(options).map_err(|e| {
+            AtlasError::Configuration(format!("Failed to create MongoDB client: {e}"))
+        })?;
+        let database = client.database(&config.database_name);
+
+        tracing::info!(database = %config.database_name, "Created MongoDB client");
+
+        Ok(Self {
+            client,
+            database,
+            config,
+        })
+    }
+
+    /// Test the connection to the server
+    pub async fn test_connection(&self) -> Result<()> {
+        self.database
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map_err(mongodb_error)?;
+
+        tracing::info!("MongoDB connection test successful");
+        Ok(())
+    }
+
+    /// Ensure the database exists
+    ///
+    /// MongoDB creates databases and collections on first write, so this only
+    /// logs the database in use.
+    pub async fn ensure_database_exists(&self) -> Result<()> {
+        tracing::info!(database = %self.config.database_name, "Using MongoDB database");
+        Ok(())
+    }
+
+    /// Ensure the indexes of the collection for a template exist
+    ///
+    /// Collection name format: `{prefix}_{template_id}`
+    ///
+    /// Documents are keyed by composition UID (`_id`), and indexed by `ehr_id`
+    /// for patient-based queries and by `ehr_id` and `time_committed` for
+    /// incremental reads.
+    pub async fn ensure_container_exists(&self, template_id: &TemplateId) -> Result<()> {
+        let collection_name = self.get_container_name(template_id);
+
+        self.get_collection(&collection_name)
+            .create_indexes([
+                index(doc! { "ehr_id": 1 }, "ehr_id"),
+                index(
+                    doc! { "ehr_id": 1, "time_committed": 1 },
+                    "ehr_id_time_committed",
+                ),
+            ])
+            .await
+            .map_err(mongodb_error)?;
+
+        tracing::debug!(collection = %collection_name, "Collection indexes ready");
+        Ok(())
+    }
+
+    /// Ensure the indexes of the control collection exist
+    pub async fn ensure_control_container_exists(&self) -> Result<()> {
+        // Watermarks and dead letters are looked up by `_id` only
+        tracing::debug!(collection = %self.config.control_container, "Control collection ready");
+        Ok(())
+    }
+
+    /// Ensure the indexes of the EHR collection exist
+    pub async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        self.get_ehr_collection()
+            .create_index(index(doc! { "ehr_id": 1 }, "ehr_id"))
+            .await
+            .map_err(mongodb_error)?;
+
+        tracing::debug!(collection = %self.config.ehr_container, "EHR collection indexes ready");
+        Ok(())
+    }
+
+    /// Get the collection name for a template
+    ///
+    /// Format: `{prefix}_{template_id}`
+    pub fn get_container_name(&self, template_id: &TemplateId) -> String {
+        template_id.to_container_name(&self.config.data_container_prefix)
+    }
+
+    /// Get the collection for a template
+    pub fn get_container_collection(&self, template_id: &TemplateId) -> Collection<Document> {
+        self.get_collection(&self.get_container_name(template_id))
+    }
+
+    /// Get the collection name for a named AQL query
+    ///
+    /// Format: `{prefix}_query_{query_name}`
+    pub fn get_query_collection_name(&self, query_name: &str) -> String {
+        container_name(
+            &self.config.data_container_prefix,
+            &format!("query_{query_name}"),
+        )
+    }
+
+    /// Get the collection for a named AQL query
+    pub fn get_query_collection(&self, query_name: &str) -> Collection<Document> {
+        self.get_collection(&self.get_query_collection_name(query_name))
+    }
+
+    /// Get the control collection
+    pub fn get_control_collection(&self) -> Collection<Document> {
+        self.get_collection(&self.config.control_container)
+    }
+
+    /// Get the EHR collection
+    pub fn get_ehr_collection(&self) -> Collection<Document> {
+        self.get_collection(&self.config.ehr_container)
+    }
+
+    /// Get a collection by name
+    pub fn get_collection(&self, name: &str) -> Collection<Document> {
+        self.database.collection(name)
+    }
+
+    /// Get the underlying driver client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Get the database name
+    pub fn database_name(&self) -> &str {
+        &self.config.database_name
+    }
+}
+
+/// Build a named ascending index
+fn index(keys: Document, name: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().name(name.to_string()).build())
+        .build()
+}
+
+/// Convert a MongoDB error into an Atlas error
+///
+/// Network failures and errors the server labels as retryable become
+/// connection errors, so that the retry policy retries them.
+pub(crate) fn mongodb_error(error: mongodb::error::Error) -> AtlasError {
+    let transient = matches!(
+        *error.kind,
+        ErrorKind::Io(_)
+            | ErrorKind::ServerSelection { .. }
+            | ErrorKind::ConnectionPoolCleared { .. }
+            | ErrorKind::DnsResolve { .. }
+    ) || error.contains_label(RETRYABLE_WRITE_ERROR)
+        || error.contains_label(SYSTEM_OVERLOADED_ERROR);
+
+    if transient {
+        AtlasError::Connection(format!("MongoDB error: {error}"))
+    } else {
+        AtlasError::Database(format!("MongoDB error: {error}"))
+    }
+}
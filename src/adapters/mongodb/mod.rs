@@ -0,0 +1,11 @@
+//! MongoDB database integration
+//!
+//! This module provides integration with MongoDB for storing openEHR
+//! compositions, with a collection per template and watermarks in a
+//! control collection.
+
+pub mod adapter;
+pub mod client;
+
+pub use adapter::MongoDbAdapter;
+pub use client::MongoDbClient;
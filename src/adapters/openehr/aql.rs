@@ -0,0 +1,947 @@
+//! AQL query building and paginated execution
+//!
+//! This module provides the AQL query builder and executor shared by the
+//! openEHR vendor implementations. Queries are parameterised: identifiers and
+//! timestamps are never interpolated into the query text but sent in the
+//! `query_parameters` field of the request body. Queries are sent to the
+//! openEHR REST `query/aql` endpoint and large result sets are retrieved page
+//! by page using the `offset` and `fetch` request parameters, so that no
+//! single request has to return the full result set. Rows can be streamed as
+//! plain value arrays or as records keyed by the result-set column names.
+
+use crate::adapters::openehr::models::{AqlColumn, AqlQueryRequest, AqlQueryResponse};
+use crate::adapters::openehr::status;
+use crate::domain::ids::{EhrId, TemplateId};
+use crate::domain::{AtlasError, OpenEhrError, Result};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+
+/// A parameterised AQL query
+///
+/// The query text references parameters as `$name`; their values are sent
+/// separately in the `query_parameters` field of the request body.
+///
+/// # Example
+///
+/// ```
+/// use atlas::adapters::openehr::aql::AqlQuery;
+///
+/// let query = AqlQuery::new("SELECT e/ehr_id/value FROM EHR e WHERE e/ehr_id/value = $ehr_id")
+///     .with_parameter("ehr_id", "7d44b88c-4199-4bad-97dc-d78268e01398");
+///
+/// assert_eq!(query.parameters().len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AqlQuery {
+    /// AQL query text
+    query: String,
+
+    /// Query parameter values, keyed by name (without the `$` prefix)
+    parameters: HashMap<String, serde_json::Value>,
+}
+
+impl AqlQuery {
+    /// Create a new query without parameters
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            parameters: HashMap::new(),
+        }
+    }
+
+    /// Query selecting the IDs of all EHRs on the server
+    ///
+    /// Rows are ordered by EHR ID, so that pages are stable.
+    pub fn ehr_ids() -> Self {
+        Self::new("SELECT e/ehr_id/value FROM EHR e ORDER BY e/ehr_id/value")
+    }
+
+    /// Query selecting the composition versions committed since a time
+    ///
+    /// The query spans all EHRs and returns one row per version, with the
+    /// columns aliased as `ehr_id`, `template_id`, `time_committed` and
+    /// `version_uid`, ordered by version UID so that pages are stable.
+    pub fn changed_compositions(since: DateTime<Utc>) -> Self {
+        Self::new(
+            "SELECT e/ehr_id/value AS ehr_id, \
+             c/archetype_details/template_id/value AS template_id, \
+             v/commit_audit/time_committed/value AS time_committed, \
+             v/uid/value AS version_uid \
+             FROM EHR e \
+             CONTAINS VERSION v \
+             CONTAINS COMPOSITION c \
+             WHERE v/commit_audit/time_committed/value >= $since \
+             ORDER BY v/uid/value",
+        )
+        .with_parameter("since", since.to_rfc3339())
+    }
+
+    /// Query selecting the deletions committed since a time
+    ///
+    /// A deleted composition has no content, so it is not matched by
+    /// [`changed_compositions`](Self::changed_compositions). This query
+    /// selects the versions whose commit audit change type is `deleted`
+    /// (openEHR terminology code 523), with the columns aliased as `ehr_id`,
+    /// `time_committed` and `version_uid`. The template of a deleted
+    /// composition is not known.
+    pub fn deleted_compositions(since: DateTime<Utc>) -> Self {
+        Self::new(
+            "SELECT e/ehr_id/value AS ehr_id, \
+             v/commit_audit/time_committed/value AS time_committed, \
+             v/uid/value AS version_uid \
+             FROM EHR e \
+             CONTAINS VERSION v \
+             WHERE v/commit_audit/change_type/defining_code/code_string = $change_type \
+             AND v/commit_audit/time_committed/value >= $since \
+             ORDER BY v/uid/value",
+        )
+        .with_parameter("change_type", "523")
+        .with_parameter("since", since.to_rfc3339())
+    }
+
+    /// Query selecting the deletions in an EHR committed since a time
+    ///
+    /// Same as [`deleted_compositions`](Self::deleted_compositions), for a
+    /// single EHR.
+    pub fn deleted_compositions_in_ehr(ehr_id: &EhrId, since: DateTime<Utc>) -> Self {
+        Self::new(
+            "SELECT e/ehr_id/value AS ehr_id, \
+             v/commit_audit/time_committed/value AS time_committed, \
+             v/uid/value AS version_uid \
+             FROM EHR e \
+             CONTAINS VERSION v \
+             WHERE e/ehr_id/value = $ehr_id \
+             AND v/commit_audit/change_type/defining_code/code_string = $change_type \
+             AND v/commit_audit/time_committed/value >= $since \
+             ORDER BY v/uid/value",
+        )
+        .with_parameter("ehr_id", ehr_id.as_str())
+        .with_parameter("change_type", "523")
+        .with_parameter("since", since.to_rfc3339())
+    }
+
+    /// Bind a value to the `$name` parameter
+    pub fn with_parameter(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.parameters.insert(name.into(), value.into());
+        self
+    }
+
+    /// Get the query text
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Get the bound parameters
+    pub fn parameters(&self) -> &HashMap<String, serde_json::Value> {
+        &self.parameters
+    }
+
+    /// Inline the bound parameters into the query text
+    ///
+    /// Only for servers that do not support `query_parameters`: string values
+    /// become quoted AQL literals with quotes and backslashes escaped, other
+    /// values are written as JSON. References to unbound parameters are kept.
+    pub fn inline_parameters(&self) -> Self {
+        let mut query = String::with_capacity(self.query.len());
+        let mut chars = self.query.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                query.push(c);
+                continue;
+            }
+
+            let mut name = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_ascii_alphanumeric() || next == '_' {
+                    name.push(next);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            match self.parameters.get(&name) {
+                Some(serde_json::Value::String(value)) => {
+                    query.push('\'');
+                    query.push_str(&value.replace('\\', "\\\\").replace('\'', "\\'"));
+                    query.push('\'');
+                }
+                Some(value) => query.push_str(&value.to_string()),
+                None => {
+                    query.push('$');
+                    query.push_str(&name);
+                }
+            }
+        }
+
+        Self::new(query)
+    }
+
+    /// Convert to a REST API request body
+    pub fn to_request(&self) -> AqlQueryRequest {
+        let request = AqlQueryRequest::new(self.query.clone());
+        if self.parameters.is_empty() {
+            request
+        } else {
+            request.with_parameters(self.parameters.clone())
+        }
+    }
+}
+
+impl fmt::Display for AqlQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.query)
+    }
+}
+
+/// Builder for the composition metadata query of an EHR and template
+///
+/// The built query returns one row per composition with the columns
+/// `uid`, `template_id`, `time_committed` and `name`, in that order, ordered
+/// by composition UID so that pages are stable.
+/// Time bounds apply to the VERSION commit audit time
+/// (`v/commit_audit/time_committed`) rather than the clinical context start
+/// time, so that back-dated compositions and amended versions are selected.
+///
+/// # Example
+///
+/// ```
+/// use atlas::adapters::openehr::aql::CompositionQueryBuilder;
+/// use atlas::domain::ids::{EhrId, TemplateId};
+/// use chrono::Utc;
+/// use std::str::FromStr;
+///
+/// let ehr_id = EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+/// let template_id = TemplateId::from_str("IDCR - Vital Signs.v1").unwrap();
+///
+/// let query = CompositionQueryBuilder::new(&ehr_id, &template_id)
+///     .since(Some(Utc::now()))
+///     .build();
+///
+/// assert!(query.query().contains("$since"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompositionQueryBuilder {
+    ehr_id: EhrId,
+    template_id: TemplateId,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl CompositionQueryBuilder {
+    /// Create a new builder for the given EHR and template
+    pub fn new(ehr_id: &EhrId, template_id: &TemplateId) -> Self {
+        Self {
+            ehr_id: ehr_id.clone(),
+            template_id: template_id.clone(),
+            since: None,
+            until: None,
+        }
+    }
+
+    /// Only select compositions committed at or after this time
+    pub fn since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// Only select compositions committed at or before this time
+    pub fn until(mut self, until: Option<DateTime<Utc>>) -> Self {
+        self.until = until;
+        self
+    }
+
+    /// Build the parameterised query
+    pub fn build(self) -> AqlQuery {
+        let mut query = String::from(
+            "SELECT c/uid/value, c/archetype_details/template_id/value, \
+             v/commit_audit/time_committed/value, c/name/value \
+             FROM EHR e[ehr_id/value=$ehr_id] \
+             CONTAINS VERSION v \
+             CONTAINS COMPOSITION c \
+             WHERE c/archetype_details/template_id/value = $template_id",
+        );
+
+        if self.since.is_some() {
+            query.push_str(" AND v/commit_audit/time_committed/value >= $since");
+        }
+
+        if self.until.is_some() {
+            query.push_str(" AND v/commit_audit/time_committed/value <= $until");
+        }
+
+        query.push_str(" ORDER BY c/uid/value");
+
+        let mut aql = AqlQuery::new(query)
+            .with_parameter("ehr_id", self.ehr_id.as_str())
+            .with_parameter("template_id", self.template_id.as_str());
+
+        if let Some(since) = self.since {
+            aql = aql.with_parameter("since", since.to_rfc3339());
+        }
+
+        if let Some(until) = self.until {
+            aql = aql.with_parameter("until", until.to_rfc3339());
+        }
+
+        aql
+    }
+}
+
+/// Shared AQL executor for openEHR vendors
+///
+/// The executor owns the HTTP details of running an AQL query. Vendors remain
+/// responsible for authentication and retries, which they apply around
+/// [`AqlExecutor::execute`] when streaming results with
+/// [`AqlExecutor::stream_rows`].
+///
+/// # Example
+///
+/// ```no_run
+/// use atlas::adapters::openehr::aql::{AqlExecutor, AqlQuery};
+/// use futures::TryStreamExt;
+///
+/// # async fn example() -> atlas::domain::Result<()> {
+/// let executor = AqlExecutor::new(reqwest::Client::new(), "https://ehrbase.example.com", 1000);
+///
+/// let rows: Vec<Vec<serde_json::Value>> = executor
+///     .stream_rows(AqlQuery::ehr_ids(), |request| {
+///         let executor = &executor;
+///         async move { executor.execute(&request, None).await }
+///     })
+///     .try_collect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AqlExecutor {
+    /// HTTP client for making requests
+    client: Client,
+
+    /// URL of the AQL query endpoint
+    endpoint: String,
+
+    /// Number of rows requested per page
+    page_size: usize,
+
+    /// Whether results are paged with `offset`/`fetch`
+    paging: bool,
+
+    /// Name of the header carrying the credentials
+    auth_header: String,
+}
+
+impl AqlExecutor {
+    /// Create a new AQL executor
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `base_url` - Base URL of the openEHR server
+    /// * `page_size` - Number of rows to request per page
+    pub fn new(client: Client, base_url: &str, page_size: usize) -> Self {
+        Self::with_endpoint(
+            client,
+            format!(
+                "{}/rest/openehr/v1/query/aql",
+                base_url.trim_end_matches('/')
+            ),
+            page_size,
+        )
+    }
+
+    /// Create a new AQL executor for a non-standard query endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `endpoint` - Full URL of the AQL query endpoint
+    /// * `page_size` - Number of rows to request per page
+    pub fn with_endpoint(client: Client, endpoint: String, page_size: usize) -> Self {
+        Self {
+            client,
+            endpoint,
+            page_size: page_size.max(1),
+            paging: true,
+            auth_header: "Authorization".to_string(),
+        }
+    }
+
+    /// Enable or disable `offset`/`fetch` paging
+    ///
+    /// Without paging, each query is sent as a single request and its response
+    /// is treated as the complete result set.
+    pub fn with_paging(mut self, paging: bool) -> Self {
+        self.paging = paging;
+        self
+    }
+
+    /// Send credentials in a header other than `Authorization`
+    pub fn with_auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = auth_header.into();
+        self
+    }
+
+    /// Get the number of rows requested per page
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Execute a single AQL request
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - AQL request, including any pagination parameters
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the server returns a non-success
+    /// status, or the response cannot be parsed.
+    pub async fn execute(
+        &self,
+        request: &AqlQueryRequest,
+        authorization: Option<String>,
+    ) -> Result<AqlQueryResponse> {
+        let mut http_request = self.client.post(&self.endpoint).json(request);
+
+        if let Some(auth) = authorization {
+            http_request = http_request.header(self.auth_header.as_str(), auth);
+        }
+
+        let resp = http_request
+            .send()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(e.to_string())))?;
+
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                format!("AQL query was rejected as unauthorized: {body}"),
+            )));
+        }
+
+        if !resp.status().is_success() {
+            return Err(status::response_error(resp, "AQL query failed").await);
+        }
+
+        resp.json::<AqlQueryResponse>()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string())))
+    }
+
+    /// Stream the rows of an AQL query, fetching one page at a time
+    ///
+    /// Pages are requested lazily as the stream is consumed. Paging stops when
+    /// a page returns fewer rows than the page size. If the server ignores the
+    /// pagination parameters and returns more rows than requested, that
+    /// response is treated as the complete result set. A server that ignores
+    /// them and returns exactly the page size is detected by a page that
+    /// repeats the previous one, or whose echoed offset differs from the one
+    /// requested; paging stops there and that page is dropped. With paging
+    /// disabled, the query is sent once without pagination parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Parameterised AQL query
+    /// * `execute_page` - Executes one paged request. Vendors use this to add
+    ///   authentication and retries around [`AqlExecutor::execute`].
+    pub fn stream_rows<'a, F, Fut>(
+        &self,
+        query: AqlQuery,
+        execute_page: F,
+    ) -> BoxStream<'a, Result<Vec<serde_json::Value>>>
+    where
+        F: Fn(AqlQueryRequest) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<AqlQueryResponse>> + Send + 'a,
+    {
+        self.stream_pages(query, execute_page)
+            .map_ok(|page| stream::iter(page.rows.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Stream the rows of an AQL query as records keyed by column name
+    ///
+    /// Pages are fetched as in [`AqlExecutor::stream_rows`]. Each row becomes a
+    /// JSON object whose keys are the column names (or aliases) of the result
+    /// set; columns without a name are keyed by their path, or by `#` and
+    /// their position if neither is returned.
+    pub fn stream_records<'a, F, Fut>(
+        &self,
+        query: AqlQuery,
+        execute_page: F,
+    ) -> BoxStream<'a, Result<AqlRecord>>
+    where
+        F: Fn(AqlQueryRequest) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<AqlQueryResponse>> + Send + 'a,
+    {
+        self.stream_pages(query, execute_page)
+            .map_ok(|page| {
+                let names = column_names(&page.columns);
+                stream::iter(page.rows.into_iter().map(move |row| {
+                    Ok(row
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, value)| {
+                            let name = names.get(i).cloned().unwrap_or_else(|| format!("#{i}"));
+                            (name, value)
+                        })
+                        .collect())
+                }))
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Stream the response pages of an AQL query
+    fn stream_pages<'a, F, Fut>(
+        &self,
+        query: AqlQuery,
+        execute_page: F,
+    ) -> BoxStream<'a, Result<AqlQueryResponse>>
+    where
+        F: Fn(AqlQueryRequest) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<AqlQueryResponse>> + Send + 'a,
+    {
+        let page_size = self.page_size;
+        let paging = self.paging;
+
+        // State: offset of the next page, and the rows of the previous page
+        stream::try_unfold(Some((0usize, None)), move |state| {
+            let page = state.map(|(offset, previous)| {
+                let request = if paging {
+                    tracing::debug!(offset = offset, fetch = page_size, "Fetching AQL page");
+                    query
+                        .to_request()
+                        .with_offset(offset as u32)
+                        .with_fetch(page_size as u32)
+                } else {
+                    query.to_request()
+                };
+                (offset, previous, execute_page(request))
+            });
+
+            async move {
+                let Some((offset, previous, page)) = page else {
+                    return Result::Ok(None);
+                };
+
+                let mut page = page.await?;
+                if offset > 0 && ignores_offset(&page, offset, previous.as_ref()) {
+                    tracing::warn!(
+                        offset = offset,
+                        "AQL server ignored the page offset, stopping pagination"
+                    );
+                    page.rows.clear();
+                    return Ok(Some((page, None)));
+                }
+
+                let next = if paging && page.rows.len() == page_size {
+                    Some((offset + page.rows.len(), Some(page.rows.clone())))
+                } else {
+                    None
+                };
+
+                Ok(Some((page, next)))
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Check whether a page was returned without applying the requested offset
+///
+/// The echoed offset is authoritative when the server returns one. Otherwise
+/// a page identical to the previous one means the server ignored the offset.
+fn ignores_offset(
+    page: &AqlQueryResponse,
+    offset: usize,
+    previous: Option<&Vec<Vec<serde_json::Value>>>,
+) -> bool {
+    match page.meta.offset {
+        Some(echoed) => echoed as usize != offset,
+        None => previous.is_some_and(|previous| !page.rows.is_empty() && *previous == page.rows),
+    }
+}
+
+/// A result-set row keyed by column name
+pub type AqlRecord = serde_json::Map<String, serde_json::Value>;
+
+/// Record keys for the columns of a result set
+fn column_names(columns: &[AqlColumn]) -> Vec<String> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            if !column.name.is_empty() {
+                column.name.clone()
+            } else if !column.path.is_empty() {
+                column.path.clone()
+            } else {
+                format!("#{i}")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::openehr::models::AqlQueryMeta;
+    use std::sync::{Arc, Mutex};
+
+    fn response_with_rows(start: usize, count: usize) -> AqlQueryResponse {
+        AqlQueryResponse {
+            meta: AqlQueryMeta::default(),
+            columns: vec![],
+            rows: (start..start + count)
+                .map(|i| vec![serde_json::json!(i)])
+                .collect(),
+        }
+    }
+
+    fn test_ids() -> (EhrId, TemplateId) {
+        use std::str::FromStr;
+
+        (
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+            TemplateId::from_str("IDCR - Vital Signs.v1").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_composition_query_has_no_interpolated_values() {
+        let (ehr_id, template_id) = test_ids();
+        let since = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let query = CompositionQueryBuilder::new(&ehr_id, &template_id)
+            .since(Some(since))
+            .build();
+
+        assert!(query.query().contains("e[ehr_id/value=$ehr_id]"));
+        assert!(query
+            .query()
+            .contains("c/archetype_details/template_id/value = $template_id"));
+        assert!(query
+            .query()
+            .contains("v/commit_audit/time_committed/value >= $since"));
+        assert!(query.query().ends_with(" ORDER BY c/uid/value"));
+        assert!(!query.query().contains("$until"));
+        assert!(!query.query().contains(ehr_id.as_str()));
+        assert!(!query.query().contains(template_id.as_str()));
+
+        assert_eq!(query.parameters().len(), 3);
+        assert_eq!(
+            query.parameters()["ehr_id"],
+            serde_json::json!("7d44b88c-4199-4bad-97dc-d78268e01398")
+        );
+        assert_eq!(
+            query.parameters()["template_id"],
+            serde_json::json!("IDCR - Vital Signs.v1")
+        );
+        assert_eq!(
+            query.parameters()["since"],
+            serde_json::json!("2024-01-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_composition_query_with_quote_in_template_id() {
+        use std::str::FromStr;
+
+        let (ehr_id, _) = test_ids();
+        let template_id = TemplateId::from_str("O'Brien's Template.v1").unwrap();
+
+        let query = CompositionQueryBuilder::new(&ehr_id, &template_id)
+            .until(Some(Utc::now()))
+            .build();
+
+        assert!(!query.query().contains('\''));
+        assert!(query.query().contains("<= $until"));
+        assert_eq!(
+            query.parameters()["template_id"],
+            serde_json::json!("O'Brien's Template.v1")
+        );
+    }
+
+    #[test]
+    fn test_aql_query_to_request() {
+        let request = AqlQuery::ehr_ids().to_request();
+        assert_eq!(
+            request.q,
+            "SELECT e/ehr_id/value FROM EHR e ORDER BY e/ehr_id/value"
+        );
+        assert!(request.query_parameters.is_none());
+
+        let (ehr_id, template_id) = test_ids();
+        let request = CompositionQueryBuilder::new(&ehr_id, &template_id)
+            .build()
+            .to_request();
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body["query_parameters"]["ehr_id"],
+            serde_json::json!("7d44b88c-4199-4bad-97dc-d78268e01398")
+        );
+    }
+
+    #[test]
+    fn test_aql_executor_endpoint() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com/", 0);
+
+        assert_eq!(
+            executor.endpoint,
+            "https://ehrbase.example.com/rest/openehr/v1/query/aql"
+        );
+        assert_eq!(executor.page_size(), 1);
+    }
+
+    #[test]
+    fn test_inline_parameters() {
+        let (ehr_id, _) = test_ids();
+        let query = AqlQuery::new(
+            "SELECT c FROM EHR e[ehr_id/value=$ehr_id] CONTAINS COMPOSITION c \
+             WHERE c/name/value = $name AND c/x = $count AND c/y = $unbound",
+        )
+        .with_parameter("ehr_id", ehr_id.as_str())
+        .with_parameter("name", "O'Brien\\s")
+        .with_parameter("count", 3);
+
+        let inlined = query.inline_parameters();
+
+        assert_eq!(
+            inlined.query(),
+            "SELECT c FROM EHR e[ehr_id/value='7d44b88c-4199-4bad-97dc-d78268e01398'] \
+             CONTAINS COMPOSITION c WHERE c/name/value = 'O\\'Brien\\\\s' AND c/x = 3 \
+             AND c/y = $unbound"
+        );
+        assert!(inlined.parameters().is_empty());
+        assert!(inlined.to_request().query_parameters.is_none());
+    }
+
+    #[test]
+    fn test_aql_executor_custom_endpoint() {
+        let executor = AqlExecutor::with_endpoint(
+            Client::new(),
+            "https://cdr.example.com/api/query".to_string(),
+            100,
+        )
+        .with_paging(false)
+        .with_auth_header("X-Api-Key");
+
+        assert_eq!(executor.endpoint, "https://cdr.example.com/api/query");
+        assert_eq!(executor.auth_header, "X-Api-Key");
+        assert!(!executor.paging);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_without_paging() {
+        let executor =
+            AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10).with_paging(false);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), {
+                let requests = requests.clone();
+                move |request: AqlQueryRequest| {
+                    requests
+                        .lock()
+                        .unwrap()
+                        .push((request.offset, request.fetch));
+                    async move { Ok(response_with_rows(0, 10)) }
+                }
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 10);
+        assert_eq!(*requests.lock().unwrap(), vec![(None, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_paginates() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let total_rows: usize = 25;
+
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), {
+                let requests = requests.clone();
+                move |request: AqlQueryRequest| {
+                    requests
+                        .lock()
+                        .unwrap()
+                        .push((request.offset, request.fetch));
+                    let offset = request.offset.unwrap() as usize;
+                    let fetch = request.fetch.unwrap() as usize;
+                    let count = fetch.min(total_rows.saturating_sub(offset));
+                    async move { Ok(response_with_rows(offset, count)) }
+                }
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 25);
+        assert_eq!(rows[24][0], serde_json::json!(24));
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                (Some(0), Some(10)),
+                (Some(10), Some(10)),
+                (Some(20), Some(10))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_exact_multiple_of_page_size() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+        let calls = Arc::new(Mutex::new(0));
+
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), {
+                let calls = calls.clone();
+                move |request: AqlQueryRequest| {
+                    *calls.lock().unwrap() += 1;
+                    let offset = request.offset.unwrap() as usize;
+                    let count = if offset < 20 { 10 } else { 0 };
+                    async move { Ok(response_with_rows(offset, count)) }
+                }
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 20);
+        assert_eq!(*calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_server_ignores_pagination() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+        let calls = Arc::new(Mutex::new(0));
+
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), {
+                let calls = calls.clone();
+                move |_request: AqlQueryRequest| {
+                    *calls.lock().unwrap() += 1;
+                    async move { Ok(response_with_rows(0, 42)) }
+                }
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 42);
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_server_ignores_offset() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+        let calls = Arc::new(Mutex::new(0));
+
+        // Without an echoed offset, the repeated page stops pagination
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), {
+                let calls = calls.clone();
+                move |_request: AqlQueryRequest| {
+                    *calls.lock().unwrap() += 1;
+                    async move { Ok(response_with_rows(0, 10)) }
+                }
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 10);
+        assert_eq!(*calls.lock().unwrap(), 2);
+
+        // An echoed offset that differs from the requested one stops it too
+        let rows: Vec<Vec<serde_json::Value>> = executor
+            .stream_rows(AqlQuery::ehr_ids(), |request: AqlQueryRequest| async move {
+                let offset = request.offset.unwrap() as usize;
+                let mut page = response_with_rows(offset * 2, 10);
+                page.meta.offset = Some(0);
+                Ok(page)
+            })
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 10);
+        assert_eq!(rows[9][0], serde_json::json!(9));
+    }
+
+    #[tokio::test]
+    async fn test_stream_records() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+
+        let records: Vec<AqlRecord> = executor
+            .stream_records(
+                AqlQuery::new("SELECT e/ehr_id/value AS ehr_id, o/data FROM EHR e"),
+                |_request: AqlQueryRequest| async move {
+                    Ok(AqlQueryResponse {
+                        meta: AqlQueryMeta::default(),
+                        columns: vec![
+                            AqlColumn {
+                                name: "ehr_id".to_string(),
+                                path: "/ehr_id/value".to_string(),
+                            },
+                            AqlColumn {
+                                name: String::new(),
+                                path: "/data".to_string(),
+                            },
+                        ],
+                        rows: vec![vec![
+                            serde_json::json!("ehr-1"),
+                            serde_json::json!(7.1),
+                            serde_json::json!("extra"),
+                        ]],
+                    })
+                },
+            )
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["ehr_id"], serde_json::json!("ehr-1"));
+        assert_eq!(records[0]["/data"], serde_json::json!(7.1));
+        assert_eq!(records[0]["#2"], serde_json::json!("extra"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_rows_propagates_errors() {
+        let executor = AqlExecutor::new(Client::new(), "https://ehrbase.example.com", 10);
+
+        let result: Result<Vec<Vec<serde_json::Value>>> = executor
+            .stream_rows(
+                AqlQuery::ehr_ids(),
+                |_request: AqlQueryRequest| async move {
+                    Err(AtlasError::OpenEhr(OpenEhrError::QueryFailed(
+                        "Mock query failed".to_string(),
+                    )))
+                },
+            )
+            .try_collect()
+            .await;
+
+        assert!(result.is_err());
+    }
+}
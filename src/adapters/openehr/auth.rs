@@ -0,0 +1,623 @@
+//! OIDC authentication for openEHR servers
+//!
+//! This module provides [`OidcAuthenticator`], which acquires OAuth2 access
+//! tokens from an OIDC token endpoint, caches them, and refreshes them before
+//! they expire. It supports the resource owner password grant (used by Better
+//! Platform) and the client credentials grant (typical for service accounts in
+//! Keycloak), and can be shared by any `OpenEhrVendor` implementation.
+
+use crate::config::{OidcGrantType, OpenEhrConfig, SecretString};
+use crate::domain::{AtlasError, OpenEhrError, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+/// Tokens are refreshed when they expire within this many seconds
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Token state for OIDC authentication
+#[derive(Debug, Clone, Default)]
+struct TokenState {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    token_expiry: Option<DateTime<Utc>>,
+}
+
+/// OIDC token request for password grant
+#[derive(Debug, Serialize)]
+struct OidcPasswordRequest {
+    grant_type: String,
+    client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    username: String,
+    password: String,
+}
+
+/// OIDC token request for client credentials grant
+#[derive(Debug, Serialize)]
+struct OidcClientCredentialsRequest {
+    grant_type: String,
+    client_id: String,
+    client_secret: String,
+}
+
+/// OIDC token request for refresh grant
+#[derive(Debug, Serialize)]
+struct OidcRefreshRequest {
+    grant_type: String,
+    client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    refresh_token: String,
+}
+
+/// OIDC token response
+#[derive(Debug, Deserialize)]
+struct OidcTokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    expires_in: u64,
+    #[serde(default)]
+    #[allow(dead_code)]
+    token_type: String,
+}
+
+/// OIDC access token provider
+///
+/// Tokens are cached in memory. [`ensure_valid`](Self::ensure_valid) refreshes
+/// the access token when it is about to expire, using the refresh token if the
+/// server issued one and falling back to a new token request otherwise.
+/// Concurrent refreshes are serialized so that only one token request is in
+/// flight at a time.
+///
+/// # Example
+///
+/// ```no_run
+/// use atlas::adapters::openehr::auth::OidcAuthenticator;
+/// use atlas::config::OpenEhrConfig;
+///
+/// # async fn example() -> atlas::domain::Result<()> {
+/// let config = OpenEhrConfig::default();
+/// let auth = OidcAuthenticator::from_config(reqwest::Client::new(), &config);
+///
+/// auth.authenticate().await?;
+/// let header = auth.authorization_header();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct OidcAuthenticator {
+    /// HTTP client used for token requests
+    client: Client,
+
+    /// OIDC token endpoint URL
+    token_url: Option<String>,
+
+    /// OIDC client ID
+    client_id: Option<String>,
+
+    /// OIDC client secret (confidential clients)
+    client_secret: Option<SecretString>,
+
+    /// OAuth2 grant used to obtain tokens
+    grant_type: OidcGrantType,
+
+    /// Username for the password grant
+    username: Option<String>,
+
+    /// Password for the password grant
+    password: Option<SecretString>,
+
+    /// Cached tokens
+    state: std::sync::Mutex<TokenState>,
+
+    /// Serializes token requests
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl OidcAuthenticator {
+    /// Create an authenticator from the openEHR configuration
+    ///
+    /// Missing settings are reported when a token is first requested.
+    pub fn from_config(client: Client, config: &OpenEhrConfig) -> Self {
+        Self {
+            client,
+            token_url: config.oidc_token_url.clone(),
+            client_id: config.client_id.clone(),
+            client_secret: config.client_secret.clone(),
+            grant_type: config.oidc_grant_type,
+            username: config.username.clone(),
+            password: config.password.clone(),
+            state: std::sync::Mutex::new(TokenState::default()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Acquire a new access token with the configured grant
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required settings are missing, the token endpoint
+    /// cannot be reached, or it rejects the request.
+    pub async fn authenticate(&self) -> Result<()> {
+        let _guard = self.refresh_lock.lock().await;
+        self.acquire_token().await
+    }
+
+    /// Ensure a valid access token is cached
+    ///
+    /// The token is refreshed if it expires within 60 seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no token has been acquired yet or the refresh fails.
+    pub async fn ensure_valid(&self) -> Result<()> {
+        if self.lock_state().access_token.is_none() {
+            return Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                "Not authenticated. Call authenticate() first.".to_string(),
+            )));
+        }
+
+        if !self.expires_soon() {
+            return Ok(());
+        }
+
+        let _guard = self.refresh_lock.lock().await;
+
+        // Another task may have refreshed the token while we were waiting
+        if self.expires_soon() {
+            self.refresh().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace a token the server rejected
+    ///
+    /// Called after a 401 response. If another task already replaced the
+    /// rejected token, the newer token is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a new token cannot be obtained.
+    pub async fn reauthenticate(&self, rejected: Option<&str>) -> Result<()> {
+        let _guard = self.refresh_lock.lock().await;
+
+        let current = self.authorization_header();
+        if current.is_some() && current.as_deref() != rejected {
+            return Ok(());
+        }
+
+        tracing::debug!("Access token rejected by server, requesting a new one");
+        self.refresh().await
+    }
+
+    /// Authorization header value with the cached Bearer token
+    pub fn authorization_header(&self) -> Option<String> {
+        self.lock_state()
+            .access_token
+            .as_ref()
+            .map(|token| format!("Bearer {token}"))
+    }
+
+    /// Whether a token is cached and has not expired
+    pub fn is_authenticated(&self) -> bool {
+        let state = self.lock_state();
+        match state.token_expiry {
+            Some(expiry) => state.access_token.is_some() && Utc::now() < expiry,
+            None => false,
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, TokenState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Whether the cached token expires within the refresh margin
+    fn expires_soon(&self) -> bool {
+        match self.lock_state().token_expiry {
+            Some(expiry) => {
+                let seconds_until_expiry = (expiry - Utc::now()).num_seconds();
+                if seconds_until_expiry < REFRESH_MARGIN_SECS {
+                    tracing::debug!(seconds_until_expiry, "Token expiring soon, refreshing");
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Refresh the access token, falling back to a new token request
+    async fn refresh(&self) -> Result<()> {
+        let refresh_token = self.lock_state().refresh_token.clone();
+
+        if let Some(refresh_token) = refresh_token {
+            match self.refresh_token(refresh_token).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!(error = %e, "OIDC token refresh failed, requesting a new token");
+                }
+            }
+        }
+
+        self.acquire_token().await
+    }
+
+    /// Acquire OIDC tokens using the configured grant
+    async fn acquire_token(&self) -> Result<()> {
+        let token_url = self.required(&self.token_url, "oidc_token_url")?;
+        let client_id = self.required(&self.client_id, "client_id")?;
+        let client_secret = self
+            .client_secret
+            .as_ref()
+            .map(|s| s.expose_secret().to_string());
+
+        tracing::debug!(
+            token_url = %token_url,
+            client_id = %client_id,
+            grant_type = %self.grant_type,
+            "Acquiring OIDC token"
+        );
+
+        let request = match self.grant_type {
+            OidcGrantType::Password => {
+                let username = self.required(&self.username, "username")?;
+                let password = self.password.as_ref().ok_or_else(|| {
+                    AtlasError::Configuration(
+                        "password is required for the OIDC password grant".to_string(),
+                    )
+                })?;
+
+                self.client.post(token_url).form(&OidcPasswordRequest {
+                    grant_type: "password".to_string(),
+                    client_id: client_id.to_string(),
+                    client_secret,
+                    username: username.to_string(),
+                    password: password.expose_secret().to_string(),
+                })
+            }
+            OidcGrantType::ClientCredentials => {
+                let client_secret = client_secret.ok_or_else(|| {
+                    AtlasError::Configuration(
+                        "client_secret is required for the OIDC client_credentials grant"
+                            .to_string(),
+                    )
+                })?;
+
+                self.client
+                    .post(token_url)
+                    .form(&OidcClientCredentialsRequest {
+                        grant_type: "client_credentials".to_string(),
+                        client_id: client_id.to_string(),
+                        client_secret,
+                    })
+            }
+        };
+
+        let token_response = Self::send(request, "OIDC token request").await?;
+        let has_refresh_token = token_response.refresh_token.is_some();
+        let token_expiry = self.store(token_response, true);
+
+        tracing::info!(
+            expires_at = %token_expiry,
+            has_refresh_token,
+            "Successfully acquired OIDC access token"
+        );
+
+        Ok(())
+    }
+
+    /// Refresh the access token using the refresh token
+    async fn refresh_token(&self, refresh_token: String) -> Result<()> {
+        let token_url = self.required(&self.token_url, "oidc_token_url")?;
+        let client_id = self.required(&self.client_id, "client_id")?;
+
+        tracing::debug!(
+            token_url = %token_url,
+            client_id = %client_id,
+            "Refreshing OIDC token"
+        );
+
+        let request = self.client.post(token_url).form(&OidcRefreshRequest {
+            grant_type: "refresh_token".to_string(),
+            client_id: client_id.to_string(),
+            client_secret: self
+                .client_secret
+                .as_ref()
+                .map(|s| s.expose_secret().to_string()),
+            refresh_token,
+        });
+
+        let token_response = Self::send(request, "OIDC token refresh").await?;
+        let token_expiry = self.store(token_response, false);
+
+        tracing::info!(
+            expires_at = %token_expiry,
+            "Successfully refreshed OIDC access token"
+        );
+
+        Ok(())
+    }
+
+    /// Send a token request and parse the response
+    async fn send(request: reqwest::RequestBuilder, what: &str) -> Result<OidcTokenResponse> {
+        let response = request.send().await.map_err(|e| {
+            AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(format!(
+                "{what} could not be sent: {e}"
+            )))
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                format!("{what} failed with status {status}: {error_text}"),
+            )));
+        }
+
+        response.json().await.map_err(|e| {
+            AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+                "Failed to parse {what} response: {e}"
+            )))
+        })
+    }
+
+    /// Cache the tokens of a token response and return the expiry
+    ///
+    /// A refresh response without a refresh token keeps the current one,
+    /// unless `replace_refresh_token` is set.
+    fn store(
+        &self,
+        token_response: OidcTokenResponse,
+        replace_refresh_token: bool,
+    ) -> DateTime<Utc> {
+        // Calculate token expiry (current time + expires_in seconds)
+        let token_expiry = Utc::now() + chrono::Duration::seconds(token_response.expires_in as i64);
+
+        let mut state = self.lock_state();
+        state.access_token = Some(token_response.access_token);
+        if replace_refresh_token || token_response.refresh_token.is_some() {
+            state.refresh_token = token_response.refresh_token;
+        }
+        state.token_expiry = Some(token_expiry);
+
+        token_expiry
+    }
+
+    fn required<'a>(&self, value: &'a Option<String>, field: &str) -> Result<&'a str> {
+        value.as_deref().filter(|v| !v.is_empty()).ok_or_else(|| {
+            AtlasError::Configuration(format!("{field} is required for OIDC authentication"))
+        })
+    }
+}
+
+/// Whether an error means the server rejected the credentials (HTTP 401)
+pub fn is_unauthorized(error: &AtlasError) -> bool {
+    matches!(
+        error,
+        AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::secret::SecretValue;
+    use secrecy::Secret;
+
+    fn create_test_authenticator(grant_type: OidcGrantType) -> OidcAuthenticator {
+        let config = OpenEhrConfig {
+            username: Some("test_user".to_string()),
+            password: Some(Secret::new(SecretValue::from("test_pass".to_string()))),
+            oidc_token_url: Some(
+                "https://keycloak.example.com/realms/ehr/protocol/openid-connect/token".to_string(),
+            ),
+            client_id: Some("atlas".to_string()),
+            client_secret: Some(Secret::new(SecretValue::from("s3cret".to_string()))),
+            oidc_grant_type: grant_type,
+            ..Default::default()
+        };
+
+        OidcAuthenticator::from_config(Client::new(), &config)
+    }
+
+    fn set_token(auth: &OidcAuthenticator, token: &str, expiry: Option<DateTime<Utc>>) {
+        let mut state = auth.lock_state();
+        state.access_token = Some(token.to_string());
+        state.token_expiry = expiry;
+    }
+
+    #[test]
+    fn test_authorization_header() {
+        let auth = create_test_authenticator(OidcGrantType::Password);
+        assert!(auth.authorization_header().is_none());
+
+        set_token(&auth, "test_access_token", None);
+        assert_eq!(
+            auth.authorization_header(),
+            Some("Bearer test_access_token".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_authenticated() {
+        let auth = create_test_authenticator(OidcGrantType::ClientCredentials);
+        assert!(!auth.is_authenticated());
+
+        set_token(
+            &auth,
+            "test_token",
+            Some(Utc::now() + chrono::Duration::hours(1)),
+        );
+        assert!(auth.is_authenticated());
+
+        set_token(
+            &auth,
+            "test_token",
+            Some(Utc::now() - chrono::Duration::hours(1)),
+        );
+        assert!(!auth.is_authenticated());
+    }
+
+    #[test]
+    fn test_expires_soon() {
+        let auth = create_test_authenticator(OidcGrantType::Password);
+
+        set_token(
+            &auth,
+            "test_token",
+            Some(Utc::now() + chrono::Duration::seconds(30)),
+        );
+        assert!(auth.expires_soon());
+
+        set_token(
+            &auth,
+            "test_token",
+            Some(Utc::now() + chrono::Duration::hours(1)),
+        );
+        assert!(!auth.expires_soon());
+    }
+
+    #[tokio::test]
+    async fn test_ensure_valid_without_token() {
+        let auth = create_test_authenticator(OidcGrantType::Password);
+
+        let err = auth.ensure_valid().await.unwrap_err();
+        assert!(is_unauthorized(&err));
+    }
+
+    #[tokio::test]
+    async fn test_reauthenticate_keeps_newer_token() {
+        let auth = create_test_authenticator(OidcGrantType::ClientCredentials);
+        set_token(
+            &auth,
+            "new_token",
+            Some(Utc::now() + chrono::Duration::hours(1)),
+        );
+
+        // The rejected token was already replaced, so no token request is made
+        auth.reauthenticate(Some("Bearer old_token")).await.unwrap();
+        assert_eq!(
+            auth.authorization_header(),
+            Some("Bearer new_token".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_acquire_token_requires_token_url() {
+        let config = OpenEhrConfig {
+            client_id: Some("atlas".to_string()),
+            ..Default::default()
+        };
+        let auth = OidcAuthenticator::from_config(Client::new(), &config);
+
+        let err = auth.authenticate().await.unwrap_err();
+        assert!(err.to_string().contains("oidc_token_url"));
+    }
+
+    #[test]
+    fn test_store_keeps_refresh_token_on_refresh() {
+        let auth = create_test_authenticator(OidcGrantType::Password);
+        auth.lock_state().refresh_token = Some("refresh".to_string());
+
+        auth.store(
+            OidcTokenResponse {
+                access_token: "access".to_string(),
+                refresh_token: None,
+                expires_in: 300,
+                token_type: "Bearer".to_string(),
+            },
+            false,
+        );
+
+        assert_eq!(auth.lock_state().refresh_token.as_deref(), Some("refresh"));
+        assert!(auth.is_authenticated());
+    }
+
+    #[test]
+    fn test_oidc_password_request_serialization() {
+        let request = OidcPasswordRequest {
+            grant_type: "password".to_string(),
+            client_id: "test_client".to_string(),
+            client_secret: None,
+            username: "test_user".to_string(),
+            password: "test_pass".to_string(),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["grant_type"], "password");
+        assert_eq!(json["client_id"], "test_client");
+        assert_eq!(json["username"], "test_user");
+        assert_eq!(json["password"], "test_pass");
+        assert!(json.get("client_secret").is_none());
+    }
+
+    #[test]
+    fn test_oidc_client_credentials_request_serialization() {
+        let request = OidcClientCredentialsRequest {
+            grant_type: "client_credentials".to_string(),
+            client_id: "test_client".to_string(),
+            client_secret: "test_secret".to_string(),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["grant_type"], "client_credentials");
+        assert_eq!(json["client_id"], "test_client");
+        assert_eq!(json["client_secret"], "test_secret");
+    }
+
+    #[test]
+    fn test_oidc_refresh_request_serialization() {
+        let request = OidcRefreshRequest {
+            grant_type: "refresh_token".to_string(),
+            client_id: "test_client".to_string(),
+            client_secret: Some("test_secret".to_string()),
+            refresh_token: "test_refresh_token".to_string(),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["grant_type"], "refresh_token");
+        assert_eq!(json["client_id"], "test_client");
+        assert_eq!(json["client_secret"], "test_secret");
+        assert_eq!(json["refresh_token"], "test_refresh_token");
+    }
+
+    #[test]
+    fn test_oidc_token_response_deserialization() {
+        let json = serde_json::json!({
+            "access_token": "test_access_token",
+            "refresh_token": "test_refresh_token",
+            "expires_in": 3600,
+            "token_type": "Bearer"
+        });
+
+        let response: OidcTokenResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(response.access_token, "test_access_token");
+        assert_eq!(
+            response.refresh_token,
+            Some("test_refresh_token".to_string())
+        );
+        assert_eq!(response.expires_in, 3600);
+        assert_eq!(response.token_type, "Bearer");
+    }
+
+    #[test]
+    fn test_oidc_token_response_deserialization_without_refresh_token() {
+        let json = serde_json::json!({
+            "access_token": "test_access_token",
+            "expires_in": 3600
+        });
+
+        let response: OidcTokenResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(response.access_token, "test_access_token");
+        assert!(response.refresh_token.is_none());
+        assert_eq!(response.expires_in, 3600);
+    }
+}
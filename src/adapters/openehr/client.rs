@@ -7,7 +7,7 @@ use crate::config::OpenEhrConfig;
 use crate::domain::{AtlasError, Result};
 use std::sync::Arc;
 
-use super::vendor::{BetterVendor, EhrBaseVendor, OpenEhrVendor};
+use super::vendor::{BetterVendor, EhrBaseVendor, GenericVendor, OpenEhrVendor};
 
 /// openEHR client that wraps a vendor implementation
 ///
@@ -50,18 +50,23 @@ impl OpenEhrClient {
 
         let vendor: Arc<dyn OpenEhrVendor> = match vendor_type.as_str() {
             "ehrbase" => {
-                let mut vendor = EhrBaseVendor::new(config);
+                let mut vendor = EhrBaseVendor::try_new(config)?;
                 vendor.authenticate().await?;
                 Arc::new(vendor)
             }
             "better" => {
-                let mut vendor = BetterVendor::new(config);
+                let mut vendor = BetterVendor::try_new(config)?;
+                vendor.authenticate().await?;
+                Arc::new(vendor)
+            }
+            "generic" => {
+                let mut vendor = GenericVendor::try_new(config)?;
                 vendor.authenticate().await?;
                 Arc::new(vendor)
             }
             _ => {
                 return Err(AtlasError::Configuration(format!(
-                    "Unsupported openEHR vendor: {vendor_type}. Supported vendors: ehrbase, better"
+                    "Unsupported openEHR vendor: {vendor_type}. Supported vendors: ehrbase, better, generic"
                 )))
             }
         };
@@ -69,6 +74,13 @@ impl OpenEhrClient {
         Ok(Self { vendor })
     }
 
+    /// Create a client from an existing vendor implementation
+    ///
+    /// The vendor is expected to be authenticated already.
+    pub fn from_vendor(vendor: Arc<dyn OpenEhrVendor>) -> Self {
+        Self { vendor }
+    }
+
     /// Get a reference to the underlying vendor implementation
     pub fn vendor(&self) -> &Arc<dyn OpenEhrVendor> {
         &self.vendor
@@ -154,6 +166,18 @@ mod tests {
         assert_eq!(client.base_url(), "http://localhost:8080/ehrbase");
     }
 
+    #[tokio::test]
+    async fn test_client_creation_with_generic_vendor() {
+        let config = OpenEhrConfig {
+            vendor_type: "Generic".to_string(),
+            ..Default::default()
+        };
+
+        let client = OpenEhrClient::new(config).await.unwrap();
+        assert!(!client.is_authenticated());
+        assert_eq!(client.base_url(), "http://localhost:8080/ehrbase");
+    }
+
     #[tokio::test]
     async fn test_client_creation_with_unsupported_vendor() {
         let config = OpenEhrConfig {
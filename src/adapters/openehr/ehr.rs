@@ -0,0 +1,285 @@
+//! EHR and EHR_STATUS resources
+//!
+//! This module provides access to the openEHR REST `ehr` and `ehr_status`
+//! resources shared by the vendor implementations. The EHR resource carries
+//! the system ID and creation time, the EHR_STATUS the subject reference and
+//! the queryable/modifiable flags.
+
+use super::status;
+use super::versions::DataValue;
+use crate::domain::ids::EhrId;
+use crate::domain::{AtlasError, Ehr, EhrStatus, OpenEhrError, PartyRef, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Subset of an EHR response needed for the EHR metadata
+#[derive(Debug, Deserialize)]
+struct EhrResponse {
+    system_id: Option<DataValue>,
+    time_created: DataValue,
+}
+
+/// EHR_STATUS response (canonical JSON)
+#[derive(Debug, Deserialize)]
+struct EhrStatusResponse {
+    uid: Option<DataValue>,
+    subject: Option<PartySelf>,
+    #[serde(default = "default_true")]
+    is_queryable: bool,
+    #[serde(default = "default_true")]
+    is_modifiable: bool,
+    other_details: Option<serde_json::Value>,
+}
+
+/// PARTY_SELF of an EHR_STATUS; the external reference is optional
+#[derive(Debug, Deserialize)]
+struct PartySelf {
+    external_ref: Option<PartyRefResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PartyRefResponse {
+    id: ObjectIdResponse,
+    namespace: Option<String>,
+    #[serde(rename = "type")]
+    party_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectIdResponse {
+    value: String,
+    scheme: Option<String>,
+}
+
+impl From<EhrStatusResponse> for EhrStatus {
+    fn from(response: EhrStatusResponse) -> Self {
+        Self {
+            uid: response.uid.map(|uid| uid.value),
+            subject: response
+                .subject
+                .and_then(|subject| subject.external_ref)
+                .map(|external_ref| PartyRef {
+                    id: external_ref.id.value,
+                    scheme: external_ref.id.scheme,
+                    namespace: external_ref.namespace,
+                    party_type: external_ref.party_type,
+                }),
+            is_queryable: response.is_queryable,
+            is_modifiable: response.is_modifiable,
+            other_details: response.other_details,
+        }
+    }
+}
+
+/// Shared client for the EHR and EHR_STATUS REST resources
+///
+/// Like [`VersionHistoryClient`](super::versions::VersionHistoryClient), this
+/// only owns the HTTP details; vendors apply authentication and retries
+/// around each call.
+#[derive(Debug, Clone)]
+pub struct EhrClient {
+    /// HTTP client for making requests
+    client: Client,
+
+    /// Base URL of the openEHR REST API
+    endpoint: String,
+
+    /// Name of the header carrying the credentials
+    auth_header: String,
+}
+
+impl EhrClient {
+    /// Create a new EHR client
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `base_url` - Base URL of the openEHR server
+    pub fn new(client: Client, base_url: &str) -> Self {
+        Self::with_endpoint(
+            client,
+            format!("{}/rest/openehr/v1", base_url.trim_end_matches('/')),
+        )
+    }
+
+    /// Create a new EHR client for a non-standard REST API path
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `endpoint` - Full URL of the openEHR REST API
+    pub fn with_endpoint(client: Client, endpoint: String) -> Self {
+        Self {
+            client,
+            endpoint,
+            auth_header: "Authorization".to_string(),
+        }
+    }
+
+    /// Send credentials in a header other than `Authorization`
+    pub fn with_auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = auth_header.into();
+        self
+    }
+
+    /// Get an EHR with its current EHR_STATUS
+    ///
+    /// # Arguments
+    ///
+    /// * `ehr_id` - EHR to retrieve
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns `EhrNotFound` if the EHR does not exist, or an error if a
+    /// request fails or a response cannot be parsed.
+    pub async fn fetch(&self, ehr_id: &EhrId, authorization: Option<String>) -> Result<Ehr> {
+        let ehr: EhrResponse = self
+            .get_json(ehr_id, &[ehr_id.as_str()], authorization.clone())
+            .await?;
+        let status: EhrStatusResponse = self
+            .get_json(ehr_id, &[ehr_id.as_str(), "ehr_status"], authorization)
+            .await?;
+
+        let time_created = DateTime::parse_from_rfc3339(&ehr.time_created.value)
+            .map_err(|e| {
+                AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+                    "Invalid EHR time_created '{}': {e}",
+                    ehr.time_created.value
+                )))
+            })?
+            .with_timezone(&Utc);
+
+        Ok(Ehr {
+            id: ehr_id.clone(),
+            time_created,
+            system_id: ehr.system_id.map(|system_id| system_id.value),
+            status: Some(status.into()),
+        })
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        ehr_id: &EhrId,
+        segments: &[&str],
+        authorization: Option<String>,
+    ) -> Result<T> {
+        let url = self.ehr_url(segments)?;
+
+        tracing::debug!(url = %url, "Fetching EHR resource");
+
+        let mut request = self.client.get(url).header("Accept", "application/json");
+
+        if let Some(auth) = authorization {
+            request = request.header(self.auth_header.as_str(), auth);
+        }
+
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(e.to_string())))?;
+
+        match resp.status() {
+            StatusCode::OK => resp
+                .json()
+                .await
+                .map_err(|e| AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string()))),
+            StatusCode::NOT_FOUND => Err(AtlasError::OpenEhr(OpenEhrError::EhrNotFound(
+                ehr_id.to_string(),
+            ))),
+            StatusCode::UNAUTHORIZED => {
+                let body = resp.text().await.unwrap_or_default();
+                Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                    format!("EHR request was rejected as unauthorized: {body}"),
+                )))
+            }
+            _ => Err(status::response_error(resp, "Failed to fetch EHR").await),
+        }
+    }
+
+    /// URL below the EHR resource, with each segment percent-encoded
+    fn ehr_url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.endpoint).map_err(|e| {
+            AtlasError::Configuration(format!("Invalid openEHR REST URL '{}': {e}", self.endpoint))
+        })?;
+
+        url.path_segments_mut()
+            .map_err(|_| {
+                AtlasError::Configuration(format!("Invalid openEHR REST URL '{}'", self.endpoint))
+            })?
+            .pop_if_empty()
+            .push("ehr")
+            .extend(segments);
+
+        Ok(url)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ehr_status_from_canonical_json() {
+        let json = serde_json::json!({
+            "_type": "EHR_STATUS",
+            "archetype_node_id": "openEHR-EHR-EHR_STATUS.generic.v1",
+            "name": {"value": "EHR Status"},
+            "uid": {"_type": "OBJECT_VERSION_ID", "value": "8849182c-82ad-4088-a07f-48ead4180515::local.ehrbase.org::1"},
+            "subject": {
+                "_type": "PARTY_SELF",
+                "external_ref": {
+                    "id": {"_type": "GENERIC_ID", "value": "9999999000", "scheme": "uk.nhs.nhs_number"},
+                    "namespace": "EHR.NETWORK",
+                    "type": "PERSON"
+                }
+            },
+            "is_queryable": true,
+            "is_modifiable": false,
+            "other_details": {"_type": "ITEM_TREE", "items": []}
+        });
+
+        let status: EhrStatus = serde_json::from_value::<EhrStatusResponse>(json)
+            .unwrap()
+            .into();
+
+        let subject = status.subject.unwrap();
+        assert_eq!(subject.id, "9999999000");
+        assert_eq!(subject.scheme.as_deref(), Some("uk.nhs.nhs_number"));
+        assert_eq!(subject.namespace.as_deref(), Some("EHR.NETWORK"));
+        assert_eq!(subject.party_type.as_deref(), Some("PERSON"));
+        assert!(status.is_queryable);
+        assert!(!status.is_modifiable);
+        assert!(status.other_details.is_some());
+    }
+
+    #[test]
+    fn test_ehr_status_without_external_ref() {
+        let json = serde_json::json!({"subject": {"_type": "PARTY_SELF"}});
+
+        let status: EhrStatus = serde_json::from_value::<EhrStatusResponse>(json)
+            .unwrap()
+            .into();
+
+        assert!(status.subject.is_none());
+        assert!(status.is_queryable);
+        assert!(status.is_modifiable);
+    }
+
+    #[test]
+    fn test_ehr_url() {
+        let client = EhrClient::new(Client::new(), "https://ehrbase.example.com/ehrbase");
+        let url = client.ehr_url(&["ehr-123", "ehr_status"]).unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "https://ehrbase.example.com/ehrbase/rest/openehr/v1/ehr/ehr-123/ehr_status"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Composition content formats on the openEHR REST API
+//!
+//! This module maps the configured [`ContentFormat`] onto composition requests
+//! and reads response bodies accordingly. Web template formats (FLAT and
+//! STRUCTURED) are selected with the `format` query parameter, canonical
+//! formats with the `Accept` header. Canonical XML is kept as a JSON string so
+//! that all formats share the `Composition::content` field.
+
+use crate::domain::{AtlasError, ContentFormat, OpenEhrError, Result};
+use reqwest::Response;
+
+/// Value of the `format` query parameter for a content format, if any
+pub fn format_parameter(format: ContentFormat) -> Option<&'static str> {
+    match format {
+        ContentFormat::Flat => Some("FLAT"),
+        ContentFormat::Structured => Some("STRUCTURED"),
+        ContentFormat::CanonicalJson | ContentFormat::CanonicalXml => None,
+    }
+}
+
+/// `Accept` header value for a content format
+pub fn accept_header(format: ContentFormat) -> &'static str {
+    match format {
+        ContentFormat::CanonicalXml => "application/xml",
+        _ => "application/json",
+    }
+}
+
+/// Read a composition response body in the given format
+///
+/// # Errors
+///
+/// Returns an error if the body cannot be read or is not valid JSON for a
+/// JSON format.
+pub async fn read_content(resp: Response, format: ContentFormat) -> Result<serde_json::Value> {
+    let result = match format {
+        ContentFormat::CanonicalXml => resp.text().await.map(serde_json::Value::String),
+        _ => resp.json::<serde_json::Value>().await,
+    };
+
+    result.map_err(|e| AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_request_mapping() {
+        assert_eq!(format_parameter(ContentFormat::Flat), Some("FLAT"));
+        assert_eq!(
+            format_parameter(ContentFormat::Structured),
+            Some("STRUCTURED")
+        );
+        assert_eq!(format_parameter(ContentFormat::CanonicalJson), None);
+        assert_eq!(format_parameter(ContentFormat::CanonicalXml), None);
+
+        assert_eq!(accept_header(ContentFormat::Flat), "application/json");
+        assert_eq!(
+            accept_header(ContentFormat::CanonicalJson),
+            "application/json"
+        );
+        assert_eq!(
+            accept_header(ContentFormat::CanonicalXml),
+            "application/xml"
+        );
+    }
+}
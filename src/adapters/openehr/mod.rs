@@ -3,10 +3,24 @@
 //! This module provides the integration with openEHR servers, including
 //! vendor-specific implementations, client factory, and API models.
 
+pub mod aql;
+pub mod auth;
 pub mod client;
+pub mod ehr;
+pub mod format;
 pub mod models;
+pub mod rate_limit;
+pub mod status;
+pub mod templates;
+pub mod tls;
 pub mod vendor;
+pub mod versions;
 
+pub use aql::AqlExecutor;
+pub use auth::OidcAuthenticator;
 pub use client::OpenEhrClient;
+pub use ehr::EhrClient;
 pub use models::{AqlQueryRequest, AqlQueryResponse, FlatComposition};
+pub use templates::{TemplateCache, TemplateClient, TemplateFilter, TemplateService};
 pub use vendor::{CompositionMetadata, EhrBaseVendor, OpenEhrVendor};
+pub use versions::VersionHistoryClient;
@@ -134,6 +134,7 @@ impl AqlQueryRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AqlQueryResponse {
     /// Query metadata
+    #[serde(default)]
     pub meta: AqlQueryMeta,
 
     /// Column definitions
@@ -146,7 +147,7 @@ pub struct AqlQueryResponse {
 }
 
 /// AQL query metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AqlQueryMeta {
     /// Schema version
     #[serde(rename = "_schema_version")]
@@ -159,15 +160,21 @@ pub struct AqlQueryMeta {
     /// Executed AQL query
     #[serde(rename = "_executed_aql")]
     pub executed_aql: Option<String>,
+
+    /// Offset of the first row, if the server echoes the paging parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
 }
 
 /// AQL column definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AqlColumn {
-    /// Column name
+    /// Column name (the alias, if the query declares one)
+    #[serde(default)]
     pub name: String,
 
     /// Column path
+    #[serde(default)]
     pub path: String,
 }
 
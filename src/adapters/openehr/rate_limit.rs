@@ -0,0 +1,383 @@
+//! Client-side rate limiting of openEHR requests
+//!
+//! Every HTTP request a vendor makes to the openEHR server first acquires a
+//! permit from the vendor's [`RateLimiter`]. The limiter combines a token
+//! bucket (requests per second, with a burst of one second's worth of
+//! requests) with a cap on the number of requests in flight. Both limits can
+//! be lowered for time-of-day windows, e.g. during clinic hours.
+//!
+//! With `adaptive` enabled, a throttled (429) or unavailable (503) response
+//! halves the effective limits, and each successful request restores them by
+//! 1% of the configured value. The limiter never raises the limits above the
+//! configured ones and has no effect on limits that are unlimited.
+
+use crate::config::schema::RateLimitConfig;
+use crate::domain::{AtlasError, OpenEhrError, Result};
+use chrono::{Local, NaiveTime};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Lowest fraction of the configured limits adaptive backoff goes down to
+const MIN_ADAPTIVE_FACTOR: f64 = 0.1;
+
+/// Fraction of the configured limits restored by each successful request
+const ADAPTIVE_RECOVERY: f64 = 0.01;
+
+/// Longest time to wait for a request to finish before checking the limits again
+///
+/// The limits change when a time-of-day window starts or ends, so a waiting
+/// request re-checks them periodically rather than only on release.
+const MAX_IN_FLIGHT_WAIT: Duration = Duration::from_secs(1);
+
+/// Limits in effect at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Maximum requests per second (0 = unlimited)
+    pub requests_per_second: f64,
+
+    /// Maximum number of concurrent requests (0 = unlimited)
+    pub max_in_flight: usize,
+}
+
+impl Limits {
+    /// Limits configured for a time of day
+    ///
+    /// The first window containing the time applies; limits it doesn't set
+    /// are taken from the base configuration.
+    pub fn at(config: &RateLimitConfig, time: NaiveTime) -> Self {
+        let base = Self {
+            requests_per_second: config.requests_per_second,
+            max_in_flight: config.max_in_flight,
+        };
+
+        config
+            .windows
+            .iter()
+            .find(|window| window.contains(time).unwrap_or(false))
+            .map(|window| Self {
+                requests_per_second: window
+                    .requests_per_second
+                    .unwrap_or(base.requests_per_second),
+                max_in_flight: window.max_in_flight.unwrap_or(base.max_in_flight),
+            })
+            .unwrap_or(base)
+    }
+
+    /// Whether no limit applies
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_second <= 0.0 && self.max_in_flight == 0
+    }
+
+    /// Limits scaled down by the adaptive factor
+    fn scaled(self, factor: f64) -> Self {
+        Self {
+            requests_per_second: self.requests_per_second * factor,
+            max_in_flight: if self.max_in_flight == 0 {
+                0
+            } else {
+                ((self.max_in_flight as f64 * factor).ceil() as usize).max(1)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    /// Tokens available in the bucket
+    tokens: f64,
+
+    /// Time the bucket was last refilled
+    refilled_at: Instant,
+
+    /// Requests currently in flight
+    in_flight: usize,
+
+    /// Fraction of the configured limits in effect (adaptive backoff)
+    factor: f64,
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: RateLimitConfig,
+    state: Mutex<State>,
+    released: Notify,
+}
+
+/// Token-bucket and in-flight limiter for openEHR requests
+///
+/// Cloning the limiter shares its state.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    shared: Arc<Shared>,
+}
+
+/// Permit for one request, released when dropped
+#[derive(Debug)]
+pub struct RateLimitPermit {
+    shared: Option<Arc<Shared>>,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            let mut state = shared.state.lock().expect("rate limiter lock poisoned");
+            state.in_flight = state.in_flight.saturating_sub(1);
+            drop(state);
+            shared.released.notify_waiters();
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Create a rate limiter from the `[openehr.rate_limit]` configuration
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                state: Mutex::new(State {
+                    tokens: 0.0,
+                    refilled_at: Instant::now(),
+                    in_flight: 0,
+                    factor: 1.0,
+                }),
+                released: Notify::new(),
+            }),
+        }
+    }
+
+    /// Limits in effect now, including adaptive backoff
+    pub fn current_limits(&self) -> Limits {
+        let factor = self.lock().factor;
+        self.limits_now().scaled(factor)
+    }
+
+    /// Wait until a request may be sent
+    ///
+    /// The returned permit counts as in flight until it is dropped.
+    pub async fn acquire(&self) -> RateLimitPermit {
+        loop {
+            let released = self.shared.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            let wait = {
+                let limits = self.limits_now();
+                let mut state = self.lock();
+
+                if limits.is_unlimited() {
+                    state.in_flight += 1;
+                    return self.permit();
+                }
+
+                let limits = limits.scaled(state.factor);
+                refill(&mut state, limits.requests_per_second);
+
+                if limits.max_in_flight > 0 && state.in_flight >= limits.max_in_flight {
+                    None
+                } else if limits.requests_per_second > 0.0 && state.tokens < 1.0 {
+                    Some(Duration::from_secs_f64(
+                        (1.0 - state.tokens) / limits.requests_per_second,
+                    ))
+                } else {
+                    if limits.requests_per_second > 0.0 {
+                        state.tokens -= 1.0;
+                    }
+                    state.in_flight += 1;
+                    return self.permit();
+                }
+            };
+
+            match wait {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => {
+                    let _ = tokio::time::timeout(MAX_IN_FLIGHT_WAIT, released).await;
+                }
+            }
+        }
+    }
+
+    /// Send a request once a permit is available, recording its outcome
+    pub async fn run<T, Fut>(&self, request: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let _permit = self.acquire().await;
+        let result = request.await;
+        self.record(&result);
+        result
+    }
+
+    /// Record the outcome of a request for adaptive backoff
+    pub fn record<T>(&self, result: &Result<T>) {
+        if !self.shared.config.adaptive {
+            return;
+        }
+
+        let mut state = self.lock();
+        match result {
+            Err(e) if is_overloaded(e) => {
+                state.factor = (state.factor / 2.0).max(MIN_ADAPTIVE_FACTOR);
+                tracing::warn!(
+                    factor = state.factor,
+                    error = %e,
+                    "openEHR server is overloaded, lowering request rate"
+                );
+            }
+            Ok(_) if state.factor < 1.0 => {
+                state.factor = (state.factor + ADAPTIVE_RECOVERY).min(1.0);
+            }
+            _ => {}
+        }
+    }
+
+    fn limits_now(&self) -> Limits {
+        Limits::at(&self.shared.config, Local::now().time())
+    }
+
+    fn permit(&self) -> RateLimitPermit {
+        RateLimitPermit {
+            shared: Some(self.shared.clone()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.shared
+            .state
+            .lock()
+            .expect("rate limiter lock poisoned")
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+/// Add the tokens accrued since the last refill, up to one second's worth
+fn refill(state: &mut State, requests_per_second: f64) {
+    let now = Instant::now();
+    let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+    state.refilled_at = now;
+
+    if requests_per_second > 0.0 {
+        let capacity = requests_per_second.max(1.0);
+        state.tokens = (state.tokens + elapsed * requests_per_second).min(capacity);
+    }
+}
+
+/// Whether an error means the server asked us to slow down (429 or 503)
+fn is_overloaded(error: &AtlasError) -> bool {
+    matches!(
+        error,
+        AtlasError::OpenEhr(
+            OpenEhrError::RateLimitExceeded(_)
+                | OpenEhrError::ServerError { status: 503, .. }
+                | OpenEhrError::ClientError { status: 429, .. }
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::schema::RateLimitWindow;
+
+    fn config(rps: f64, max_in_flight: usize) -> RateLimitConfig {
+        RateLimitConfig {
+            requests_per_second: rps,
+            max_in_flight,
+            adaptive: true,
+            windows: vec![RateLimitWindow {
+                start: "07:00".to_string(),
+                end: "19:00".to_string(),
+                requests_per_second: Some(2.0),
+                max_in_flight: None,
+            }],
+        }
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(value, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_limits_at_time_of_day() {
+        let config = config(20.0, 8);
+
+        let day = Limits::at(&config, time("12:30"));
+        assert_eq!(day.requests_per_second, 2.0);
+        assert_eq!(day.max_in_flight, 8);
+
+        let night = Limits::at(&config, time("19:00"));
+        assert_eq!(night.requests_per_second, 20.0);
+
+        assert!(Limits::at(&RateLimitConfig::default(), time("12:00")).is_unlimited());
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_in_flight: 1,
+            ..RateLimitConfig::default()
+        });
+
+        let permit = limiter.acquire().await;
+        let blocked = tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await;
+        assert!(blocked.is_err());
+
+        drop(permit);
+        let acquired = tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await;
+        assert!(acquired.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 50.0,
+            ..RateLimitConfig::default()
+        });
+
+        let started = Instant::now();
+        for _ in 0..5 {
+            drop(limiter.acquire().await);
+        }
+        // The bucket starts empty, so 5 requests take about 100ms at 50/s
+        assert!(started.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_adaptive_backoff() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 10.0,
+            max_in_flight: 4,
+            ..RateLimitConfig::default()
+        });
+
+        let throttled: Result<()> = Err(AtlasError::OpenEhr(OpenEhrError::RateLimitExceeded(
+            "unspecified".to_string(),
+        )));
+        limiter.record(&throttled);
+        assert_eq!(limiter.current_limits().requests_per_second, 5.0);
+        assert_eq!(limiter.current_limits().max_in_flight, 2);
+
+        for _ in 0..10 {
+            limiter.record(&throttled);
+        }
+        assert_eq!(limiter.current_limits().requests_per_second, 1.0);
+        assert_eq!(limiter.current_limits().max_in_flight, 1);
+
+        for _ in 0..200 {
+            limiter.record(&Ok(()));
+        }
+        assert_eq!(limiter.current_limits().requests_per_second, 10.0);
+
+        // Other errors don't lower the limits
+        limiter.record::<()>(&Err(AtlasError::OpenEhr(
+            OpenEhrError::CompositionNotFound("uid".to_string()),
+        )));
+        assert_eq!(limiter.current_limits().max_in_flight, 4);
+    }
+}
@@ -0,0 +1,86 @@
+//! Errors for unsuccessful openEHR responses
+//!
+//! Unexpected HTTP statuses are mapped to [`OpenEhrError`] variants that the
+//! shared retry policy can classify: throttling (429, or 503 with a
+//! Retry-After header) keeps the Retry-After value, 408 is a timeout and 5xx
+//! is a server error. Any other status is a failed request that would fail
+//! again if retried.
+
+use crate::domain::{AtlasError, OpenEhrError};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+
+/// Build the error for an unexpected response status
+///
+/// # Arguments
+///
+/// * `resp` - Response with the unexpected status
+/// * `action` - What failed, e.g. "Failed to fetch EHR"
+pub async fn response_error(resp: Response, action: &str) -> AtlasError {
+    let status = resp.status();
+    let retry_after = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = resp.text().await.unwrap_or_default();
+
+    AtlasError::OpenEhr(status_error(
+        status,
+        retry_after,
+        format!("{action} with status {status}: {body}"),
+    ))
+}
+
+fn status_error(status: StatusCode, retry_after: Option<String>, message: String) -> OpenEhrError {
+    match (status, retry_after) {
+        (StatusCode::TOO_MANY_REQUESTS, retry_after) => OpenEhrError::RateLimitExceeded(
+            retry_after.unwrap_or_else(|| "unspecified".to_string()),
+        ),
+        (StatusCode::SERVICE_UNAVAILABLE, Some(retry_after)) => {
+            OpenEhrError::RateLimitExceeded(retry_after)
+        }
+        (StatusCode::REQUEST_TIMEOUT, _) => OpenEhrError::Timeout(message),
+        (status, _) if status.is_server_error() => OpenEhrError::ServerError {
+            status: status.as_u16(),
+            message,
+        },
+        _ => OpenEhrError::QueryFailed(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_error() {
+        let throttled = AtlasError::OpenEhr(status_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some("30".to_string()),
+            "Failed".to_string(),
+        ));
+        assert!(throttled.is_retryable());
+        assert_eq!(throttled.retry_after(), Some(Duration::from_secs(30)));
+
+        let unavailable = AtlasError::OpenEhr(status_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            None,
+            "Failed".to_string(),
+        ));
+        assert!(unavailable.is_retryable());
+        assert_eq!(unavailable.retry_after(), None);
+
+        let bad_request = AtlasError::OpenEhr(status_error(
+            StatusCode::BAD_REQUEST,
+            None,
+            "Failed to fetch EHR with status 400 Bad Request: ".to_string(),
+        ));
+        assert!(!bad_request.is_retryable());
+        assert!(matches!(
+            bad_request,
+            AtlasError::OpenEhr(OpenEhrError::QueryFailed(_))
+        ));
+    }
+}
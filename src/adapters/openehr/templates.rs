@@ -0,0 +1,511 @@
+//! Template definitions
+//!
+//! This module provides access to the openEHR REST `definition/template`
+//! resource shared by the vendor implementations, an on-disk cache of the
+//! downloaded definitions, and the [`TemplateService`] combining both.
+//! Operational templates are requested as XML and web templates as
+//! `application/openehr.wt+json`.
+
+use super::status;
+use super::vendor::OpenEhrVendor;
+use crate::config::TemplateConfig;
+use crate::domain::ids::TemplateId;
+use crate::domain::{AtlasError, OpenEhrError, Result, TemplateDefinition, TemplateFormat};
+use glob::Pattern;
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Shared client for the template definition REST resource
+///
+/// Like [`VersionHistoryClient`](super::versions::VersionHistoryClient), this
+/// only owns the HTTP details; vendors apply authentication and retries
+/// around each call.
+#[derive(Debug, Clone)]
+pub struct TemplateClient {
+    /// HTTP client for making requests
+    client: Client,
+
+    /// Base URL of the openEHR REST API
+    endpoint: String,
+
+    /// Name of the header carrying the credentials
+    auth_header: String,
+}
+
+impl TemplateClient {
+    /// Create a new template client
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `base_url` - Base URL of the openEHR server
+    pub fn new(client: Client, base_url: &str) -> Self {
+        Self::with_endpoint(
+            client,
+            format!("{}/rest/openehr/v1", base_url.trim_end_matches('/')),
+        )
+    }
+
+    /// Create a new template client for a non-standard REST API path
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `endpoint` - Full URL of the openEHR REST API
+    pub fn with_endpoint(client: Client, endpoint: String) -> Self {
+        Self {
+            client,
+            endpoint,
+            auth_header: "Authorization".to_string(),
+        }
+    }
+
+    /// Send credentials in a header other than `Authorization`
+    pub fn with_auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = auth_header.into();
+        self
+    }
+
+    /// Download the definition of a template
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template to download
+    /// * `format` - Representation to request
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateNotFound` if the server does not know the template, or
+    /// an error if the request fails or the server returns another non-success
+    /// status.
+    pub async fn fetch(
+        &self,
+        template_id: &TemplateId,
+        format: TemplateFormat,
+        authorization: Option<String>,
+    ) -> Result<TemplateDefinition> {
+        let url = self.template_url(template_id)?;
+
+        tracing::debug!(url = %url, format = %format, "Fetching template definition");
+
+        let accept = match format {
+            TemplateFormat::WebTemplate => "application/openehr.wt+json",
+            TemplateFormat::Opt => "application/xml",
+        };
+        let mut request = self.client.get(url).header("Accept", accept);
+
+        if let Some(auth) = authorization {
+            request = request.header(self.auth_header.as_str(), auth);
+        }
+
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(e.to_string())))?;
+
+        match resp.status() {
+            StatusCode::OK => {
+                let content = resp.text().await.map_err(|e| {
+                    AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string()))
+                })?;
+                Ok(TemplateDefinition::new(
+                    template_id.clone(),
+                    format,
+                    content,
+                ))
+            }
+            StatusCode::NOT_FOUND => Err(AtlasError::OpenEhr(OpenEhrError::TemplateNotFound(
+                template_id.to_string(),
+            ))),
+            StatusCode::UNAUTHORIZED => {
+                let body = resp.text().await.unwrap_or_default();
+                Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                    format!("Template request was rejected as unauthorized: {body}"),
+                )))
+            }
+            _ => Err(status::response_error(resp, "Failed to fetch template").await),
+        }
+    }
+
+    /// List the IDs of all ADL 1.4 templates known to the server
+    ///
+    /// # Arguments
+    ///
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response is not a list of
+    /// templates.
+    pub async fn list(&self, authorization: Option<String>) -> Result<Vec<TemplateId>> {
+        let url = self.templates_url(&[])?;
+
+        tracing::debug!(url = %url, "Listing template definitions");
+
+        let mut request = self.client.get(url).header("Accept", "application/json");
+
+        if let Some(auth) = authorization {
+            request = request.header(self.auth_header.as_str(), auth);
+        }
+
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(e.to_string())))?;
+
+        match resp.status() {
+            StatusCode::OK => {
+                let templates: Vec<TemplateSummary> = resp.json().await.map_err(|e| {
+                    AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string()))
+                })?;
+                templates
+                    .into_iter()
+                    .map(|t| {
+                        TemplateId::new(&t.template_id).map_err(|e| {
+                            AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string()))
+                        })
+                    })
+                    .collect()
+            }
+            StatusCode::UNAUTHORIZED => {
+                let body = resp.text().await.unwrap_or_default();
+                Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                    format!("Template list request was rejected as unauthorized: {body}"),
+                )))
+            }
+            _ => Err(status::response_error(resp, "Failed to list templates").await),
+        }
+    }
+
+    /// URL of an ADL 1.4 template, with the template ID percent-encoded
+    fn template_url(&self, template_id: &TemplateId) -> Result<Url> {
+        self.templates_url(&[template_id.as_str()])
+    }
+
+    /// URL below the ADL 1.4 template resource
+    fn templates_url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.endpoint).map_err(|e| {
+            AtlasError::Configuration(format!("Invalid openEHR REST URL '{}': {e}", self.endpoint))
+        })?;
+
+        url.path_segments_mut()
+            .map_err(|_| {
+                AtlasError::Configuration(format!("Invalid openEHR REST URL '{}'", self.endpoint))
+            })?
+            .pop_if_empty()
+            .extend(["definition", "template", "adl1.4"])
+            .extend(segments);
+
+        Ok(url)
+    }
+}
+
+/// Entry of the template list returned by `GET definition/template/adl1.4`
+#[derive(Debug, Deserialize)]
+struct TemplateSummary {
+    template_id: String,
+}
+
+/// Include/exclude glob filter applied to discovered templates
+///
+/// A template is selected if it matches any include pattern (or no include
+/// patterns are configured) and matches no exclude pattern. Patterns use
+/// shell glob syntax, e.g. `IDCR - *` or `*.v1`.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl TemplateFilter {
+    /// Compile a filter from include and exclude glob patterns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is not a valid glob.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: compile_patterns(include)?,
+            exclude: compile_patterns(exclude)?,
+        })
+    }
+
+    /// Whether a template is selected by the filter
+    pub fn matches(&self, template_id: &TemplateId) -> bool {
+        let id = template_id.as_str();
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(id)))
+            && !self.exclude.iter().any(|p| p.matches(id))
+    }
+
+    /// Keep the selected templates, sorted and without duplicates
+    pub fn apply(&self, template_ids: Vec<TemplateId>) -> Vec<TemplateId> {
+        let mut selected: Vec<TemplateId> = template_ids
+            .into_iter()
+            .filter(|id| self.matches(id))
+            .collect();
+        selected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        selected.dedup();
+        selected
+    }
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Pattern::new(p).map_err(|e| {
+                AtlasError::Configuration(format!("Invalid template pattern '{p}': {e}"))
+            })
+        })
+        .collect()
+}
+
+/// On-disk cache of template definitions
+///
+/// Definitions are stored as `<dir>/<template>/<hash>.<ext>`, so every
+/// revision of a template seen on the server is kept. A `latest.<format>`
+/// file records the hash of the most recently stored revision per format.
+#[derive(Debug, Clone)]
+pub struct TemplateCache {
+    dir: PathBuf,
+}
+
+impl TemplateCache {
+    /// Create a cache rooted at `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store a definition and mark it as the latest revision
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory or files cannot be written.
+    pub fn store(&self, definition: &TemplateDefinition) -> Result<PathBuf> {
+        let dir = self.template_dir(&definition.template_id);
+        std::fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+
+        let path = dir.join(format!(
+            "{}.{}",
+            definition.hash,
+            definition.format.extension()
+        ));
+        if !path.exists() {
+            std::fs::write(&path, &definition.content).map_err(|e| io_error(&path, e))?;
+            tracing::info!(
+                template_id = %definition.template_id,
+                hash = %definition.hash,
+                path = %path.display(),
+                "Cached new template revision"
+            );
+        }
+
+        let latest = Self::latest_path(&dir, definition.format);
+        std::fs::write(&latest, &definition.hash).map_err(|e| io_error(&latest, e))?;
+
+        Ok(path)
+    }
+
+    /// Load the latest cached revision of a template, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a cached file exists but cannot be read.
+    pub fn latest(
+        &self,
+        template_id: &TemplateId,
+        format: TemplateFormat,
+    ) -> Result<Option<TemplateDefinition>> {
+        let dir = self.template_dir(template_id);
+        let latest = Self::latest_path(&dir, format);
+        if !latest.exists() {
+            return Ok(None);
+        }
+
+        let hash = std::fs::read_to_string(&latest).map_err(|e| io_error(&latest, e))?;
+        let path = dir.join(format!("{}.{}", hash.trim(), format.extension()));
+        let content = std::fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+
+        Ok(Some(TemplateDefinition::new(
+            template_id.clone(),
+            format,
+            content,
+        )))
+    }
+
+    fn template_dir(&self, template_id: &TemplateId) -> PathBuf {
+        self.dir.join(template_id.to_container_name(""))
+    }
+
+    fn latest_path(dir: &Path, format: TemplateFormat) -> PathBuf {
+        dir.join(format!("latest.{format}"))
+    }
+}
+
+/// Downloads template definitions and keeps them in the template cache
+///
+/// # Example
+///
+/// ```no_run
+/// use atlas::adapters::openehr::templates::TemplateService;
+/// use atlas::adapters::openehr::OpenEhrClient;
+/// use atlas::config::OpenEhrConfig;
+/// use atlas::domain::ids::TemplateId;
+///
+/// # async fn example() -> atlas::domain::Result<()> {
+/// let config = OpenEhrConfig::default();
+/// let client = OpenEhrClient::new(config.clone()).await?;
+/// let service = TemplateService::new(client.vendor().clone(), &config.templates);
+///
+/// let template_id = TemplateId::new("IDCR - Vital Signs.v1").unwrap();
+/// let definition = service.load(&template_id).await?;
+/// for node in definition.nodes()? {
+///     println!("{} ({})", node.path, node.rm_type);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct TemplateService {
+    vendor: Arc<dyn OpenEhrVendor>,
+    cache: TemplateCache,
+    format: TemplateFormat,
+}
+
+impl TemplateService {
+    /// Create a template service for a vendor
+    pub fn new(vendor: Arc<dyn OpenEhrVendor>, config: &TemplateConfig) -> Self {
+        Self {
+            vendor,
+            cache: TemplateCache::new(&config.cache_dir),
+            format: config.format,
+        }
+    }
+
+    /// Download a template definition and cache it
+    ///
+    /// If the download fails for any reason other than the template not
+    /// existing, the latest cached revision is returned instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateNotFound` if the server does not know the template, or
+    /// the download error if no cached revision is available.
+    pub async fn load(&self, template_id: &TemplateId) -> Result<TemplateDefinition> {
+        match self.vendor.get_template(template_id, self.format).await {
+            Ok(definition) => {
+                self.cache.store(&definition)?;
+                Ok(definition)
+            }
+            Err(e @ AtlasError::OpenEhr(OpenEhrError::TemplateNotFound(_))) => Err(e),
+            Err(e) => match self.cache.latest(template_id, self.format)? {
+                Some(definition) => {
+                    tracing::warn!(
+                        template_id = %template_id,
+                        hash = %definition.hash,
+                        error = %e,
+                        "Template download failed, using cached definition"
+                    );
+                    Ok(definition)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Load the latest cached definition of a template without downloading it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a cached file exists but cannot be read.
+    pub fn cached(&self, template_id: &TemplateId) -> Result<Option<TemplateDefinition>> {
+        self.cache.latest(template_id, self.format)
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> AtlasError {
+    AtlasError::Io(format!("Template cache '{}': {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_id() -> TemplateId {
+        TemplateId::new("IDCR - Vital Signs.v1").unwrap()
+    }
+
+    #[test]
+    fn test_template_url_encodes_id() {
+        let client = TemplateClient::new(Client::new(), "https://ehrbase.example.com/ehrbase/");
+        let url = client.template_url(&template_id()).unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "https://ehrbase.example.com/ehrbase/rest/openehr/v1/definition/template/adl1.4/IDCR%20-%20Vital%20Signs.v1"
+        );
+    }
+
+    #[test]
+    fn test_template_filter() {
+        let ids: Vec<TemplateId> = [
+            "IDCR - Vital Signs.v1",
+            "IDCR - Lab Report.v0",
+            "Medication.v1",
+            "IDCR - Vital Signs.v1",
+        ]
+        .iter()
+        .map(|id| TemplateId::new(*id).unwrap())
+        .collect();
+
+        let all = TemplateFilter::default().apply(ids.clone());
+        assert_eq!(all.len(), 3);
+
+        let filter =
+            TemplateFilter::new(&["IDCR - *".to_string()], &["*Lab*".to_string()]).unwrap();
+        assert_eq!(filter.apply(ids), vec![template_id()]);
+
+        assert!(TemplateFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_template_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TemplateCache::new(dir.path());
+        assert!(cache
+            .latest(&template_id(), TemplateFormat::WebTemplate)
+            .unwrap()
+            .is_none());
+
+        let first = TemplateDefinition::new(
+            template_id(),
+            TemplateFormat::WebTemplate,
+            r#"{"tree":{}}"#.to_string(),
+        );
+        let first_path = cache.store(&first).unwrap();
+        assert!(first_path.ends_with(format!("{}.json", first.hash)));
+
+        // A new revision is stored alongside the old one and becomes the latest
+        let second = TemplateDefinition::new(
+            template_id(),
+            TemplateFormat::WebTemplate,
+            r#"{"tree":{"id":"v2"}}"#.to_string(),
+        );
+        cache.store(&second).unwrap();
+        assert!(first_path.exists());
+
+        let latest = cache
+            .latest(&template_id(), TemplateFormat::WebTemplate)
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest, second);
+
+        // Formats are tracked separately
+        assert!(cache
+            .latest(&template_id(), TemplateFormat::Opt)
+            .unwrap()
+            .is_none());
+    }
+}
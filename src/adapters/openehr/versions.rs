@@ -0,0 +1,373 @@
+//! Composition version history
+//!
+//! This module provides access to the openEHR REST `versioned_composition`
+//! resource shared by the vendor implementations. The revision history of a
+//! VERSIONED_COMPOSITION lists every version with its commit audit, and each
+//! ORIGINAL_VERSION carries the lifecycle state of that version.
+
+use super::status;
+use super::vendor::CompositionMetadata;
+use crate::domain::ids::{CompositionUid, EhrId};
+use crate::domain::{AtlasError, CompositionVersion, OpenEhrError, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+/// Coded or plain openEHR data value, of which only the `value` is used
+#[derive(Debug, Clone, Deserialize)]
+pub struct DataValue {
+    /// Textual value
+    pub value: String,
+}
+
+/// Commit audit of a version
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditDetails {
+    /// Time the version was committed
+    pub time_committed: DataValue,
+
+    /// Type of change (creation, amendment, modification, deleted, ...)
+    pub change_type: Option<DataValue>,
+}
+
+/// Single entry of a revision history
+#[derive(Debug, Clone, Deserialize)]
+pub struct RevisionHistoryItem {
+    /// Version UID (`{uuid}::{system_id}::{version}`)
+    pub version_id: DataValue,
+
+    /// Audits of the version; the first one is the commit audit
+    #[serde(default)]
+    pub audits: Vec<AuditDetails>,
+}
+
+/// Revision history response, either wrapped in `items` or as a plain list
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RevisionHistoryResponse {
+    Wrapped { items: Vec<RevisionHistoryItem> },
+    List(Vec<RevisionHistoryItem>),
+}
+
+/// Subset of an ORIGINAL_VERSION needed for the version information
+#[derive(Debug, Deserialize)]
+struct OriginalVersionResponse {
+    lifecycle_state: Option<DataValue>,
+}
+
+impl RevisionHistoryItem {
+    /// Get the UID of this version
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version ID is not a valid composition UID.
+    pub fn version_uid(&self) -> Result<CompositionUid> {
+        CompositionUid::parse(&self.version_id.value)
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e)))
+    }
+
+    /// Build the metadata of this version
+    ///
+    /// The EHR, template and name are taken from `latest`; the UID, commit
+    /// time and version information come from the revision history.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version ID, version number or commit time
+    /// cannot be parsed.
+    pub fn to_metadata(
+        &self,
+        latest: &CompositionMetadata,
+        lifecycle_state: Option<String>,
+    ) -> Result<CompositionMetadata> {
+        let uid = self.version_uid()?;
+
+        let version_number = uid
+            .version()
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| {
+                AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+                    "Invalid version number in version ID: {uid}"
+                )))
+            })?;
+
+        let commit_audit = self.audits.first().ok_or_else(|| {
+            AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+                "Missing commit audit for version: {uid}"
+            )))
+        })?;
+
+        let time_committed = DateTime::parse_from_rfc3339(&commit_audit.time_committed.value)
+            .map_err(|e| {
+                AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+                    "Invalid timestamp format: {e}"
+                )))
+            })?
+            .with_timezone(&Utc);
+
+        let mut metadata = CompositionMetadata::new(
+            uid,
+            latest.template_id.clone(),
+            latest.ehr_id.clone(),
+            time_committed,
+        )
+        .with_version(CompositionVersion {
+            version_number,
+            lifecycle_state,
+            change_type: commit_audit
+                .change_type
+                .as_ref()
+                .map(|change_type| change_type.value.clone()),
+        });
+
+        if let Some(ref name) = latest.name {
+            metadata = metadata.with_name(name.clone());
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Shared client for the versioned composition REST resource
+///
+/// Like [`AqlExecutor`](super::aql::AqlExecutor), this only owns the HTTP
+/// details; vendors apply authentication and retries around each call.
+#[derive(Debug, Clone)]
+pub struct VersionHistoryClient {
+    /// HTTP client for making requests
+    client: Client,
+
+    /// Base URL of the openEHR REST API
+    endpoint: String,
+
+    /// Name of the header carrying the credentials
+    auth_header: String,
+}
+
+impl VersionHistoryClient {
+    /// Create a new version history client
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `base_url` - Base URL of the openEHR server
+    pub fn new(client: Client, base_url: &str) -> Self {
+        Self::with_endpoint(
+            client,
+            format!("{}/rest/openehr/v1", base_url.trim_end_matches('/')),
+        )
+    }
+
+    /// Create a new version history client for a non-standard REST API path
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - HTTP client to send requests with
+    /// * `endpoint` - Full URL of the openEHR REST API
+    pub fn with_endpoint(client: Client, endpoint: String) -> Self {
+        Self {
+            client,
+            endpoint,
+            auth_header: "Authorization".to_string(),
+        }
+    }
+
+    /// Send credentials in a header other than `Authorization`
+    pub fn with_auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = auth_header.into();
+        self
+    }
+
+    /// Get the revision history of the versioned composition a UID belongs to
+    ///
+    /// Items are returned ordered by version number, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `ehr_id` - EHR the composition belongs to
+    /// * `uid` - UID of any version of the composition
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the server returns a non-success
+    /// status, or the response cannot be parsed.
+    pub async fn revision_history(
+        &self,
+        ehr_id: &EhrId,
+        uid: &CompositionUid,
+        authorization: Option<String>,
+    ) -> Result<Vec<RevisionHistoryItem>> {
+        let url = format!(
+            "{}/ehr/{}/versioned_composition/{}/revision_history",
+            self.endpoint,
+            ehr_id,
+            uid.base_uuid()
+        );
+
+        let response: RevisionHistoryResponse = self.get_json(&url, uid, authorization).await?;
+        let mut items = match response {
+            RevisionHistoryResponse::Wrapped { items } => items,
+            RevisionHistoryResponse::List(items) => items,
+        };
+
+        items.sort_by_key(|item| {
+            item.version_uid()
+                .ok()
+                .and_then(|uid| uid.version().and_then(|v| v.parse::<u32>().ok()))
+        });
+
+        Ok(items)
+    }
+
+    /// Get the lifecycle state of a specific version
+    ///
+    /// # Arguments
+    ///
+    /// * `ehr_id` - EHR the composition belongs to
+    /// * `version_uid` - UID of the version
+    /// * `authorization` - Optional credentials header value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the server returns a non-success
+    /// status, or the response cannot be parsed.
+    pub async fn lifecycle_state(
+        &self,
+        ehr_id: &EhrId,
+        version_uid: &CompositionUid,
+        authorization: Option<String>,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/ehr/{}/versioned_composition/{}/version/{}",
+            self.endpoint,
+            ehr_id,
+            version_uid.base_uuid(),
+            version_uid
+        );
+
+        let response: OriginalVersionResponse =
+            self.get_json(&url, version_uid, authorization).await?;
+
+        Ok(response.lifecycle_state.map(|state| state.value))
+    }
+
+    /// Send a GET request and parse the JSON response
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        uid: &CompositionUid,
+        authorization: Option<String>,
+    ) -> Result<T> {
+        tracing::debug!(url = %url, composition_uid = %uid, "Fetching version history");
+
+        let mut request = self.client.get(url).header("Accept", "application/json");
+
+        if let Some(auth) = authorization {
+            request = request.header(self.auth_header.as_str(), auth);
+        }
+
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| AtlasError::OpenEhr(OpenEhrError::ConnectionFailed(e.to_string())))?;
+
+        match resp.status() {
+            StatusCode::OK => resp
+                .json::<T>()
+                .await
+                .map_err(|e| AtlasError::OpenEhr(OpenEhrError::InvalidResponse(e.to_string()))),
+            StatusCode::NOT_FOUND => Err(AtlasError::OpenEhr(OpenEhrError::CompositionNotFound(
+                uid.to_string(),
+            ))),
+            StatusCode::UNAUTHORIZED => {
+                let body = resp.text().await.unwrap_or_default();
+                Err(AtlasError::OpenEhr(OpenEhrError::AuthenticationFailed(
+                    format!("Version history request was rejected as unauthorized: {body}"),
+                )))
+            }
+            _ => Err(status::response_error(resp, "Failed to fetch version history").await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ids::TemplateId;
+    use std::str::FromStr;
+
+    fn latest_metadata() -> CompositionMetadata {
+        CompositionMetadata::new(
+            CompositionUid::from_str("84d7c3f5::local.ehrbase.org::2").unwrap(),
+            TemplateId::from_str("IDCR - Vital Signs.v1").unwrap(),
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+            Utc::now(),
+        )
+        .with_name("Vital Signs".to_string())
+    }
+
+    #[test]
+    fn test_revision_history_formats() {
+        let wrapped = r#"{"items": [{"version_id": {"value": "84d7c3f5::local.ehrbase.org::1"}, "audits": []}]}"#;
+        let list = r#"[{"version_id": {"value": "84d7c3f5::local.ehrbase.org::1"}}]"#;
+
+        for json in [wrapped, list] {
+            let response: RevisionHistoryResponse = serde_json::from_str(json).unwrap();
+            let items = match response {
+                RevisionHistoryResponse::Wrapped { items } => items,
+                RevisionHistoryResponse::List(items) => items,
+            };
+            assert_eq!(items.len(), 1);
+            assert_eq!(
+                items[0].version_uid().unwrap().as_str(),
+                "84d7c3f5::local.ehrbase.org::1"
+            );
+        }
+    }
+
+    #[test]
+    fn test_revision_history_item_to_metadata() {
+        let item: RevisionHistoryItem = serde_json::from_value(serde_json::json!({
+            "version_id": {"value": "84d7c3f5::local.ehrbase.org::1"},
+            "audits": [{
+                "system_id": "local.ehrbase.org",
+                "time_committed": {"value": "2024-01-15T10:30:00.000+01:00"},
+                "change_type": {
+                    "value": "creation",
+                    "defining_code": {"code_string": "249"}
+                }
+            }]
+        }))
+        .unwrap();
+
+        let metadata = item
+            .to_metadata(&latest_metadata(), Some("complete".to_string()))
+            .unwrap();
+
+        assert_eq!(metadata.uid.as_str(), "84d7c3f5::local.ehrbase.org::1");
+        assert_eq!(metadata.name.as_deref(), Some("Vital Signs"));
+        assert_eq!(
+            metadata.time_committed.to_rfc3339(),
+            "2024-01-15T09:30:00+00:00"
+        );
+        assert_eq!(
+            metadata.version,
+            Some(CompositionVersion {
+                version_number: 1,
+                lifecycle_state: Some("complete".to_string()),
+                change_type: Some("creation".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_revision_history_item_without_audit() {
+        let item: RevisionHistoryItem = serde_json::from_value(serde_json::json!({
+            "version_id": {"value": "84d7c3f5::local.ehrbase.org::1"}
+        }))
+        .unwrap();
+
+        assert!(item.to_metadata(&latest_metadata(), None).is_err());
+    }
+}
@@ -0,0 +1,880 @@
+//! Parquet adapter implementing database traits
+//!
+//! This module provides the implementation of DatabaseClient and StateStorage traits
+//! for the parquet target.
+//!
+//! # Layout
+//!
+//! ```text
+//! <root_dir>/
+//! ├── atlas_state.json                   watermarks and dead letters
+//! ├── <template>/<date>/part-N.parquet   compositions
+//! ├── _ehrs/<date>/part-N.parquet        EHR metadata and EHR_STATUS
+//! └── _queries/<query>/<date>/part-N.parquet
+//! ```
+//!
+//! `<template>` is the template ID in lowercase with other characters than
+//! letters and digits replaced by underscores, and `<date>` is the UTC date
+//! of the export. Rows are buffered per directory and written as one file
+//! with one row group per `openehr.query.batch_size` rows; the rest is written
+//! when the export flushes the client. Files are never rewritten: a
+//! re-exported composition is written again, and a soft-deleted composition
+//! gets a row with its `id`, `ehr_id`, `template_id` and `deleted_at`.
+//! Readers keep the last row of each `id`.
+//!
+//! A watermark saved while rows of its template are still buffered is kept in
+//! memory until those rows are written, so the state file never claims rows
+//! that a crash would lose.
+
+use crate::adapters::cosmosdb::models::CosmosEhr;
+use crate::adapters::database::traits::{
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
+};
+use crate::adapters::filesystem::index::{CompositionIndex, IndexCache, IndexEntry};
+use crate::adapters::filesystem::state::{Change, StateFile};
+use crate::adapters::parquet::writer::{self, ParquetWriter};
+use crate::config::schema::ParquetConfig;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::core::transform::flatten::flatten_composition;
+use crate::core::transform::preserve::preserve_composition;
+use crate::domain::composition::Composition;
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use crate::domain::{AtlasError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+/// Directory of the EHR files
+const EHR_DIR: &str = "_ehrs";
+
+/// Directory of the named AQL query files
+const QUERY_DIR: &str = "_queries";
+
+/// Parquet implementation of database traits
+///
+/// Writes Parquet files below a root directory and keeps the export state in
+/// a local JSON file.
+pub struct ParquetAdapter {
+    root: PathBuf,
+    writer: ParquetWriter,
+    state: StateFile,
+    /// Watermarks waiting for buffered rows, per dataset, with the number of
+    /// rows appended to the dataset when they were saved
+    deferred: Mutex<HashMap<PathBuf, Vec<(u64, Watermark)>>>,
+    /// Composition index of each template dataset, read on first use and
+    /// then kept up to date by the writes of this adapter
+    indexes: IndexCache,
+}
+
+impl ParquetAdapter {
+    /// Create a new parquet adapter
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Parquet configuration
+    /// * `row_group_size` - Number of rows of a file, which has one row group,
+    ///   normally the export batch size
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing state file cannot be read.
+    pub fn new(config: ParquetConfig, row_group_size: usize) -> Result<Self> {
+        let root = PathBuf::from(&config.root_dir);
+        let state = StateFile::open(root.join(&config.state_file))?;
+
+        Ok(Self {
+            writer: ParquetWriter::new(config.compression, row_group_size),
+            state,
+            root,
+            deferred: Mutex::new(HashMap::new()),
+            indexes: IndexCache::default(),
+        })
+    }
+
+    /// Root directory of the exported files
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the compositions of a template
+    fn template_dir(&self, template_id: &TemplateId) -> PathBuf {
+        self.root.join(template_id.to_container_name(""))
+    }
+
+    /// Directory of the result set of a named AQL query
+    fn query_dir(&self, query_name: &str) -> PathBuf {
+        self.root.join(QUERY_DIR).join(query_name)
+    }
+
+    /// Append documents to today's directory of a dataset
+    async fn write(&self, dataset: &Path, documents: Vec<serde_json::Value>) -> Result<()> {
+        let dir = dataset.join(Utc::now().format("%Y-%m-%d").to_string());
+        self.writer.append(dataset, &dir, documents).await?;
+        self.save_written_watermarks(dataset).await
+    }
+
+    /// Save the deferred watermarks of a dataset whose rows have been written
+    async fn save_written_watermarks(&self, dataset: &Path) -> Result<()> {
+        let written = self.writer.progress(dataset).await.written;
+
+        let ready: Vec<Watermark> = {
+            let mut deferred = self.deferred.lock().await;
+            let Some(watermarks) = deferred.get_mut(dataset) else {
+                return Ok(());
+            };
+            let count = watermarks.partition_point(|(appended, _)| *appended <= written);
+            watermarks.drain(..count).map(|(_, w)| w).collect()
+        };
+        if ready.is_empty() {
+            return Ok(());
+        }
+
+        self.state
+            .update(ready.into_iter().map(Change::Watermark))
+            .await
+    }
+
+    /// Write documents, reporting them as failures if the writer refused them
+    ///
+    /// Documents the writer accepted are written by a later append or flush
+    /// even if their file could not be written yet, so they are not failures.
+    async fn write_batch(
+        &self,
+        dataset: &Path,
+        documents: Vec<serde_json::Value>,
+    ) -> Result<BulkInsertResult> {
+        let ids: Vec<String> = documents
+            .iter()
+            .map(|doc| {
+                doc.get("id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string()
+            })
+            .collect();
+
+        match self.write(dataset, documents).await {
+            Ok(()) => {
+                tracing::debug!(
+                    dataset = %dataset.display(),
+                    count = ids.len(),
+                    "Documents appended to Parquet"
+                );
+                Ok(BulkInsertResult {
+                    success_count: ids.len(),
+                    failure_count: 0,
+                    failures: Vec::new(),
+                })
+            }
+            Err(e) => {
+                tracing::error!(dataset = %dataset.display(), error = %e, "Failed to write Parquet file");
+                let failures: Vec<BulkInsertFailure> = ids
+                    .into_iter()
+                    .map(|document_id| BulkInsertFailure {
+                        document_id,
+                        error: e.to_string(),
+                        is_throttled: false,
+                    })
+                    .collect();
+
+                Ok(BulkInsertResult {
+                    success_count: 0,
+                    failure_count: failures.len(),
+                    failures,
+                })
+            }
+        }
+    }
+
+    /// IDs of the stored compositions of an EHR that are not deleted
+    ///
+    /// The files and buffered rows of the template are read once, on the
+    /// first call.
+    async fn live_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let dataset = self.template_dir(template_id);
+
+        self.indexes
+            .live_ids(&dataset, ehr_id.as_str(), || async {
+                let pending = self.writer.pending(&dataset).await;
+                let dataset = dataset.clone();
+
+                tokio::task::spawn_blocking(move || {
+                    // The last row of a composition decides whether it is deleted
+                    let mut index = CompositionIndex::default();
+                    for row in writer::read_text_columns(&dataset, &["id", "ehr_id", "deleted_at"])?
+                    {
+                        if let [Some(id), Some(ehr_id), deleted_at] = &row[..] {
+                            index.insert(IndexEntry {
+                                ehr_id: ehr_id.clone(),
+                                id: id.clone(),
+                                live: deleted_at.is_none(),
+                            });
+                        }
+                    }
+
+                    // Buffered rows come after the written ones
+                    index.extend(pending.iter().filter_map(IndexEntry::from_document));
+
+                    Ok(index)
+                })
+                .await
+                .map_err(|e| AtlasError::Io(format!("Failed to read exported files: {e}")))?
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl DatabaseClient for ParquetAdapter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+
+        if tokio::fs::metadata(&self.root)
+            .await?
+            .permissions()
+            .readonly()
+        {
+            return Err(AtlasError::Io(format!(
+                "Directory {} is not writable",
+                self.root.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_database_exists(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        Ok(())
+    }
+
+    async fn ensure_container_exists(&self, template_id: &TemplateId) -> Result<()> {
+        tokio::fs::create_dir_all(self.template_dir(template_id)).await?;
+        Ok(())
+    }
+
+    async fn ensure_control_container_exists(&self) -> Result<()> {
+        if let Some(parent) = self.state.path().parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(())
+    }
+
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        tokio::fs::create_dir_all(self.root.join(EHR_DIR)).await?;
+        Ok(())
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would write EHR status to Parquet"
+            );
+            return Ok(());
+        }
+
+        let document = serde_json::to_value(CosmosEhr::from_domain(ehr))
+            .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+        self.write(&self.root.join(EHR_DIR), vec![document]).await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status written to Parquet");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        tokio::fs::create_dir_all(self.query_dir(query_name)).await?;
+        Ok(())
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would write {} query rows to Parquet",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        // Query columns become Parquet columns next to the row ID
+        let exported_at = Utc::now();
+        let documents = rows
+            .into_iter()
+            .map(|row| {
+                let mut document = row.data;
+                document.insert("id".to_string(), json!(row.id));
+                document.insert("exported_at".to_string(), json!(exported_at));
+                serde_json::Value::Object(document)
+            })
+            .collect();
+
+        self.write_batch(&self.query_dir(query_name), documents)
+            .await
+    }
+
+    async fn bulk_insert_json(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<serde_json::Value>,
+        _max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                count = documents.len(),
+                "DRY RUN: Would write {} compositions to Parquet",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let dataset = self.template_dir(template_id);
+        let entries: Vec<IndexEntry> = documents
+            .iter()
+            .filter_map(IndexEntry::from_document)
+            .collect();
+
+        let result = self.write_batch(&dataset, documents).await?;
+        if result.failure_count == 0 {
+            self.indexes.record(&dataset, entries).await;
+        }
+
+        Ok(result)
+    }
+
+    async fn bulk_insert_compositions(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        let documents = compositions
+            .into_iter()
+            .map(|composition| preserve_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.bulk_insert_json(template_id, documents, max_retries, dry_run)
+            .await
+    }
+
+    async fn bulk_insert_compositions_flattened(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        let documents = compositions
+            .into_iter()
+            .map(|composition| flatten_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.bulk_insert_json(template_id, documents, max_retries, dry_run)
+            .await
+    }
+
+    async fn check_composition_exists(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &str,
+        composition_id: &str,
+    ) -> Result<bool> {
+        let ehr_id = EhrId::new(ehr_id).map_err(AtlasError::Validation)?;
+        let ids = self.live_composition_ids(template_id, &ehr_id).await?;
+
+        Ok(ids.iter().any(|id| id == composition_id))
+    }
+
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        self.live_composition_ids(template_id, ehr_id).await
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if policy == DeletionPolicy::HardDelete {
+            return Err(AtlasError::Database(
+                "hard_delete is not supported by the parquet target".to_string(),
+            ));
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in Parquet",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let deleted_at = Utc::now();
+        let records: Vec<serde_json::Value> = composition_ids
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "ehr_id": ehr_id.as_str(),
+                    "template_id": template_id.as_str(),
+                    "deleted_at": deleted_at,
+                })
+            })
+            .collect();
+        let count = records.len();
+        let dataset = self.template_dir(template_id);
+        let entries: Vec<IndexEntry> = records
+            .iter()
+            .filter_map(IndexEntry::from_document)
+            .collect();
+        self.write(&dataset, records).await?;
+        self.indexes.record(&dataset, entries).await;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count,
+            policy = %policy,
+            "Applied deletion policy in Parquet"
+        );
+
+        Ok(count)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let result = self.writer.flush_all().await;
+
+        let datasets: Vec<PathBuf> = self.deferred.lock().await.keys().cloned().collect();
+        for dataset in datasets {
+            self.save_written_watermarks(&dataset).await?;
+        }
+        self.state.compact().await?;
+
+        result
+    }
+
+    fn database_name(&self) -> &str {
+        "parquet"
+    }
+}
+
+#[async_trait]
+impl StateStorage for ParquetAdapter {
+    async fn load_watermark(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Option<Watermark>> {
+        let watermark_id = Watermark::generate_id(template_id, ehr_id);
+
+        let deferred = self
+            .deferred
+            .lock()
+            .await
+            .get(&self.template_dir(template_id))
+            .and_then(|watermarks| {
+                watermarks
+                    .iter()
+                    .rev()
+                    .find(|(_, w)| w.id == watermark_id)
+                    .map(|(_, w)| w.clone())
+            });
+        if deferred.is_some() {
+            return Ok(deferred);
+        }
+
+        Ok(self
+            .state
+            .read(|state| state.watermarks.get(&watermark_id).cloned())
+            .await)
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                template_id = %watermark.template_id.as_str(),
+                ehr_id = %watermark.ehr_id.as_str(),
+                watermark_id = %watermark.id,
+                "DRY RUN: Would save watermark to state file"
+            );
+            return Ok(());
+        }
+
+        // Keep the watermark in memory while rows before it are buffered
+        let dataset = self.template_dir(&watermark.template_id);
+        let progress = self.writer.progress(&dataset).await;
+        if progress.written < progress.appended {
+            self.deferred
+                .lock()
+                .await
+                .entry(dataset)
+                .or_default()
+                .push((progress.appended, watermark.clone()));
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::Watermark(watermark.clone())])
+            .await
+    }
+
+    async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
+        let mut watermarks = self.state.read(|state| state.watermarks.clone()).await;
+        for (_, watermark) in self.deferred.lock().await.values().flatten() {
+            watermarks.insert(watermark.id.clone(), watermark.clone());
+        }
+        let mut watermarks: Vec<Watermark> = watermarks.into_values().collect();
+        watermarks.sort_by(|a, b| {
+            (a.template_id.as_str(), a.ehr_id.as_str())
+                .cmp(&(b.template_id.as_str(), b.ehr_id.as_str()))
+        });
+
+        Ok(watermarks)
+    }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        let id = QueryWatermark::generate_id(query_name);
+
+        Ok(self
+            .state
+            .read(|state| state.query_watermarks.get(&id).cloned())
+            .await)
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to state file"
+            );
+            return Ok(());
+        }
+
+        // The watermark covers the rows of this run
+        self.writer
+            .flush(&self.query_dir(&watermark.query_name))
+            .await?;
+
+        self.state
+            .update([Change::QueryWatermark(watermark.clone())])
+            .await
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        let id = DeadLetter::generate_id(composition_uid);
+
+        Ok(self
+            .state
+            .read(|state| state.dead_letters.get(&id).cloned())
+            .await)
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to state file"
+            );
+            return Ok(());
+        }
+
+        self.state
+            .update([Change::DeadLetter(dead_letter.clone())])
+            .await
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from state file"
+            );
+            return Ok(());
+        }
+
+        // The retried composition is written before its dead letter is removed
+        let dataset = self.template_dir(&dead_letter.template_id);
+        self.writer.flush(&dataset).await?;
+        self.save_written_watermarks(&dataset).await?;
+
+        self.state
+            .update([Change::DeleteDeadLetter(dead_letter.id.clone())])
+            .await
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut dead_letters: Vec<DeadLetter> = self
+            .state
+            .read(|state| state.dead_letters.values().cloned().collect())
+            .await;
+        dead_letters.sort_by_key(|dead_letter| dead_letter.first_failed_at);
+
+        Ok(dead_letters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::schema::ParquetCompression;
+    use crate::core::state::watermark::WatermarkBuilder;
+    use crate::domain::composition::CompositionBuilder;
+    use std::str::FromStr;
+
+    fn adapter(root: &Path) -> ParquetAdapter {
+        ParquetAdapter::new(
+            ParquetConfig {
+                root_dir: root.display().to_string(),
+                compression: ParquetCompression::Snappy,
+                state_file: "atlas_state.json".to_string(),
+            },
+            100,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_flattened_compositions_and_soft_delete() {
+        let root = tempfile::tempdir().unwrap();
+        let adapter = adapter(root.path());
+        let template_id = TemplateId::new("IDCR - Vital Signs.v1").unwrap();
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+
+        let compositions = ["c1::local::1", "c2::local::1"]
+            .iter()
+            .map(|uid| {
+                CompositionBuilder::new()
+                    .uid(CompositionUid::from_str(uid).unwrap())
+                    .ehr_id(ehr_id.clone())
+                    .template_id(template_id.clone())
+                    .time_committed(Utc::now())
+                    .content(json!({"vital_signs/body_temperature:0|magnitude": 37}))
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let result = adapter
+            .bulk_insert_compositions_flattened(
+                &template_id,
+                compositions,
+                "full".to_string(),
+                3,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 2);
+
+        // Buffered rows count as stored before they are written
+        assert_eq!(
+            adapter
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        adapter.flush().await.unwrap();
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        assert!(root
+            .path()
+            .join("idcr_vital_signs_v1")
+            .join(date)
+            .join("part-1.parquet")
+            .is_file());
+
+        assert_eq!(
+            adapter
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        adapter
+            .delete_compositions(
+                &template_id,
+                &ehr_id,
+                &["c1::local::1".to_string()],
+                DeletionPolicy::SoftDelete,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(!adapter
+            .check_composition_exists(&template_id, ehr_id.as_str(), "c1::local::1")
+            .await
+            .unwrap());
+        assert!(adapter
+            .check_composition_exists(&template_id, ehr_id.as_str(), "c2::local::1")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_writes_after_first_read() {
+        let root = tempfile::tempdir().unwrap();
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+        let document = |id: &str| json!({"id": id, "ehr_id": ehr_id.as_str()});
+
+        let previous = adapter(root.path());
+        previous
+            .bulk_insert_json(&template_id, vec![document("c1::local::1")], 3, false)
+            .await
+            .unwrap();
+        previous.flush().await.unwrap();
+
+        // The index is read from the written files and the buffered rows
+        let resumed = adapter(root.path());
+        resumed
+            .bulk_insert_json(&template_id, vec![document("c2::local::1")], 3, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            resumed
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1", "c2::local::1"]
+        );
+
+        resumed
+            .delete_compositions(
+                &template_id,
+                &ehr_id,
+                &["c1::local::1".to_string()],
+                DeletionPolicy::SoftDelete,
+                false,
+            )
+            .await
+            .unwrap();
+        resumed
+            .bulk_insert_json(&template_id, vec![document("c3::local::1")], 3, false)
+            .await
+            .unwrap();
+
+        let expected = vec!["c2::local::1", "c3::local::1"];
+        assert_eq!(
+            resumed
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            expected
+        );
+        resumed.flush().await.unwrap();
+        assert_eq!(
+            adapter(root.path())
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watermark_deferred_until_rows_are_written() {
+        let root = tempfile::tempdir().unwrap();
+        let adapter = adapter(root.path());
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+        let watermark = WatermarkBuilder::new(template_id.clone(), ehr_id.clone())
+            .compositions_exported_count(1)
+            .build();
+
+        adapter
+            .bulk_insert_json(
+                &template_id,
+                vec![json!({"id": "c1::local::1", "ehr_id": ehr_id.as_str()})],
+                3,
+                false,
+            )
+            .await
+            .unwrap();
+        adapter.save_watermark(&watermark, false).await.unwrap();
+
+        // The state file doesn't claim the buffered row yet
+        assert!(adapter
+            .load_watermark(&template_id, &ehr_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(adapter.get_all_watermarks().await.unwrap().len(), 1);
+        assert!(self::adapter(root.path())
+            .load_watermark(&template_id, &ehr_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        adapter.flush().await.unwrap();
+        let reopened = self::adapter(root.path());
+        assert_eq!(
+            reopened
+                .load_watermark(&template_id, &ehr_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .compositions_exported_count,
+            1
+        );
+        assert_eq!(
+            reopened
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c1::local::1"]
+        );
+    }
+}
@@ -0,0 +1,12 @@
+//! Apache Parquet integration
+//!
+//! This module provides a target that writes flattened openEHR compositions
+//! as Parquet files, with an Arrow schema inferred per template from the
+//! flattened field names and values, and keeps the export state in a local
+//! JSON file.
+
+pub mod adapter;
+pub mod schema;
+pub mod writer;
+
+pub use adapter::ParquetAdapter;
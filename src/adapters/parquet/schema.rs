@@ -0,0 +1,358 @@
+//! Arrow schema inference for flattened documents
+//!
+//! Every top-level field of a document becomes a nullable column. Column
+//! types are derived from the observed values:
+//!
+//! - `*_magnitude` fields → `Float64`, even if a value happens to be whole
+//! - other whole numbers → `Int64`, other numbers → `Float64`
+//! - RFC 3339 date-time strings → `Timestamp(Microsecond, UTC)`
+//! - booleans → `Boolean`
+//! - other strings, objects and arrays → `Utf8` (objects and arrays as JSON)
+//!
+//! Within the first batch that has a field, conflicting values widen the new
+//! column: `Int64` and `Float64` become `Float64`, and any other conflict
+//! becomes `Utf8`. Once written, a column keeps its type, so every file of a
+//! dataset agrees on it. Later values that don't fit are written as text to a
+//! companion `{name}__text` column instead, and are null in the column itself.
+//! Fields that are only ever null get no column until a value appears.
+
+use crate::domain::{AtlasError, Result};
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder,
+    TimestampMicrosecondBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::DateTime;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Suffix of flattened DV_QUANTITY magnitude fields
+const MAGNITUDE_SUFFIX: &str = "_magnitude";
+
+/// Suffix of the text columns holding values that don't fit their column
+pub const CONFLICT_SUFFIX: &str = "__text";
+
+/// Timestamp type of date-time columns
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into()))
+}
+
+/// Infer the column type of a value, or `None` for null
+fn infer_type(name: &str, value: &Value) -> Option<DataType> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(DataType::Boolean),
+        Value::Number(n) if n.is_f64() || name.ends_with(MAGNITUDE_SUFFIX) => {
+            Some(DataType::Float64)
+        }
+        Value::Number(n) if n.is_i64() => Some(DataType::Int64),
+        // u64 values beyond i64::MAX
+        Value::Number(_) => Some(DataType::Float64),
+        Value::String(s) if DateTime::parse_from_rfc3339(s).is_ok() => Some(timestamp_type()),
+        Value::String(_) | Value::Array(_) | Value::Object(_) => Some(DataType::Utf8),
+    }
+}
+
+/// Widen two column types to one that can hold values of both
+fn widen(a: &DataType, b: &DataType) -> DataType {
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => {
+            DataType::Float64
+        }
+        _ => DataType::Utf8,
+    }
+}
+
+/// Whether a column of a type holds values of an observed type
+fn holds(column: &DataType, observed: &DataType) -> bool {
+    column == observed
+        || *column == DataType::Utf8
+        || (*column == DataType::Float64 && *observed == DataType::Int64)
+}
+
+/// Whether a value fits a column type
+fn fits(data_type: &DataType, value: &Value) -> bool {
+    match data_type {
+        DataType::Boolean => value.is_boolean(),
+        DataType::Int64 => value.is_i64(),
+        DataType::Float64 => value.is_number(),
+        DataType::Timestamp(..) => value
+            .as_str()
+            .is_some_and(|s| DateTime::parse_from_rfc3339(s).is_ok()),
+        _ => true,
+    }
+}
+
+/// Merge the columns of documents into a schema
+///
+/// Existing columns keep their position and type, new columns are appended
+/// in name order. A `{name}__text` column is added for an existing column
+/// that doesn't hold the observed values.
+///
+/// # Returns
+///
+/// Returns the merged schema, which is `schema` itself if nothing changed.
+pub fn merge_schema(schema: &Schema, documents: &[Value]) -> Schema {
+    let mut observed: BTreeMap<String, DataType> = BTreeMap::new();
+    for document in documents {
+        let Some(fields) = document.as_object() else {
+            continue;
+        };
+        for (name, value) in fields {
+            if let Some(data_type) = infer_type(name, value) {
+                let merged = match observed.get(name) {
+                    Some(current) => widen(current, &data_type),
+                    None => data_type,
+                };
+                observed.insert(name.clone(), merged);
+            }
+        }
+    }
+
+    for field in schema.fields() {
+        if let Some(data_type) = observed.remove(field.name()) {
+            if !holds(field.data_type(), &data_type) {
+                observed.insert(format!("{}{CONFLICT_SUFFIX}", field.name()), DataType::Utf8);
+            }
+        }
+    }
+
+    let mut fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .map(|field| field.as_ref().clone())
+        .collect();
+    fields.extend(
+        observed
+            .into_iter()
+            .filter(|(name, _)| schema.field_with_name(name).is_err())
+            .map(|(name, data_type)| Field::new(name, data_type, true)),
+    );
+
+    Schema::new(fields)
+}
+
+/// Convert documents to a record batch of a schema
+///
+/// Values that don't fit their column type are written as null, and as text
+/// to the `{name}__text` column if the schema has one. `Utf8` columns hold
+/// any value as text.
+///
+/// # Errors
+///
+/// Returns an error if the record batch cannot be built.
+pub fn to_record_batch(schema: Arc<Schema>, documents: &[Value]) -> Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            // A conflict column takes the values that don't fit its column
+            let conflict = field
+                .name()
+                .strip_suffix(CONFLICT_SUFFIX)
+                .and_then(|name| Some((name, schema.field_with_name(name).ok()?.data_type())));
+
+            let values = documents.iter().map(|document| match conflict {
+                Some((name, data_type)) => document
+                    .get(name)
+                    .filter(|value| !fits(data_type, value))
+                    .unwrap_or(&Value::Null),
+                None => document.get(field.name()).unwrap_or(&Value::Null),
+            });
+            build_column(field.data_type(), values)
+        })
+        .collect();
+
+    RecordBatch::try_new(schema, columns).map_err(|e| AtlasError::Serialization(e.to_string()))
+}
+
+/// Build the array of a column
+fn build_column<'a>(data_type: &DataType, values: impl Iterator<Item = &'a Value>) -> ArrayRef {
+    match data_type {
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            values.for_each(|v| builder.append_option(v.as_bool()));
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| builder.append_option(v.as_i64()));
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            values.for_each(|v| builder.append_option(v.as_f64()));
+            Arc::new(builder.finish())
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("+00:00");
+            values.for_each(|v| {
+                builder.append_option(
+                    v.as_str()
+                        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                        .map(|t| t.timestamp_micros()),
+                )
+            });
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            values.for_each(|v| match v {
+                Value::Null => builder.append_null(),
+                Value::String(s) => builder.append_value(s),
+                other => builder.append_value(other.to_string()),
+            });
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray};
+    use serde_json::json;
+
+    #[test]
+    fn test_infer_types() {
+        let documents = vec![json!({
+            "id": "c1::local::1",
+            "time_committed": "2025-01-15T10:30:00Z",
+            "vital_signs_body_temperature_0_magnitude": 37,
+            "vital_signs_pulse_0_rate_magnitude": 72.5,
+            "vital_signs_pulse_0_position_code": 3,
+            "vital_signs_pulse_0_regular": true,
+            "atlas_metadata": {"export_mode": "full"},
+            "ctx_health_care_facility_id": null,
+        })];
+
+        let schema = merge_schema(&Schema::empty(), &documents);
+        let type_of = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+
+        assert_eq!(type_of("id"), DataType::Utf8);
+        assert_eq!(type_of("time_committed"), timestamp_type());
+        assert_eq!(
+            type_of("vital_signs_body_temperature_0_magnitude"),
+            DataType::Float64
+        );
+        assert_eq!(
+            type_of("vital_signs_pulse_0_rate_magnitude"),
+            DataType::Float64
+        );
+        assert_eq!(
+            type_of("vital_signs_pulse_0_position_code"),
+            DataType::Int64
+        );
+        assert_eq!(type_of("vital_signs_pulse_0_regular"), DataType::Boolean);
+        assert_eq!(type_of("atlas_metadata"), DataType::Utf8);
+        assert!(schema
+            .field_with_name("ctx_health_care_facility_id")
+            .is_err());
+        assert!(schema.fields().iter().all(|f| f.is_nullable()));
+    }
+
+    #[test]
+    fn test_schema_evolution() {
+        let first = merge_schema(&Schema::empty(), &[json!({"id": "a", "count": 1.5})]);
+        let second = merge_schema(
+            &first,
+            &[json!({"id": "b", "count": 1, "new_field": "2025-01-15T10:30:00Z"})],
+        );
+
+        let names: Vec<&str> = second.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, vec!["count", "id", "new_field"]);
+        assert_eq!(
+            second.field_with_name("count").unwrap().data_type(),
+            &DataType::Float64
+        );
+
+        // A conflicting value goes to a text column, the column keeps its type
+        let third = merge_schema(&second, &[json!({"new_field": "not a date"})]);
+        assert_eq!(
+            third.field_with_name("new_field").unwrap().data_type(),
+            &timestamp_type()
+        );
+        assert_eq!(
+            third
+                .field_with_name("new_field__text")
+                .unwrap()
+                .data_type(),
+            &DataType::Utf8
+        );
+        assert_eq!(merge_schema(&third, &[json!({"id": "c"})]), third);
+        assert_eq!(
+            merge_schema(&third, &[json!({"new_field": false, "count": 2})]),
+            third
+        );
+    }
+
+    #[test]
+    fn test_conflicting_values() {
+        let schema = merge_schema(&Schema::empty(), &[json!({"id": "a", "code": 1})]);
+        let documents = vec![
+            json!({"id": "b", "code": 2}),
+            json!({"id": "c", "code": "at0001"}),
+            json!({"id": "d", "code": 2.5}),
+        ];
+        let schema = Arc::new(merge_schema(&schema, &documents));
+
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, vec!["code", "id", "code__text"]);
+        assert_eq!(
+            schema.field_with_name("code").unwrap().data_type(),
+            &DataType::Int64
+        );
+
+        let batch = to_record_batch(schema, &documents).unwrap();
+        let code = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(code.value(0), 2);
+        assert!(code.is_null(1));
+        assert!(code.is_null(2));
+
+        let text = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(text.is_null(0));
+        assert_eq!(text.value(1), "at0001");
+        assert_eq!(text.value(2), "2.5");
+    }
+
+    #[test]
+    fn test_to_record_batch() {
+        let documents = vec![
+            json!({"id": "a", "temp_magnitude": 37, "time": "2025-01-15T10:30:00+01:00"}),
+            json!({"id": "b", "extra": [1, 2]}),
+        ];
+        let schema = Arc::new(merge_schema(&Schema::empty(), &documents));
+
+        let batch = to_record_batch(schema, &documents).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let column = |name: &str| batch.column(batch.schema().index_of(name).unwrap()).clone();
+        let temp = column("temp_magnitude");
+        let temp = temp.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(temp.value(0), 37.0);
+        assert!(temp.is_null(1));
+
+        let time = column("time");
+        let time = time
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(time.value(0), 1_736_933_400_000_000);
+
+        let extra = column("extra");
+        let extra = extra.as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(extra.is_null(0));
+        assert_eq!(extra.value(1), "[1,2]");
+    }
+}
@@ -0,0 +1,572 @@
+//! Parquet part files
+//!
+//! Documents are buffered per dataset and written as a new `part-N.parquet`
+//! file with a single row group whenever `rows_per_file` documents are
+//! pending, so small per-EHR batches don't each become a file. The rest is
+//! written by [`ParquetWriter::flush`]. A file is written under a temporary
+//! name and renamed once complete, so readers never see a partial file. Each
+//! file carries the schema merged over all files written to the dataset so
+//! far; readers such as Spark (`mergeSchema`) and DuckDB (`union_by_name`)
+//! reconcile the columns of older files.
+//!
+//! Each dataset has its own lock, so datasets are written concurrently. The
+//! schema and the next part numbers are read from disk on first use and then
+//! kept in memory.
+
+use crate::adapters::filesystem::writer::part_files;
+use crate::adapters::parquet::schema::{merge_schema, to_record_batch};
+use crate::config::schema::ParquetCompression;
+use crate::domain::{AtlasError, Result};
+use arrow::array::{Array, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Writes documents as Parquet files
+#[derive(Debug)]
+pub struct ParquetWriter {
+    properties: WriterProperties,
+    rows_per_file: usize,
+    datasets: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<Dataset>>>>,
+}
+
+/// Documents appended to and written for a dataset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of documents appended
+    pub appended: u64,
+
+    /// Number of appended documents written to files
+    pub written: u64,
+}
+
+/// Buffer and file state of a dataset
+#[derive(Debug, Default)]
+struct Dataset {
+    /// Schema of the files written so far, read from disk on first use
+    schema: Option<SchemaRef>,
+
+    /// Next part number of each directory
+    next_parts: HashMap<PathBuf, u32>,
+
+    /// Directory of the buffered documents
+    dir: PathBuf,
+
+    /// Documents not written yet, in append order
+    buffer: Vec<serde_json::Value>,
+
+    /// Documents appended and written so far
+    progress: Progress,
+}
+
+impl ParquetWriter {
+    /// Create a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - Compression codec of the column chunks
+    /// * `rows_per_file` - Number of rows of a file, which has one row group
+    pub fn new(compression: ParquetCompression, rows_per_file: usize) -> Self {
+        let compression = match compression {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        };
+
+        let rows_per_file = rows_per_file.max(1);
+
+        Self {
+            properties: WriterProperties::builder()
+                .set_compression(compression)
+                .set_max_row_group_size(rows_per_file)
+                .build(),
+            rows_per_file,
+            datasets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// State of a dataset, created on first use
+    fn dataset(&self, dataset: &Path) -> Arc<Mutex<Dataset>> {
+        self.datasets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(dataset.to_path_buf())
+            .or_default()
+            .clone()
+    }
+
+    /// Append documents to a dataset
+    ///
+    /// A file is written for every `rows_per_file` pending documents. Pending
+    /// documents of another directory, such as the previous day's, are
+    /// written first.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Directory of the dataset, whose schema the documents are merged into
+    /// * `dir` - Directory of the part files below `dataset`
+    /// * `documents` - Documents to append
+    ///
+    /// # Returns
+    ///
+    /// Returns the progress of the dataset after the documents. A file that
+    /// cannot be written once the documents are pending is logged, and its
+    /// documents stay pending for the next append or flush.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pending documents of another directory cannot
+    /// be written. The documents are then not appended; those pending stay
+    /// pending.
+    pub async fn append(
+        &self,
+        dataset: &Path,
+        dir: &Path,
+        documents: Vec<serde_json::Value>,
+    ) -> Result<Progress> {
+        let state = self.dataset(dataset);
+        let mut state = state.lock().await;
+
+        if state.dir != dir && !state.buffer.is_empty() {
+            let count = state.buffer.len();
+            self.write_part(dataset, &mut state, count).await?;
+        }
+
+        state.dir = dir.to_path_buf();
+        state.progress.appended += documents.len() as u64;
+        state.buffer.extend(documents);
+
+        while state.buffer.len() >= self.rows_per_file {
+            if let Err(e) = self
+                .write_part(dataset, &mut state, self.rows_per_file)
+                .await
+            {
+                tracing::warn!(
+                    dataset = %dataset.display(),
+                    pending = state.buffer.len(),
+                    error = %e,
+                    "Failed to write Parquet file, keeping rows pending"
+                );
+                break;
+            }
+        }
+
+        Ok(state.progress)
+    }
+
+    /// Write the pending documents of a dataset
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written. The documents stay
+    /// pending.
+    pub async fn flush(&self, dataset: &Path) -> Result<Progress> {
+        let state = self.dataset(dataset);
+        let mut state = state.lock().await;
+
+        if !state.buffer.is_empty() {
+            let count = state.buffer.len();
+            self.write_part(dataset, &mut state, count).await?;
+        }
+
+        Ok(state.progress)
+    }
+
+    /// Write the pending documents of all datasets
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error after trying every dataset.
+    pub async fn flush_all(&self) -> Result<()> {
+        let datasets: Vec<PathBuf> = self
+            .datasets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+
+        let mut result = Ok(());
+        for dataset in datasets {
+            if let Err(e) = self.flush(&dataset).await {
+                tracing::error!(dataset = %dataset.display(), error = %e, "Failed to write Parquet file");
+                result = result.and(Err(e));
+            }
+        }
+
+        result
+    }
+
+    /// Progress of a dataset
+    pub async fn progress(&self, dataset: &Path) -> Progress {
+        self.dataset(dataset).lock().await.progress
+    }
+
+    /// Pending documents of a dataset, in append order
+    pub async fn pending(&self, dataset: &Path) -> Vec<serde_json::Value> {
+        self.dataset(dataset).lock().await.buffer.clone()
+    }
+
+    /// Write the first `count` pending documents of a dataset to a new part file
+    async fn write_part(&self, dataset: &Path, state: &mut Dataset, count: usize) -> Result<()> {
+        let current = match &state.schema {
+            Some(schema) => schema.clone(),
+            None => {
+                let dataset = dataset.to_path_buf();
+                tokio::task::spawn_blocking(move || latest_schema(&dataset))
+                    .await
+                    .map_err(|e| AtlasError::Io(format!("Failed to read Parquet schema: {e}")))??
+            }
+        };
+
+        let dir = state.dir.clone();
+        let number = match state.next_parts.get(&dir) {
+            Some(number) => *number,
+            None => {
+                let dir = dir.clone();
+                tokio::task::spawn_blocking(move || next_part_number(&dir))
+                    .await
+                    .map_err(|e| AtlasError::Io(format!("Failed to list Parquet files: {e}")))??
+            }
+        };
+
+        let rest = state.buffer.split_off(count);
+        let documents = std::mem::replace(&mut state.buffer, rest);
+
+        let schema = Arc::new(merge_schema(&current, &documents));
+        if schema != current {
+            tracing::debug!(
+                dataset = %dataset.display(),
+                columns = schema.fields().len(),
+                "Parquet schema extended"
+            );
+        }
+
+        let properties = self.properties.clone();
+        let batch_schema = schema.clone();
+        let part_dir = dir.clone();
+        let (result, mut documents) = tokio::task::spawn_blocking(move || {
+            let result = to_record_batch(batch_schema, &documents)
+                .and_then(|batch| write_file(&part_dir, number, batch, properties));
+            (result, documents)
+        })
+        .await
+        .map_err(|e| AtlasError::Io(format!("Failed to write Parquet file: {e}")))?;
+
+        match result {
+            Ok(path) => {
+                tracing::debug!(path = %path.display(), count, "Parquet file written");
+                state.schema = Some(schema);
+                state.next_parts.insert(dir, number + 1);
+                state.progress.written += count as u64;
+                Ok(())
+            }
+            Err(e) => {
+                // Keep the documents pending, ahead of those appended since
+                documents.append(&mut state.buffer);
+                state.buffer = documents;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Next part number of a directory
+fn next_part_number(dir: &Path) -> Result<u32> {
+    Ok(part_files(dir)?
+        .iter()
+        .filter(|path| path.parent() == Some(dir))
+        .filter_map(|path| part_number(path))
+        .max()
+        .unwrap_or(0)
+        + 1)
+}
+
+/// Write a record batch to a part file of a directory
+fn write_file(
+    dir: &Path,
+    number: u32,
+    batch: arrow::record_batch::RecordBatch,
+    properties: WriterProperties,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+
+    let path = dir.join(format!("part-{number}.parquet"));
+
+    // The leading dot keeps the unfinished file out of `part_files`
+    let temp = dir.join(format!(".part-{number}.parquet.tmp"));
+    let file = File::create(&temp)?;
+    let mut writer =
+        ArrowWriter::try_new(file, batch.schema(), Some(properties)).map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+    std::fs::rename(&temp, &path)?;
+
+    Ok(path)
+}
+
+/// Schema of the most recent part file of a dataset, or an empty schema
+fn latest_schema(dataset: &Path) -> Result<SchemaRef> {
+    // Part files are listed oldest first, and each one carries all columns so far
+    match part_files(dataset)?.last() {
+        Some(path) => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)
+                .map_err(parquet_error)?;
+            Ok(builder.schema().clone())
+        }
+        None => Ok(Arc::new(Schema::empty())),
+    }
+}
+
+/// Part number of a `part-N.parquet` file name
+fn part_number(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("part-")?
+        .strip_suffix(".parquet")?
+        .parse()
+        .ok()
+}
+
+/// Read columns of all part files of a dataset as text, oldest first
+///
+/// Columns a file doesn't have are returned as nulls.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read.
+pub fn read_text_columns(dataset: &Path, columns: &[&str]) -> Result<Vec<Vec<Option<String>>>> {
+    let mut rows = Vec::new();
+
+    for path in part_files(dataset)? {
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&path)?).map_err(parquet_error)?;
+        let indices: Vec<Option<usize>> = columns
+            .iter()
+            .map(|name| builder.schema().index_of(name).ok())
+            .collect();
+        let mask =
+            ProjectionMask::roots(builder.parquet_schema(), indices.iter().flatten().copied());
+        let reader = builder
+            .with_projection(mask)
+            .build()
+            .map_err(parquet_error)?;
+
+        for batch in reader {
+            let batch = batch.map_err(|e| AtlasError::Serialization(e.to_string()))?;
+            let arrays = columns
+                .iter()
+                .map(|name| {
+                    batch
+                        .column_by_name(name)
+                        .map(|array| cast(array, &DataType::Utf8))
+                        .transpose()
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| AtlasError::Serialization(e.to_string()))?;
+
+            for row in 0..batch.num_rows() {
+                rows.push(
+                    arrays
+                        .iter()
+                        .map(|array| {
+                            let strings = array.as_ref()?.as_string::<i32>();
+                            strings
+                                .is_valid(row)
+                                .then(|| strings.value(row).to_string())
+                        })
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Convert a Parquet error into an Atlas error
+fn parquet_error(error: parquet::errors::ParquetError) -> AtlasError {
+    AtlasError::Io(format!("Parquet error: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_write_parts_and_evolve_schema() {
+        let root = tempfile::tempdir().unwrap();
+        let dataset = root.path().join("vital_signs_v1");
+        let dir = dataset.join("2025-01-15");
+        let part = |number: u32| dir.join(format!("part-{number}.parquet"));
+
+        let writer = ParquetWriter::new(ParquetCompression::Zstd, 2);
+        let progress = writer
+            .append(
+                &dataset,
+                &dir,
+                vec![json!({"id": "a", "pulse_magnitude": 72})],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            progress,
+            Progress {
+                appended: 1,
+                written: 0
+            }
+        );
+        assert!(!part(1).exists());
+        assert_eq!(writer.pending(&dataset).await.len(), 1);
+
+        // A file is written once the buffer holds two rows
+        let progress = writer
+            .append(
+                &dataset,
+                &dir,
+                vec![
+                    json!({"id": "b", "temp_magnitude": 37.5, "deleted_at": "2025-01-15T10:30:00Z"}),
+                    json!({"id": "c"}),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(progress.written, 2);
+        assert!(part(1).is_file());
+
+        let progress = writer.flush(&dataset).await.unwrap();
+        assert_eq!(progress.written, 3);
+        assert!(part(2).is_file());
+        assert!(writer.pending(&dataset).await.is_empty());
+
+        let rows = read_text_columns(&dataset, &["id", "deleted_at", "missing"]).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("a".to_string()), None, None],
+                vec![
+                    Some("b".to_string()),
+                    Some("2025-01-15T10:30:00Z".to_string()),
+                    None
+                ],
+                vec![Some("c".to_string()), None, None],
+            ]
+        );
+
+        // A new writer picks up the schema and numbering of the dataset
+        let writer = ParquetWriter::new(ParquetCompression::Snappy, 100);
+        writer
+            .append(&dataset, &dir, vec![json!({"id": "d"})])
+            .await
+            .unwrap();
+        writer.flush_all().await.unwrap();
+        assert!(part(3).is_file());
+
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(part(3)).unwrap()).unwrap();
+        let names: Vec<&str> = builder
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["deleted_at", "id", "pulse_magnitude", "temp_magnitude"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_rows_written_before_directory_change() {
+        let root = tempfile::tempdir().unwrap();
+        let dataset = root.path().join("_ehrs");
+
+        let writer = ParquetWriter::new(ParquetCompression::None, 100);
+        writer
+            .append(
+                &dataset,
+                &dataset.join("2025-01-15"),
+                vec![json!({"id": "a"})],
+            )
+            .await
+            .unwrap();
+        let progress = writer
+            .append(
+                &dataset,
+                &dataset.join("2025-01-16"),
+                vec![json!({"id": "b"})],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(progress.written, 1);
+        assert!(dataset.join("2025-01-15/part-1.parquet").is_file());
+        assert!(!dataset.join("2025-01-16").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_keeps_appended_rows() {
+        let root = tempfile::tempdir().unwrap();
+        let dataset = root.path().join("vital_signs_v1");
+        let blocked = dataset.join("2025-01-15");
+        std::fs::create_dir_all(&dataset).unwrap();
+        std::fs::write(&blocked, "not a directory").unwrap();
+
+        // The documents are pending even though their file can't be written
+        let writer = ParquetWriter::new(ParquetCompression::None, 2);
+        let progress = writer
+            .append(
+                &dataset,
+                &blocked,
+                vec![json!({"id": "a"}), json!({"id": "b"}), json!({"id": "c"})],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            progress,
+            Progress {
+                appended: 3,
+                written: 0
+            }
+        );
+        assert_eq!(writer.pending(&dataset).await.len(), 3);
+
+        // Documents of another directory are refused while those can't be written
+        assert!(writer
+            .append(
+                &dataset,
+                &dataset.join("2025-01-16"),
+                vec![json!({"id": "d"})]
+            )
+            .await
+            .is_err());
+        let ids: Vec<_> = writer
+            .pending(&dataset)
+            .await
+            .iter()
+            .map(|doc| doc["id"].clone())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+
+        std::fs::remove_file(&blocked).unwrap();
+        let progress = writer.flush(&dataset).await.unwrap();
+        assert_eq!(
+            progress,
+            Progress {
+                appended: 3,
+                written: 3
+            }
+        );
+        assert!(blocked.join("part-1.parquet").is_file());
+    }
+}
@@ -4,13 +4,19 @@
 //! for PostgreSQL.
 
 use crate::adapters::database::traits::{
-    BulkInsertFailure, BulkInsertResult, DatabaseClient, StateStorage,
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
 };
 use crate::adapters::postgresql::client::PostgreSQLClient;
-use crate::adapters::postgresql::models::{PostgreSQLComposition, PostgreSQLWatermark};
-use crate::core::state::watermark::Watermark;
+use crate::adapters::postgresql::models::{
+    PostgreSQLComposition, PostgreSQLDeadLetter, PostgreSQLEhr, PostgreSQLQueryWatermark,
+    PostgreSQLWatermark,
+};
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
 use crate::domain::composition::Composition;
-use crate::domain::ids::{EhrId, TemplateId};
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use crate::domain::{AtlasError, Result};
 use async_trait::async_trait;
 use std::any::Any;
@@ -65,11 +71,137 @@ impl DatabaseClient for PostgreSQLAdapter {
         self.client.ensure_watermarks_table_exists().await
     }
 
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        // No-op: the ehrs table is created in ensure_database_exists
+        Ok(())
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would save EHR status to PostgreSQL"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLEhr::from_domain(ehr);
+
+        let upsert_query = r#"
+            INSERT INTO ehrs (
+                ehr_id, system_id, time_created, ehr_status_uid,
+                subject_id, subject_id_scheme, subject_namespace, subject_type,
+                is_queryable, is_modifiable, other_details, exported_at, atlas_version
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW(), $12)
+            ON CONFLICT (ehr_id) DO UPDATE SET
+                system_id = EXCLUDED.system_id,
+                time_created = EXCLUDED.time_created,
+                ehr_status_uid = EXCLUDED.ehr_status_uid,
+                subject_id = EXCLUDED.subject_id,
+                subject_id_scheme = EXCLUDED.subject_id_scheme,
+                subject_namespace = EXCLUDED.subject_namespace,
+                subject_type = EXCLUDED.subject_type,
+                is_queryable = EXCLUDED.is_queryable,
+                is_modifiable = EXCLUDED.is_modifiable,
+                other_details = EXCLUDED.other_details,
+                exported_at = EXCLUDED.exported_at,
+                atlas_version = EXCLUDED.atlas_version
+        "#;
+
+        self.client
+            .execute(
+                upsert_query,
+                &[
+                    &row.ehr_id,
+                    &row.system_id,
+                    &row.time_created,
+                    &row.ehr_status_uid,
+                    &row.subject_id,
+                    &row.subject_id_scheme,
+                    &row.subject_namespace,
+                    &row.subject_type,
+                    &row.is_queryable,
+                    &row.is_modifiable,
+                    &row.other_details,
+                    &row.atlas_version,
+                ],
+            )
+            .await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status saved to PostgreSQL");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        self.client.ensure_query_table_exists(query_name).await
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would save {} query rows to PostgreSQL",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let upsert_query = format!(
+            r#"
+            INSERT INTO "{query_name}" (id, data, exported_at, atlas_version)
+            VALUES ($1, $2, NOW(), $3)
+            ON CONFLICT (id) DO UPDATE SET
+                data = EXCLUDED.data,
+                exported_at = EXCLUDED.exported_at,
+                atlas_version = EXCLUDED.atlas_version
+            "#
+        );
+        let atlas_version = env!("CARGO_PKG_VERSION");
+
+        let mut success_count = 0;
+        let mut failures = Vec::new();
+
+        for row in rows {
+            let data = serde_json::Value::Object(row.data);
+
+            match self
+                .client
+                .execute(&upsert_query, &[&row.id, &data, &atlas_version])
+                .await
+            {
+                Ok(_) => success_count += 1,
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id: row.id,
+                    error: e.to_string(),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
     async fn bulk_insert_json(
         &self,
         _template_id: &TemplateId,
         documents: Vec<serde_json::Value>,
-        _max_retries: usize,
+        max_retries: usize,
         dry_run: bool,
     ) -> Result<BulkInsertResult> {
         // If dry-run, skip actual write and return success
@@ -86,6 +218,11 @@ impl DatabaseClient for PostgreSQLAdapter {
             });
         }
 
+        let retry = self
+            .client
+            .retry_policy()
+            .clone()
+            .with_max_retries(max_retries);
         let mut success_count = 0;
         let mut failures = Vec::new();
 
@@ -133,14 +270,19 @@ impl DatabaseClient for PostgreSQLAdapter {
             let insert_query = r#"
                 INSERT INTO compositions (
                     id, ehr_id, composition_uid, template_id, time_committed,
-                    content, export_mode, exported_at, atlas_version, checksum
+                    content, export_mode, exported_at, atlas_version, checksum,
+                    version_number, lifecycle_state, change_type, composition_format
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
                 ON CONFLICT (id) DO UPDATE SET
                     time_committed = EXCLUDED.time_committed,
                     content = EXCLUDED.content,
                     exported_at = EXCLUDED.exported_at,
-                    checksum = EXCLUDED.checksum
+                    checksum = EXCLUDED.checksum,
+                    version_number = COALESCE(EXCLUDED.version_number, compositions.version_number),
+                    lifecycle_state = COALESCE(EXCLUDED.lifecycle_state, compositions.lifecycle_state),
+                    change_type = COALESCE(EXCLUDED.change_type, compositions.change_type),
+                    composition_format = EXCLUDED.composition_format
             "#;
 
             // Convert content to serde_json::Value for ToSql
@@ -149,7 +291,8 @@ impl DatabaseClient for PostgreSQLAdapter {
 
             match self
                 .client
-                .execute(
+                .execute_with_retry(
+                    &retry,
                     insert_query,
                     &[
                         &pg_comp.id,
@@ -162,6 +305,10 @@ impl DatabaseClient for PostgreSQLAdapter {
                         &pg_comp.exported_at,
                         &pg_comp.atlas_version,
                         &pg_comp.checksum,
+                        &pg_comp.version_number,
+                        &pg_comp.lifecycle_state,
+                        &pg_comp.change_type,
+                        &pg_comp.composition_format,
                     ],
                 )
                 .await
@@ -191,7 +338,7 @@ impl DatabaseClient for PostgreSQLAdapter {
         _template_id: &TemplateId,
         compositions: Vec<Composition>,
         export_mode: String,
-        _max_retries: usize,
+        max_retries: usize,
         dry_run: bool,
     ) -> Result<BulkInsertResult> {
         // If dry-run, skip actual write and return success
@@ -208,6 +355,11 @@ impl DatabaseClient for PostgreSQLAdapter {
             });
         }
 
+        let retry = self
+            .client
+            .retry_policy()
+            .clone()
+            .with_max_retries(max_retries);
         let mut success_count = 0;
         let mut failures = Vec::new();
 
@@ -234,13 +386,18 @@ impl DatabaseClient for PostgreSQLAdapter {
             let insert_query = r#"
                 INSERT INTO compositions (
                     id, ehr_id, composition_uid, template_id, time_committed,
-                    content, export_mode, exported_at, atlas_version, checksum
+                    content, export_mode, exported_at, atlas_version, checksum,
+                    version_number, lifecycle_state, change_type, composition_format
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
                 ON CONFLICT (id) DO UPDATE SET
                     time_committed = EXCLUDED.time_committed,
                     content = EXCLUDED.content,
-                    exported_at = EXCLUDED.exported_at
+                    exported_at = EXCLUDED.exported_at,
+                    version_number = COALESCE(EXCLUDED.version_number, compositions.version_number),
+                    lifecycle_state = COALESCE(EXCLUDED.lifecycle_state, compositions.lifecycle_state),
+                    change_type = COALESCE(EXCLUDED.change_type, compositions.change_type),
+                    composition_format = EXCLUDED.composition_format
             "#;
 
             let content_json = serde_json::to_value(&pg_comp.content).map_err(|e| {
@@ -249,7 +406,8 @@ impl DatabaseClient for PostgreSQLAdapter {
 
             match self
                 .client
-                .execute(
+                .execute_with_retry(
+                    &retry,
                     insert_query,
                     &[
                         &pg_comp.id,
@@ -262,6 +420,10 @@ impl DatabaseClient for PostgreSQLAdapter {
                         &pg_comp.exported_at,
                         &pg_comp.atlas_version,
                         &pg_comp.checksum,
+                        &pg_comp.version_number,
+                        &pg_comp.lifecycle_state,
+                        &pg_comp.change_type,
+                        &pg_comp.composition_format,
                     ],
                 )
                 .await
@@ -296,7 +458,7 @@ impl DatabaseClient for PostgreSQLAdapter {
         _template_id: &TemplateId,
         compositions: Vec<Composition>,
         export_mode: String,
-        _max_retries: usize,
+        max_retries: usize,
         dry_run: bool,
     ) -> Result<BulkInsertResult> {
         // If dry-run, skip actual write and return success
@@ -313,6 +475,11 @@ impl DatabaseClient for PostgreSQLAdapter {
             });
         }
 
+        let retry = self
+            .client
+            .retry_policy()
+            .clone()
+            .with_max_retries(max_retries);
         let mut success_count = 0;
         let mut failures = Vec::new();
 
@@ -339,13 +506,18 @@ impl DatabaseClient for PostgreSQLAdapter {
             let insert_query = r#"
                 INSERT INTO compositions (
                     id, ehr_id, composition_uid, template_id, time_committed,
-                    content, export_mode, exported_at, atlas_version, checksum
+                    content, export_mode, exported_at, atlas_version, checksum,
+                    version_number, lifecycle_state, change_type, composition_format
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
                 ON CONFLICT (id) DO UPDATE SET
                     time_committed = EXCLUDED.time_committed,
                     content = EXCLUDED.content,
-                    exported_at = EXCLUDED.exported_at
+                    exported_at = EXCLUDED.exported_at,
+                    version_number = COALESCE(EXCLUDED.version_number, compositions.version_number),
+                    lifecycle_state = COALESCE(EXCLUDED.lifecycle_state, compositions.lifecycle_state),
+                    change_type = COALESCE(EXCLUDED.change_type, compositions.change_type),
+                    composition_format = EXCLUDED.composition_format
             "#;
 
             let content_json = serde_json::to_value(&pg_comp.content).map_err(|e| {
@@ -354,7 +526,8 @@ impl DatabaseClient for PostgreSQLAdapter {
 
             match self
                 .client
-                .execute(
+                .execute_with_retry(
+                    &retry,
                     insert_query,
                     &[
                         &pg_comp.id,
@@ -367,6 +540,10 @@ impl DatabaseClient for PostgreSQLAdapter {
                         &pg_comp.exported_at,
                         &pg_comp.atlas_version,
                         &pg_comp.checksum,
+                        &pg_comp.version_number,
+                        &pg_comp.lifecycle_state,
+                        &pg_comp.change_type,
+                        &pg_comp.composition_format,
                     ],
                 )
                 .await
@@ -417,6 +594,72 @@ impl DatabaseClient for PostgreSQLAdapter {
         }
     }
 
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let query = "SELECT id FROM compositions \
+                     WHERE ehr_id = $1 AND template_id = $2 AND deleted_at IS NULL";
+
+        let rows = self
+            .client
+            .query(query, &[&ehr_id.as_str(), &template_id.as_str()])
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get::<_, String>(0)).collect())
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in PostgreSQL",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let statement = match policy {
+            DeletionPolicy::HardDelete => {
+                "DELETE FROM compositions WHERE ehr_id = $1 AND id = ANY($2)"
+            }
+            _ => {
+                "UPDATE compositions SET deleted_at = NOW() \
+                 WHERE ehr_id = $1 AND id = ANY($2) AND deleted_at IS NULL"
+            }
+        };
+
+        let affected = self
+            .client
+            .execute(statement, &[&ehr_id.as_str(), &composition_ids])
+            .await?;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = affected,
+            policy = %policy,
+            "Applied deletion policy in PostgreSQL"
+        );
+
+        Ok(affected as usize)
+    }
+
     fn database_name(&self) -> &str {
         "postgresql"
     }
@@ -571,4 +814,174 @@ impl StateStorage for PostgreSQLAdapter {
 
         Ok(watermarks)
     }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        let query = "SELECT * FROM query_watermarks WHERE id = $1";
+        let rows = self
+            .client
+            .query(query, &[&QueryWatermark::generate_id(query_name)])
+            .await?;
+
+        Ok(rows.first().map(|row| {
+            PostgreSQLQueryWatermark {
+                id: row.get("id"),
+                query_name: row.get("query_name"),
+                watermark_value: row.get("watermark_value"),
+                rows_exported_count: row.get("rows_exported_count"),
+                last_export_started_at: row.get("last_export_started_at"),
+                last_export_completed_at: row.get("last_export_completed_at"),
+                last_export_status: row.get("last_export_status"),
+            }
+            .to_domain()
+        }))
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to PostgreSQL"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLQueryWatermark::from_domain(watermark);
+
+        let upsert_query = r#"
+            INSERT INTO query_watermarks (
+                id, query_name, watermark_value, rows_exported_count,
+                last_export_started_at, last_export_completed_at, last_export_status
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO UPDATE SET
+                watermark_value = EXCLUDED.watermark_value,
+                rows_exported_count = EXCLUDED.rows_exported_count,
+                last_export_started_at = EXCLUDED.last_export_started_at,
+                last_export_completed_at = EXCLUDED.last_export_completed_at,
+                last_export_status = EXCLUDED.last_export_status
+        "#;
+
+        self.client
+            .execute(
+                upsert_query,
+                &[
+                    &row.id,
+                    &row.query_name,
+                    &row.watermark_value,
+                    &row.rows_exported_count,
+                    &row.last_export_started_at,
+                    &row.last_export_completed_at,
+                    &row.last_export_status,
+                ],
+            )
+            .await?;
+
+        tracing::debug!(query = %watermark.query_name, "Query watermark saved to PostgreSQL");
+
+        Ok(())
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        let query = "SELECT * FROM dead_letters WHERE id = $1";
+        let rows = self
+            .client
+            .query(query, &[&DeadLetter::generate_id(composition_uid)])
+            .await?;
+
+        rows.first()
+            .map(|row| dead_letter_from_row(row).to_domain())
+            .transpose()
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to PostgreSQL"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLDeadLetter::from_domain(dead_letter)?;
+
+        let upsert_query = r#"
+            INSERT INTO dead_letters (
+                id, composition_uid, template_id, ehr_id, time_committed, version,
+                stage, error, attempt_count, first_failed_at, last_failed_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (id) DO UPDATE SET
+                version = EXCLUDED.version,
+                stage = EXCLUDED.stage,
+                error = EXCLUDED.error,
+                attempt_count = EXCLUDED.attempt_count,
+                last_failed_at = EXCLUDED.last_failed_at
+        "#;
+
+        self.client
+            .execute(
+                upsert_query,
+                &[
+                    &row.id,
+                    &row.composition_uid,
+                    &row.template_id,
+                    &row.ehr_id,
+                    &row.time_committed,
+                    &row.version,
+                    &row.stage,
+                    &row.error,
+                    &row.attempt_count,
+                    &row.first_failed_at,
+                    &row.last_failed_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from PostgreSQL"
+            );
+            return Ok(());
+        }
+
+        self.client
+            .execute("DELETE FROM dead_letters WHERE id = $1", &[&dead_letter.id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let query = "SELECT * FROM dead_letters ORDER BY first_failed_at";
+        let rows = self.client.query(query, &[]).await?;
+
+        rows.iter()
+            .map(|row| dead_letter_from_row(row).to_domain())
+            .collect()
+    }
+}
+
+/// Read a `dead_letters` row
+fn dead_letter_from_row(row: &tokio_postgres::Row) -> PostgreSQLDeadLetter {
+    PostgreSQLDeadLetter {
+        id: row.get("id"),
+        composition_uid: row.get("composition_uid"),
+        template_id: row.get("template_id"),
+        ehr_id: row.get("ehr_id"),
+        time_committed: row.get("time_committed"),
+        version: row.get("version"),
+        stage: row.get("stage"),
+        error: row.get("error"),
+        attempt_count: row.get("attempt_count"),
+        first_failed_at: row.get("first_failed_at"),
+        last_failed_at: row.get("last_failed_at"),
+    }
 }
@@ -2,11 +2,15 @@
 //!
 //! This module provides the client for interacting with PostgreSQL.
 
+use crate::adapters::retry::RetryPolicy;
 use crate::config::schema::PostgreSQLConfig;
 use crate::domain::{AtlasError, Result};
-use deadpool_postgres::{Config as PoolConfig, Manager, ManagerConfig, Pool, RecyclingMethod};
+use deadpool_postgres::{
+    Config as PoolConfig, Manager, ManagerConfig, Pool, PoolError, RecyclingMethod,
+};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::error::SqlState;
 use tokio_postgres::Row;
 
 /// PostgreSQL client for Atlas
@@ -19,6 +23,9 @@ pub struct PostgreSQLClient {
 
     /// Configuration
     config: PostgreSQLConfig,
+
+    /// Retry policy for transient failures
+    retry: RetryPolicy,
 }
 
 impl PostgreSQLClient {
@@ -208,7 +215,17 @@ impl PostgreSQLClient {
 
         tracing::info!("PostgreSQL connection test successful");
 
-        Ok(Self { pool, config })
+        Ok(Self {
+            pool,
+            config,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Set the retry policy for statements that fail transiently
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     /// Test the connection to PostgreSQL
@@ -230,7 +247,8 @@ impl PostgreSQLClient {
 
     /// Ensure the database schema exists
     ///
-    /// This runs the migration SQL to create tables and indexes if they don't exist.
+    /// This runs the migration SQL to create tables and indexes if they don't exist,
+    /// followed by the additive migrations that bring existing tables up to date.
     ///
     /// # Errors
     ///
@@ -240,8 +258,22 @@ impl PostgreSQLClient {
             AtlasError::Database(format!("Failed to get connection from pool: {e}"))
         })?;
 
-        // Read migration SQL
-        let migration_sql = include_str!("../../../migrations/001_initial_schema.sql");
+        // Read migration SQL (all migrations are idempotent and run in order)
+        let migration_sql = concat!(
+            include_str!("../../../migrations/001_initial_schema.sql"),
+            "\n",
+            include_str!("../../../migrations/002_composition_versions.sql"),
+            "\n",
+            include_str!("../../../migrations/003_composition_deletions.sql"),
+            "\n",
+            include_str!("../../../migrations/004_composition_format.sql"),
+            "\n",
+            include_str!("../../../migrations/005_ehrs.sql"),
+            "\n",
+            include_str!("../../../migrations/006_query_watermarks.sql"),
+            "\n",
+            include_str!("../../../migrations/007_dead_letters.sql"),
+        );
 
         // Execute migration
         client.batch_execute(migration_sql).await.map_err(|e| {
@@ -309,20 +341,64 @@ impl PostgreSQLClient {
         Ok(())
     }
 
+    /// Ensure the result table of a named AQL query exists
+    ///
+    /// Query tables are not part of the migrations since their names come
+    /// from the configuration. The name has been validated as a plain SQL
+    /// identifier and is quoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - Name of the query, used as the table name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table cannot be created.
+    pub async fn ensure_query_table_exists(&self, query_name: &str) -> Result<()> {
+        let statement = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS "{query_name}" (
+                id TEXT PRIMARY KEY,
+                data JSONB NOT NULL,
+                exported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                atlas_version TEXT NOT NULL
+            )
+            "#
+        );
+
+        self.execute(&statement, &[]).await?;
+
+        tracing::debug!(table = %query_name, "Query table ready");
+        Ok(())
+    }
+
     /// Get a connection from the pool
     ///
     /// # Errors
     ///
     /// Returns an error if a connection cannot be obtained.
     pub async fn get_connection(&self) -> Result<deadpool_postgres::Object> {
-        self.pool
-            .get()
-            .await
-            .map_err(|e| AtlasError::Database(format!("Failed to get connection from pool: {e}")))
+        self.pool.get().await.map_err(|e| {
+            let message = format!("Failed to get connection from pool: {e}");
+            let transient = match &e {
+                PoolError::Timeout(_) => true,
+                PoolError::Backend(e) => is_transient(e),
+                _ => false,
+            };
+
+            if transient {
+                AtlasError::Connection(message)
+            } else {
+                AtlasError::Database(message)
+            }
+        })
     }
 
     /// Execute a query and return rows
     ///
+    /// Transient failures (lost connections, serialization failures,
+    /// deadlocks) are retried with the client's retry policy.
+    ///
     /// # Arguments
     ///
     /// * `query` - SQL query
@@ -335,6 +411,14 @@ impl PostgreSQLClient {
         &self,
         query: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<Vec<Row>> {
+        self.retry.retry(|| self.query_once(query, params)).await
+    }
+
+    async fn query_once(
+        &self,
+        query: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
     ) -> Result<Vec<Row>> {
         let client = self.get_connection().await?;
 
@@ -346,16 +430,18 @@ impl PostgreSQLClient {
         client
             .execute(&timeout_query, &[])
             .await
-            .map_err(|e| AtlasError::Database(format!("Failed to set statement timeout: {e}")))?;
+            .map_err(|e| database_error("Failed to set statement timeout", &e))?;
 
         client
             .query(query, params)
             .await
-            .map_err(|e| AtlasError::Database(format!("Query failed: {e}")))
+            .map_err(|e| database_error("Query failed", &e))
     }
 
     /// Execute a statement and return the number of affected rows
     ///
+    /// Transient failures are retried with the client's retry policy.
+    ///
     /// # Arguments
     ///
     /// * `statement` - SQL statement
@@ -368,6 +454,34 @@ impl PostgreSQLClient {
         &self,
         statement: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<u64> {
+        self.execute_with_retry(&self.retry, statement, params)
+            .await
+    }
+
+    /// Execute a statement with a specific retry policy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statement fails.
+    pub async fn execute_with_retry(
+        &self,
+        retry: &RetryPolicy,
+        statement: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<u64> {
+        retry.retry(|| self.execute_once(statement, params)).await
+    }
+
+    /// Retry policy of the client
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    async fn execute_once(
+        &self,
+        statement: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
     ) -> Result<u64> {
         let client = self.get_connection().await?;
 
@@ -379,12 +493,12 @@ impl PostgreSQLClient {
         client
             .execute(&timeout_query, &[])
             .await
-            .map_err(|e| AtlasError::Database(format!("Failed to set statement timeout: {e}")))?;
+            .map_err(|e| database_error("Failed to set statement timeout", &e))?;
 
         client
             .execute(statement, params)
             .await
-            .map_err(|e| AtlasError::Database(format!("Statement execution failed: {e}")))
+            .map_err(|e| database_error("Statement execution failed", &e))
     }
 
     /// Get the connection string (without password)
@@ -406,11 +520,53 @@ impl PostgreSQLClient {
     }
 }
 
+/// Wrap a PostgreSQL error, as a connection error if it is transient
+fn database_error(context: &str, error: &tokio_postgres::Error) -> AtlasError {
+    let message = format!("{context}: {error}");
+
+    if is_transient(error) {
+        AtlasError::Connection(message)
+    } else {
+        AtlasError::Database(message)
+    }
+}
+
+/// Whether a PostgreSQL error may succeed if retried
+fn is_transient(error: &tokio_postgres::Error) -> bool {
+    error.is_closed() || error.code().is_some_and(is_transient_state)
+}
+
+/// Whether an SQLSTATE denotes a transient failure
+///
+/// Connection exceptions (class 08), serialization failures, deadlocks, too
+/// many connections and server shutdown or startup are transient.
+fn is_transient_state(state: &SqlState) -> bool {
+    state.code().starts_with("08")
+        || [
+            SqlState::T_R_SERIALIZATION_FAILURE,
+            SqlState::T_R_DEADLOCK_DETECTED,
+            SqlState::TOO_MANY_CONNECTIONS,
+            SqlState::ADMIN_SHUTDOWN,
+            SqlState::CRASH_SHUTDOWN,
+            SqlState::CANNOT_CONNECT_NOW,
+        ]
+        .contains(state)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tokio_postgres::NoTls;
 
+    #[test]
+    fn test_is_transient_state() {
+        assert!(is_transient_state(&SqlState::CONNECTION_FAILURE));
+        assert!(is_transient_state(&SqlState::T_R_SERIALIZATION_FAILURE));
+        assert!(is_transient_state(&SqlState::ADMIN_SHUTDOWN));
+        assert!(!is_transient_state(&SqlState::UNIQUE_VIOLATION));
+        assert!(!is_transient_state(&SqlState::SYNTAX_ERROR));
+    }
+
     #[test]
     fn test_connection_string_safe() {
         use crate::config::secret::SecretValue;
@@ -438,6 +594,7 @@ mod tests {
             .build()
             .unwrap(),
             config: config.clone(),
+            retry: RetryPolicy::default(),
         };
 
         let safe_str = client.connection_string_safe();
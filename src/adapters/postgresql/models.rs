@@ -3,9 +3,11 @@
 //! This module defines the document structures used when storing compositions
 //! in PostgreSQL.
 
-use crate::core::state::watermark::{ExportStatus, Watermark};
-use crate::domain::composition::Composition;
-use crate::domain::ids::{EhrId, TemplateId};
+use crate::core::state::dead_letter::{DeadLetter, DeadLetterStage};
+use crate::core::state::watermark::{ExportStatus, QueryWatermark, Watermark};
+use crate::domain::composition::{Composition, CompositionVersion};
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use crate::domain::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -46,6 +48,18 @@ pub struct PostgreSQLComposition {
 
     /// Checksum of the composition content (SHA-256)
     pub checksum: Option<String>,
+
+    /// Version number (version history exports only)
+    pub version_number: Option<i32>,
+
+    /// Lifecycle state of the version (version history exports only)
+    pub lifecycle_state: Option<String>,
+
+    /// Change type of the version (version history exports only)
+    pub change_type: Option<String>,
+
+    /// Format the content was retrieved in (flat, structured, canonical_json, canonical_xml)
+    pub composition_format: String,
 }
 
 impl PostgreSQLComposition {
@@ -55,6 +69,8 @@ impl PostgreSQLComposition {
         let ehr_id = composition.ehr_id.to_string();
         let composition_uid = composition.uid.to_string();
         let template_id = composition.template_id.to_string();
+        let (version_number, lifecycle_state, change_type) = version_columns(composition.version);
+        let composition_format = composition.content_format.to_string();
 
         Ok(Self {
             id,
@@ -67,6 +83,10 @@ impl PostgreSQLComposition {
             exported_at: Utc::now(),
             atlas_version: env!("CARGO_PKG_VERSION").to_string(),
             checksum: None,
+            version_number,
+            lifecycle_state,
+            change_type,
+            composition_format,
         })
     }
 
@@ -77,6 +97,9 @@ impl PostgreSQLComposition {
         let composition_uid = composition.uid.to_string();
         let template_id = composition.template_id.to_string();
 
+        let (version_number, lifecycle_state, change_type) = version_columns(composition.version);
+        let composition_format = composition.content_format.to_string();
+
         // Flatten the content
         let flattened_content = flatten_json(&composition.content);
 
@@ -91,6 +114,10 @@ impl PostgreSQLComposition {
             exported_at: Utc::now(),
             atlas_version: env!("CARGO_PKG_VERSION").to_string(),
             checksum: None,
+            version_number,
+            lifecycle_state,
+            change_type,
+            composition_format,
         })
     }
 
@@ -173,6 +200,19 @@ impl PostgreSQLComposition {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let version = json
+            .get("version")
+            .cloned()
+            .and_then(|v| serde_json::from_value::<CompositionVersion>(v).ok());
+        let (version_number, lifecycle_state, change_type) = version_columns(version);
+
+        let composition_format = json
+            .get("atlas_metadata")
+            .and_then(|m| m.get("composition_format"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("flat")
+            .to_string();
+
         Ok(Self {
             id,
             ehr_id,
@@ -184,6 +224,10 @@ impl PostgreSQLComposition {
             exported_at,
             atlas_version,
             checksum,
+            version_number,
+            lifecycle_state,
+            change_type,
+            composition_format,
         })
     }
 
@@ -270,6 +314,19 @@ impl PostgreSQLComposition {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let version = json
+            .get("version")
+            .cloned()
+            .and_then(|v| serde_json::from_value::<CompositionVersion>(v).ok());
+        let (version_number, lifecycle_state, change_type) = version_columns(version);
+
+        let composition_format = json
+            .get("atlas_metadata")
+            .and_then(|m| m.get("composition_format"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("flat")
+            .to_string();
+
         Ok(Self {
             id,
             ehr_id,
@@ -281,10 +338,28 @@ impl PostgreSQLComposition {
             exported_at,
             atlas_version,
             checksum,
+            version_number,
+            lifecycle_state,
+            change_type,
+            composition_format,
         })
     }
 }
 
+/// Split optional version information into its column values
+fn version_columns(
+    version: Option<CompositionVersion>,
+) -> (Option<i32>, Option<String>, Option<String>) {
+    match version {
+        Some(version) => (
+            i32::try_from(version.version_number).ok(),
+            version.lifecycle_state,
+            version.change_type,
+        ),
+        None => (None, None, None),
+    }
+}
+
 /// Watermark document for PostgreSQL storage
 ///
 /// This structure maps to the `watermarks` table in PostgreSQL.
@@ -333,13 +408,7 @@ impl PostgreSQLWatermark {
             compositions_exported_count: watermark.compositions_exported_count as i64,
             last_export_started_at: watermark.last_export_started_at,
             last_export_completed_at: watermark.last_export_completed_at,
-            last_export_status: match watermark.last_export_status {
-                ExportStatus::InProgress => "in_progress".to_string(),
-                ExportStatus::Completed => "completed".to_string(),
-                ExportStatus::Failed => "failed".to_string(),
-                ExportStatus::Interrupted => "interrupted".to_string(),
-                ExportStatus::NotStarted => "not_started".to_string(),
-            },
+            last_export_status: status_to_str(&watermark.last_export_status).to_string(),
         }
     }
 
@@ -358,14 +427,7 @@ impl PostgreSQLWatermark {
             None
         };
 
-        let last_export_status = match self.last_export_status.as_str() {
-            "in_progress" => ExportStatus::InProgress,
-            "completed" => ExportStatus::Completed,
-            "failed" => ExportStatus::Failed,
-            "interrupted" => ExportStatus::Interrupted,
-            "not_started" => ExportStatus::NotStarted,
-            _ => ExportStatus::Failed,
-        };
+        let last_export_status = status_from_str(&self.last_export_status);
 
         Ok(Watermark {
             id: self.id.clone(),
@@ -381,6 +443,248 @@ impl PostgreSQLWatermark {
     }
 }
 
+/// EHR row for PostgreSQL storage
+///
+/// This structure maps to the `ehrs` table in PostgreSQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostgreSQLEhr {
+    /// EHR ID
+    pub ehr_id: String,
+
+    /// System ID where the EHR was created
+    pub system_id: Option<String>,
+
+    /// Time the EHR was created
+    pub time_created: DateTime<Utc>,
+
+    /// Version UID of the EHR_STATUS
+    pub ehr_status_uid: Option<String>,
+
+    /// Subject identifier in the external system
+    pub subject_id: Option<String>,
+
+    /// Identification scheme of the subject identifier
+    pub subject_id_scheme: Option<String>,
+
+    /// Namespace of the external system
+    pub subject_namespace: Option<String>,
+
+    /// Type of the subject (e.g. PERSON)
+    pub subject_type: Option<String>,
+
+    /// Whether the EHR is queryable
+    pub is_queryable: Option<bool>,
+
+    /// Whether the EHR is modifiable
+    pub is_modifiable: Option<bool>,
+
+    /// EHR_STATUS other details
+    pub other_details: Option<Value>,
+
+    /// Atlas version
+    pub atlas_version: String,
+}
+
+impl PostgreSQLEhr {
+    /// Convert from domain Ehr to PostgreSQL row
+    pub fn from_domain(ehr: &Ehr) -> Self {
+        let status = ehr.status.as_ref();
+        let subject = ehr.subject();
+
+        Self {
+            ehr_id: ehr.id.to_string(),
+            system_id: ehr.system_id.clone(),
+            time_created: ehr.time_created,
+            ehr_status_uid: status.and_then(|s| s.uid.clone()),
+            subject_id: subject.map(|s| s.id.clone()),
+            subject_id_scheme: subject.and_then(|s| s.scheme.clone()),
+            subject_namespace: subject.and_then(|s| s.namespace.clone()),
+            subject_type: subject.and_then(|s| s.party_type.clone()),
+            is_queryable: status.map(|s| s.is_queryable),
+            is_modifiable: status.map(|s| s.is_modifiable),
+            other_details: status.and_then(|s| s.other_details.clone()),
+            atlas_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Query watermark row for PostgreSQL storage
+///
+/// This structure maps to the `query_watermarks` table in PostgreSQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostgreSQLQueryWatermark {
+    /// Watermark ID
+    pub id: String,
+
+    /// Name of the AQL query
+    pub query_name: String,
+
+    /// Highest watermark column value exported
+    pub watermark_value: Option<Value>,
+
+    /// Count of rows exported
+    pub rows_exported_count: i64,
+
+    /// Timestamp when the export started
+    pub last_export_started_at: DateTime<Utc>,
+
+    /// Timestamp when the export completed
+    pub last_export_completed_at: Option<DateTime<Utc>>,
+
+    /// Export status
+    pub last_export_status: String,
+}
+
+impl PostgreSQLQueryWatermark {
+    /// Convert from domain QueryWatermark to PostgreSQL row
+    pub fn from_domain(watermark: &QueryWatermark) -> Self {
+        Self {
+            id: watermark.id.clone(),
+            query_name: watermark.query_name.clone(),
+            watermark_value: watermark.value.clone(),
+            rows_exported_count: watermark.rows_exported_count as i64,
+            last_export_started_at: watermark.last_export_started_at,
+            last_export_completed_at: watermark.last_export_completed_at,
+            last_export_status: status_to_str(&watermark.last_export_status).to_string(),
+        }
+    }
+
+    /// Convert to domain QueryWatermark
+    pub fn to_domain(&self) -> QueryWatermark {
+        QueryWatermark {
+            id: self.id.clone(),
+            query_name: self.query_name.clone(),
+            value: self.watermark_value.clone(),
+            rows_exported_count: self.rows_exported_count as u64,
+            last_export_started_at: self.last_export_started_at,
+            last_export_completed_at: self.last_export_completed_at,
+            last_export_status: status_from_str(&self.last_export_status),
+        }
+    }
+}
+
+/// Dead-letter row for PostgreSQL storage
+///
+/// This structure maps to the `dead_letters` table in PostgreSQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostgreSQLDeadLetter {
+    /// Dead-letter ID
+    pub id: String,
+
+    /// Composition UID (including version)
+    pub composition_uid: String,
+
+    /// Template ID
+    pub template_id: String,
+
+    /// EHR ID
+    pub ehr_id: String,
+
+    /// Time the composition was committed
+    pub time_committed: DateTime<Utc>,
+
+    /// Version information as JSON
+    pub version: Option<Value>,
+
+    /// Step at which the last attempt failed
+    pub stage: String,
+
+    /// Error of the last attempt
+    pub error: String,
+
+    /// Number of failed attempts
+    pub attempt_count: i32,
+
+    /// Timestamp of the first failure
+    pub first_failed_at: DateTime<Utc>,
+
+    /// Timestamp of the last failure
+    pub last_failed_at: DateTime<Utc>,
+}
+
+impl PostgreSQLDeadLetter {
+    /// Convert from domain DeadLetter to PostgreSQL row
+    pub fn from_domain(dead_letter: &DeadLetter) -> Result<Self> {
+        let version = dead_letter
+            .version
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()?;
+
+        Ok(Self {
+            id: dead_letter.id.clone(),
+            composition_uid: dead_letter.composition_uid.to_string(),
+            template_id: dead_letter.template_id.to_string(),
+            ehr_id: dead_letter.ehr_id.to_string(),
+            time_committed: dead_letter.time_committed,
+            version,
+            stage: dead_letter.stage.to_string(),
+            error: dead_letter.error.clone(),
+            attempt_count: dead_letter.attempt_count as i32,
+            first_failed_at: dead_letter.first_failed_at,
+            last_failed_at: dead_letter.last_failed_at,
+        })
+    }
+
+    /// Convert to domain DeadLetter
+    pub fn to_domain(&self) -> Result<DeadLetter> {
+        use crate::domain::AtlasError;
+
+        let version = self
+            .version
+            .clone()
+            .map(serde_json::from_value::<CompositionVersion>)
+            .transpose()?;
+
+        Ok(DeadLetter {
+            id: self.id.clone(),
+            composition_uid: CompositionUid::new(&self.composition_uid)
+                .map_err(AtlasError::Validation)?,
+            template_id: TemplateId::new(&self.template_id).map_err(AtlasError::Validation)?,
+            ehr_id: EhrId::new(&self.ehr_id).map_err(AtlasError::Validation)?,
+            time_committed: self.time_committed,
+            version,
+            stage: stage_from_str(&self.stage),
+            error: self.error.clone(),
+            attempt_count: self.attempt_count.max(0) as u32,
+            first_failed_at: self.first_failed_at,
+            last_failed_at: self.last_failed_at,
+        })
+    }
+}
+
+/// Dead-letter stage of a column value; unknown values are retried as fetches
+fn stage_from_str(stage: &str) -> DeadLetterStage {
+    match stage {
+        "version_history" => DeadLetterStage::VersionHistory,
+        "insert" => DeadLetterStage::Insert,
+        _ => DeadLetterStage::Fetch,
+    }
+}
+
+/// Column value of an export status
+fn status_to_str(status: &ExportStatus) -> &'static str {
+    match status {
+        ExportStatus::InProgress => "in_progress",
+        ExportStatus::Completed => "completed",
+        ExportStatus::Failed => "failed",
+        ExportStatus::Interrupted => "interrupted",
+        ExportStatus::NotStarted => "not_started",
+    }
+}
+
+/// Export status of a column value; unknown values are treated as failed
+fn status_from_str(status: &str) -> ExportStatus {
+    match status {
+        "in_progress" => ExportStatus::InProgress,
+        "completed" => ExportStatus::Completed,
+        "failed" => ExportStatus::Failed,
+        "interrupted" => ExportStatus::Interrupted,
+        "not_started" => ExportStatus::NotStarted,
+        _ => ExportStatus::Failed,
+    }
+}
+
 /// Flatten a JSON object into a HashMap of dot-separated paths
 ///
 /// Converts nested JSON like:
@@ -465,4 +769,149 @@ mod tests {
         assert_eq!(flattened.get("items[0].name"), Some(&json!("item1")));
         assert_eq!(flattened.get("items[1].name"), Some(&json!("item2")));
     }
+
+    #[test]
+    fn test_from_json_preserved_with_version() {
+        let json = json!({
+            "id": "84d7c3f5::local.ehrbase.org::2",
+            "ehr_id": "7d44b88c-4199-4bad-97dc-d78268e01398",
+            "composition_uid": "84d7c3f5::local.ehrbase.org::2",
+            "template_id": "vital_signs",
+            "time_committed": "2024-01-15T10:30:00Z",
+            "content": {"ctx/language": "en"},
+            "version": {
+                "version_number": 2,
+                "lifecycle_state": "complete",
+                "change_type": "amendment"
+            },
+            "atlas_metadata": {"export_mode": "preserve"}
+        });
+
+        let pg_comp = PostgreSQLComposition::from_json_preserved(json).unwrap();
+
+        assert_eq!(pg_comp.version_number, Some(2));
+        assert_eq!(pg_comp.lifecycle_state.as_deref(), Some("complete"));
+        assert_eq!(pg_comp.change_type.as_deref(), Some("amendment"));
+    }
+
+    #[test]
+    fn test_from_json_preserved_without_version() {
+        let json = json!({
+            "id": "84d7c3f5::local.ehrbase.org::1",
+            "ehr_id": "7d44b88c-4199-4bad-97dc-d78268e01398",
+            "composition_uid": "84d7c3f5::local.ehrbase.org::1",
+            "template_id": "vital_signs",
+            "time_committed": "2024-01-15T10:30:00Z",
+            "content": {"ctx/language": "en"}
+        });
+
+        let pg_comp = PostgreSQLComposition::from_json_preserved(json).unwrap();
+
+        assert_eq!(pg_comp.version_number, None);
+        assert_eq!(pg_comp.lifecycle_state, None);
+        assert_eq!(pg_comp.change_type, None);
+        assert_eq!(pg_comp.composition_format, "flat");
+    }
+
+    #[test]
+    fn test_from_domain_preserved_records_format() {
+        use crate::domain::composition::ContentFormat;
+        use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+
+        let composition = Composition::builder()
+            .uid(CompositionUid::new("84d7c3f5::local.ehrbase.org::1").unwrap())
+            .ehr_id(EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::new("vital_signs").unwrap())
+            .time_committed(Utc::now())
+            .content(json!(
+                "<composition xmlns=\"http://schemas.openehr.org/v1\"/>"
+            ))
+            .content_format(ContentFormat::CanonicalXml)
+            .build()
+            .unwrap();
+
+        let pg_comp =
+            PostgreSQLComposition::from_domain_preserved(composition, "full".to_string()).unwrap();
+
+        assert_eq!(pg_comp.composition_format, "canonical_xml");
+        assert!(pg_comp
+            .content
+            .as_str()
+            .unwrap()
+            .starts_with("<composition"));
+    }
+
+    #[test]
+    fn test_ehr_from_domain() {
+        use crate::domain::ehr::{EhrStatus, PartyRef};
+
+        let mut ehr = Ehr::new(EhrId::new("ehr-123").unwrap(), Utc::now());
+        let row = PostgreSQLEhr::from_domain(&ehr);
+        assert_eq!(row.ehr_id, "ehr-123");
+        assert!(row.subject_id.is_none());
+        assert!(row.is_queryable.is_none());
+
+        ehr.status = Some(EhrStatus {
+            uid: None,
+            subject: Some(PartyRef {
+                id: "9999999000".to_string(),
+                scheme: Some("uk.nhs.nhs_number".to_string()),
+                namespace: Some("EHR.NETWORK".to_string()),
+                party_type: Some("PERSON".to_string()),
+            }),
+            is_queryable: true,
+            is_modifiable: false,
+            other_details: Some(json!({"_type": "ITEM_TREE"})),
+        });
+
+        let row = PostgreSQLEhr::from_domain(&ehr);
+        assert_eq!(row.subject_id.as_deref(), Some("9999999000"));
+        assert_eq!(row.subject_id_scheme.as_deref(), Some("uk.nhs.nhs_number"));
+        assert_eq!(row.subject_namespace.as_deref(), Some("EHR.NETWORK"));
+        assert_eq!(row.subject_type.as_deref(), Some("PERSON"));
+        assert_eq!(row.is_queryable, Some(true));
+        assert_eq!(row.is_modifiable, Some(false));
+        assert!(row.other_details.is_some());
+    }
+
+    #[test]
+    fn test_query_watermark_round_trip() {
+        let mut watermark = QueryWatermark::new("latest_hba1c");
+        watermark.advance(&json!("2025-03-01T09:30:00Z"));
+        watermark.rows_exported_count = 12;
+        watermark.mark_completed();
+
+        let row = PostgreSQLQueryWatermark::from_domain(&watermark);
+        assert_eq!(row.id, "query_latest_hba1c");
+        assert_eq!(row.last_export_status, "completed");
+
+        let restored = row.to_domain();
+        assert_eq!(restored.value, Some(json!("2025-03-01T09:30:00Z")));
+        assert_eq!(restored.rows_exported_count, 12);
+        assert_eq!(restored.last_export_status, ExportStatus::Completed);
+    }
+
+    #[test]
+    fn test_dead_letter_round_trip() {
+        use crate::adapters::openehr::vendor::CompositionMetadata;
+
+        let mut metadata = CompositionMetadata::new(
+            CompositionUid::new("84d7c3f5::local.ehrbase.org::2").unwrap(),
+            TemplateId::new("vital_signs.v1").unwrap(),
+            EhrId::new("ehr-123").unwrap(),
+            Utc::now(),
+        );
+        metadata.version = Some(CompositionVersion {
+            version_number: 2,
+            lifecycle_state: Some("complete".to_string()),
+            change_type: Some("amendment".to_string()),
+        });
+        let dead_letter = DeadLetter::new(&metadata, DeadLetterStage::VersionHistory, "timeout");
+
+        let row = PostgreSQLDeadLetter::from_domain(&dead_letter).unwrap();
+        assert_eq!(row.stage, "version_history");
+        assert_eq!(row.version.as_ref().unwrap()["version_number"], 2);
+
+        assert_eq!(row.to_domain().unwrap(), dead_letter);
+    }
 }
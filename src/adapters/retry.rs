@@ -0,0 +1,327 @@
+//! Shared retry policy for remote operations
+//!
+//! openEHR requests, Cosmos DB writes and PostgreSQL statements are retried
+//! through [`RetryPolicy`]. Only errors that [`AtlasError::is_retryable`]
+//! classifies as transient are retried. The delay before each retry follows
+//! either an exponential backoff (`[openehr.retry]`) or an explicit schedule
+//! (`export.retry_backoff_ms`), with equal jitter so that concurrent workers
+//! don't retry in lockstep. A Retry-After hint sent by the server replaces
+//! the computed delay, capped at the maximum delay.
+
+use crate::config::schema::{ExportConfig, RetryConfig};
+use crate::domain::{AtlasError, Result};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// How delays between attempts are computed
+#[derive(Debug, Clone, PartialEq)]
+enum Backoff {
+    /// `initial * multiplier^(retry - 1)`
+    Exponential { initial: Duration, multiplier: f64 },
+
+    /// Fixed delays per retry; the last one is repeated
+    Schedule(Vec<Duration>),
+}
+
+/// Retry policy for operations against openEHR servers and databases
+///
+/// # Example
+///
+/// ```no_run
+/// use atlas::adapters::retry::RetryPolicy;
+/// use atlas::config::schema::RetryConfig;
+///
+/// # async fn example() -> atlas::domain::Result<()> {
+/// let policy = RetryPolicy::from_config(&RetryConfig::default());
+/// let value = policy.retry(|| async { Ok::<_, atlas::domain::AtlasError>(42) }).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    max_retries: usize,
+
+    /// Delays between attempts
+    backoff: Backoff,
+
+    /// Upper bound for any delay, including Retry-After
+    max_delay: Duration,
+
+    /// Whether delays are randomised
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Create a policy with exponential backoff
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Number of retries after the first attempt
+    /// * `initial_delay` - Delay before the first retry
+    /// * `max_delay` - Upper bound for any delay
+    /// * `multiplier` - Factor applied to the delay after each retry
+    pub fn exponential(
+        max_retries: usize,
+        initial_delay: Duration,
+        max_delay: Duration,
+        multiplier: f64,
+    ) -> Self {
+        Self {
+            max_retries,
+            backoff: Backoff::Exponential {
+                initial: initial_delay,
+                multiplier: multiplier.max(1.0),
+            },
+            max_delay,
+            jitter: true,
+        }
+    }
+
+    /// Create a policy with fixed delays, in milliseconds
+    ///
+    /// The last delay is used for any retry beyond the schedule, and the
+    /// longest delay caps Retry-After hints.
+    pub fn from_schedule(max_retries: usize, delays_ms: &[u64]) -> Self {
+        let delays: Vec<Duration> = delays_ms
+            .iter()
+            .copied()
+            .map(Duration::from_millis)
+            .collect();
+        let max_delay = delays.iter().max().copied().unwrap_or_default();
+
+        Self {
+            max_retries,
+            backoff: Backoff::Schedule(delays),
+            max_delay,
+            jitter: true,
+        }
+    }
+
+    /// Policy for openEHR requests (`[openehr.retry]`)
+    pub fn from_config(config: &RetryConfig) -> Self {
+        Self::exponential(
+            config.max_retries,
+            Duration::from_millis(config.initial_delay_ms),
+            Duration::from_millis(config.max_delay_ms),
+            config.backoff_multiplier,
+        )
+    }
+
+    /// Policy for database writes (`export.max_retries` and `export.retry_backoff_ms`)
+    pub fn from_export_config(config: &ExportConfig) -> Self {
+        Self::from_schedule(config.max_retries, &config.retry_backoff_ms)
+    }
+
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self::from_schedule(0, &[])
+    }
+
+    /// Set the number of retries after the first attempt
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Disable jitter, making delays deterministic
+    pub fn without_jitter(mut self) -> Self {
+        self.jitter = false;
+        self
+    }
+
+    /// Number of retries after the first attempt
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Backoff delay before a retry, without jitter
+    ///
+    /// # Arguments
+    ///
+    /// * `retry` - Number of the retry, starting at 1
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let delay = match &self.backoff {
+            Backoff::Exponential {
+                initial,
+                multiplier,
+            } => {
+                let exponent = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+                let millis = initial.as_millis() as f64 * multiplier.powi(exponent);
+                Duration::from_millis(millis.min(self.max_delay.as_millis() as f64) as u64)
+            }
+            Backoff::Schedule(delays) => delays
+                .get(retry.saturating_sub(1))
+                .or(delays.last())
+                .copied()
+                .unwrap_or_default(),
+        };
+
+        delay.min(self.max_delay)
+    }
+
+    /// Delay before retrying after an error
+    ///
+    /// A Retry-After hint from the error is honoured as-is (capped at the
+    /// maximum delay). Otherwise the backoff delay is used with equal jitter,
+    /// i.e. a random delay between half and all of the backoff.
+    pub fn delay(&self, retry: usize, error: &AtlasError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(self.max_delay);
+        }
+
+        let backoff = self.backoff(retry);
+        if !self.jitter || backoff.is_zero() {
+            return backoff;
+        }
+
+        let half = backoff / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// Run an operation, retrying transient errors
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt if it is not retryable or the
+    /// retries are exhausted.
+    pub async fn retry<T, F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.retry_if(AtlasError::is_retryable, operation).await
+    }
+
+    /// Run an operation, retrying the errors accepted by `should_retry`
+    ///
+    /// This lets callers retry errors they can recover from themselves, such
+    /// as an expired token that the operation replaces before failing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt if `should_retry` rejects it or
+    /// the retries are exhausted.
+    pub async fn retry_if<T, F, Fut, P>(&self, should_retry: P, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+        P: Fn(&AtlasError) -> bool,
+    {
+        let mut retry = 0;
+
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < self.max_retries && should_retry(&e) => {
+                    retry += 1;
+                    let delay = self.delay(retry, &e);
+
+                    tracing::warn!(
+                        retry = retry,
+                        max_retries = self.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "Retrying after transient error"
+                    );
+
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// The default `[openehr.retry]` policy
+    fn default() -> Self {
+        Self::from_config(&RetryConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{CosmosDbError, OpenEhrError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_backoff() {
+        let exponential = RetryPolicy::exponential(
+            5,
+            Duration::from_millis(100),
+            Duration::from_millis(500),
+            2.0,
+        );
+        assert_eq!(exponential.backoff(1), Duration::from_millis(100));
+        assert_eq!(exponential.backoff(2), Duration::from_millis(200));
+        assert_eq!(exponential.backoff(3), Duration::from_millis(400));
+        assert_eq!(exponential.backoff(4), Duration::from_millis(500));
+
+        let schedule = RetryPolicy::from_schedule(5, &[1000, 2000, 4000]);
+        assert_eq!(schedule.backoff(1), Duration::from_millis(1000));
+        assert_eq!(schedule.backoff(3), Duration::from_millis(4000));
+        assert_eq!(schedule.backoff(5), Duration::from_millis(4000));
+    }
+
+    #[test]
+    fn test_delay_jitter_and_retry_after() {
+        let policy = RetryPolicy::from_schedule(3, &[1000, 2000]);
+        let error = AtlasError::Connection("reset".to_string());
+
+        for _ in 0..20 {
+            let delay = policy.delay(2, &error);
+            assert!(delay >= Duration::from_millis(1000) && delay <= Duration::from_millis(2000));
+        }
+        assert_eq!(
+            policy.clone().without_jitter().delay(2, &error),
+            Duration::from_millis(2000)
+        );
+
+        // Retry-After replaces the backoff and is capped at the maximum delay
+        let throttled = AtlasError::from(CosmosDbError::Throttled("0.5".to_string()));
+        assert_eq!(policy.delay(1, &throttled), Duration::from_millis(500));
+        let rate_limited = AtlasError::from(OpenEhrError::RateLimitExceeded("3600".to_string()));
+        assert_eq!(policy.delay(1, &rate_limited), Duration::from_millis(2000));
+    }
+
+    #[tokio::test]
+    async fn test_retry_only_transient_errors() {
+        let policy = RetryPolicy::from_schedule(2, &[0]);
+
+        let attempts = AtomicUsize::new(0);
+        let result = policy
+            .retry(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(AtlasError::Connection("reset".to_string())),
+                    n => Ok(n),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<()> = policy
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(AtlasError::Connection("reset".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<()> = policy
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(AtlasError::from(OpenEhrError::CompositionNotFound(
+                    "uid".to_string(),
+                )))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,756 @@
+//! Elasticsearch/OpenSearch adapter implementing database traits
+//!
+//! This module provides the implementation of DatabaseClient and StateStorage traits
+//! for Elasticsearch and OpenSearch. Documents have the same shape as in Cosmos DB
+//! and are indexed under their `id`; watermarks and dead letters are stored in the
+//! control index.
+
+use crate::adapters::cosmosdb::models::{CosmosEhr, CosmosQueryRow};
+use crate::adapters::database::traits::{
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
+};
+use crate::adapters::retry::RetryPolicy;
+use crate::adapters::search::bulk;
+use crate::adapters::search::client::SearchClient;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::core::transform::{flatten::flatten_composition, preserve::preserve_composition};
+use crate::domain::composition::Composition;
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use crate::domain::{AtlasError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Elasticsearch/OpenSearch implementation of database traits
+///
+/// This wraps the SearchClient and implements the DatabaseClient and StateStorage traits.
+pub struct SearchAdapter {
+    client: Arc<SearchClient>,
+    retry: RetryPolicy,
+}
+
+impl SearchAdapter {
+    /// Create a new search adapter
+    pub fn new(client: SearchClient) -> Self {
+        Self::new_with_arc(Arc::new(client))
+    }
+
+    /// Create a new search adapter with an Arc-wrapped client
+    pub fn new_with_arc(client: Arc<SearchClient>) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the retry policy for transient write failures
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Get a reference to the underlying client
+    pub fn client(&self) -> &Arc<SearchClient> {
+        &self.client
+    }
+
+    /// Index documents with `_bulk` requests
+    ///
+    /// Documents rejected by the cluster are reported as failures; the others
+    /// are still indexed. Transient failures of the whole request are retried,
+    /// and documents throttled with status 429 are sent again until the
+    /// retries are exhausted.
+    async fn bulk_index(
+        &self,
+        index: &str,
+        mut documents: Vec<(String, Option<String>, Value)>,
+        mut failures: Vec<BulkInsertFailure>,
+        retry: &RetryPolicy,
+    ) -> Result<BulkInsertResult> {
+        let mut success_count = 0;
+        let mut attempt = 0;
+
+        while !documents.is_empty() {
+            let body = bulk::index_body(
+                index,
+                documents
+                    .iter()
+                    .map(|(id, routing, source)| (id.as_str(), routing.as_deref(), source)),
+            );
+
+            let response = retry
+                .retry(|| async { self.client.bulk(body.clone()).await })
+                .await?;
+
+            let (throttled, rejected): (Vec<_>, Vec<_>) = bulk::failures(&response)
+                .into_iter()
+                .partition(|failure| failure.is_throttled);
+            success_count += documents.len() - throttled.len() - rejected.len();
+            failures.extend(rejected);
+
+            if throttled.is_empty() || attempt >= retry.max_retries() {
+                failures.extend(throttled);
+                break;
+            }
+
+            attempt += 1;
+            let error = AtlasError::Connection(format!(
+                "{} documents throttled by the search cluster",
+                throttled.len()
+            ));
+            let delay = retry.delay(attempt, &error);
+
+            tracing::warn!(
+                retry = attempt,
+                max_retries = retry.max_retries(),
+                delay_ms = delay.as_millis() as u64,
+                throttled = throttled.len(),
+                "Retrying throttled bulk items"
+            );
+
+            let throttled: HashSet<String> = throttled
+                .into_iter()
+                .map(|failure| failure.document_id)
+                .collect();
+            documents.retain(|(id, _, _)| throttled.contains(id));
+
+            tokio::time::sleep(delay).await;
+        }
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
+    /// Index transformed composition documents
+    async fn insert_documents(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<Value>,
+        max_retries: usize,
+    ) -> Result<BulkInsertResult> {
+        let mut indexed = Vec::new();
+        let mut failures = Vec::new();
+
+        for document in documents {
+            match document.get("id").and_then(|v| v.as_str()) {
+                Some(id) => {
+                    let routing = document
+                        .get("ehr_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|ehr_id| self.client.routing(ehr_id))
+                        .map(str::to_string);
+                    indexed.push((id.to_string(), routing, document));
+                }
+                None => failures.push(BulkInsertFailure {
+                    document_id: "unknown".to_string(),
+                    error: "Failed to index composition: missing 'id' field".to_string(),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        self.bulk_index(
+            &self.client.index_name(template_id),
+            indexed,
+            failures,
+            &self.retry.clone().with_max_retries(max_retries),
+        )
+        .await
+    }
+
+    /// Load a document of the control index
+    async fn load_state<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>> {
+        self.client
+            .get_document(self.client.control_index(), id, None)
+            .await?
+            .map(|source| {
+                serde_json::from_value(source).map_err(|e| {
+                    AtlasError::Serialization(format!("Failed to deserialize {id}: {e}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Save a document of the control index
+    async fn save_state<T: Serialize>(&self, id: &str, value: &T) -> Result<()> {
+        let source = serde_json::to_value(value)?;
+
+        self.retry
+            .retry(|| async {
+                self.client
+                    .put_document(self.client.control_index(), id, None, source.clone())
+                    .await
+            })
+            .await
+    }
+
+    /// Find documents of the control index that have a field
+    async fn find_state<T: DeserializeOwned>(&self, field: &str) -> Result<Vec<T>> {
+        self.client
+            .search_all(
+                self.client.control_index(),
+                json!({ "exists": { "field": field } }),
+                None,
+                true,
+            )
+            .await?
+            .into_iter()
+            .map(|(_, source)| {
+                serde_json::from_value(source)
+                    .map_err(|e| AtlasError::Serialization(format!("Failed to deserialize: {e}")))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DatabaseClient for SearchAdapter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.client.test_connection().await
+    }
+
+    async fn ensure_database_exists(&self) -> Result<()> {
+        // Install the template first, so it applies to every composition index
+        self.client.ensure_index_template().await
+    }
+
+    async fn ensure_container_exists(&self, template_id: &TemplateId) -> Result<()> {
+        self.client
+            .ensure_index_exists(&self.client.index_name(template_id))
+            .await
+    }
+
+    async fn ensure_control_container_exists(&self) -> Result<()> {
+        self.client
+            .ensure_index_exists(self.client.control_index())
+            .await
+    }
+
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        self.client
+            .ensure_index_exists(self.client.ehr_index())
+            .await
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would index EHR status in search cluster"
+            );
+            return Ok(());
+        }
+
+        let source = serde_json::to_value(CosmosEhr::from_domain(ehr))?;
+
+        self.retry
+            .retry(|| async {
+                self.client
+                    .put_document(
+                        self.client.ehr_index(),
+                        ehr.id.as_str(),
+                        None,
+                        source.clone(),
+                    )
+                    .await
+            })
+            .await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status indexed in search cluster");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        self.client
+            .ensure_index_exists(&self.client.query_index_name(query_name))
+            .await
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would index {} query rows in search cluster",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let mut documents = Vec::new();
+        let mut failures = Vec::new();
+
+        for row in rows {
+            let document_id = row.id.clone();
+            match serde_json::to_value(CosmosQueryRow::new(query_name, row)) {
+                Ok(source) => documents.push((document_id, None, source)),
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id,
+                    error: e.to_string(),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        self.bulk_index(
+            &self.client.query_index_name(query_name),
+            documents,
+            failures,
+            &self.retry,
+        )
+        .await
+    }
+
+    async fn bulk_insert_json(
+        &self,
+        template_id: &TemplateId,
+        documents: Vec<Value>,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would index {} compositions in search cluster",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn bulk_insert_compositions(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // Transform compositions using preserve_composition
+        let documents = compositions
+            .into_iter()
+            .map(|composition| preserve_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would index {} compositions (preserved format) in search cluster",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn bulk_insert_compositions_flattened(
+        &self,
+        template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // Transform compositions using flatten_composition
+        let documents = compositions
+            .into_iter()
+            .map(|composition| flatten_composition(composition, export_mode.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                count = documents.len(),
+                "DRY RUN: Would index {} compositions (flattened format) in search cluster",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_documents(template_id, documents, max_retries)
+            .await
+    }
+
+    async fn check_composition_exists(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &str,
+        composition_id: &str,
+    ) -> Result<bool> {
+        let source = self
+            .client
+            .get_document(
+                &self.client.index_name(template_id),
+                composition_id,
+                self.client.routing(ehr_id),
+            )
+            .await?;
+
+        Ok(source.is_some_and(|source| source["ehr_id"] == ehr_id))
+    }
+
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let hits = self
+            .client
+            .search_all(
+                &self.client.index_name(template_id),
+                live_compositions_query(ehr_id, None),
+                self.client.routing(ehr_id.as_str()),
+                false,
+            )
+            .await?;
+
+        let mut ids: Vec<String> = hits.into_iter().map(|(id, _)| id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in search cluster",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let index = self.client.index_name(template_id);
+        let routing = self.client.routing(ehr_id.as_str());
+
+        let affected = match policy {
+            DeletionPolicy::HardDelete => {
+                let query = json!({
+                    "bool": {
+                        "filter": [
+                            { "term": { "ehr_id": ehr_id.as_str() } },
+                            { "ids": { "values": composition_ids } },
+                        ]
+                    }
+                });
+                self.client
+                    .by_query(
+                        &index,
+                        "_delete_by_query",
+                        routing,
+                        json!({ "query": query }),
+                    )
+                    .await?
+            }
+            _ => {
+                self.client
+                    .by_query(
+                        &index,
+                        "_update_by_query",
+                        routing,
+                        json!({
+                            "query": live_compositions_query(ehr_id, Some(composition_ids)),
+                            "script": {
+                                "source": "ctx._source.deleted_at = params.deleted_at",
+                                "lang": "painless",
+                                "params": { "deleted_at": Utc::now() },
+                            },
+                        }),
+                    )
+                    .await?
+            }
+        } as usize;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = affected,
+            policy = %policy,
+            "Applied deletion policy in search cluster"
+        );
+
+        Ok(affected)
+    }
+
+    fn database_name(&self) -> &str {
+        self.client.index_prefix()
+    }
+}
+
+/// Query matching the compositions of an EHR that are not soft-deleted
+///
+/// Optionally restricted to the given document IDs.
+fn live_compositions_query(ehr_id: &EhrId, ids: Option<&[String]>) -> Value {
+    let mut filter = vec![json!({ "term": { "ehr_id": ehr_id.as_str() } })];
+    if let Some(ids) = ids {
+        filter.push(json!({ "ids": { "values": ids } }));
+    }
+
+    json!({
+        "bool": {
+            "filter": filter,
+            "must_not": [{ "exists": { "field": "deleted_at" } }],
+        }
+    })
+}
+
+#[async_trait]
+impl StateStorage for SearchAdapter {
+    async fn load_watermark(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Option<Watermark>> {
+        self.load_state(&Watermark::generate_id(template_id, ehr_id))
+            .await
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark, dry_run: bool) -> Result<()> {
+        // If dry-run, skip actual write
+        if dry_run {
+            tracing::info!(
+                template_id = %watermark.template_id.as_str(),
+                ehr_id = %watermark.ehr_id.as_str(),
+                watermark_id = %watermark.id,
+                "DRY RUN: Would save watermark to search cluster"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&watermark.id, watermark).await?;
+
+        tracing::debug!(
+            template_id = %watermark.template_id.as_str(),
+            ehr_id = %watermark.ehr_id.as_str(),
+            "Watermark saved to search cluster"
+        );
+
+        Ok(())
+    }
+
+    async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
+        // Per-EHR watermarks are the only control documents with this field
+        let mut watermarks: Vec<Watermark> = self.find_state("last_exported_timestamp").await?;
+
+        watermarks.sort_by(|a, b| {
+            (a.template_id.as_str(), a.ehr_id.as_str())
+                .cmp(&(b.template_id.as_str(), b.ehr_id.as_str()))
+        });
+        Ok(watermarks)
+    }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        self.load_state(&QueryWatermark::generate_id(query_name))
+            .await
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to search cluster"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&watermark.id, watermark).await?;
+
+        tracing::debug!(query = %watermark.query_name, "Query watermark saved to search cluster");
+
+        Ok(())
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        self.load_state(&DeadLetter::generate_id(composition_uid))
+            .await
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to search cluster"
+            );
+            return Ok(());
+        }
+
+        self.save_state(&dead_letter.id, dead_letter).await
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from search cluster"
+            );
+            return Ok(());
+        }
+
+        self.client
+            .delete_document(self.client.control_index(), &dead_letter.id, None)
+            .await
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        // Dead letters are the only control documents with this field
+        let mut dead_letters: Vec<DeadLetter> = self.find_state("attempt_count").await?;
+
+        dead_letters.sort_by_key(|dead_letter| dead_letter.first_failed_at);
+        Ok(dead_letters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::schema::SearchConfig;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_throttled_items_are_retried() {
+        let mut server = mockito::Server::new_async().await;
+
+        let first = server
+            .mock("POST", "/_bulk")
+            .match_body(mockito::Matcher::Regex("c1::local::1".to_string()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"errors": true, "items": [
+                    {"index": {"_id": "c1::local::1", "status": 201}},
+                    {"index": {"_id": "c2::local::1", "status": 429, "error": {
+                        "type": "es_rejected_execution_exception", "reason": "rejected execution"
+                    }}},
+                    {"index": {"_id": "c3::local::1", "status": 400, "error": {
+                        "type": "mapper_parsing_exception", "reason": "failed to parse"
+                    }}},
+                ]})
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let retried = server
+            .mock("POST", "/_bulk")
+            .match_body(mockito::Matcher::Regex(
+                r"^[^\n]*c2::local::1[^\n]*\n\{\}\n$".to_string(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"errors": false, "items": [
+                    {"index": {"_id": "c2::local::1", "status": 201}},
+                ]})
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config: SearchConfig = serde_json::from_value(json!({"url": server.url()})).unwrap();
+        let adapter = SearchAdapter::new(SearchClient::new(config).await.unwrap());
+        let documents = ["c1::local::1", "c2::local::1", "c3::local::1"]
+            .into_iter()
+            .map(|id| (id.to_string(), None, json!({})))
+            .collect();
+
+        let result = adapter
+            .bulk_index(
+                "compositions",
+                documents,
+                Vec::new(),
+                &RetryPolicy::from_schedule(2, &[0]),
+            )
+            .await
+            .unwrap();
+
+        first.assert_async().await;
+        retried.assert_async().await;
+        assert_eq!(result.success_count, 2);
+        assert_eq!(result.failure_count, 1);
+        assert_eq!(result.failures[0].document_id, "c3::local::1");
+    }
+
+    #[test]
+    fn test_live_compositions_query() {
+        let ehr_id = EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+
+        let query = live_compositions_query(&ehr_id, None);
+        assert_eq!(
+            query["bool"]["filter"],
+            json!([{"term": {"ehr_id": "7d44b88c-4199-4bad-97dc-d78268e01398"}}])
+        );
+        assert_eq!(
+            query["bool"]["must_not"],
+            json!([{"exists": {"field": "deleted_at"}}])
+        );
+
+        let ids = vec!["c1::local::1".to_string()];
+        let query = live_compositions_query(&ehr_id, Some(&ids));
+        assert_eq!(
+            query["bool"]["filter"][1],
+            json!({"ids": {"values": ["c1::local::1"]}})
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! `_bulk` request bodies and responses
+//!
+//! A bulk request is newline-delimited JSON: an action line naming the index,
+//! document ID and optional routing, followed by the document source. The
+//! response reports the outcome of every action in request order.
+
+use crate::adapters::database::traits::BulkInsertFailure;
+use serde_json::{json, Value};
+
+/// Build the body of a `_bulk` request that indexes documents
+///
+/// # Arguments
+///
+/// * `index` - Index the documents are written to
+/// * `documents` - Document ID, routing value and source of each document
+pub fn index_body<'a>(
+    index: &str,
+    documents: impl IntoIterator<Item = (&'a str, Option<&'a str>, &'a Value)>,
+) -> String {
+    let mut body = String::new();
+
+    for (id, routing, source) in documents {
+        let mut action = json!({ "_index": index, "_id": id });
+        if let Some(routing) = routing {
+            action["routing"] = json!(routing);
+        }
+
+        body.push_str(&json!({ "index": action }).to_string());
+        body.push('\n');
+        body.push_str(&source.to_string());
+        body.push('\n');
+    }
+
+    body
+}
+
+/// Collect the failed actions of a `_bulk` response
+///
+/// Rejections with status 429 are flagged as throttled, so that the adapter
+/// sends them again.
+pub fn failures(response: &Value) -> Vec<BulkInsertFailure> {
+    if !response["errors"].as_bool().unwrap_or(false) {
+        return Vec::new();
+    }
+
+    response["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_object()?.values().next())
+        .filter(|result| result.get("error").is_some())
+        .map(|result| {
+            let error = &result["error"];
+            BulkInsertFailure {
+                document_id: result["_id"].as_str().unwrap_or("unknown").to_string(),
+                error: format!(
+                    "{}: {}",
+                    error["type"].as_str().unwrap_or("error"),
+                    error["reason"].as_str().unwrap_or_default()
+                ),
+                is_throttled: result["status"].as_u64() == Some(429),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_body() {
+        let first = json!({"id": "c1::local::1", "ehr_id": "e1"});
+        let second = json!({"id": "c2::local::1"});
+
+        let body = index_body(
+            "compositions_vital_signs_v1",
+            [
+                ("c1::local::1", Some("e1"), &first),
+                ("c2::local::1", None, &second),
+            ],
+        );
+
+        let lines: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(body.ends_with('\n'));
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            json!({"index": {"_index": "compositions_vital_signs_v1", "_id": "c1::local::1", "routing": "e1"}})
+        );
+        assert_eq!(lines[1], first);
+        assert!(lines[2]["index"].get("routing").is_none());
+    }
+
+    #[test]
+    fn test_failures() {
+        let response = json!({
+            "errors": true,
+            "items": [
+                {"index": {"_id": "c1::local::1", "status": 201, "result": "created"}},
+                {"index": {"_id": "c2::local::1", "status": 400, "error": {
+                    "type": "mapper_parsing_exception",
+                    "reason": "failed to parse field [time_committed] of type [date]"
+                }}},
+                {"index": {"_id": "c3::local::1", "status": 429, "error": {
+                    "type": "es_rejected_execution_exception",
+                    "reason": "rejected execution"
+                }}},
+            ]
+        });
+
+        let failures = failures(&response);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].document_id, "c2::local::1");
+        assert!(failures[0].error.starts_with("mapper_parsing_exception: "));
+        assert!(!failures[0].is_throttled);
+        assert!(failures[1].is_throttled);
+
+        assert!(super::failures(&json!({"errors": false, "items": []})).is_empty());
+    }
+}
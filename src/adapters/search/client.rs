@@ -0,0 +1,648 @@
+//! Elasticsearch/OpenSearch client implementation
+//!
+//! This module provides a client for the REST API shared by Elasticsearch and
+//! OpenSearch. Compositions are stored in an index per template, named like
+//! the Cosmos DB containers.
+
+use crate::config::schema::SearchConfig;
+use crate::domain::ids::{container_name, TemplateId};
+use crate::domain::{AtlasError, Result};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Certificate, Client, ClientBuilder, Method, StatusCode, Url};
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How long a scroll context is kept between pages
+const SCROLL_KEEP_ALIVE: &str = "1m";
+
+/// Number of hits per scroll page
+const SCROLL_PAGE_SIZE: usize = 1000;
+
+/// Body of a request
+#[derive(Debug, Clone)]
+pub enum Body {
+    /// A JSON document
+    Json(Value),
+    /// Newline-delimited JSON (`_bulk`)
+    Ndjson(String),
+}
+
+/// Elasticsearch/OpenSearch client
+#[derive(Clone)]
+pub struct SearchClient {
+    http: Client,
+    base_url: Url,
+    config: SearchConfig,
+    index_template: Value,
+}
+
+impl SearchClient {
+    /// Create a new client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL, CA bundle or index template file is invalid.
+    pub async fn new(config: SearchConfig) -> Result<Self> {
+        let base_url = Url::parse(&config.url)
+            .map_err(|e| AtlasError::Configuration(format!("Invalid search.url: {e}")))?;
+
+        let mut builder = ClientBuilder::new()
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .connect_timeout(Duration::from_secs(30));
+
+        if !config.tls_verify {
+            tracing::warn!(
+                "⚠️  SECURITY WARNING: TLS certificate verification is DISABLED for search cluster at {}. \
+                This configuration should only be used in development/testing environments.",
+                config.url
+            );
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        if let Some(ref path) = config.tls_ca_cert {
+            for certificate in load_ca_bundle(path)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let http = builder
+            .build()
+            .map_err(|e| AtlasError::Configuration(format!("Failed to build HTTP client: {e}")))?;
+
+        let user_template = match config.index_template_file {
+            Some(ref path) => Some(load_index_template(path)?),
+            None => None,
+        };
+
+        Ok(Self {
+            http,
+            base_url,
+            index_template: index_template(user_template),
+            config,
+        })
+    }
+
+    /// Test the connection to the cluster
+    pub async fn test_connection(&self) -> Result<()> {
+        let (_, info) = self.send(Method::GET, &[], &[], None).await?;
+
+        tracing::info!(
+            distribution = info["version"]["distribution"]
+                .as_str()
+                .unwrap_or("elasticsearch"),
+            version = info["version"]["number"].as_str().unwrap_or("unknown"),
+            "Search cluster connection test successful"
+        );
+        Ok(())
+    }
+
+    /// Install the index template of the composition indices
+    ///
+    /// The template applies to `{index_prefix}_*`, so indices created later,
+    /// including those created implicitly by `_bulk`, get its mappings.
+    pub async fn ensure_index_template(&self) -> Result<()> {
+        let name = format!("atlas_{}", self.config.index_prefix);
+        let body = json!({
+            "index_patterns": [format!("{}_*", self.config.index_prefix)],
+            "priority": 100,
+            "template": self.index_template,
+            "_meta": { "managed_by": "atlas" },
+        });
+
+        self.send(
+            Method::PUT,
+            &["_index_template", &name],
+            &[],
+            Some(Body::Json(body)),
+        )
+        .await?;
+
+        tracing::info!(template = %name, "Index template installed");
+        Ok(())
+    }
+
+    /// Ensure an index exists, creating it if necessary
+    pub async fn ensure_index_exists(&self, index: &str) -> Result<()> {
+        let (status, body) = self
+            .send_allowing(Method::PUT, &[index], &[], None, &[StatusCode::BAD_REQUEST])
+            .await?;
+
+        match status {
+            StatusCode::BAD_REQUEST
+                if body["error"]["type"] == "resource_already_exists_exception" =>
+            {
+                tracing::debug!(index = %index, "Index already exists");
+                Ok(())
+            }
+            StatusCode::BAD_REQUEST => Err(request_error(status, &body)),
+            _ => {
+                tracing::info!(index = %index, "Index created");
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the index name for a template
+    ///
+    /// Format: `{prefix}_{template_id}`
+    pub fn index_name(&self, template_id: &TemplateId) -> String {
+        template_id.to_container_name(&self.config.index_prefix)
+    }
+
+    /// Get the index name for a named AQL query
+    ///
+    /// Format: `{prefix}_query_{query_name}`, lowercased, so that the index
+    /// template of the prefix applies to it
+    pub fn query_index_name(&self, query_name: &str) -> String {
+        container_name(&self.config.index_prefix, &format!("query_{query_name}"))
+    }
+
+    /// Get the control index name
+    pub fn control_index(&self) -> &str {
+        &self.config.control_index
+    }
+
+    /// Get the EHR index name
+    pub fn ehr_index(&self) -> &str {
+        &self.config.ehr_index
+    }
+
+    /// Routing value of a composition, if documents are routed by EHR
+    pub fn routing<'a>(&self, ehr_id: &'a str) -> Option<&'a str> {
+        self.config.route_by_ehr_id.then_some(ehr_id)
+    }
+
+    /// Send a `_bulk` request
+    ///
+    /// # Returns
+    ///
+    /// Returns the response, whose items report the outcome of each action.
+    pub async fn bulk(&self, body: String) -> Result<Value> {
+        let (_, response) = self
+            .send(Method::POST, &["_bulk"], &[], Some(Body::Ndjson(body)))
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Get the source of a document, or `None` if it doesn't exist
+    pub async fn get_document(
+        &self,
+        index: &str,
+        id: &str,
+        routing: Option<&str>,
+    ) -> Result<Option<Value>> {
+        let query = routing_query(routing);
+        let (status, mut body) = self
+            .send_allowing(
+                Method::GET,
+                &[index, "_doc", id],
+                &query,
+                None,
+                &[StatusCode::NOT_FOUND],
+            )
+            .await?;
+
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(body["_source"].take()))
+    }
+
+    /// Create or replace a document
+    ///
+    /// The change is visible to searches when this returns.
+    pub async fn put_document(
+        &self,
+        index: &str,
+        id: &str,
+        routing: Option<&str>,
+        source: Value,
+    ) -> Result<()> {
+        let mut query = routing_query(routing);
+        query.push(("refresh", "wait_for"));
+
+        self.send(
+            Method::PUT,
+            &[index, "_doc", id],
+            &query,
+            Some(Body::Json(source)),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete a document
+    ///
+    /// A document that no longer exists is treated as deleted.
+    pub async fn delete_document(
+        &self,
+        index: &str,
+        id: &str,
+        routing: Option<&str>,
+    ) -> Result<()> {
+        let mut query = routing_query(routing);
+        query.push(("refresh", "wait_for"));
+
+        self.send_allowing(
+            Method::DELETE,
+            &[index, "_doc", id],
+            &query,
+            None,
+            &[StatusCode::NOT_FOUND],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find all documents matching a query
+    ///
+    /// Pages through the hits with a scroll. A missing index has no hits.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID and source of each hit (`Value::Null` without `source`).
+    pub async fn search_all(
+        &self,
+        index: &str,
+        query: Value,
+        routing: Option<&str>,
+        source: bool,
+    ) -> Result<Vec<(String, Value)>> {
+        let mut params = routing_query(routing);
+        params.push(("scroll", SCROLL_KEEP_ALIVE));
+        params.push(("ignore_unavailable", "true"));
+
+        let (_, mut page) = self
+            .send(
+                Method::POST,
+                &[index, "_search"],
+                &params,
+                Some(Body::Json(json!({
+                    "query": query,
+                    "size": SCROLL_PAGE_SIZE,
+                    "sort": ["_doc"],
+                    "_source": source,
+                }))),
+            )
+            .await?;
+
+        let mut hits = Vec::new();
+        let mut scroll_ids = Vec::new();
+
+        loop {
+            if let Some(scroll_id) = page["_scroll_id"].as_str() {
+                scroll_ids.push(scroll_id.to_string());
+            }
+
+            let page_hits = page["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            if page_hits.is_empty() {
+                break;
+            }
+
+            for mut hit in page_hits {
+                let id = hit["_id"].as_str().unwrap_or_default().to_string();
+                hits.push((id, hit["_source"].take()));
+            }
+
+            let Some(scroll_id) = scroll_ids.last() else {
+                break;
+            };
+            (_, page) = self
+                .send(
+                    Method::POST,
+                    &["_search", "scroll"],
+                    &[],
+                    Some(Body::Json(json!({
+                        "scroll": SCROLL_KEEP_ALIVE,
+                        "scroll_id": scroll_id,
+                    }))),
+                )
+                .await?;
+        }
+
+        if !scroll_ids.is_empty() {
+            // Best effort: the contexts expire on their own
+            let _ = self
+                .send_allowing(
+                    Method::DELETE,
+                    &["_search", "scroll"],
+                    &[],
+                    Some(Body::Json(json!({ "scroll_id": scroll_ids }))),
+                    &[StatusCode::NOT_FOUND],
+                )
+                .await;
+        }
+
+        Ok(hits)
+    }
+
+    /// Run an `_update_by_query` or `_delete_by_query` request
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the documents
+    /// * `endpoint` - `_update_by_query` or `_delete_by_query`
+    /// * `routing` - Routing value of the documents
+    /// * `body` - Query and, for updates, the script
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of updated or deleted documents.
+    pub async fn by_query(
+        &self,
+        index: &str,
+        endpoint: &str,
+        routing: Option<&str>,
+        body: Value,
+    ) -> Result<u64> {
+        let mut query = routing_query(routing);
+        query.push(("conflicts", "proceed"));
+        query.push(("refresh", "true"));
+        query.push(("ignore_unavailable", "true"));
+
+        let (_, response) = self
+            .send(
+                Method::POST,
+                &[index, endpoint],
+                &query,
+                Some(Body::Json(body)),
+            )
+            .await?;
+
+        Ok(response["updated"].as_u64().unwrap_or(0) + response["deleted"].as_u64().unwrap_or(0))
+    }
+
+    /// Get the prefix of the composition indices
+    pub fn index_prefix(&self) -> &str {
+        &self.config.index_prefix
+    }
+
+    /// Send a request
+    async fn send(
+        &self,
+        method: Method,
+        path: &[&str],
+        query: &[(&str, &str)],
+        body: Option<Body>,
+    ) -> Result<(StatusCode, Value)> {
+        self.send_allowing(method, path, query, body, &[]).await
+    }
+
+    /// Send a request, accepting some error statuses
+    ///
+    /// Responses with a success status or one of `allowed` are returned;
+    /// other statuses are errors.
+    async fn send_allowing(
+        &self,
+        method: Method,
+        path: &[&str],
+        query: &[(&str, &str)],
+        body: Option<Body>,
+        allowed: &[StatusCode],
+    ) -> Result<(StatusCode, Value)> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| AtlasError::Configuration("search.url cannot be a base URL".to_string()))?
+            .pop_if_empty()
+            .extend(path);
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let mut request = self.http.request(method, url.clone());
+
+        if let Some(ref api_key) = self.config.api_key {
+            let api_key: &str = api_key.expose_secret().as_ref();
+            request = request.header(AUTHORIZATION, format!("ApiKey {api_key}"));
+        } else if let Some(ref username) = self.config.username {
+            let password = self.config.password.as_ref().map(|password| {
+                let password: &str = password.expose_secret().as_ref();
+                password.to_string()
+            });
+            request = request.basic_auth(username, password);
+        }
+
+        request = match body {
+            Some(Body::Json(value)) => request.json(&value),
+            Some(Body::Ndjson(text)) => request
+                .header(CONTENT_TYPE, "application/x-ndjson")
+                .body(text),
+            None => request,
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AtlasError::Connection(format!("Search request to {url} failed: {e}")))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| AtlasError::Connection(format!("Failed to read search response: {e}")))?;
+        let value = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).unwrap_or(Value::String(text))
+        };
+
+        if status.is_success() || allowed.contains(&status) {
+            Ok((status, value))
+        } else {
+            Err(request_error(status, &value))
+        }
+    }
+}
+
+/// Query parameters for an optional routing value
+fn routing_query(routing: Option<&str>) -> Vec<(&str, &str)> {
+    routing
+        .map(|routing| vec![("routing", routing)])
+        .unwrap_or_default()
+}
+
+/// Convert an error response into an Atlas error
+///
+/// Throttling and unavailability become connection errors, so that the
+/// retry policy retries them.
+fn request_error(status: StatusCode, body: &Value) -> AtlasError {
+    let reason = match body["error"] {
+        Value::Object(ref error) => format!(
+            "{}: {}",
+            error.get("type").and_then(Value::as_str).unwrap_or("error"),
+            error
+                .get("reason")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        ),
+        Value::Null => body.to_string(),
+        ref other => other.to_string(),
+    };
+    let message = format!("Search request failed with status {status}: {reason}");
+
+    match status {
+        StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => AtlasError::Connection(message),
+        _ => AtlasError::Database(message),
+    }
+}
+
+/// Build the `template` section of the composition index template
+///
+/// The fields Atlas filters on are mapped as keywords and dates, unless the
+/// user template maps them itself.
+fn index_template(user_template: Option<Value>) -> Value {
+    let mut template = user_template.unwrap_or_else(|| json!({}));
+
+    let atlas_fields = [
+        ("id", "keyword"),
+        ("ehr_id", "keyword"),
+        ("composition_uid", "keyword"),
+        ("template_id", "keyword"),
+        ("time_committed", "date"),
+        ("deleted_at", "date"),
+    ];
+
+    if !template["mappings"]["properties"].is_object() {
+        template["mappings"]["properties"] = json!({});
+    }
+    let properties = &mut template["mappings"]["properties"];
+    for (field, field_type) in atlas_fields {
+        if properties.get(field).is_none() {
+            properties[field] = json!({ "type": field_type });
+        }
+    }
+
+    template
+}
+
+/// Load the settings and mappings of the composition indices
+fn load_index_template(path: &str) -> Result<Value> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        AtlasError::Configuration(format!(
+            "Failed to read search.index_template_file '{path}': {e}"
+        ))
+    })?;
+
+    let template: Value = serde_json::from_str(&text).map_err(|e| {
+        AtlasError::Configuration(format!(
+            "search.index_template_file '{path}' is not valid JSON: {e}"
+        ))
+    })?;
+
+    match template {
+        Value::Object(ref fields)
+            if fields
+                .keys()
+                .all(|key| matches!(key.as_str(), "settings" | "mappings" | "aliases")) =>
+        {
+            Ok(template)
+        }
+        _ => Err(AtlasError::Configuration(format!(
+            "search.index_template_file '{path}' must be an object with 'settings', 'mappings' or 'aliases'"
+        ))),
+    }
+}
+
+/// Load the certificates of a PEM CA bundle
+fn load_ca_bundle(path: &str) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path).map_err(|e| {
+        AtlasError::Configuration(format!("Failed to read search.tls_ca_cert '{path}': {e}"))
+    })?;
+
+    let certificates = Certificate::from_pem_bundle(&pem).map_err(|e| {
+        AtlasError::Configuration(format!(
+            "search.tls_ca_cert '{path}' is not a valid PEM certificate bundle: {e}"
+        ))
+    })?;
+
+    if certificates.is_empty() {
+        return Err(AtlasError::Configuration(format!(
+            "search.tls_ca_cert '{path}' does not contain any PEM certificates"
+        )));
+    }
+
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_template() {
+        let template = index_template(None);
+        assert_eq!(
+            template["mappings"]["properties"]["ehr_id"],
+            json!({"type": "keyword"})
+        );
+        assert_eq!(
+            template["mappings"]["properties"]["time_committed"],
+            json!({"type": "date"})
+        );
+
+        // User mappings are kept and take precedence
+        let template = index_template(Some(json!({
+            "settings": {"analysis": {"analyzer": {"default": {"type": "english"}}}},
+            "mappings": {"properties": {
+                "id": {"type": "text"},
+                "vital_signs_narrative": {"type": "text", "analyzer": "english"}
+            }}
+        })));
+        let properties = &template["mappings"]["properties"];
+        assert_eq!(properties["id"], json!({"type": "text"}));
+        assert_eq!(properties["vital_signs_narrative"]["analyzer"], "english");
+        assert_eq!(properties["composition_uid"], json!({"type": "keyword"}));
+        assert_eq!(
+            template["settings"]["analysis"]["analyzer"]["default"]["type"],
+            "english"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_names() {
+        let config: SearchConfig =
+            serde_json::from_value(json!({"url": "http://localhost:9200"})).unwrap();
+        let client = SearchClient::new(config).await.unwrap();
+
+        assert_eq!(
+            client.index_name(&TemplateId::new("IDCR - Vital Signs.v1").unwrap()),
+            "compositions_idcr_vital_signs_v1"
+        );
+        assert_eq!(
+            client.query_index_name("Latest_HbA1c"),
+            "compositions_query_latest_hba1c"
+        );
+    }
+
+    #[test]
+    fn test_load_index_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("template.json");
+
+        std::fs::write(&path, r#"{"mappings": {"dynamic": true}}"#).unwrap();
+        assert!(load_index_template(path.to_str().unwrap()).is_ok());
+
+        std::fs::write(&path, r#"{"index_patterns": ["*"]}"#).unwrap();
+        assert!(load_index_template(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_request_error() {
+        let body = json!({"error": {"type": "cluster_block_exception", "reason": "read-only"}});
+        assert!(matches!(
+            request_error(StatusCode::TOO_MANY_REQUESTS, &body),
+            AtlasError::Connection(_)
+        ));
+        assert!(matches!(
+            request_error(StatusCode::FORBIDDEN, &body),
+            AtlasError::Database(ref message) if message.contains("cluster_block_exception: read-only")
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+//! Elasticsearch/OpenSearch integration
+//!
+//! This module provides integration with Elasticsearch and OpenSearch for
+//! full-text search over openEHR compositions, with an index per template and
+//! watermarks in a control index.
+
+pub mod adapter;
+pub mod bulk;
+pub mod client;
+
+pub use adapter::SearchAdapter;
+pub use client::SearchClient;
@@ -0,0 +1,1045 @@
+//! SQLite adapter implementing database traits
+//!
+//! This module provides the implementation of DatabaseClient and StateStorage traits
+//! for SQLite. Rows are converted with the PostgreSQL models, since both targets
+//! share the same table layout.
+
+use crate::adapters::database::traits::{
+    BulkInsertFailure, BulkInsertResult, DatabaseClient, QueryRow, StateStorage,
+};
+use crate::adapters::postgresql::models::{
+    PostgreSQLComposition, PostgreSQLDeadLetter, PostgreSQLEhr, PostgreSQLQueryWatermark,
+    PostgreSQLWatermark,
+};
+use crate::adapters::sqlite::client::SQLiteClient;
+use crate::config::DeletionPolicy;
+use crate::core::state::dead_letter::DeadLetter;
+use crate::core::state::watermark::{QueryWatermark, Watermark};
+use crate::domain::composition::Composition;
+use crate::domain::ehr::Ehr;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use crate::domain::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row, TransactionBehavior};
+use std::any::Any;
+use std::sync::Arc;
+
+/// Columns of a transformed composition document that are not content
+const DOCUMENT_METADATA_FIELDS: &[&str] = &[
+    "id",
+    "ehr_id",
+    "composition_uid",
+    "template_id",
+    "time_committed",
+    "version",
+    "atlas_metadata",
+];
+
+/// Insert or update a composition row
+const UPSERT_COMPOSITION: &str = r#"
+    INSERT INTO compositions (
+        id, ehr_id, composition_uid, template_id, time_committed,
+        content, export_mode, exported_at, atlas_version, checksum,
+        version_number, lifecycle_state, change_type, composition_format
+    )
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+    ON CONFLICT (id) DO UPDATE SET
+        time_committed = excluded.time_committed,
+        content = excluded.content,
+        exported_at = excluded.exported_at,
+        checksum = excluded.checksum,
+        version_number = COALESCE(excluded.version_number, compositions.version_number),
+        lifecycle_state = COALESCE(excluded.lifecycle_state, compositions.lifecycle_state),
+        change_type = COALESCE(excluded.change_type, compositions.change_type),
+        composition_format = excluded.composition_format,
+        deleted_at = NULL
+"#;
+
+/// SQLite implementation of database traits
+///
+/// This wraps the SQLiteClient and implements the DatabaseClient and StateStorage traits.
+pub struct SQLiteAdapter {
+    client: Arc<SQLiteClient>,
+}
+
+impl SQLiteAdapter {
+    /// Create a new SQLite adapter
+    pub fn new(client: SQLiteClient) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    /// Create a new SQLite adapter from an Arc-wrapped client
+    pub fn new_with_arc(client: Arc<SQLiteClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get a reference to the underlying client
+    pub fn client(&self) -> &Arc<SQLiteClient> {
+        &self.client
+    }
+
+    /// Upsert composition rows in one transaction
+    ///
+    /// Rows that fail are reported as failures and don't roll back the others.
+    async fn upsert_compositions(
+        &self,
+        rows: Vec<PostgreSQLComposition>,
+        mut failures: Vec<BulkInsertFailure>,
+    ) -> Result<BulkInsertResult> {
+        let (success_count, insert_failures) = self
+            .client
+            .call(move |conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let mut success_count = 0;
+                let mut failures = Vec::new();
+                {
+                    let mut statement = tx.prepare_cached(UPSERT_COMPOSITION)?;
+                    for row in &rows {
+                        match statement.execute(params![
+                            row.id,
+                            row.ehr_id,
+                            row.composition_uid,
+                            row.template_id,
+                            row.time_committed,
+                            row.content,
+                            row.export_mode,
+                            row.exported_at,
+                            row.atlas_version,
+                            row.checksum,
+                            row.version_number,
+                            row.lifecycle_state,
+                            row.change_type,
+                            row.composition_format,
+                        ]) {
+                            Ok(_) => success_count += 1,
+                            Err(e) => failures.push(BulkInsertFailure {
+                                document_id: row.id.clone(),
+                                error: format!("Database insert failed: {e}"),
+                                is_throttled: false,
+                            }),
+                        }
+                    }
+                }
+                tx.commit()?;
+
+                Ok((success_count, failures))
+            })
+            .await?;
+
+        failures.extend(insert_failures);
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
+    /// Convert domain compositions to rows and upsert them
+    async fn insert_compositions(
+        &self,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        to_row: fn(Composition, String) -> Result<PostgreSQLComposition>,
+    ) -> Result<BulkInsertResult> {
+        let mut rows = Vec::new();
+        let mut failures = Vec::new();
+
+        for composition in compositions {
+            let doc_id = composition.uid.to_string();
+            match to_row(composition, export_mode.clone()) {
+                Ok(row) => rows.push(row),
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id: doc_id,
+                    error: format!("Failed to convert composition: {e}"),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        self.upsert_compositions(rows, failures).await
+    }
+}
+
+/// Convert a transformed composition document to a row
+///
+/// Preserved documents carry their content in `content`, flattened documents
+/// have their fields next to the metadata.
+fn composition_from_json(mut doc: serde_json::Value) -> Result<PostgreSQLComposition> {
+    if doc.get("content").is_none() {
+        if let Some(fields) = doc.as_object_mut() {
+            let content: serde_json::Map<String, serde_json::Value> = fields
+                .iter()
+                .filter(|(key, _)| !DOCUMENT_METADATA_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            fields.retain(|key, _| DOCUMENT_METADATA_FIELDS.contains(&key.as_str()));
+            fields.insert("content".to_string(), serde_json::Value::Object(content));
+        }
+    }
+
+    PostgreSQLComposition::from_json_preserved(doc)
+}
+
+#[async_trait]
+impl DatabaseClient for SQLiteAdapter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.client.test_connection().await
+    }
+
+    async fn ensure_database_exists(&self) -> Result<()> {
+        self.client.ensure_database_exists().await
+    }
+
+    async fn ensure_container_exists(&self, _template_id: &TemplateId) -> Result<()> {
+        // No-op: SQLite uses a single table for all compositions
+        Ok(())
+    }
+
+    async fn ensure_control_container_exists(&self) -> Result<()> {
+        // No-op: the watermarks table is created in ensure_database_exists
+        Ok(())
+    }
+
+    async fn ensure_ehr_container_exists(&self) -> Result<()> {
+        // No-op: the ehrs table is created in ensure_database_exists
+        Ok(())
+    }
+
+    async fn upsert_ehr(&self, ehr: &Ehr, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                ehr_id = %ehr.id.as_str(),
+                "DRY RUN: Would save EHR status to SQLite"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLEhr::from_domain(ehr);
+        let exported_at = Utc::now();
+
+        self.client
+            .call(move |conn| {
+                conn.execute(
+                    r#"
+                    INSERT INTO ehrs (
+                        ehr_id, system_id, time_created, ehr_status_uid,
+                        subject_id, subject_id_scheme, subject_namespace, subject_type,
+                        is_queryable, is_modifiable, other_details, exported_at, atlas_version
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                    ON CONFLICT (ehr_id) DO UPDATE SET
+                        system_id = excluded.system_id,
+                        time_created = excluded.time_created,
+                        ehr_status_uid = excluded.ehr_status_uid,
+                        subject_id = excluded.subject_id,
+                        subject_id_scheme = excluded.subject_id_scheme,
+                        subject_namespace = excluded.subject_namespace,
+                        subject_type = excluded.subject_type,
+                        is_queryable = excluded.is_queryable,
+                        is_modifiable = excluded.is_modifiable,
+                        other_details = excluded.other_details,
+                        exported_at = excluded.exported_at,
+                        atlas_version = excluded.atlas_version
+                    "#,
+                    params![
+                        row.ehr_id,
+                        row.system_id,
+                        row.time_created,
+                        row.ehr_status_uid,
+                        row.subject_id,
+                        row.subject_id_scheme,
+                        row.subject_namespace,
+                        row.subject_type,
+                        row.is_queryable,
+                        row.is_modifiable,
+                        row.other_details,
+                        exported_at,
+                        row.atlas_version,
+                    ],
+                )
+            })
+            .await?;
+
+        tracing::debug!(ehr_id = %ehr.id.as_str(), "EHR status saved to SQLite");
+
+        Ok(())
+    }
+
+    async fn ensure_query_container_exists(&self, query_name: &str) -> Result<()> {
+        self.client.ensure_query_table_exists(query_name).await
+    }
+
+    async fn upsert_query_rows(
+        &self,
+        query_name: &str,
+        rows: Vec<QueryRow>,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        if dry_run {
+            tracing::info!(
+                query = %query_name,
+                count = rows.len(),
+                "DRY RUN: Would save {} query rows to SQLite",
+                rows.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let upsert_query = format!(
+            r#"
+            INSERT INTO "{query_name}" (id, data, exported_at, atlas_version)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (id) DO UPDATE SET
+                data = excluded.data,
+                exported_at = excluded.exported_at,
+                atlas_version = excluded.atlas_version
+            "#
+        );
+        let exported_at = Utc::now();
+
+        let (success_count, failures) = self
+            .client
+            .call(move |conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let mut success_count = 0;
+                let mut failures = Vec::new();
+                {
+                    let mut statement = tx.prepare(&upsert_query)?;
+                    for row in &rows {
+                        let data = serde_json::Value::Object(row.data.clone());
+                        match statement.execute(params![
+                            row.id,
+                            data,
+                            exported_at,
+                            env!("CARGO_PKG_VERSION")
+                        ]) {
+                            Ok(_) => success_count += 1,
+                            Err(e) => failures.push(BulkInsertFailure {
+                                document_id: row.id.clone(),
+                                error: e.to_string(),
+                                is_throttled: false,
+                            }),
+                        }
+                    }
+                }
+                tx.commit()?;
+
+                Ok((success_count, failures))
+            })
+            .await?;
+
+        Ok(BulkInsertResult {
+            success_count,
+            failure_count: failures.len(),
+            failures,
+        })
+    }
+
+    async fn bulk_insert_json(
+        &self,
+        _template_id: &TemplateId,
+        documents: Vec<serde_json::Value>,
+        _max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                count = documents.len(),
+                "DRY RUN: Would insert {} compositions into SQLite",
+                documents.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: documents.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        let mut rows = Vec::new();
+        let mut failures = Vec::new();
+
+        for doc in documents {
+            let doc_id = doc
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            match composition_from_json(doc) {
+                Ok(row) => rows.push(row),
+                Err(e) => failures.push(BulkInsertFailure {
+                    document_id: doc_id,
+                    error: format!("Failed to convert composition: {e}"),
+                    is_throttled: false,
+                }),
+            }
+        }
+
+        self.upsert_compositions(rows, failures).await
+    }
+
+    async fn bulk_insert_compositions(
+        &self,
+        _template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        _max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                count = compositions.len(),
+                "DRY RUN: Would insert {} compositions (preserved format) into SQLite",
+                compositions.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: compositions.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_compositions(
+            compositions,
+            export_mode,
+            PostgreSQLComposition::from_domain_preserved,
+        )
+        .await
+    }
+
+    async fn bulk_insert_compositions_flattened(
+        &self,
+        _template_id: &TemplateId,
+        compositions: Vec<Composition>,
+        export_mode: String,
+        _max_retries: usize,
+        dry_run: bool,
+    ) -> Result<BulkInsertResult> {
+        // If dry-run, skip actual write and return success
+        if dry_run {
+            tracing::info!(
+                count = compositions.len(),
+                "DRY RUN: Would insert {} compositions (flattened format) into SQLite",
+                compositions.len()
+            );
+            return Ok(BulkInsertResult {
+                success_count: compositions.len(),
+                failure_count: 0,
+                failures: Vec::new(),
+            });
+        }
+
+        self.insert_compositions(
+            compositions,
+            export_mode,
+            PostgreSQLComposition::from_domain_flattened,
+        )
+        .await
+    }
+
+    async fn check_composition_exists(
+        &self,
+        _template_id: &TemplateId,
+        ehr_id: &str,
+        composition_id: &str,
+    ) -> Result<bool> {
+        let ehr_id = ehr_id.to_string();
+        let composition_id = composition_id.to_string();
+
+        self.client
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM compositions WHERE id = ?1 AND ehr_id = ?2)",
+                    params![composition_id, ehr_id],
+                    |row| row.get(0),
+                )
+            })
+            .await
+    }
+
+    async fn list_composition_ids(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Vec<String>> {
+        let ehr_id = ehr_id.to_string();
+        let template_id = template_id.to_string();
+
+        self.client
+            .call(move |conn| {
+                let mut statement = conn.prepare(
+                    "SELECT id FROM compositions \
+                     WHERE ehr_id = ?1 AND template_id = ?2 AND deleted_at IS NULL \
+                     ORDER BY id",
+                )?;
+                let ids = statement
+                    .query_map(params![ehr_id, template_id], |row| row.get(0))?
+                    .collect();
+                ids
+            })
+            .await
+    }
+
+    async fn delete_compositions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        composition_ids: &[String],
+        policy: DeletionPolicy,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if composition_ids.is_empty() || policy == DeletionPolicy::Ignore {
+            return Ok(0);
+        }
+
+        if dry_run {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                ehr_id = %ehr_id.as_str(),
+                count = composition_ids.len(),
+                policy = %policy,
+                "DRY RUN: Would apply deletion policy to {} compositions in SQLite",
+                composition_ids.len()
+            );
+            return Ok(composition_ids.len());
+        }
+
+        let statement = match policy {
+            DeletionPolicy::HardDelete => "DELETE FROM compositions WHERE ehr_id = ?1 AND id = ?2",
+            _ => {
+                "UPDATE compositions SET deleted_at = ?3 \
+                 WHERE ehr_id = ?1 AND id = ?2 AND deleted_at IS NULL"
+            }
+        };
+        let ehr = ehr_id.to_string();
+        let ids = composition_ids.to_vec();
+        let deleted_at = Utc::now();
+
+        let affected = self
+            .client
+            .call(move |conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let mut affected = 0;
+                {
+                    let mut statement = tx.prepare(statement)?;
+                    for id in &ids {
+                        affected += if policy == DeletionPolicy::HardDelete {
+                            statement.execute(params![ehr, id])?
+                        } else {
+                            statement.execute(params![ehr, id, deleted_at])?
+                        };
+                    }
+                }
+                tx.commit()?;
+
+                Ok(affected)
+            })
+            .await?;
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = affected,
+            policy = %policy,
+            "Applied deletion policy in SQLite"
+        );
+
+        Ok(affected)
+    }
+
+    fn database_name(&self) -> &str {
+        "sqlite"
+    }
+}
+
+#[async_trait]
+impl StateStorage for SQLiteAdapter {
+    async fn load_watermark(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Result<Option<Watermark>> {
+        let watermark_id = Watermark::generate_id(template_id, ehr_id);
+
+        let row = self
+            .client
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT * FROM watermarks WHERE id = ?1",
+                    params![watermark_id],
+                    watermark_from_row,
+                )
+                .optional()
+            })
+            .await?;
+
+        row.map(|row| row.to_domain()).transpose()
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark, dry_run: bool) -> Result<()> {
+        // If dry-run, skip actual write
+        if dry_run {
+            tracing::info!(
+                template_id = %watermark.template_id.as_str(),
+                ehr_id = %watermark.ehr_id.as_str(),
+                watermark_id = %watermark.id,
+                "DRY RUN: Would save watermark to SQLite"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLWatermark::from_domain(watermark);
+
+        self.client
+            .call(move |conn| {
+                conn.execute(
+                    r#"
+                    INSERT INTO watermarks (
+                        id, template_id, ehr_id, last_exported_timestamp,
+                        last_exported_composition_uid, compositions_exported_count,
+                        last_export_started_at, last_export_completed_at, last_export_status
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                    ON CONFLICT (id) DO UPDATE SET
+                        last_exported_timestamp = excluded.last_exported_timestamp,
+                        last_exported_composition_uid = excluded.last_exported_composition_uid,
+                        compositions_exported_count = excluded.compositions_exported_count,
+                        last_export_started_at = excluded.last_export_started_at,
+                        last_export_completed_at = excluded.last_export_completed_at,
+                        last_export_status = excluded.last_export_status
+                    "#,
+                    params![
+                        row.id,
+                        row.template_id,
+                        row.ehr_id,
+                        row.last_exported_timestamp,
+                        row.last_exported_composition_uid,
+                        row.compositions_exported_count,
+                        row.last_export_started_at,
+                        row.last_export_completed_at,
+                        row.last_export_status,
+                    ],
+                )
+            })
+            .await?;
+
+        tracing::debug!(
+            template_id = %watermark.template_id.as_str(),
+            ehr_id = %watermark.ehr_id.as_str(),
+            "Watermark saved to SQLite"
+        );
+
+        Ok(())
+    }
+
+    async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
+        let rows: Vec<PostgreSQLWatermark> = self
+            .client
+            .call(|conn| {
+                let mut statement =
+                    conn.prepare("SELECT * FROM watermarks ORDER BY template_id, ehr_id")?;
+                let rows = statement.query_map([], watermark_from_row)?.collect();
+                rows
+            })
+            .await?;
+
+        rows.iter()
+            .map(PostgreSQLWatermark::to_domain)
+            .collect::<Result<Vec<_>>>()
+    }
+
+    async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        let id = QueryWatermark::generate_id(query_name);
+
+        let row = self
+            .client
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT * FROM query_watermarks WHERE id = ?1",
+                    params![id],
+                    |row| {
+                        Ok(PostgreSQLQueryWatermark {
+                            id: row.get("id")?,
+                            query_name: row.get("query_name")?,
+                            watermark_value: row.get("watermark_value")?,
+                            rows_exported_count: row.get("rows_exported_count")?,
+                            last_export_started_at: row.get("last_export_started_at")?,
+                            last_export_completed_at: row.get("last_export_completed_at")?,
+                            last_export_status: row.get("last_export_status")?,
+                        })
+                    },
+                )
+                .optional()
+            })
+            .await?;
+
+        Ok(row.map(|row| row.to_domain()))
+    }
+
+    async fn save_query_watermark(&self, watermark: &QueryWatermark, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                query = %watermark.query_name,
+                "DRY RUN: Would save query watermark to SQLite"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLQueryWatermark::from_domain(watermark);
+
+        self.client
+            .call(move |conn| {
+                conn.execute(
+                    r#"
+                    INSERT INTO query_watermarks (
+                        id, query_name, watermark_value, rows_exported_count,
+                        last_export_started_at, last_export_completed_at, last_export_status
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                    ON CONFLICT (id) DO UPDATE SET
+                        watermark_value = excluded.watermark_value,
+                        rows_exported_count = excluded.rows_exported_count,
+                        last_export_started_at = excluded.last_export_started_at,
+                        last_export_completed_at = excluded.last_export_completed_at,
+                        last_export_status = excluded.last_export_status
+                    "#,
+                    params![
+                        row.id,
+                        row.query_name,
+                        row.watermark_value,
+                        row.rows_exported_count,
+                        row.last_export_started_at,
+                        row.last_export_completed_at,
+                        row.last_export_status,
+                    ],
+                )
+            })
+            .await?;
+
+        tracing::debug!(query = %watermark.query_name, "Query watermark saved to SQLite");
+
+        Ok(())
+    }
+
+    async fn load_dead_letter(
+        &self,
+        composition_uid: &CompositionUid,
+    ) -> Result<Option<DeadLetter>> {
+        let id = DeadLetter::generate_id(composition_uid);
+
+        let row = self
+            .client
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT * FROM dead_letters WHERE id = ?1",
+                    params![id],
+                    dead_letter_from_row,
+                )
+                .optional()
+            })
+            .await?;
+
+        row.map(|row| row.to_domain()).transpose()
+    }
+
+    async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would save dead letter to SQLite"
+            );
+            return Ok(());
+        }
+
+        let row = PostgreSQLDeadLetter::from_domain(dead_letter)?;
+
+        self.client
+            .call(move |conn| {
+                conn.execute(
+                    r#"
+                    INSERT INTO dead_letters (
+                        id, composition_uid, template_id, ehr_id, time_committed, version,
+                        stage, error, attempt_count, first_failed_at, last_failed_at
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                    ON CONFLICT (id) DO UPDATE SET
+                        version = excluded.version,
+                        stage = excluded.stage,
+                        error = excluded.error,
+                        attempt_count = excluded.attempt_count,
+                        last_failed_at = excluded.last_failed_at
+                    "#,
+                    params![
+                        row.id,
+                        row.composition_uid,
+                        row.template_id,
+                        row.ehr_id,
+                        row.time_committed,
+                        row.version,
+                        row.stage,
+                        row.error,
+                        row.attempt_count,
+                        row.first_failed_at,
+                        row.last_failed_at,
+                    ],
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        if dry_run {
+            tracing::info!(
+                composition_uid = %dead_letter.composition_uid,
+                "DRY RUN: Would delete dead letter from SQLite"
+            );
+            return Ok(());
+        }
+
+        let id = dead_letter.id.clone();
+        self.client
+            .call(move |conn| conn.execute("DELETE FROM dead_letters WHERE id = ?1", params![id]))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let rows: Vec<PostgreSQLDeadLetter> = self
+            .client
+            .call(|conn| {
+                let mut statement =
+                    conn.prepare("SELECT * FROM dead_letters ORDER BY first_failed_at")?;
+                let rows = statement.query_map([], dead_letter_from_row)?.collect();
+                rows
+            })
+            .await?;
+
+        rows.iter().map(PostgreSQLDeadLetter::to_domain).collect()
+    }
+}
+
+/// Read a `watermarks` row
+fn watermark_from_row(row: &Row<'_>) -> rusqlite::Result<PostgreSQLWatermark> {
+    Ok(PostgreSQLWatermark {
+        id: row.get("id")?,
+        template_id: row.get("template_id")?,
+        ehr_id: row.get("ehr_id")?,
+        last_exported_timestamp: row.get("last_exported_timestamp")?,
+        last_exported_composition_uid: row.get("last_exported_composition_uid")?,
+        compositions_exported_count: row.get("compositions_exported_count")?,
+        last_export_started_at: row.get("last_export_started_at")?,
+        last_export_completed_at: row.get("last_export_completed_at")?,
+        last_export_status: row.get("last_export_status")?,
+    })
+}
+
+/// Read a `dead_letters` row
+fn dead_letter_from_row(row: &Row<'_>) -> rusqlite::Result<PostgreSQLDeadLetter> {
+    Ok(PostgreSQLDeadLetter {
+        id: row.get("id")?,
+        composition_uid: row.get("composition_uid")?,
+        template_id: row.get("template_id")?,
+        ehr_id: row.get("ehr_id")?,
+        time_committed: row.get("time_committed")?,
+        version: row.get("version")?,
+        stage: row.get("stage")?,
+        error: row.get("error")?,
+        attempt_count: row.get("attempt_count")?,
+        first_failed_at: row.get("first_failed_at")?,
+        last_failed_at: row.get("last_failed_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::retry::RetryPolicy;
+    use crate::config::schema::SQLiteConfig;
+    use crate::core::state::watermark::WatermarkBuilder;
+    use crate::core::transform::flatten::flatten_composition;
+    use crate::domain::composition::CompositionBuilder;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    async fn adapter(dir: &std::path::Path) -> SQLiteAdapter {
+        let client = SQLiteClient::new(SQLiteConfig {
+            path: dir.join("atlas.db").display().to_string(),
+            busy_timeout_seconds: 5,
+        })
+        .await
+        .unwrap();
+        let adapter = SQLiteAdapter::new(client);
+        adapter.ensure_database_exists().await.unwrap();
+        adapter
+    }
+
+    fn composition(uid: &str) -> Composition {
+        CompositionBuilder::new()
+            .uid(CompositionUid::from_str(uid).unwrap())
+            .ehr_id(EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::from_str("vital_signs.v1").unwrap())
+            .time_committed(Utc::now())
+            .content(json!({"vital_signs/body_temperature:0|magnitude": 37.5}))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compositions_and_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        let adapter = adapter(dir.path()).await;
+        let template_id = TemplateId::from_str("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+
+        let document =
+            flatten_composition(composition("c1::local::1"), "full".to_string()).unwrap();
+        let result = adapter
+            .bulk_insert_json(&template_id, vec![document, json!({"id": "bad"})], 3, false)
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failures[0].document_id, "bad");
+
+        adapter
+            .bulk_insert_compositions(
+                &template_id,
+                vec![composition("c2::local::1")],
+                "full".to_string(),
+                3,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let magnitude: f64 = adapter
+            .client()
+            .call(|conn| {
+                conn.query_row(
+                    "SELECT content ->> 'vital_signs_body_temperature_0_magnitude' \
+                     FROM compositions WHERE id = 'c1::local::1'",
+                    [],
+                    |row| row.get(0),
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(magnitude, 37.5);
+
+        let deleted = adapter
+            .delete_compositions(
+                &template_id,
+                &ehr_id,
+                &["c1::local::1".to_string()],
+                DeletionPolicy::SoftDelete,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(
+            adapter
+                .list_composition_ids(&template_id, &ehr_id)
+                .await
+                .unwrap(),
+            vec!["c2::local::1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_locked_database_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("atlas.db");
+        let open = |retry: RetryPolicy| {
+            let path = path.display().to_string();
+            async move {
+                let client = SQLiteClient::new(SQLiteConfig {
+                    path,
+                    busy_timeout_seconds: 0,
+                })
+                .await
+                .unwrap()
+                .with_retry_policy(retry);
+                SQLiteAdapter::new(client)
+            }
+        };
+        let template_id = TemplateId::from_str("vital_signs.v1").unwrap();
+        let document = || flatten_composition(composition("c1::local::1"), "full".to_string());
+
+        let adapter = open(RetryPolicy::none()).await;
+        adapter.ensure_database_exists().await.unwrap();
+
+        // Another connection holds the write lock
+        let other = rusqlite::Connection::open(&path).unwrap();
+        other.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let error = adapter
+            .bulk_insert_json(&template_id, vec![document().unwrap()], 3, false)
+            .await
+            .unwrap_err();
+        assert!(error.is_retryable());
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            other.execute_batch("COMMIT").unwrap();
+        });
+
+        let adapter = open(RetryPolicy::from_schedule(50, &[20]).without_jitter()).await;
+        let result = adapter
+            .bulk_insert_json(&template_id, vec![document().unwrap()], 3, false)
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 1);
+
+        release.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watermark_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let adapter = adapter(dir.path()).await;
+        let template_id = TemplateId::from_str("vital_signs.v1").unwrap();
+        let ehr_id = EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+
+        assert!(adapter
+            .load_watermark(&template_id, &ehr_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut watermark = WatermarkBuilder::new(template_id.clone(), ehr_id.clone()).build();
+        watermark.update_after_export(
+            CompositionUid::from_str("c1::local::1").unwrap(),
+            Utc::now(),
+        );
+        adapter.save_watermark(&watermark, false).await.unwrap();
+
+        let loaded = adapter
+            .load_watermark(&template_id, &ehr_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            loaded.last_exported_timestamp.timestamp_micros(),
+            watermark.last_exported_timestamp.timestamp_micros()
+        );
+        assert_eq!(loaded.compositions_exported_count, 1);
+        assert_eq!(adapter.get_all_watermarks().await.unwrap().len(), 1);
+    }
+}
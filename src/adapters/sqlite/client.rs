@@ -0,0 +1,191 @@
+//! SQLite client implementation
+//!
+//! This module provides a client for a SQLite database file. SQLite calls
+//! are blocking, so every statement runs on Tokio's blocking thread pool
+//! while holding the single connection.
+
+use crate::adapters::retry::RetryPolicy;
+use crate::config::schema::SQLiteConfig;
+use crate::domain::{AtlasError, Result};
+use rusqlite::{Connection, ErrorCode};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// SQLite client
+///
+/// Holds one connection to the database file. Writers are serialized by
+/// SQLite anyway, so a pool would not add throughput.
+#[derive(Clone)]
+pub struct SQLiteClient {
+    connection: Arc<Mutex<Connection>>,
+    path: String,
+    retry: RetryPolicy,
+}
+
+impl SQLiteClient {
+    /// Open the database file
+    ///
+    /// The file is created if it doesn't exist. The database is switched to
+    /// write-ahead logging, so readers such as the `sqlite3` shell don't block
+    /// an export.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened.
+    pub async fn new(config: SQLiteConfig) -> Result<Self> {
+        let path = config.path.clone();
+
+        let connection = tokio::task::spawn_blocking(move || -> Result<Connection> {
+            if let Some(parent) = std::path::Path::new(&config.path).parent() {
+                if !parent.as_os_str().is_empty() {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+
+            let connection = Connection::open(&config.path).map_err(|e| {
+                AtlasError::Connection(format!(
+                    "Failed to open SQLite database {}: {e}",
+                    config.path
+                ))
+            })?;
+            connection
+                .busy_timeout(Duration::from_secs(config.busy_timeout_seconds))
+                .map_err(sqlite_error)?;
+            connection
+                .pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+                .map_err(sqlite_error)?;
+
+            Ok(connection)
+        })
+        .await
+        .map_err(|e| AtlasError::Database(format!("SQLite task failed: {e}")))??;
+
+        tracing::info!(path = %path, "Opened SQLite database");
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            path,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Set the retry policy for statements that fail transiently
+    ///
+    /// A statement that is still locked out by another connection after
+    /// `busy_timeout_seconds` is retried with this policy.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Retry policy of the client
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Path of the database file
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Run a closure with the connection on the blocking thread pool
+    ///
+    /// A busy or locked database is retried with the client's retry policy,
+    /// running the closure again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the closure, or an error if the task fails.
+    pub async fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: Fn(&mut Connection) -> rusqlite::Result<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let f = Arc::new(f);
+
+        self.retry
+            .retry(|| {
+                let connection = self.connection.clone();
+                let f = f.clone();
+
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        let mut connection = connection.lock().map_err(|_| {
+                            AtlasError::Database("SQLite connection poisoned".to_string())
+                        })?;
+                        f(&mut connection).map_err(sqlite_error)
+                    })
+                    .await
+                    .map_err(|e| AtlasError::Database(format!("SQLite task failed: {e}")))?
+                }
+            })
+            .await
+    }
+
+    /// Test the connection to the database
+    pub async fn test_connection(&self) -> Result<()> {
+        self.call(|conn| conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)))
+            .await?;
+
+        tracing::info!("SQLite connection test successful");
+        Ok(())
+    }
+
+    /// Ensure the database schema exists
+    ///
+    /// The schema mirrors the PostgreSQL migrations and is idempotent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema cannot be created.
+    pub async fn ensure_database_exists(&self) -> Result<()> {
+        let schema = include_str!("../../../migrations/sqlite/001_initial_schema.sql");
+
+        self.call(move |conn| conn.execute_batch(schema)).await?;
+
+        tracing::info!("SQLite schema initialized successfully");
+        Ok(())
+    }
+
+    /// Ensure the result table of a named AQL query exists
+    ///
+    /// The name has been validated as a plain SQL identifier and is quoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `query_name` - Name of the query, used as the table name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table cannot be created.
+    pub async fn ensure_query_table_exists(&self, query_name: &str) -> Result<()> {
+        let statement = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS "{query_name}" (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL CHECK (json_valid(data)),
+                exported_at TEXT NOT NULL,
+                atlas_version TEXT NOT NULL
+            )
+            "#
+        );
+
+        self.call(move |conn| conn.execute_batch(&statement))
+            .await?;
+
+        tracing::debug!(table = %query_name, "Query table ready");
+        Ok(())
+    }
+}
+
+/// Convert a SQLite error into an Atlas error
+///
+/// A busy or locked database is a transient connection error.
+fn sqlite_error(error: rusqlite::Error) -> AtlasError {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => {
+            AtlasError::Connection(format!("SQLite database is locked: {error}"))
+        }
+        _ => AtlasError::Database(format!("SQLite error: {error}")),
+    }
+}
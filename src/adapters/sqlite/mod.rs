@@ -0,0 +1,11 @@
+//! SQLite database integration
+//!
+//! This module provides integration with an embedded SQLite database file
+//! for storing openEHR compositions. The schema mirrors the PostgreSQL
+//! target, with JSON columns queried through SQLite's JSON functions.
+
+pub mod adapter;
+pub mod client;
+
+pub use adapter::SQLiteAdapter;
+pub use client::SQLiteClient;
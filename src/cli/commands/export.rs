@@ -32,6 +32,14 @@ pub struct ExportArgs {
     #[arg(long)]
     pub mode: Option<String>,
 
+    /// Only export compositions at or after this time (ISO 8601)
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub since: Option<String>,
+
+    /// Only export compositions at or before this time (ISO 8601)
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub until: Option<String>,
+
     /// Enable anonymization of PHI/PII data
     #[arg(long)]
     pub anonymize: bool,
@@ -78,6 +86,16 @@ impl ExportArgs {
             config.openehr.query.ehr_ids = ids;
         }
 
+        if let Some(since) = &self.since {
+            tracing::info!(since = %since, "Overriding time range start from CLI");
+            config.openehr.query.time_range_start = Some(since.clone());
+        }
+
+        if let Some(until) = &self.until {
+            tracing::info!(until = %until, "Overriding time range end from CLI");
+            config.openehr.query.time_range_end = Some(until.clone());
+        }
+
         // Apply dry-run flag from CLI
         if self.dry_run {
             tracing::info!("Enabling dry-run mode from CLI");
@@ -147,7 +165,11 @@ impl ExportArgs {
         if !self.yes && !self.dry_run {
             println!("Export Configuration:");
             println!("  Mode: {}", config.export.mode);
-            println!("  Templates: {:?}", config.openehr.query.template_ids);
+            if config.openehr.query.template_ids.is_empty() {
+                println!("  Templates: All (discovered on openEHR server)");
+            } else {
+                println!("  Templates: {:?}", config.openehr.query.template_ids);
+            }
             println!(
                 "  EHRs: {}",
                 if config.openehr.query.ehr_ids.is_empty() {
@@ -156,6 +178,25 @@ impl ExportArgs {
                     format!("{:?}", config.openehr.query.ehr_ids)
                 }
             );
+            if config.openehr.query.time_range_start.is_some()
+                || config.openehr.query.time_range_end.is_some()
+            {
+                println!(
+                    "  Time range: {} to {}",
+                    config
+                        .openehr
+                        .query
+                        .time_range_start
+                        .as_deref()
+                        .unwrap_or("beginning"),
+                    config
+                        .openehr
+                        .query
+                        .time_range_end
+                        .as_deref()
+                        .unwrap_or("now")
+                );
+            }
             println!("  Batch size: {}", config.openehr.query.batch_size);
             println!();
             print!("Proceed with export? [y/N]: ");
@@ -217,6 +258,15 @@ impl ExportArgs {
         println!("  Successful: {}", summary.successful_exports);
         println!("  Failed: {}", summary.failed_exports);
         println!("  Duplicates Skipped: {}", summary.duplicates_skipped);
+        if summary.compositions_deleted > 0 {
+            println!("  Deleted: {}", summary.compositions_deleted);
+        }
+        if summary.ehr_statuses_exported > 0 {
+            println!("  EHR Statuses: {}", summary.ehr_statuses_exported);
+        }
+        if summary.query_rows_exported > 0 {
+            println!("  Query Rows: {}", summary.query_rows_exported);
+        }
         println!("  Duration: {:.2}s", summary.duration.as_secs_f64());
         println!("  Success Rate: {:.2}%", summary.success_rate());
         println!();
@@ -308,6 +358,8 @@ mod tests {
             template_id: None,
             ehr_id: None,
             mode: None,
+            since: None,
+            until: None,
             anonymize: false,
             anonymize_mode: None,
             anonymize_dry_run: false,
@@ -328,6 +380,8 @@ mod tests {
             template_id: Some("vital_signs.v1".to_string()),
             ehr_id: Some("ehr1,ehr2".to_string()),
             mode: Some("full".to_string()),
+            since: Some("2023-01-01T00:00:00Z".to_string()),
+            until: Some("2023-12-31T23:59:59Z".to_string()),
             anonymize: false,
             anonymize_mode: None,
             anonymize_dry_run: false,
@@ -337,6 +391,8 @@ mod tests {
         assert!(args.dry_run);
         assert_eq!(args.template_id, Some("vital_signs.v1".to_string()));
         assert_eq!(args.ehr_id, Some("ehr1,ehr2".to_string()));
+        assert_eq!(args.since, Some("2023-01-01T00:00:00Z".to_string()));
+        assert_eq!(args.until, Some("2023-12-31T23:59:59Z".to_string()));
         assert_eq!(args.mode, Some("full".to_string()));
     }
 }
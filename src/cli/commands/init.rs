@@ -224,6 +224,12 @@ tls_verify = true
 # Use this instead of disabling TLS verification in production
 # tls_ca_cert = "/path/to/ca.crt"
 
+# Optional: Client certificate for mutual TLS (PEM certificate + PKCS#8 key, or PKCS#12)
+# tls_client_cert = "/path/to/client.pem"
+# tls_client_key = "/path/to/client.key"
+# tls_client_pkcs12 = "/path/to/client.p12"
+# tls_client_pkcs12_password = "${ATLAS_OPENEHR_TLS_CLIENT_PKCS12_PASSWORD}"
+
 # Query configuration
 [openehr.query]
 # Template IDs to export (required)
@@ -4,5 +4,6 @@
 
 pub mod export;
 pub mod init;
+pub mod retry_failed;
 pub mod status;
 pub mod validate;
@@ -0,0 +1,135 @@
+//! Retry-failed command implementation
+//!
+//! This module implements the `retry-failed` command, which reprocesses the
+//! compositions recorded as dead letters by earlier exports.
+
+use crate::config::load_config;
+use crate::core::export::ExportCoordinator;
+use clap::Args;
+use tokio::sync::watch;
+
+/// Arguments for the retry-failed command
+#[derive(Args, Debug)]
+pub struct RetryFailedArgs {
+    /// Dry run mode - fetch and transform without writing to the database
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Only retry compositions of these template ID(s) (comma-separated)
+    #[arg(long)]
+    pub template_id: Option<String>,
+
+    /// Only retry compositions of these EHR ID(s) (comma-separated)
+    #[arg(long)]
+    pub ehr_id: Option<String>,
+}
+
+impl RetryFailedArgs {
+    /// Execute the retry-failed command
+    pub async fn execute(
+        &self,
+        config_path: &str,
+        shutdown_signal: watch::Receiver<bool>,
+    ) -> anyhow::Result<i32> {
+        tracing::info!("Starting retry-failed command");
+
+        // Load configuration
+        let mut config = load_config(config_path)?;
+
+        // The configured filters select which dead letters are retried
+        config.openehr.query.template_ids = split_ids(self.template_id.as_deref());
+        config.openehr.query.ehr_ids = split_ids(self.ehr_id.as_deref());
+
+        if self.dry_run {
+            tracing::info!("Enabling dry-run mode from CLI");
+            config.export.dry_run = true;
+            println!("🔍 DRY RUN MODE - No data will be written to the database");
+            println!();
+        }
+
+        // Validate configuration
+        if let Err(e) = config.validate() {
+            tracing::error!(error = %e, "Configuration validation failed");
+            eprintln!("Configuration validation failed: {e}");
+            return Ok(2); // Configuration error exit code
+        }
+
+        let coordinator = match ExportCoordinator::new(config, shutdown_signal).await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to create export coordinator");
+                eprintln!("Failed to initialize retry: {e}");
+                return Ok(4); // Connection error exit code
+            }
+        };
+
+        println!("🔁 Retrying failed compositions...");
+        println!();
+
+        let summary = match coordinator.retry_failed().await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!(error = %e, "Retry failed");
+                eprintln!("Retry failed: {e}");
+                return Ok(5); // Fatal error exit code
+            }
+        };
+
+        if summary.total_compositions == 0 && !summary.interrupted {
+            println!("✅ No failed compositions to retry.");
+            return Ok(0);
+        }
+
+        println!("📊 Retry Summary:");
+        println!("  Total EHRs: {}", summary.total_ehrs);
+        println!("  Total Compositions: {}", summary.total_compositions);
+        println!("  Recovered: {}", summary.successful_exports);
+        println!("  Still Failing: {}", summary.failed_exports);
+        println!("  Duration: {:.2}s", summary.duration.as_secs_f64());
+        println!();
+
+        if !summary.errors.is_empty() {
+            println!("⚠️  Errors encountered:");
+            for error in &summary.errors {
+                println!("  - {:?}: {}", error.error_type, error.message);
+                if let Some(context) = &error.context {
+                    println!("    Context: {context}");
+                }
+            }
+            println!();
+        }
+
+        let exit_code = if summary.interrupted {
+            println!("⚠️  Retry interrupted gracefully. Remaining compositions are kept for the next run.");
+            130 // SIGINT exit code (standard Unix convention)
+        } else if summary.failed_exports > 0 {
+            println!("⚠️  Some compositions are still failing and will be retried next time");
+            1 // Partial success
+        } else {
+            println!("✅ All failed compositions were exported!");
+            0
+        };
+
+        Ok(exit_code)
+    }
+}
+
+/// Split a comma-separated list of IDs
+fn split_ids(ids: Option<&str>) -> Vec<String> {
+    ids.map(|ids| ids.split(',').map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_ids() {
+        assert!(split_ids(None).is_empty());
+        assert_eq!(
+            split_ids(Some("vital_signs.v1, lab_results.v1")),
+            vec!["vital_signs.v1", "lab_results.v1"]
+        );
+    }
+}
@@ -3,6 +3,7 @@
 //! This module implements the `validate-config` command for validating
 //! the Atlas configuration file.
 
+use crate::adapters::openehr::tls;
 use crate::config::load_config;
 use crate::config::schema::DatabaseTarget;
 use clap::Args;
@@ -32,8 +33,14 @@ impl ValidateArgs {
             }
         };
 
-        // Validate configuration
-        match config.validate() {
+        // Validate configuration, including the TLS files it refers to
+        let validation = config.validate().and_then(|_| {
+            tls::build_client(&config.openehr)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+
+        match validation {
             Ok(_) => {
                 println!("✅ Configuration is valid");
                 println!();
@@ -43,6 +50,11 @@ impl ValidateArgs {
                 println!("  Log Level: {}", config.application.log_level);
                 println!("  openEHR Server: {}", config.openehr.base_url);
                 println!("  openEHR Vendor: {}", config.openehr.vendor);
+                if config.openehr.tls_client_pkcs12.is_some()
+                    || config.openehr.tls_client_cert.is_some()
+                {
+                    println!("  openEHR Mutual TLS: enabled");
+                }
 
                 // Display database-specific configuration
                 match config.database_target {
@@ -69,6 +81,40 @@ impl ValidateArgs {
                             println!("  Max Connections: {}", pg_config.max_connections);
                         }
                     }
+                    DatabaseTarget::SQLite => {
+                        if let Some(ref sqlite_config) = config.sqlite {
+                            println!("  Database Target: SQLite");
+                            println!("  Database File: {}", sqlite_config.path);
+                        }
+                    }
+                    DatabaseTarget::MongoDB => {
+                        if let Some(ref mongo_config) = config.mongodb {
+                            println!("  Database Target: MongoDB");
+                            println!("  Database: {}", mongo_config.database_name);
+                            println!("  Max Pool Size: {}", mongo_config.max_pool_size);
+                        }
+                    }
+                    DatabaseTarget::Search => {
+                        if let Some(ref search_config) = config.search {
+                            println!("  Database Target: Search");
+                            println!("  URL: {}", search_config.url);
+                            println!("  Index Prefix: {}", search_config.index_prefix);
+                        }
+                    }
+                    DatabaseTarget::Filesystem => {
+                        if let Some(ref fs_config) = config.filesystem {
+                            println!("  Database Target: Filesystem");
+                            println!("  Root Directory: {}", fs_config.root_dir);
+                            println!("  Compression: {}", fs_config.compression);
+                        }
+                    }
+                    DatabaseTarget::Parquet => {
+                        if let Some(ref pq_config) = config.parquet {
+                            println!("  Database Target: Parquet");
+                            println!("  Root Directory: {}", pq_config.root_dir);
+                            println!("  Compression: {}", pq_config.compression);
+                        }
+                    }
                 }
 
                 println!("  Export Mode: {}", config.export.mode);
@@ -78,7 +124,15 @@ impl ValidateArgs {
                 );
                 println!("  Batch Size: {}", config.openehr.query.batch_size);
                 println!("  Parallel EHRs: {}", config.openehr.query.parallel_ehrs);
-                println!("  Template IDs: {:?}", config.openehr.query.template_ids);
+                if config.openehr.query.template_ids.is_empty() {
+                    println!(
+                        "  Template IDs: all (include: {:?}, exclude: {:?})",
+                        config.openehr.query.template_include,
+                        config.openehr.query.template_exclude
+                    );
+                } else {
+                    println!("  Template IDs: {:?}", config.openehr.query.template_ids);
+                }
                 println!();
                 Ok(0)
             }
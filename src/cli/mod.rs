@@ -37,6 +37,9 @@ pub enum Commands {
     /// Show export status and watermarks
     Status(commands::status::StatusArgs),
 
+    /// Retry compositions that failed to export in earlier runs
+    RetryFailed(commands::retry_failed::RetryFailedArgs),
+
     /// Initialize a new configuration file
     Init(commands::init::InitArgs),
 }
@@ -76,6 +79,18 @@ mod tests {
         assert!(matches!(cli.command, Commands::Status(_)));
     }
 
+    #[test]
+    fn test_cli_parse_retry_failed() {
+        let cli = Cli::parse_from(["atlas", "retry-failed", "--template-id", "vital_signs.v1"]);
+        match cli.command {
+            Commands::RetryFailed(args) => {
+                assert_eq!(args.template_id.as_deref(), Some("vital_signs.v1"));
+                assert!(!args.dry_run);
+            }
+            _ => panic!("expected retry-failed command"),
+        }
+    }
+
     #[test]
     fn test_cli_parse_init() {
         let cli = Cli::parse_from(["atlas", "init"]);
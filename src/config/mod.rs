@@ -165,7 +165,9 @@ pub mod secret;
 // Re-export commonly used types
 pub use loader::load_config;
 pub use schema::{
-    ApplicationConfig, AtlasConfig, CosmosDbConfig, Environment, ExportConfig, LoggingConfig,
-    OpenEhrConfig, QueryConfig, StateConfig, VerificationConfig,
+    ApplicationConfig, AqlQueryConfig, AtlasConfig, ChangeDetection, CosmosDbConfig,
+    DeletionPolicy, Environment, ExportConfig, FileCompression, FilesystemConfig, LoggingConfig,
+    MongoDbConfig, OidcGrantType, OpenEhrConfig, ParquetCompression, ParquetConfig, QueryConfig,
+    SQLiteConfig, SearchConfig, StateConfig, TemplateConfig, VendorOptions, VerificationConfig,
 };
 pub use secret::{secret_string, secret_string_opt, SecretString, SecretValue};
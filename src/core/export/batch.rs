@@ -3,7 +3,8 @@
 //! This module handles the transformation and bulk insertion of compositions
 //! to database backends in batches.
 
-use crate::adapters::database::traits::DatabaseClient;
+use crate::adapters::database::traits::{BulkInsertFailure, DatabaseClient};
+use crate::adapters::openehr::vendor::CompositionMetadata;
 use crate::anonymization::config::AnonymizationConfig;
 use crate::anonymization::engine::AnonymizationEngine;
 use crate::core::state::{StateManager, Watermark};
@@ -27,6 +28,8 @@ pub struct BatchConfig {
     pub dry_run: bool,
     /// Anonymization configuration (optional)
     pub anonymization: Option<AnonymizationConfig>,
+    /// Maximum number of retries for transient write failures
+    pub max_retries: usize,
 }
 
 impl BatchConfig {
@@ -42,9 +45,16 @@ impl BatchConfig {
             composition_format,
             dry_run,
             anonymization,
+            max_retries: 3,
         }
     }
 
+    /// Set the maximum number of retries for transient write failures
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
     /// Create from export config strings
     pub fn from_config(
         batch_size: usize,
@@ -73,6 +83,10 @@ pub struct BatchResult {
     pub duplicates_skipped: usize,
     /// Errors encountered
     pub errors: Vec<String>,
+    /// Compositions that could not be stored
+    pub failures: Vec<BulkInsertFailure>,
+    /// Metadata of the compositions of the batch, in batch order
+    pub compositions: Vec<CompositionMetadata>,
     /// Checksums of successfully exported compositions (composition_uid -> checksum)
     pub checksums: HashMap<CompositionUid, String>,
     /// Anonymization statistics (if anonymization was enabled)
@@ -100,6 +114,8 @@ impl BatchResult {
             failed: 0,
             duplicates_skipped: 0,
             errors: Vec::new(),
+            failures: Vec::new(),
+            compositions: Vec::new(),
             checksums: HashMap::new(),
             anonymization_stats: None,
         }
@@ -127,6 +143,7 @@ impl BatchResult {
         self.failed += other.failed;
         self.duplicates_skipped += other.duplicates_skipped;
         self.errors.extend(other.errors);
+        self.failures.extend(other.failures);
         self.checksums.extend(other.checksums);
 
         // Merge anonymization stats if present
@@ -286,14 +303,67 @@ impl BatchProcessor {
         template_id: &TemplateId,
         ehr_id: &EhrId,
         watermark: &mut Watermark,
+    ) -> Result<BatchResult> {
+        if compositions.is_empty() {
+            tracing::debug!("No compositions to process in batch");
+            return Ok(BatchResult::new());
+        }
+
+        let result = self.store_batch(&compositions, template_id, ehr_id).await?;
+
+        // Update watermark with the most recently committed composition, as
+        // version histories are not ordered by commit time
+        if let Some(newest) = compositions
+            .iter()
+            .max_by_key(|composition| composition.time_committed)
+        {
+            watermark.update_after_export(newest.uid.clone(), newest.time_committed);
+
+            // Checkpoint progress
+            if let Err(e) = self
+                .state_manager
+                .checkpoint_batch(watermark, self.config.dry_run)
+                .await
+            {
+                tracing::warn!(error = %e, "Failed to checkpoint watermark");
+                // Don't fail the batch, just log the warning
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Transform, anonymize and bulk insert a batch of compositions without
+    /// touching the watermark
+    ///
+    /// Used directly when reprocessing dead letters, whose compositions are
+    /// older than the watermark.
+    pub async fn store_batch(
+        &self,
+        compositions: &[Composition],
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
     ) -> Result<BatchResult> {
         let mut result = BatchResult::new();
 
         if compositions.is_empty() {
-            tracing::debug!("No compositions to process in batch");
             return Ok(result);
         }
 
+        result.compositions = compositions
+            .iter()
+            .map(|composition| {
+                let mut metadata = CompositionMetadata::new(
+                    composition.uid.clone(),
+                    template_id.clone(),
+                    ehr_id.clone(),
+                    composition.time_committed,
+                );
+                metadata.version = composition.version.clone();
+                metadata
+            })
+            .collect();
+
         tracing::info!(
             template_id = %template_id.as_str(),
             ehr_id = %ehr_id.as_str(),
@@ -304,7 +374,7 @@ impl BatchProcessor {
 
         // Transform and anonymize compositions
         let (transformed_json, anonymization_stats) =
-            self.transform_and_anonymize(&compositions).await?;
+            self.transform_and_anonymize(compositions).await?;
 
         // Store anonymization stats in result
         result.anonymization_stats = anonymization_stats;
@@ -315,7 +385,7 @@ impl BatchProcessor {
             .bulk_insert_json(
                 template_id,
                 transformed_json,
-                3, // max_retries
+                self.config.max_retries,
                 self.config.dry_run,
             )
             .await?;
@@ -326,6 +396,7 @@ impl BatchProcessor {
         // Add failure details
         for failure in bulk_result.failures {
             result.add_failure(format!("{}: {}", failure.document_id, failure.error));
+            result.failures.push(failure);
         }
 
         tracing::info!(
@@ -334,24 +405,6 @@ impl BatchProcessor {
             "Bulk insert completed"
         );
 
-        // Update watermark with last composition
-        if let Some(last_composition) = compositions.last() {
-            watermark.update_after_export(
-                last_composition.uid.clone(),
-                last_composition.time_committed,
-            );
-
-            // Checkpoint progress
-            if let Err(e) = self
-                .state_manager
-                .checkpoint_batch(watermark, self.config.dry_run)
-                .await
-            {
-                tracing::warn!(error = %e, "Failed to checkpoint watermark");
-                // Don't fail the batch, just log the warning
-            }
-        }
-
         Ok(result)
     }
 }
@@ -359,8 +412,9 @@ impl BatchProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::adapters::database::traits::{BulkInsertResult, StateStorage};
-    use crate::core::state::watermark::WatermarkBuilder;
+    use crate::adapters::database::traits::{BulkInsertResult, QueryRow, StateStorage};
+    use crate::core::state::dead_letter::DeadLetter;
+    use crate::core::state::watermark::{QueryWatermark, WatermarkBuilder};
     use crate::domain::composition::Composition;
     use async_trait::async_trait;
     use chrono::Utc;
@@ -415,6 +469,31 @@ mod tests {
             Ok(())
         }
 
+        async fn ensure_ehr_container_exists(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn upsert_ehr(&self, _ehr: &crate::domain::Ehr, _dry_run: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn ensure_query_container_exists(&self, _query_name: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn upsert_query_rows(
+            &self,
+            _query_name: &str,
+            rows: Vec<QueryRow>,
+            _dry_run: bool,
+        ) -> Result<BulkInsertResult> {
+            Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: vec![],
+            })
+        }
+
         async fn check_composition_exists(
             &self,
             _template_id: &TemplateId,
@@ -424,6 +503,25 @@ mod tests {
             Ok(false)
         }
 
+        async fn list_composition_ids(
+            &self,
+            _template_id: &TemplateId,
+            _ehr_id: &EhrId,
+        ) -> Result<Vec<String>> {
+            Ok(vec![])
+        }
+
+        async fn delete_compositions(
+            &self,
+            _template_id: &TemplateId,
+            _ehr_id: &EhrId,
+            composition_ids: &[String],
+            _policy: crate::config::DeletionPolicy,
+            _dry_run: bool,
+        ) -> Result<usize> {
+            Ok(composition_ids.len())
+        }
+
         fn database_name(&self) -> &str {
             "mock_database"
         }
@@ -551,6 +649,41 @@ mod tests {
         async fn get_all_watermarks(&self) -> Result<Vec<Watermark>> {
             Ok(self.watermarks.lock().unwrap().values().cloned().collect())
         }
+
+        async fn load_query_watermark(&self, _query_name: &str) -> Result<Option<QueryWatermark>> {
+            Ok(None)
+        }
+
+        async fn save_query_watermark(
+            &self,
+            _watermark: &QueryWatermark,
+            _dry_run: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn load_dead_letter(
+            &self,
+            _composition_uid: &CompositionUid,
+        ) -> Result<Option<DeadLetter>> {
+            Ok(None)
+        }
+
+        async fn save_dead_letter(&self, _dead_letter: &DeadLetter, _dry_run: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete_dead_letter(
+            &self,
+            _dead_letter: &DeadLetter,
+            _dry_run: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+            Ok(vec![])
+        }
     }
 
     // Helper to create test composition
@@ -564,6 +697,8 @@ mod tests {
                 "test": "data",
                 "archetype_node_id": "openEHR-EHR-COMPOSITION.encounter.v1"
             }),
+            content_format: Default::default(),
+            version: None,
         }
     }
 
@@ -825,7 +960,7 @@ mod tests {
             .await
             .unwrap();
 
-        // Watermark should be updated with the last composition UID
+        // Watermark should be updated with the newest composition UID
         assert_ne!(watermark.last_exported_composition_uid, initial_last_uid);
         assert_eq!(
             watermark
@@ -836,4 +971,59 @@ mod tests {
             "uid1::local::1"
         );
     }
+
+    #[tokio::test]
+    async fn test_process_batch_advances_watermark_to_newest_composition() {
+        let db_client = Arc::new(
+            MockDatabaseClient::new().with_insert_result(BulkInsertResult {
+                success_count: 3,
+                failure_count: 0,
+                failures: vec![],
+            }),
+        );
+        let state_manager = Arc::new(StateManager::new_with_storage(Arc::new(
+            MockStateStorage::new(),
+        )));
+        let config = BatchConfig::new(100, CompositionFormat::Preserve, false, None);
+        let processor = BatchProcessor::new(db_client, state_manager, config);
+
+        let template_id = TemplateId::new("vital_signs").unwrap();
+        let ehr_id = EhrId::new("test-ehr").unwrap();
+        let mut watermark = WatermarkBuilder::new(template_id.clone(), ehr_id.clone()).build();
+
+        // Versions are ordered by UID, not by commit time
+        let committed = Utc::now() - chrono::Duration::days(1);
+        let compositions: Vec<Composition> =
+            [("a::local::1", 0), ("a::local::2", 2), ("b::local::1", 1)]
+                .into_iter()
+                .map(|(uid, hours)| {
+                    let mut composition = create_test_composition(uid, "vital_signs", "test-ehr");
+                    composition.time_committed = committed + chrono::Duration::hours(hours);
+                    composition
+                })
+                .collect();
+
+        let result = processor
+            .process_batch(compositions, &template_id, &ehr_id, &mut watermark)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            watermark.last_exported_timestamp,
+            committed + chrono::Duration::hours(2)
+        );
+        assert_eq!(
+            watermark
+                .last_exported_composition_uid
+                .as_ref()
+                .map(|uid| uid.as_str()),
+            Some("a::local::2")
+        );
+        let uids: Vec<&str> = result
+            .compositions
+            .iter()
+            .map(|metadata| metadata.uid.as_str())
+            .collect();
+        assert_eq!(uids, vec!["a::local::1", "a::local::2", "b::local::1"]);
+    }
 }
@@ -0,0 +1,318 @@
+//! Contribution-based change detection for incremental exports
+//!
+//! Instead of querying every {EHR, template} pair for new compositions, the
+//! change feed runs a single AQL query over the composition versions
+//! committed since a global watermark and returns the EHRs that changed for
+//! each template. Only those pairs are then fed into the regular export
+//! pipeline, which still applies its per-pair watermarks.
+//!
+//! Deleted compositions have no content and are found by a second query over
+//! the deletions committed since the watermark. Their template is unknown, so
+//! an EHR with a deletion is processed for every template, which applies the
+//! deletion policy.
+
+use crate::adapters::openehr::aql::{AqlQuery, AqlRecord};
+use crate::adapters::openehr::vendor::OpenEhrVendor;
+use crate::domain::ids::{EhrId, TemplateId};
+use crate::domain::{AtlasError, OpenEhrError, Result};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use std::collections::{HashMap, HashSet};
+
+/// Name of the query watermark holding the commit time of the last change feed run
+pub const CHANGE_FEED_WATERMARK: &str = "@change_feed";
+
+/// EHRs with composition versions committed since the change feed watermark
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSet {
+    /// Changed EHRs per template
+    changes: HashMap<TemplateId, HashSet<EhrId>>,
+
+    /// EHRs with deleted compositions, changed for every template
+    deletions: HashSet<EhrId>,
+
+    /// Number of composition versions found
+    versions: usize,
+}
+
+impl ChangeSet {
+    /// Build a change set from the rows of [`AqlQuery::changed_compositions`]
+    ///
+    /// Only versions of the given templates are kept, and of the given EHRs
+    /// unless `ehr_ids` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row lacks the `ehr_id` or `template_id` column,
+    /// e.g. because the server ignores column aliases.
+    pub fn from_records(
+        records: &[AqlRecord],
+        template_ids: &[TemplateId],
+        ehr_ids: &[EhrId],
+    ) -> Result<Self> {
+        let templates: HashSet<&str> = template_ids.iter().map(|id| id.as_str()).collect();
+        let ehrs: HashSet<&str> = ehr_ids.iter().map(|id| id.as_str()).collect();
+        let mut change_set = Self::default();
+
+        for record in records {
+            let ehr_id = column(record, "ehr_id")?;
+            let template_id = column(record, "template_id")?;
+
+            if !templates.contains(template_id) || !(ehrs.is_empty() || ehrs.contains(ehr_id)) {
+                continue;
+            }
+
+            let ehr_id = EhrId::new(ehr_id).map_err(AtlasError::Validation)?;
+            let template_id = TemplateId::new(template_id).map_err(AtlasError::Validation)?;
+
+            change_set
+                .changes
+                .entry(template_id)
+                .or_default()
+                .insert(ehr_id);
+            change_set.versions += 1;
+        }
+
+        Ok(change_set)
+    }
+
+    /// Add the deletions from the rows of [`AqlQuery::deleted_compositions`]
+    ///
+    /// Only deletions in the given EHRs are kept, unless `ehr_ids` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row lacks the `ehr_id` column.
+    pub fn with_deletions(mut self, records: &[AqlRecord], ehr_ids: &[EhrId]) -> Result<Self> {
+        let ehrs: HashSet<&str> = ehr_ids.iter().map(|id| id.as_str()).collect();
+
+        for record in records {
+            let ehr_id = column(record, "ehr_id")?;
+            if !(ehrs.is_empty() || ehrs.contains(ehr_id)) {
+                continue;
+            }
+
+            self.deletions
+                .insert(EhrId::new(ehr_id).map_err(AtlasError::Validation)?);
+            self.versions += 1;
+        }
+
+        Ok(self)
+    }
+
+    /// EHRs changed for a template, sorted by ID
+    ///
+    /// Includes the EHRs with deleted compositions.
+    pub fn ehr_ids_for(&self, template_id: &TemplateId) -> Vec<EhrId> {
+        sorted(
+            self.changes
+                .get(template_id)
+                .into_iter()
+                .flatten()
+                .chain(&self.deletions)
+                .collect::<HashSet<_>>()
+                .into_iter(),
+        )
+    }
+
+    /// EHRs changed for any template, sorted by ID
+    pub fn ehr_ids(&self) -> Vec<EhrId> {
+        sorted(
+            self.changes
+                .values()
+                .flatten()
+                .chain(&self.deletions)
+                .collect::<HashSet<_>>()
+                .into_iter(),
+        )
+    }
+
+    /// EHRs with deleted compositions, sorted by ID
+    pub fn deleted_ehr_ids(&self) -> Vec<EhrId> {
+        sorted(self.deletions.iter())
+    }
+
+    /// Number of composition versions found
+    pub fn versions(&self) -> usize {
+        self.versions
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.deletions.is_empty()
+    }
+}
+
+/// Find the EHRs with composition versions committed since a time
+///
+/// # Arguments
+///
+/// * `vendor` - openEHR vendor to query
+/// * `since` - Commit time to search from
+/// * `template_ids` - Templates being exported
+/// * `ehr_ids` - EHRs being exported (empty for all)
+/// * `include_deletions` - Also query the deleted compositions
+///
+/// # Errors
+///
+/// Returns an error if a query fails or returns unexpected rows.
+pub async fn detect_changes(
+    vendor: &dyn OpenEhrVendor,
+    since: DateTime<Utc>,
+    template_ids: &[TemplateId],
+    ehr_ids: &[EhrId],
+    include_deletions: bool,
+) -> Result<ChangeSet> {
+    tracing::info!(since = %since, "Querying composition versions committed since last run");
+
+    let records: Vec<AqlRecord> = vendor
+        .query_records(AqlQuery::changed_compositions(since))
+        .try_collect()
+        .await?;
+
+    let mut change_set = ChangeSet::from_records(&records, template_ids, ehr_ids)?;
+
+    if include_deletions {
+        let deletions: Vec<AqlRecord> = vendor
+            .query_records(AqlQuery::deleted_compositions(since))
+            .try_collect()
+            .await?;
+
+        change_set = change_set.with_deletions(&deletions, ehr_ids)?;
+    }
+
+    tracing::info!(
+        versions = change_set.versions(),
+        ehr_count = change_set.ehr_ids().len(),
+        deleted_ehr_count = change_set.deleted_ehr_ids().len(),
+        "Detected changed EHRs"
+    );
+
+    Ok(change_set)
+}
+
+fn sorted<'a>(ehr_ids: impl Iterator<Item = &'a EhrId>) -> Vec<EhrId> {
+    let mut ehr_ids: Vec<EhrId> = ehr_ids.cloned().collect();
+    ehr_ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    ehr_ids
+}
+
+fn column<'a>(record: &'a AqlRecord, name: &str) -> Result<&'a str> {
+    record
+        .get(name)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| {
+            AtlasError::OpenEhr(OpenEhrError::InvalidResponse(format!(
+            "Change feed row has no '{name}' column; the server must support AQL column aliases"
+        )))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(ehr_id: &str, template_id: &str) -> AqlRecord {
+        json!({
+            "ehr_id": ehr_id,
+            "template_id": template_id,
+            "time_committed": "2025-03-01T09:30:00Z"
+        })
+        .as_object()
+        .cloned()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_change_set_from_records() {
+        let vital_signs = TemplateId::new("vital_signs.v1").unwrap();
+        let lab_results = TemplateId::new("lab_results.v1").unwrap();
+        let records = vec![
+            record("ehr-1", "vital_signs.v1"),
+            record("ehr-1", "vital_signs.v1"),
+            record("ehr-2", "vital_signs.v1"),
+            record("ehr-2", "lab_results.v1"),
+            record("ehr-3", "discharge_summary.v1"),
+        ];
+
+        let change_set =
+            ChangeSet::from_records(&records, &[vital_signs.clone(), lab_results.clone()], &[])
+                .unwrap();
+
+        assert_eq!(change_set.versions(), 4);
+        assert_eq!(change_set.ehr_ids_for(&vital_signs).len(), 2);
+        assert_eq!(
+            change_set.ehr_ids_for(&lab_results),
+            vec![EhrId::new("ehr-2").unwrap()]
+        );
+        assert_eq!(change_set.ehr_ids().len(), 2);
+
+        // Configured EHR IDs restrict the change set
+        let change_set = ChangeSet::from_records(
+            &records,
+            std::slice::from_ref(&vital_signs),
+            &[EhrId::new("ehr-2").unwrap()],
+        )
+        .unwrap();
+        assert_eq!(
+            change_set.ehr_ids_for(&vital_signs),
+            vec![EhrId::new("ehr-2").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_change_set_with_deletions() {
+        let vital_signs = TemplateId::new("vital_signs.v1").unwrap();
+        let lab_results = TemplateId::new("lab_results.v1").unwrap();
+        let deletion = |ehr_id: &str| {
+            json!({"ehr_id": ehr_id, "time_committed": "2025-03-02T10:00:00Z"})
+                .as_object()
+                .cloned()
+                .unwrap()
+        };
+
+        let change_set = ChangeSet::from_records(
+            &[record("ehr-1", "vital_signs.v1")],
+            &[vital_signs.clone(), lab_results.clone()],
+            &[],
+        )
+        .unwrap()
+        .with_deletions(&[deletion("ehr-2")], &[])
+        .unwrap();
+
+        // A deletion changes the EHR for every template
+        assert!(!change_set.is_empty());
+        assert_eq!(change_set.versions(), 2);
+        assert_eq!(
+            change_set.ehr_ids_for(&vital_signs),
+            vec![EhrId::new("ehr-1").unwrap(), EhrId::new("ehr-2").unwrap()]
+        );
+        assert_eq!(
+            change_set.ehr_ids_for(&lab_results),
+            vec![EhrId::new("ehr-2").unwrap()]
+        );
+        assert_eq!(
+            change_set.deleted_ehr_ids(),
+            vec![EhrId::new("ehr-2").unwrap()]
+        );
+
+        // Configured EHR IDs restrict the deletions
+        let change_set = ChangeSet::default()
+            .with_deletions(&[deletion("ehr-2")], &[EhrId::new("ehr-1").unwrap()])
+            .unwrap();
+        assert!(change_set.is_empty());
+    }
+
+    #[test]
+    fn test_change_set_requires_aliased_columns() {
+        let records = vec![json!({"/ehr_id/value": "ehr-1"})
+            .as_object()
+            .cloned()
+            .unwrap()];
+
+        let result =
+            ChangeSet::from_records(&records, &[TemplateId::new("vital_signs.v1").unwrap()], &[]);
+        assert!(result.is_err());
+    }
+}
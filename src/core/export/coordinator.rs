@@ -5,29 +5,39 @@
 
 use crate::adapters::cosmosdb::{CosmosDbAdapter, CosmosDbClient};
 use crate::adapters::database::create_database_and_state;
-use crate::adapters::database::traits::DatabaseClient;
+use crate::adapters::database::traits::{DatabaseClient, StateStorage};
+use crate::adapters::openehr::aql::{AqlQuery, AqlRecord};
+use crate::adapters::openehr::templates::{TemplateFilter, TemplateService};
+use crate::adapters::openehr::vendor::CompositionMetadata;
 use crate::adapters::openehr::OpenEhrClient;
-use crate::config::schema::DatabaseTarget;
+use crate::config::schema::{ChangeDetection, DatabaseTarget, DeletionPolicy};
 use crate::config::AtlasConfig;
 use crate::core::export::batch::{BatchConfig, BatchProcessor};
+use crate::core::export::changes::{self, ChangeSet, CHANGE_FEED_WATERMARK};
+use crate::core::export::query::QueryExporter;
 use crate::core::export::summary::{ExportError, ExportErrorType, ExportSummary};
-use crate::core::state::{StateManager, Watermark, WatermarkBuilder};
+use crate::core::state::{
+    DeadLetter, DeadLetterStage, QueryWatermark, StateManager, Watermark, WatermarkBuilder,
+};
 use crate::core::verification::Verifier;
+use crate::domain::composition::Composition;
 use crate::domain::ids::{EhrId, TemplateId};
-use crate::domain::Result;
+use crate::domain::{AtlasError, OpenEhrError, Result};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::sync::mpsc;
 use tokio::sync::watch;
 
 /// Export coordinator
 pub struct ExportCoordinator {
     config: AtlasConfig,
-    #[allow(dead_code)] // Will be used in future phases
     openehr_client: Arc<OpenEhrClient>,
     database_client: Arc<dyn DatabaseClient + Send + Sync>,
     state_manager: Arc<StateManager>,
-    #[allow(dead_code)] // Will be used in future phases
     batch_processor: Arc<BatchProcessor>,
     /// Cosmos DB client for verification (only available when using CosmosDB)
     cosmos_client: Option<Arc<CosmosDbClient>>,
@@ -49,6 +59,32 @@ impl ExportCoordinator {
         // Create database client and state storage using factory
         let (database_client, state_storage) = create_database_and_state(&config).await?;
 
+        Self::with_clients(
+            config,
+            openehr_client,
+            database_client,
+            state_storage,
+            shutdown_signal,
+        )
+        .await
+    }
+
+    /// Create a new export coordinator from existing clients
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Atlas configuration
+    /// * `openehr_client` - openEHR client
+    /// * `database_client` - Database client for the configured target
+    /// * `state_storage` - State storage for the configured target
+    /// * `shutdown_signal` - Receiver for shutdown signal (true = shutdown requested)
+    pub async fn with_clients(
+        config: AtlasConfig,
+        openehr_client: Arc<OpenEhrClient>,
+        database_client: Arc<dyn DatabaseClient + Send + Sync>,
+        state_storage: Arc<dyn StateStorage + Send + Sync>,
+        shutdown_signal: watch::Receiver<bool>,
+    ) -> Result<Self> {
         // Ensure database exists
         database_client.ensure_database_exists().await?;
 
@@ -64,7 +100,8 @@ impl ExportCoordinator {
             &config.export.export_composition_format,
             config.export.dry_run,
             config.anonymization.clone(),
-        )?;
+        )?
+        .with_max_retries(config.export.max_retries);
 
         // Create batch processor
         let batch_processor = Arc::new(BatchProcessor::new(
@@ -110,6 +147,10 @@ impl ExportCoordinator {
 
     /// Validate configuration and parse template IDs
     ///
+    /// If no template IDs are configured, the templates on the openEHR server
+    /// are discovered and filtered with `query.template_include` and
+    /// `query.template_exclude`.
+    ///
     /// # Arguments
     ///
     /// * `summary` - Export summary to update with errors if validation fails
@@ -117,8 +158,9 @@ impl ExportCoordinator {
     /// # Returns
     ///
     /// Returns `Ok(Some(template_ids))` if validation succeeds and template IDs are valid,
-    /// `Ok(None)` if validation fails (error added to summary), or `Err` for unexpected errors
-    fn validate_and_prepare_export(
+    /// `Ok(None)` if validation or discovery fails (error added to summary), or `Err` for
+    /// unexpected errors
+    async fn validate_and_prepare_export(
         &self,
         summary: &mut ExportSummary,
     ) -> Result<Option<Vec<TemplateId>>> {
@@ -130,14 +172,27 @@ impl ExportCoordinator {
         }
 
         // Get template IDs to process
-        let template_ids: Vec<TemplateId> = self
-            .config
-            .openehr
-            .query
-            .template_ids
-            .iter()
-            .filter_map(|id| TemplateId::from_str(id).ok())
-            .collect();
+        let template_ids: Vec<TemplateId> = if self.config.openehr.query.template_ids.is_empty() {
+            match self.discover_templates().await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    tracing::error!(error = %e, "Template discovery failed");
+                    summary.add_error(ExportError::new(
+                        ExportErrorType::Query,
+                        format!("Failed to discover templates: {e}"),
+                    ));
+                    return Ok(None);
+                }
+            }
+        } else {
+            self.config
+                .openehr
+                .query
+                .template_ids
+                .iter()
+                .filter_map(|id| TemplateId::from_str(id).ok())
+                .collect()
+        };
 
         if template_ids.is_empty() {
             tracing::warn!("No valid template IDs to process");
@@ -147,12 +202,102 @@ impl ExportCoordinator {
         Ok(Some(template_ids))
     }
 
+    /// List the templates on the openEHR server selected by the configured patterns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template list cannot be retrieved.
+    async fn discover_templates(&self) -> Result<Vec<TemplateId>> {
+        let query = &self.config.openehr.query;
+        let filter = TemplateFilter::new(&query.template_include, &query.template_exclude)?;
+
+        let available = self.openehr_client.vendor().list_templates().await?;
+        let available_count = available.len();
+        let selected = filter.apply(available);
+
+        tracing::info!(
+            available = available_count,
+            selected = selected.len(),
+            template_ids = ?selected.iter().map(TemplateId::as_str).collect::<Vec<_>>(),
+            "Discovered templates on openEHR server"
+        );
+
+        Ok(selected)
+    }
+
+    /// Download and cache the definitions of the templates to export
+    ///
+    /// # Arguments
+    ///
+    /// * `template_ids` - List of template IDs to export
+    /// * `summary` - Export summary to update with errors
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if a template does not exist on the openEHR server (error
+    /// added to summary). Other download failures are logged and do not stop
+    /// the export.
+    async fn load_templates(
+        &self,
+        template_ids: &[TemplateId],
+        summary: &mut ExportSummary,
+    ) -> bool {
+        let config = &self.config.openehr.templates;
+        if !config.enabled {
+            return true;
+        }
+
+        let service = TemplateService::new(self.openehr_client.vendor().clone(), config);
+
+        for template_id in template_ids {
+            match service.load(template_id).await {
+                Ok(definition) => {
+                    tracing::info!(
+                        template_id = %template_id.as_str(),
+                        format = %definition.format,
+                        hash = %definition.hash,
+                        nodes = definition.nodes().map(|nodes| nodes.len()).unwrap_or_default(),
+                        "Loaded template definition"
+                    );
+                }
+                Err(AtlasError::OpenEhr(OpenEhrError::TemplateNotFound(_))) => {
+                    tracing::error!(
+                        template_id = %template_id.as_str(),
+                        "Template does not exist on the openEHR server"
+                    );
+                    summary.add_error(
+                        ExportError::new(
+                            ExportErrorType::Configuration,
+                            format!(
+                                "Template '{}' does not exist on the openEHR server",
+                                template_id.as_str()
+                            ),
+                        )
+                        .with_context(format!("template_id={}", template_id.as_str())),
+                    );
+                    return false;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        template_id = %template_id.as_str(),
+                        error = %e,
+                        "Failed to load template definition, continuing without it"
+                    );
+                }
+            }
+        }
+
+        true
+    }
+
     /// Process all templates for all EHRs
     ///
     /// # Arguments
     ///
     /// * `template_ids` - List of template IDs to process
     /// * `ehr_ids` - List of EHR IDs to process
+    /// * `change_set` - Changed EHRs per template, limiting each template to
+    ///   its own changed EHRs
     /// * `summary` - Export summary to update with results
     ///
     /// # Returns
@@ -163,6 +308,7 @@ impl ExportCoordinator {
         &self,
         template_ids: &[TemplateId],
         ehr_ids: &[EhrId],
+        change_set: Option<&ChangeSet>,
         summary: &mut ExportSummary,
     ) -> Result<bool> {
         for template_id in template_ids {
@@ -200,6 +346,22 @@ impl ExportCoordinator {
                 continue;
             }
 
+            let changed_ehr_ids;
+            let ehr_ids = match change_set {
+                Some(change_set) => {
+                    changed_ehr_ids = change_set.ehr_ids_for(template_id);
+                    if changed_ehr_ids.is_empty() {
+                        tracing::info!(
+                            template_id = %template_id.as_str(),
+                            "No changed EHRs for template"
+                        );
+                        continue;
+                    }
+                    &changed_ehr_ids
+                }
+                None => ehr_ids,
+            };
+
             // Process each EHR for this template
             if !self
                 .process_ehrs_for_template(template_id, ehr_ids, summary)
@@ -212,69 +374,273 @@ impl ExportCoordinator {
         Ok(true)
     }
 
-    /// Process all EHRs for a single template
+    /// Export the metadata and EHR_STATUS of each EHR
+    ///
+    /// Up to `openehr.query.parallel_ehrs` EHRs are fetched and stored
+    /// concurrently. Failures are recorded in the summary and do not stop the
+    /// composition export.
     ///
     /// # Arguments
     ///
-    /// * `template_id` - Template ID to process
-    /// * `ehr_ids` - List of EHR IDs to process
+    /// * `ehr_ids` - List of EHR IDs to export
     /// * `summary` - Export summary to update with results
     ///
     /// # Returns
     ///
-    /// Returns `Ok(true)` if processing completed normally,
-    /// `Ok(false)` if shutdown was requested, or `Err` for unexpected errors
-    async fn process_ehrs_for_template(
-        &self,
-        template_id: &TemplateId,
-        ehr_ids: &[EhrId],
-        summary: &mut ExportSummary,
-    ) -> Result<bool> {
-        for ehr_id in ehr_ids {
-            // Check for shutdown signal before processing each EHR
+    /// Returns `false` if shutdown was requested, `true` otherwise
+    async fn export_ehr_statuses(&self, ehr_ids: &[EhrId], summary: &mut ExportSummary) -> bool {
+        if let Err(e) = self.database_client.ensure_ehr_container_exists().await {
+            tracing::error!(error = %e, "Failed to create EHR container");
+            summary.add_error(ExportError::new(
+                ExportErrorType::Storage,
+                format!("Failed to create EHR container: {e}"),
+            ));
+            return true;
+        }
+
+        let parallel_ehrs = self.config.openehr.query.parallel_ehrs.max(1);
+        let dry_run = self.config.export.dry_run;
+        let vendor = self.openehr_client.vendor();
+
+        let mut results = stream::iter(ehr_ids)
+            .map(|ehr_id| async move {
+                if self.is_shutdown_requested() {
+                    return None;
+                }
+
+                let result = match vendor.get_ehr(ehr_id).await {
+                    Ok(ehr) => self
+                        .database_client
+                        .upsert_ehr(&ehr, dry_run)
+                        .await
+                        .map_err(|e| (ExportErrorType::Storage, e)),
+                    Err(e) => Err((ExportErrorType::Query, e)),
+                };
+                Some((ehr_id, result))
+            })
+            .buffer_unordered(parallel_ehrs);
+
+        let mut interrupted = false;
+        while let Some(result) = results.next().await {
+            match result {
+                Some((_, Ok(()))) => summary.ehr_statuses_exported += 1,
+                Some((ehr_id, Err((error_type, e)))) => {
+                    tracing::warn!(
+                        ehr_id = %ehr_id.as_str(),
+                        error = %e,
+                        "Failed to export EHR status"
+                    );
+                    summary.add_error(
+                        ExportError::new(error_type, format!("Failed to export EHR status: {e}"))
+                            .with_context(format!("ehr_id={}", ehr_id.as_str())),
+                    );
+                }
+                None => interrupted = true,
+            }
+        }
+
+        tracing::info!(
+            exported = summary.ehr_statuses_exported,
+            ehr_count = ehr_ids.len(),
+            "Exported EHR statuses"
+        );
+
+        if interrupted {
+            summary.interrupted = true;
+            summary.shutdown_reason = Some("User signal (SIGTERM/SIGINT)".to_string());
+            return false;
+        }
+
+        true
+    }
+
+    /// Export the result sets of the configured AQL queries
+    ///
+    /// Queries run one after another; a failing query is recorded in the
+    /// summary and does not stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Export summary to update with results
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if shutdown was requested, `true` otherwise
+    async fn export_aql_queries(&self, summary: &mut ExportSummary) -> bool {
+        let exporter = QueryExporter::new(
+            self.openehr_client.vendor().as_ref(),
+            self.database_client.as_ref(),
+            &self.state_manager,
+            &self.shutdown_signal,
+        )
+        .with_batch_size(self.config.openehr.query.batch_size)
+        .with_incremental(self.config.export.mode == "incremental")
+        .with_dry_run(self.config.export.dry_run);
+
+        for query in &self.config.export.aql_queries {
             if self.is_shutdown_requested() {
-                tracing::info!("Shutdown signal received, stopping export");
                 summary.interrupted = true;
                 summary.shutdown_reason = Some("User signal (SIGTERM/SIGINT)".to_string());
-                return Ok(false);
+                return false;
             }
 
-            match self
-                .process_ehr_for_template(template_id, ehr_id, summary)
-                .await
-            {
-                Ok(_) => {
-                    tracing::debug!(
-                        template_id = %template_id.as_str(),
-                        ehr_id = %ehr_id.as_str(),
-                        "Completed processing EHR"
-                    );
+            match exporter.export(query).await {
+                Ok(result) => {
+                    summary.query_rows_exported += result.rows_exported;
+
+                    for failure in result.failures {
+                        summary.add_error(
+                            ExportError::new(
+                                ExportErrorType::Storage,
+                                format!("Failed to store query row: {}", failure.error),
+                            )
+                            .with_context(format!(
+                                "query={}, row_id={}",
+                                query.name, failure.document_id
+                            )),
+                        );
+                    }
+
+                    if result.interrupted {
+                        summary.interrupted = true;
+                        summary.shutdown_reason = Some("User signal (SIGTERM/SIGINT)".to_string());
+                        return false;
+                    }
                 }
                 Err(e) => {
-                    tracing::error!(
-                        template_id = %template_id.as_str(),
-                        ehr_id = %ehr_id.as_str(),
-                        error = %e,
-                        "Failed to process EHR"
-                    );
+                    tracing::error!(query = %query.name, error = %e, "Failed to export AQL query");
                     summary.add_error(
                         ExportError::new(
-                            ExportErrorType::Unknown,
-                            format!("Failed to process EHR: {e}"),
+                            ExportErrorType::Query,
+                            format!("Failed to export AQL query: {e}"),
                         )
-                        .with_context(format!(
-                            "template_id={}, ehr_id={}",
-                            template_id.as_str(),
-                            ehr_id.as_str()
-                        )),
+                        .with_context(format!("query={}", query.name)),
                     );
                 }
             }
         }
 
+        true
+    }
+
+    /// Process all EHRs for a single template
+    ///
+    /// EHRs are processed by a bounded pipeline that runs up to
+    /// `openehr.query.parallel_ehrs` EHRs concurrently. Each EHR is an
+    /// independent unit of work with its own summary, which is merged into
+    /// `summary` as the unit completes. The shutdown signal is checked before
+    /// each unit starts; units already in flight are allowed to finish so their
+    /// watermarks are saved consistently.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID to process
+    /// * `ehr_ids` - List of EHR IDs to process
+    /// * `summary` - Export summary to update with results
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if processing completed normally,
+    /// `Ok(false)` if shutdown was requested, or `Err` for unexpected errors
+    async fn process_ehrs_for_template(
+        &self,
+        template_id: &TemplateId,
+        ehr_ids: &[EhrId],
+        summary: &mut ExportSummary,
+    ) -> Result<bool> {
+        let parallel_ehrs = self.config.openehr.query.parallel_ehrs.max(1);
+
+        tracing::debug!(
+            template_id = %template_id.as_str(),
+            ehr_count = ehr_ids.len(),
+            parallel_ehrs = parallel_ehrs,
+            "Processing EHRs for template"
+        );
+
+        let mut units = stream::iter(ehr_ids)
+            .map(|ehr_id| self.process_ehr_unit(template_id, ehr_id))
+            .buffer_unordered(parallel_ehrs);
+
+        let mut skipped_ehrs = 0;
+        while let Some(unit_summary) = units.next().await {
+            match unit_summary {
+                Some(unit_summary) => summary.merge(unit_summary),
+                None => skipped_ehrs += 1,
+            }
+        }
+
+        if skipped_ehrs > 0 {
+            tracing::info!(
+                template_id = %template_id.as_str(),
+                skipped_ehrs = skipped_ehrs,
+                "Shutdown signal received, stopping export"
+            );
+            summary.interrupted = true;
+            summary.shutdown_reason = Some("User signal (SIGTERM/SIGINT)".to_string());
+            return Ok(false);
+        }
+
         Ok(true)
     }
 
+    /// Process a single EHR as an independent unit of work
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID to process
+    /// * `ehr_id` - EHR ID to process
+    ///
+    /// # Returns
+    ///
+    /// Returns the summary for this EHR, or `None` if shutdown was requested
+    /// before the unit started
+    async fn process_ehr_unit(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+    ) -> Option<ExportSummary> {
+        // Check for shutdown signal before starting each EHR
+        if self.is_shutdown_requested() {
+            return None;
+        }
+
+        let mut unit_summary = ExportSummary::new();
+
+        match self
+            .process_ehr_for_template(template_id, ehr_id, &mut unit_summary)
+            .await
+        {
+            Ok(_) => {
+                tracing::debug!(
+                    template_id = %template_id.as_str(),
+                    ehr_id = %ehr_id.as_str(),
+                    "Completed processing EHR"
+                );
+            }
+            Err(e) => {
+                tracing::error!(
+                    template_id = %template_id.as_str(),
+                    ehr_id = %ehr_id.as_str(),
+                    error = %e,
+                    "Failed to process EHR"
+                );
+                unit_summary.add_error(
+                    ExportError::new(
+                        ExportErrorType::Unknown,
+                        format!("Failed to process EHR: {e}"),
+                    )
+                    .with_context(format!(
+                        "template_id={}, ehr_id={}",
+                        template_id.as_str(),
+                        ehr_id.as_str()
+                    )),
+                );
+            }
+        }
+
+        Some(unit_summary)
+    }
+
     /// Run post-export verification if enabled
     ///
     /// # Arguments
@@ -358,13 +724,24 @@ impl ExportCoordinator {
         tracing::info!("Starting export process");
 
         // Validate configuration and get template IDs
-        let template_ids = match self.validate_and_prepare_export(&mut summary)? {
+        let template_ids = match self.validate_and_prepare_export(&mut summary).await? {
             Some(ids) => ids,
             None => return Ok(summary.with_duration(start_time.elapsed())),
         };
 
-        // Get EHR IDs to process
-        let ehr_ids = self.get_ehr_ids_to_process().await?;
+        // Download template definitions, failing fast on unknown templates
+        if !self.load_templates(&template_ids, &mut summary).await {
+            return Ok(summary.with_duration(start_time.elapsed()));
+        }
+
+        // Get EHR IDs to process, restricted to the changed EHRs when the
+        // contribution change feed is used
+        let change_feed_started = Utc::now();
+        let change_set = self.detect_changes(&template_ids).await?;
+        let ehr_ids = match change_set {
+            Some(ref change_set) => change_set.ehr_ids(),
+            None => self.get_ehr_ids_to_process().await?,
+        };
         summary.total_ehrs = ehr_ids.len();
 
         tracing::info!(
@@ -373,11 +750,33 @@ impl ExportCoordinator {
             "Processing templates and EHRs"
         );
 
+        // Export EHR metadata and EHR_STATUS
+        if self.config.export.include_ehr_status
+            && !self.export_ehr_statuses(&ehr_ids, &mut summary).await
+        {
+            return Ok(summary.with_duration(start_time.elapsed()));
+        }
+
         // Process all templates
-        if !self
-            .process_templates(&template_ids, &ehr_ids, &mut summary)
-            .await?
+        let errors_before = summary.errors.len();
+        let completed = self
+            .process_templates(&template_ids, &ehr_ids, change_set.as_ref(), &mut summary)
+            .await?;
+        self.flush_database(&mut summary).await;
+        if !completed {
+            return Ok(summary.with_duration(start_time.elapsed()));
+        }
+
+        // Advance the change feed only when every composition was exported,
+        // so failed EHRs are detected again by the next run
+        if self.config.export.change_detection == ChangeDetection::Contribution
+            && summary.errors.len() == errors_before
         {
+            self.save_change_feed_watermark(change_feed_started).await?;
+        }
+
+        // Export the result sets of the named AQL queries
+        if !self.export_aql_queries(&mut summary).await {
             return Ok(summary.with_duration(start_time.elapsed()));
         }
 
@@ -391,6 +790,167 @@ impl ExportCoordinator {
         Ok(summary)
     }
 
+    /// Reprocess the compositions recorded as dead letters
+    ///
+    /// Each dead letter is fetched from openEHR again and stored without
+    /// moving the watermark. Dead letters that succeed are deleted, those that
+    /// fail again have their attempt count incremented. When
+    /// `openehr.query.template_ids` or `ehr_ids` is set, only the dead letters
+    /// of those templates and EHRs are retried.
+    pub async fn retry_failed(&self) -> Result<ExportSummary> {
+        let start_time = Instant::now();
+        let mut summary = ExportSummary::new();
+        summary.dry_run = self.config.export.dry_run;
+
+        let query = &self.config.openehr.query;
+        let dead_letters: Vec<DeadLetter> = self
+            .state_manager
+            .list_dead_letters()
+            .await?
+            .into_iter()
+            .filter(|dead_letter| {
+                let template_selected = query.template_ids.is_empty()
+                    || query
+                        .template_ids
+                        .contains(&dead_letter.template_id.to_string());
+                let ehr_selected = query.ehr_ids.is_empty()
+                    || query.ehr_ids.contains(&dead_letter.ehr_id.to_string());
+                template_selected && ehr_selected
+            })
+            .collect();
+
+        tracing::info!(count = dead_letters.len(), "Retrying failed compositions");
+
+        let mut containers = HashSet::new();
+        let mut ehr_ids = HashSet::new();
+        for dead_letter in &dead_letters {
+            if self.is_shutdown_requested() {
+                tracing::info!("Shutdown signal received, stopping retry");
+                summary.interrupted = true;
+                summary.shutdown_reason = Some("User signal (SIGTERM/SIGINT)".to_string());
+                break;
+            }
+
+            if containers.insert(dead_letter.template_id.clone()) {
+                self.database_client
+                    .ensure_container_exists(&dead_letter.template_id)
+                    .await?;
+            }
+            ehr_ids.insert(dead_letter.ehr_id.clone());
+
+            self.retry_dead_letter(dead_letter, &mut summary).await;
+        }
+        self.flush_database(&mut summary).await;
+
+        summary.total_ehrs = ehr_ids.len();
+        let summary = summary.with_duration(start_time.elapsed());
+        summary.log_summary();
+
+        Ok(summary)
+    }
+
+    /// Write out the documents buffered by the database client
+    ///
+    /// A failure is recorded in the summary, which also keeps the change feed
+    /// from advancing.
+    async fn flush_database(&self, summary: &mut ExportSummary) {
+        if let Err(e) = self.database_client.flush().await {
+            tracing::error!(error = %e, "Failed to write buffered documents");
+            summary.add_error(ExportError::new(
+                ExportErrorType::Storage,
+                format!("Failed to write buffered documents: {e}"),
+            ));
+        }
+    }
+
+    /// Fetch and store the composition of a dead letter again
+    ///
+    /// A dead letter of a version history is retried as a whole and keeps its
+    /// stage until every version has been exported.
+    async fn retry_dead_letter(&self, dead_letter: &DeadLetter, summary: &mut ExportSummary) {
+        let metadata = dead_letter.metadata();
+        let vendor = self.openehr_client.vendor();
+
+        tracing::debug!(
+            composition_uid = %dead_letter.composition_uid,
+            stage = %dead_letter.stage,
+            attempt_count = dead_letter.attempt_count,
+            "Retrying dead letter"
+        );
+
+        let result = async {
+            let versions = if dead_letter.stage == DeadLetterStage::VersionHistory {
+                vendor
+                    .get_composition_versions(&metadata)
+                    .await
+                    .map_err(|e| (DeadLetterStage::VersionHistory, e.to_string()))?
+            } else {
+                vec![metadata.clone()]
+            };
+
+            let mut compositions = Vec::with_capacity(versions.len());
+            for version in &versions {
+                compositions.push(
+                    vendor
+                        .fetch_composition(version)
+                        .await
+                        .map_err(|e| (DeadLetterStage::Fetch, e.to_string()))?,
+                );
+            }
+
+            let stored = self
+                .batch_processor
+                .store_batch(&compositions, &metadata.template_id, &metadata.ehr_id)
+                .await
+                .map_err(|e| (DeadLetterStage::Insert, e.to_string()))?;
+
+            match stored.failures.into_iter().next() {
+                Some(failure) => Err((DeadLetterStage::Insert, failure.error)),
+                None => Ok(compositions.len()),
+            }
+        }
+        .await;
+
+        match result {
+            Ok(count) => {
+                summary.total_compositions += count;
+                summary.successful_exports += count;
+
+                if let Err(e) = self
+                    .state_manager
+                    .delete_dead_letter(dead_letter, self.config.export.dry_run)
+                    .await
+                {
+                    summary.add_error(
+                        ExportError::new(
+                            ExportErrorType::State,
+                            format!("Failed to delete dead letter: {e}"),
+                        )
+                        .with_context(format!("composition_uid={}", metadata.uid.as_str())),
+                    );
+                }
+            }
+            Err((stage, error)) => {
+                let stage = if dead_letter.stage == DeadLetterStage::VersionHistory {
+                    DeadLetterStage::VersionHistory
+                } else {
+                    stage
+                };
+
+                tracing::warn!(
+                    composition_uid = %metadata.uid,
+                    stage = %stage,
+                    error = %error,
+                    "Retry of failed composition failed"
+                );
+
+                summary.total_compositions += 1;
+                summary.failed_exports += 1;
+                summary.add_error(self.record_dead_letter(&metadata, stage, &error).await);
+            }
+        }
+    }
+
     /// Load or create watermark for a template and EHR
     ///
     /// # Arguments
@@ -400,12 +960,13 @@ impl ExportCoordinator {
     ///
     /// # Returns
     ///
-    /// Returns the watermark (either loaded from state or newly created)
+    /// Returns the watermark (either loaded from state or newly created) and
+    /// whether it was loaded
     async fn load_or_create_watermark(
         &self,
         template_id: &TemplateId,
         ehr_id: &EhrId,
-    ) -> Result<Watermark> {
+    ) -> Result<(Watermark, bool)> {
         let watermark = match self
             .state_manager
             .load_watermark(template_id, ehr_id)
@@ -418,7 +979,7 @@ impl ExportCoordinator {
                     last_exported = %wm.last_exported_timestamp,
                     "Loaded existing watermark - incremental export"
                 );
-                wm
+                (wm, true)
             }
             None => {
                 tracing::info!(
@@ -426,14 +987,24 @@ impl ExportCoordinator {
                     ehr_id = %ehr_id.as_str(),
                     "No watermark found - full export"
                 );
-                WatermarkBuilder::new(template_id.clone(), ehr_id.clone()).build()
+                (
+                    WatermarkBuilder::new(template_id.clone(), ehr_id.clone()).build(),
+                    false,
+                )
             }
         };
 
         Ok(watermark)
     }
 
-    /// Fetch compositions for an EHR and template
+    /// Fetch composition metadata for an EHR and template
+    ///
+    /// The query is bounded by `openehr.query.time_range_start` and
+    /// `time_range_end` on the version commit time. In incremental mode the
+    /// lower bound is the later of `time_range_start` and the watermark minus
+    /// `export.incremental_overlap_secs`. The returned metadata is sorted
+    /// by commit time so that compositions are exported, and the watermark
+    /// advanced, in chronological order.
     ///
     /// # Arguments
     ///
@@ -443,27 +1014,40 @@ impl ExportCoordinator {
     ///
     /// # Returns
     ///
-    /// Returns a vector of compositions
-    async fn fetch_compositions_for_ehr(
+    /// Returns a vector of composition metadata
+    async fn fetch_composition_metadata_for_ehr(
         &self,
         template_id: &TemplateId,
         ehr_id: &EhrId,
         watermark: &Watermark,
-    ) -> Result<Vec<crate::domain::Composition>> {
+    ) -> Result<Vec<CompositionMetadata>> {
+        let query = &self.config.openehr.query;
+        let time_range_start = query
+            .time_range_start()
+            .map_err(crate::domain::AtlasError::Configuration)?;
+        let until = query
+            .time_range_end()
+            .map_err(crate::domain::AtlasError::Configuration)?;
+
         // Determine the timestamp to query from (for incremental exports)
         let since = if self.config.export.mode == "incremental" {
-            Some(watermark.last_exported_timestamp)
+            let overlap =
+                chrono::Duration::seconds(self.config.export.incremental_overlap_secs as i64);
+            let watermark_since = watermark.last_exported_timestamp - overlap;
+            Some(time_range_start.map_or(watermark_since, |start| start.max(watermark_since)))
         } else {
-            None
+            time_range_start
         };
 
         // Fetch composition metadata from openEHR
-        let compositions_metadata = self
+        let mut compositions_metadata = self
             .openehr_client
             .vendor()
-            .get_compositions_for_ehr(ehr_id, template_id, since)
+            .get_compositions_for_ehr(ehr_id, template_id, since, until)
             .await?;
 
+        compositions_metadata.sort_by_key(|metadata| metadata.time_committed);
+
         tracing::info!(
             template_id = %template_id.as_str(),
             ehr_id = %ehr_id.as_str(),
@@ -471,27 +1055,148 @@ impl ExportCoordinator {
             "Found compositions for EHR"
         );
 
-        // Fetch full composition data
-        let mut compositions = Vec::new();
+        Ok(compositions_metadata)
+    }
+
+    /// Fetch full compositions and send them downstream in `batch_size` chunks
+    ///
+    /// With `export.include_versions`, every version in the revision history of
+    /// each composition is fetched, oldest first, so that the latest version of
+    /// a composition is sent after all of its earlier versions.
+    ///
+    /// Compositions that fail to fetch are recorded as dead letters and
+    /// skipped. Fetching stops early if the receiving side of the channel has
+    /// been dropped (e.g., after a write failure).
+    ///
+    /// # Arguments
+    ///
+    /// * `compositions_metadata` - Metadata of the compositions to fetch
+    /// * `sender` - Channel to send composition chunks to
+    ///
+    /// # Returns
+    ///
+    /// Returns an error for each composition that could not be fetched
+    async fn fetch_composition_chunks(
+        &self,
+        compositions_metadata: Vec<CompositionMetadata>,
+        sender: mpsc::Sender<Vec<Composition>>,
+    ) -> Vec<ExportError> {
+        let batch_size = self.config.openehr.query.batch_size.max(1);
+        let mut chunk = Vec::with_capacity(batch_size);
+        let mut errors = Vec::new();
+
         for metadata in compositions_metadata {
-            match self
-                .openehr_client
-                .vendor()
-                .fetch_composition(&metadata)
-                .await
-            {
-                Ok(composition) => compositions.push(composition),
-                Err(e) => {
-                    tracing::warn!(
-                        composition_uid = %metadata.uid,
-                        error = %e,
-                        "Failed to fetch composition, skipping"
-                    );
+            let versions = if self.config.export.include_versions {
+                match self
+                    .openehr_client
+                    .vendor()
+                    .get_composition_versions(&metadata)
+                    .await
+                {
+                    Ok(versions) => versions,
+                    Err(e) => {
+                        tracing::warn!(
+                            composition_uid = %metadata.uid,
+                            error = %e,
+                            "Failed to fetch composition version history, skipping"
+                        );
+                        errors.push(
+                            self.record_dead_letter(
+                                &metadata,
+                                DeadLetterStage::VersionHistory,
+                                &e.to_string(),
+                            )
+                            .await,
+                        );
+                        continue;
+                    }
+                }
+            } else {
+                vec![metadata]
+            };
+
+            for metadata in versions {
+                match self
+                    .openehr_client
+                    .vendor()
+                    .fetch_composition(&metadata)
+                    .await
+                {
+                    Ok(composition) => chunk.push(composition),
+                    Err(e) => {
+                        tracing::warn!(
+                            composition_uid = %metadata.uid,
+                            error = %e,
+                            "Failed to fetch composition, skipping"
+                        );
+                        errors.push(
+                            self.record_dead_letter(
+                                &metadata,
+                                DeadLetterStage::Fetch,
+                                &e.to_string(),
+                            )
+                            .await,
+                        );
+                    }
+                }
+
+                if chunk.len() >= batch_size {
+                    let full_chunk = std::mem::replace(&mut chunk, Vec::with_capacity(batch_size));
+                    if sender.send(full_chunk).await.is_err() {
+                        return errors;
+                    }
                 }
             }
         }
 
-        Ok(compositions)
+        if !chunk.is_empty() {
+            let _ = sender.send(chunk).await;
+        }
+
+        errors
+    }
+
+    /// Record a composition that failed to export as a dead letter
+    ///
+    /// A failure to record the dead letter is logged and does not stop the
+    /// export.
+    ///
+    /// # Returns
+    ///
+    /// Returns the error to add to the export summary
+    async fn record_dead_letter(
+        &self,
+        metadata: &CompositionMetadata,
+        stage: DeadLetterStage,
+        error: &str,
+    ) -> ExportError {
+        if let Err(e) = self
+            .state_manager
+            .record_dead_letter(metadata, stage, error, self.config.export.dry_run)
+            .await
+        {
+            tracing::error!(
+                composition_uid = %metadata.uid,
+                error = %e,
+                "Failed to record dead letter"
+            );
+        }
+
+        let error_type = match stage {
+            DeadLetterStage::VersionHistory | DeadLetterStage::Fetch => ExportErrorType::Query,
+            DeadLetterStage::Insert => ExportErrorType::Storage,
+        };
+
+        ExportError::new(
+            error_type,
+            format!("Failed to export composition ({stage}): {error}"),
+        )
+        .with_context(format!(
+            "template_id={}, ehr_id={}, composition_uid={}",
+            metadata.template_id.as_str(),
+            metadata.ehr_id.as_str(),
+            metadata.uid.as_str()
+        ))
     }
 
     /// Process compositions and update summary
@@ -505,7 +1210,7 @@ impl ExportCoordinator {
     /// * `summary` - Export summary to update
     async fn process_and_update_summary(
         &self,
-        compositions: Vec<crate::domain::Composition>,
+        compositions: Vec<Composition>,
         template_id: &TemplateId,
         ehr_id: &EhrId,
         watermark: &mut Watermark,
@@ -517,7 +1222,7 @@ impl ExportCoordinator {
 
         let batch_result = self
             .batch_processor
-            .process_batch(compositions.clone(), template_id, ehr_id, watermark)
+            .process_batch(compositions, template_id, ehr_id, watermark)
             .await?;
 
         // Update summary with batch results
@@ -526,6 +1231,18 @@ impl ExportCoordinator {
         summary.failed_exports += batch_result.failed;
         summary.duplicates_skipped += batch_result.duplicates_skipped;
 
+        // Record compositions that could not be stored as dead letters
+        for failure in &batch_result.failures {
+            if let Some(metadata) = batch_result
+                .compositions
+                .iter()
+                .find(|metadata| metadata.uid.as_str() == failure.document_id)
+            {
+                self.record_dead_letter(metadata, DeadLetterStage::Insert, &failure.error)
+                    .await;
+            }
+        }
+
         // Add batch errors to summary
         for error_msg in batch_result.errors {
             summary.add_error(
@@ -538,12 +1255,8 @@ impl ExportCoordinator {
         }
 
         // Add compositions to summary for verification
-        for composition in &compositions {
-            summary.add_exported_composition(
-                composition.uid.clone(),
-                ehr_id.clone(),
-                template_id.clone(),
-            );
+        for metadata in batch_result.compositions {
+            summary.add_exported_composition(metadata.uid, ehr_id.clone(), template_id.clone());
         }
 
         Ok(())
@@ -576,6 +1289,70 @@ impl ExportCoordinator {
         Ok(ehr_ids)
     }
 
+    /// Detect the EHRs changed since the last run from the change feed
+    ///
+    /// Returns `None` when every EHR has to be scanned: with per-EHR change
+    /// detection, in full mode, or on the first run in contribution mode. The
+    /// feed is queried from the saved commit time minus
+    /// `export.incremental_overlap_secs`.
+    async fn detect_changes(&self, template_ids: &[TemplateId]) -> Result<Option<ChangeSet>> {
+        if self.config.export.change_detection != ChangeDetection::Contribution
+            || self.config.export.mode != "incremental"
+        {
+            return Ok(None);
+        }
+
+        let since = self
+            .state_manager
+            .load_query_watermark(CHANGE_FEED_WATERMARK)
+            .await?
+            .and_then(|watermark| watermark.value)
+            .and_then(|value| value.as_str().map(DateTime::parse_from_rfc3339))
+            .and_then(|since| since.ok());
+
+        let Some(since) = since else {
+            tracing::info!("No change feed watermark found - scanning all EHRs");
+            return Ok(None);
+        };
+
+        let overlap = chrono::Duration::seconds(self.config.export.incremental_overlap_secs as i64);
+        let ehr_ids: Vec<EhrId> = self
+            .config
+            .openehr
+            .query
+            .ehr_ids
+            .iter()
+            .filter_map(|id| EhrId::from_str(id).ok())
+            .collect();
+
+        changes::detect_changes(
+            self.openehr_client.vendor().as_ref(),
+            since.with_timezone(&Utc) - overlap,
+            template_ids,
+            &ehr_ids,
+            self.config.export.deletion_policy != DeletionPolicy::Ignore,
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Save the start time of this run as the change feed watermark
+    async fn save_change_feed_watermark(&self, started: DateTime<Utc>) -> Result<()> {
+        let mut watermark = self
+            .state_manager
+            .load_query_watermark(CHANGE_FEED_WATERMARK)
+            .await?
+            .unwrap_or_else(|| QueryWatermark::new(CHANGE_FEED_WATERMARK));
+
+        watermark.value = Some(serde_json::Value::String(started.to_rfc3339()));
+        watermark.last_export_started_at = started;
+        watermark.mark_completed();
+
+        self.state_manager
+            .save_query_watermark(&watermark, self.config.export.dry_run)
+            .await
+    }
+
     /// Process a single EHR for a template
     ///
     /// # Arguments
@@ -592,65 +1369,277 @@ impl ExportCoordinator {
         tracing::debug!(
             template_id = %template_id.as_str(),
             ehr_id = %ehr_id.as_str(),
-            "Processing EHR for template"
+            "Processing EHR for template"
+        );
+
+        // Load or create watermark
+        let (mut watermark, loaded) = self.load_or_create_watermark(template_id, ehr_id).await?;
+
+        // Deletions are looked up from the previous export of the pair on;
+        // a pair exported for the first time has nothing stored to delete
+        let deletion_scan = if !loaded {
+            DeletionScan::Skip
+        } else if self.config.export.mode == "incremental" {
+            let overlap =
+                chrono::Duration::seconds(self.config.export.incremental_overlap_secs as i64);
+            DeletionScan::Since(watermark.last_exported_timestamp - overlap)
+        } else {
+            DeletionScan::Full
+        };
+
+        // Mark export as started
+        watermark.mark_started();
+        self.state_manager
+            .save_watermark(&watermark, self.config.export.dry_run)
+            .await?;
+
+        // Fetch composition metadata for this EHR and template
+        let compositions_metadata = self
+            .fetch_composition_metadata_for_ehr(template_id, ehr_id, &watermark)
+            .await?;
+
+        // Stream compositions through fetch -> transform -> insert in
+        // `batch_size` chunks. The channel holds at most one pending chunk, so
+        // the next chunk is fetched while the current one is being written,
+        // and memory use stays bounded regardless of the number of compositions.
+        let (sender, receiver) = mpsc::channel(1);
+        let producer = self.fetch_composition_chunks(compositions_metadata, sender);
+        let consumer = async {
+            // The consumer owns the receiver, so a write failure drops it and
+            // the producer stops instead of waiting to send the next chunk
+            let mut receiver = receiver;
+            while let Some(chunk) = receiver.recv().await {
+                // The watermark is checkpointed after each chunk by the batch processor
+                self.process_and_update_summary(
+                    chunk,
+                    template_id,
+                    ehr_id,
+                    &mut watermark,
+                    summary,
+                )
+                .await?;
+            }
+            Ok::<(), crate::domain::AtlasError>(())
+        };
+        let (fetch_errors, consumer_result) = tokio::join!(producer, consumer);
+        consumer_result?;
+
+        summary.total_compositions += fetch_errors.len();
+        summary.failed_exports += fetch_errors.len();
+        for error in fetch_errors {
+            summary.add_error(error);
+        }
+
+        // Mark export as completed and save watermark
+        watermark.mark_completed();
+        self.state_manager
+            .save_watermark(&watermark, self.config.export.dry_run)
+            .await?;
+
+        // Propagate compositions deleted in openEHR to the target
+        if self.config.export.deletion_policy != DeletionPolicy::Ignore {
+            if let Err(e) = self
+                .propagate_deletions(template_id, ehr_id, deletion_scan, summary)
+                .await
+            {
+                tracing::error!(
+                    template_id = %template_id.as_str(),
+                    ehr_id = %ehr_id.as_str(),
+                    error = %e,
+                    "Failed to propagate deletions"
+                );
+                summary.add_error(
+                    ExportError::new(
+                        ExportErrorType::Storage,
+                        format!("Failed to propagate deletions: {e}"),
+                    )
+                    .with_context(format!(
+                        "template_id={}, ehr_id={}",
+                        template_id.as_str(),
+                        ehr_id.as_str()
+                    )),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply the deletion policy to compositions deleted in openEHR
+    ///
+    /// In incremental mode, the deletions committed in the EHR since the
+    /// watermark (minus `export.incremental_overlap_secs`) are queried, and
+    /// the stored versions of those compositions are deleted. In full mode,
+    /// the compositions stored in the target for the EHR and template are
+    /// compared with all compositions that still exist in openEHR, regardless
+    /// of the configured time range, and stored compositions (or stored
+    /// versions of a composition) whose versioned object no longer exists are
+    /// deleted. Either way they are deleted or marked as deleted according to
+    /// `export.deletion_policy`.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_id` - Template ID
+    /// * `ehr_id` - EHR ID
+    /// * `scan` - How to find the deleted compositions
+    /// * `summary` - Export summary to update with the number of deletions
+    async fn propagate_deletions(
+        &self,
+        template_id: &TemplateId,
+        ehr_id: &EhrId,
+        scan: DeletionScan,
+        summary: &mut ExportSummary,
+    ) -> Result<()> {
+        let deleted_ids = match scan {
+            DeletionScan::Skip => return Ok(()),
+            DeletionScan::Since(since) => {
+                let deletions: Vec<AqlRecord> = self
+                    .openehr_client
+                    .vendor()
+                    .query_records(AqlQuery::deleted_compositions_in_ehr(ehr_id, since))
+                    .try_collect()
+                    .await?;
+                if deletions.is_empty() {
+                    return Ok(());
+                }
+
+                let stored_ids = self
+                    .database_client
+                    .list_composition_ids(template_id, ehr_id)
+                    .await?;
+                find_stored_deletions(stored_ids, &deletions)?
+            }
+            DeletionScan::Full => {
+                let live_compositions = self
+                    .openehr_client
+                    .vendor()
+                    .get_compositions_for_ehr(ehr_id, template_id, None, None)
+                    .await?;
+
+                let stored_ids = self
+                    .database_client
+                    .list_composition_ids(template_id, ehr_id)
+                    .await?;
+                find_deleted_compositions(stored_ids, &live_compositions)
+            }
+        };
+        if deleted_ids.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!(
+            template_id = %template_id.as_str(),
+            ehr_id = %ehr_id.as_str(),
+            count = deleted_ids.len(),
+            policy = %self.config.export.deletion_policy,
+            "Found compositions deleted in openEHR"
         );
 
-        // Load or create watermark
-        let mut watermark = self.load_or_create_watermark(template_id, ehr_id).await?;
-
-        // Mark export as started
-        watermark.mark_started();
-        self.state_manager
-            .save_watermark(&watermark, self.config.export.dry_run)
+        summary.compositions_deleted += self
+            .database_client
+            .delete_compositions(
+                template_id,
+                ehr_id,
+                &deleted_ids,
+                self.config.export.deletion_policy,
+                self.config.export.dry_run,
+            )
             .await?;
 
-        // Fetch compositions for this EHR and template
-        let compositions = self
-            .fetch_compositions_for_ehr(template_id, ehr_id, &watermark)
-            .await?;
+        Ok(())
+    }
+}
 
-        // If no compositions found, mark as completed and return
-        if compositions.is_empty() {
-            watermark.mark_completed();
-            self.state_manager
-                .save_watermark(&watermark, self.config.export.dry_run)
-                .await?;
-            return Ok(());
-        }
+/// How the compositions deleted in openEHR are found for an {EHR, template} pair
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DeletionScan {
+    /// Nothing was exported for the pair before
+    Skip,
+    /// Query the deletions committed since a time
+    Since(DateTime<Utc>),
+    /// Compare the stored compositions with all compositions in openEHR
+    Full,
+}
 
-        // Process compositions and update summary
-        self.process_and_update_summary(compositions, template_id, ehr_id, &mut watermark, summary)
-            .await?;
+/// Find stored compositions deleted by the rows of
+/// [`AqlQuery::deleted_compositions_in_ehr`]
+///
+/// Stored IDs are matched on the object UUID of the deletion's version UID,
+/// so every stored version of a deleted composition is returned.
+fn find_stored_deletions(stored_ids: Vec<String>, deletions: &[AqlRecord]) -> Result<Vec<String>> {
+    let deleted_uuids = deletions
+        .iter()
+        .map(|record| {
+            let version_uid = record
+                .get("version_uid")
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| {
+                    AtlasError::OpenEhr(OpenEhrError::InvalidResponse(
+                        "Deletion row has no 'version_uid' column; the server must support AQL column aliases"
+                            .to_string(),
+                    ))
+                })?;
+            Ok(version_uid.split("::").next().unwrap_or(version_uid))
+        })
+        .collect::<Result<HashSet<&str>>>()?;
 
-        // Mark export as completed and save watermark
-        watermark.mark_completed();
-        self.state_manager
-            .save_watermark(&watermark, self.config.export.dry_run)
-            .await?;
+    Ok(stored_ids
+        .into_iter()
+        .filter(|id| deleted_uuids.contains(id.split("::").next().unwrap_or(id)))
+        .collect())
+}
 
-        Ok(())
-    }
+/// Find stored compositions whose versioned object no longer exists in openEHR
+///
+/// Stored IDs are versioned composition UIDs (`{uuid}::{system_id}::{version}`)
+/// and are matched on the object UUID, so earlier versions of a live
+/// composition are kept.
+fn find_deleted_compositions(
+    stored_ids: Vec<String>,
+    live_compositions: &[CompositionMetadata],
+) -> Vec<String> {
+    let live_uuids: HashSet<&str> = live_compositions
+        .iter()
+        .map(|metadata| metadata.uid.base_uuid())
+        .collect();
+
+    stored_ids
+        .into_iter()
+        .filter(|id| {
+            let uuid = id.split("::").next().unwrap_or(id);
+            !live_uuids.contains(uuid)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::adapters::database::traits::{BulkInsertResult, StateStorage};
+    use crate::adapters::database::traits::{BulkInsertResult, QueryRow, StateStorage};
+    use crate::adapters::openehr::aql::{AqlQuery, AqlRecord};
     use crate::adapters::openehr::vendor::{CompositionMetadata, OpenEhrVendor};
-    use crate::core::state::watermark::Watermark;
+    use crate::core::state::watermark::{QueryWatermark, Watermark};
+    use crate::core::state::DeadLetter;
     use crate::domain::composition::Composition;
     use crate::domain::ids::CompositionUid;
     use async_trait::async_trait;
     use chrono::Utc;
+    use futures::stream::BoxStream;
     use std::any::Any;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Mutex;
+    use std::time::Duration;
 
     // Mock openEHR Vendor
     struct MockOpenEhrVendor {
         ehr_ids: Vec<EhrId>,
         compositions_metadata: Vec<CompositionMetadata>,
         compositions: Vec<Composition>,
+        records: Vec<AqlRecord>,
+        deletion_records: Vec<AqlRecord>,
         should_fail: bool,
+        fetch_count: AtomicUsize,
+        list_count: AtomicUsize,
     }
 
     impl MockOpenEhrVendor {
@@ -659,10 +1648,30 @@ mod tests {
                 ehr_ids: vec![],
                 compositions_metadata: vec![],
                 compositions: vec![],
+                records: vec![],
+                deletion_records: vec![],
                 should_fail: false,
+                fetch_count: AtomicUsize::new(0),
+                list_count: AtomicUsize::new(0),
             }
         }
 
+        fn with_records(mut self, records: Vec<serde_json::Value>) -> Self {
+            self.records = records
+                .into_iter()
+                .map(|record| record.as_object().cloned().unwrap())
+                .collect();
+            self
+        }
+
+        fn with_deletion_records(mut self, records: Vec<serde_json::Value>) -> Self {
+            self.deletion_records = records
+                .into_iter()
+                .map(|record| record.as_object().cloned().unwrap())
+                .collect();
+            self
+        }
+
         fn with_ehr_ids(mut self, ehr_ids: Vec<EhrId>) -> Self {
             self.ehr_ids = ehr_ids;
             self
@@ -713,7 +1722,9 @@ mod tests {
             _ehr_id: &EhrId,
             _template_id: &TemplateId,
             _since: Option<chrono::DateTime<Utc>>,
+            _until: Option<chrono::DateTime<Utc>>,
         ) -> Result<Vec<CompositionMetadata>> {
+            self.list_count.fetch_add(1, Ordering::SeqCst);
             if self.should_fail {
                 return Err(crate::domain::AtlasError::OpenEhr(
                     crate::domain::OpenEhrError::QueryFailed("Mock query failed".to_string()),
@@ -722,13 +1733,19 @@ mod tests {
             Ok(self.compositions_metadata.clone())
         }
 
-        async fn fetch_composition(&self, _metadata: &CompositionMetadata) -> Result<Composition> {
+        async fn fetch_composition(&self, metadata: &CompositionMetadata) -> Result<Composition> {
+            self.fetch_count.fetch_add(1, Ordering::SeqCst);
             if self.should_fail {
                 return Err(crate::domain::AtlasError::OpenEhr(
                     crate::domain::OpenEhrError::QueryFailed("Mock fetch failed".to_string()),
                 ));
             }
-            if let Some(comp) = self.compositions.first() {
+            if let Some(comp) = self
+                .compositions
+                .iter()
+                .find(|comp| comp.uid == metadata.uid)
+                .or(self.compositions.first())
+            {
                 Ok(comp.clone())
             } else {
                 Err(crate::domain::AtlasError::OpenEhr(
@@ -737,6 +1754,65 @@ mod tests {
             }
         }
 
+        async fn get_composition_versions(
+            &self,
+            metadata: &CompositionMetadata,
+        ) -> Result<Vec<CompositionMetadata>> {
+            Ok(vec![metadata.clone()])
+        }
+
+        async fn get_template(
+            &self,
+            template_id: &TemplateId,
+            format: crate::domain::TemplateFormat,
+        ) -> Result<crate::domain::TemplateDefinition> {
+            if self.should_fail {
+                return Err(crate::domain::AtlasError::OpenEhr(
+                    crate::domain::OpenEhrError::TemplateNotFound(template_id.to_string()),
+                ));
+            }
+            Ok(crate::domain::TemplateDefinition::new(
+                template_id.clone(),
+                format,
+                r#"{"tree":{"id":"mock","rmType":"COMPOSITION","min":1,"max":1}}"#.to_string(),
+            ))
+        }
+
+        async fn get_ehr(&self, ehr_id: &EhrId) -> Result<crate::domain::Ehr> {
+            if self.should_fail {
+                return Err(crate::domain::AtlasError::OpenEhr(
+                    crate::domain::OpenEhrError::EhrNotFound(ehr_id.to_string()),
+                ));
+            }
+            Ok(crate::domain::Ehr::new(ehr_id.clone(), Utc::now()))
+        }
+
+        async fn list_templates(&self) -> Result<Vec<TemplateId>> {
+            if self.should_fail {
+                return Err(crate::domain::AtlasError::OpenEhr(
+                    crate::domain::OpenEhrError::ConnectionFailed("Mock failure".to_string()),
+                ));
+            }
+            Ok(vec![TemplateId::new("vital_signs.v1").unwrap()])
+        }
+
+        fn query_records(&self, query: AqlQuery) -> BoxStream<'_, Result<AqlRecord>> {
+            if self.should_fail {
+                return stream::once(async {
+                    Err(crate::domain::AtlasError::OpenEhr(
+                        crate::domain::OpenEhrError::QueryFailed("Mock failure".to_string()),
+                    ))
+                })
+                .boxed();
+            }
+            let records = if query.query().contains("change_type") {
+                self.deletion_records.clone()
+            } else {
+                self.records.clone()
+            };
+            stream::iter(records.into_iter().map(Ok)).boxed()
+        }
+
         fn is_authenticated(&self) -> bool {
             !self.should_fail
         }
@@ -749,14 +1825,20 @@ mod tests {
     // Mock Database Client
     struct MockDatabaseClient {
         should_fail: bool,
+        fail_inserts: bool,
         insert_results: Mutex<Vec<BulkInsertResult>>,
+        stored_ids: Vec<String>,
+        deleted_ids: Mutex<Vec<String>>,
     }
 
     impl MockDatabaseClient {
         fn new() -> Self {
             Self {
                 should_fail: false,
+                fail_inserts: false,
                 insert_results: Mutex::new(vec![]),
+                stored_ids: vec![],
+                deleted_ids: Mutex::new(vec![]),
             }
         }
 
@@ -765,6 +1847,16 @@ mod tests {
             self
         }
 
+        fn with_insert_failure(mut self) -> Self {
+            self.fail_inserts = true;
+            self
+        }
+
+        fn with_stored_ids(mut self, stored_ids: Vec<String>) -> Self {
+            self.stored_ids = stored_ids;
+            self
+        }
+
         fn with_insert_result(self, result: BulkInsertResult) -> Self {
             self.insert_results.lock().unwrap().push(result);
             self
@@ -821,6 +1913,43 @@ mod tests {
             Ok(())
         }
 
+        async fn ensure_ehr_container_exists(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn upsert_ehr(&self, _ehr: &crate::domain::Ehr, _dry_run: bool) -> Result<()> {
+            if self.should_fail {
+                return Err(crate::domain::AtlasError::CosmosDb(
+                    crate::domain::CosmosDbError::WriteFailed("Mock EHR write failed".to_string()),
+                ));
+            }
+            Ok(())
+        }
+
+        async fn ensure_query_container_exists(&self, _query_name: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn upsert_query_rows(
+            &self,
+            _query_name: &str,
+            rows: Vec<QueryRow>,
+            _dry_run: bool,
+        ) -> Result<BulkInsertResult> {
+            if self.should_fail {
+                return Err(crate::domain::AtlasError::CosmosDb(
+                    crate::domain::CosmosDbError::WriteFailed(
+                        "Mock query write failed".to_string(),
+                    ),
+                ));
+            }
+            Ok(BulkInsertResult {
+                success_count: rows.len(),
+                failure_count: 0,
+                failures: vec![],
+            })
+        }
+
         async fn check_composition_exists(
             &self,
             _template_id: &TemplateId,
@@ -835,6 +1964,29 @@ mod tests {
             Ok(false)
         }
 
+        async fn list_composition_ids(
+            &self,
+            _template_id: &TemplateId,
+            _ehr_id: &EhrId,
+        ) -> Result<Vec<String>> {
+            Ok(self.stored_ids.clone())
+        }
+
+        async fn delete_compositions(
+            &self,
+            _template_id: &TemplateId,
+            _ehr_id: &EhrId,
+            composition_ids: &[String],
+            _policy: crate::config::DeletionPolicy,
+            _dry_run: bool,
+        ) -> Result<usize> {
+            self.deleted_ids
+                .lock()
+                .unwrap()
+                .extend_from_slice(composition_ids);
+            Ok(composition_ids.len())
+        }
+
         fn database_name(&self) -> &str {
             "mock_database"
         }
@@ -846,7 +1998,7 @@ mod tests {
             _max_retries: usize,
             _dry_run: bool,
         ) -> Result<BulkInsertResult> {
-            if self.should_fail {
+            if self.should_fail || self.fail_inserts {
                 return Err(crate::domain::AtlasError::CosmosDb(
                     crate::domain::CosmosDbError::InsertFailed("Mock insert failed".to_string()),
                 ));
@@ -905,6 +2057,8 @@ mod tests {
     // Mock State Storage
     struct MockStateStorage {
         watermarks: Mutex<std::collections::HashMap<String, Watermark>>,
+        query_watermarks: Mutex<std::collections::HashMap<String, QueryWatermark>>,
+        dead_letters: Mutex<std::collections::HashMap<String, DeadLetter>>,
         should_fail: bool,
     }
 
@@ -912,6 +2066,8 @@ mod tests {
         fn new() -> Self {
             Self {
                 watermarks: Mutex::new(std::collections::HashMap::new()),
+                query_watermarks: Mutex::new(std::collections::HashMap::new()),
+                dead_letters: Mutex::new(std::collections::HashMap::new()),
                 should_fail: false,
             }
         }
@@ -977,6 +2133,66 @@ mod tests {
             }
             Ok(self.watermarks.lock().unwrap().values().cloned().collect())
         }
+
+        async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+            Ok(self
+                .query_watermarks
+                .lock()
+                .unwrap()
+                .get(query_name)
+                .cloned())
+        }
+
+        async fn save_query_watermark(
+            &self,
+            watermark: &QueryWatermark,
+            _dry_run: bool,
+        ) -> Result<()> {
+            self.query_watermarks
+                .lock()
+                .unwrap()
+                .insert(watermark.query_name.clone(), watermark.clone());
+            Ok(())
+        }
+
+        async fn load_dead_letter(
+            &self,
+            composition_uid: &CompositionUid,
+        ) -> Result<Option<DeadLetter>> {
+            Ok(self
+                .dead_letters
+                .lock()
+                .unwrap()
+                .get(&DeadLetter::generate_id(composition_uid))
+                .cloned())
+        }
+
+        async fn save_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+            if !dry_run {
+                self.dead_letters
+                    .lock()
+                    .unwrap()
+                    .insert(dead_letter.id.clone(), dead_letter.clone());
+            }
+            Ok(())
+        }
+
+        async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+            if !dry_run {
+                self.dead_letters.lock().unwrap().remove(&dead_letter.id);
+            }
+            Ok(())
+        }
+
+        async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+            Ok(self
+                .dead_letters
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect())
+        }
     }
 
     #[test]
@@ -997,8 +2213,57 @@ mod tests {
         assert!(*rx.borrow());
     }
 
+    // Helper to create a test configuration
+    fn create_test_config(
+        batch_size: usize,
+        database_target: &str,
+        target_section: &str,
+    ) -> AtlasConfig {
+        toml::from_str(&format!(
+            r#"
+            database_target = "{database_target}"
+
+            [application]
+
+            [openehr]
+            base_url = "http://mock.ehrbase.org"
+            username = "atlas"
+            password = "secret"
+
+            [openehr.query]
+            template_ids = ["vital_signs.v1"]
+            batch_size = {batch_size}
+
+            [export]
+
+            {target_section}
+
+            [state]
+            "#
+        ))
+        .unwrap()
+    }
+
+    // Helper to create a coordinator with mock clients
+    async fn create_test_coordinator(
+        config: AtlasConfig,
+        vendor: Arc<MockOpenEhrVendor>,
+        database: Arc<dyn DatabaseClient + Send + Sync>,
+        storage: Arc<dyn StateStorage + Send + Sync>,
+    ) -> ExportCoordinator {
+        let (_tx, shutdown) = watch::channel(false);
+        ExportCoordinator::with_clients(
+            config,
+            Arc::new(OpenEhrClient::from_vendor(vendor)),
+            database,
+            storage,
+            shutdown,
+        )
+        .await
+        .unwrap()
+    }
+
     // Helper to create test composition metadata
-    #[allow(dead_code)]
     fn create_test_metadata(uid_str: &str, template_id: &str, ehr_id: &str) -> CompositionMetadata {
         CompositionMetadata::new(
             CompositionUid::parse(uid_str).unwrap(),
@@ -1019,6 +2284,8 @@ mod tests {
                 "test": "data",
                 "archetype_node_id": "openEHR-EHR-COMPOSITION.encounter.v1"
             }),
+            content_format: Default::default(),
+            version: None,
         }
     }
 
@@ -1076,6 +2343,101 @@ mod tests {
         assert_eq!(loaded.unwrap().template_id, template_id);
     }
 
+    #[tokio::test]
+    async fn test_state_manager_records_dead_letters() {
+        let state_manager = StateManager::new_with_storage(Arc::new(MockStateStorage::new()));
+        let metadata = CompositionMetadata::new(
+            CompositionUid::new("84d7c3f5::local.ehrbase.org::1").unwrap(),
+            TemplateId::new("vital_signs").unwrap(),
+            EhrId::new("test-ehr").unwrap(),
+            Utc::now(),
+        );
+
+        state_manager
+            .record_dead_letter(&metadata, DeadLetterStage::Fetch, "timeout", false)
+            .await
+            .unwrap();
+        let dead_letter = state_manager
+            .record_dead_letter(&metadata, DeadLetterStage::Insert, "conflict", false)
+            .await
+            .unwrap();
+
+        // Repeated failures update the existing dead letter
+        assert_eq!(dead_letter.attempt_count, 2);
+        assert_eq!(dead_letter.stage, DeadLetterStage::Insert);
+        let dead_letters = state_manager.list_dead_letters().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+
+        // Dry runs leave the dead letter in place
+        state_manager
+            .delete_dead_letter(&dead_letter, true)
+            .await
+            .unwrap();
+        assert_eq!(state_manager.list_dead_letters().await.unwrap().len(), 1);
+
+        state_manager
+            .delete_dead_letter(&dead_letter, false)
+            .await
+            .unwrap();
+        assert!(state_manager.list_dead_letters().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_exporter_advances_watermark() {
+        let vendor = MockOpenEhrVendor::new().with_records(vec![
+            serde_json::json!({"ehr_id": "ehr-1", "measured_at": "2025-03-01T09:30:00Z"}),
+            serde_json::json!({"ehr_id": "ehr-2", "measured_at": "2025-03-02T08:00:00Z"}),
+            serde_json::json!({"ehr_id": "ehr-3", "measured_at": "2025-02-27T12:00:00Z"}),
+        ]);
+        let database = MockDatabaseClient::new();
+        let storage = Arc::new(MockStateStorage::new());
+        let state_manager = StateManager::new_with_storage(storage.clone());
+        let (_tx, shutdown) = watch::channel(false);
+        let query: crate::config::AqlQueryConfig = toml::from_str(
+            r#"
+            name = "latest_hba1c"
+            query = "SELECT ... WHERE c/context/start_time/value > $watermark"
+            key_columns = ["ehr_id"]
+            watermark_column = "measured_at"
+            "#,
+        )
+        .unwrap();
+
+        let exporter =
+            QueryExporter::new(&vendor, &database, &state_manager, &shutdown).with_batch_size(2);
+        let result = exporter.export(&query).await.unwrap();
+
+        assert_eq!(result.rows_exported, 3);
+        assert!(result.failures.is_empty());
+        assert!(!result.interrupted);
+
+        let watermark = storage
+            .load_query_watermark("latest_hba1c")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            watermark.value,
+            Some(serde_json::json!("2025-03-02T08:00:00Z"))
+        );
+        assert_eq!(watermark.rows_exported_count, 3);
+        assert!(matches!(
+            watermark.last_export_status,
+            crate::core::state::ExportStatus::Completed
+        ));
+
+        // A failing query leaves the watermark untouched
+        let vendor = MockOpenEhrVendor::new().with_failure();
+        let exporter = QueryExporter::new(&vendor, &database, &state_manager, &shutdown);
+        assert!(exporter.export(&query).await.is_err());
+        let watermark = storage
+            .load_query_watermark("latest_hba1c")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(watermark.rows_exported_count, 3);
+    }
+
     #[tokio::test]
     async fn test_mock_database_client_bulk_insert() {
         let result = BulkInsertResult {
@@ -1114,4 +2476,283 @@ mod tests {
             .await
             .is_err());
     }
+
+    #[test]
+    fn test_find_deleted_compositions() {
+        let live = vec![
+            create_test_metadata("uid1::local::2", "vital_signs", "ehr1"),
+            create_test_metadata("uid3::local::1", "vital_signs", "ehr1"),
+        ];
+        let stored = vec![
+            "uid1::local::1".to_string(),
+            "uid1::local::2".to_string(),
+            "uid2::local::1".to_string(),
+            "uid3::local::1".to_string(),
+        ];
+
+        let deleted = find_deleted_compositions(stored, &live);
+
+        assert_eq!(deleted, vec!["uid2::local::1".to_string()]);
+    }
+
+    #[test]
+    fn test_find_stored_deletions() {
+        let deletions: Vec<AqlRecord> =
+            [serde_json::json!({"ehr_id": "ehr1", "version_uid": "uid2::local::3"})]
+                .iter()
+                .map(|record| record.as_object().cloned().unwrap())
+                .collect();
+        let stored = vec![
+            "uid1::local::1".to_string(),
+            "uid2::local::1".to_string(),
+            "uid2::local::2".to_string(),
+        ];
+
+        assert_eq!(
+            find_stored_deletions(stored, &deletions).unwrap(),
+            vec!["uid2::local::1".to_string(), "uid2::local::2".to_string()]
+        );
+
+        let without_alias: Vec<AqlRecord> = [serde_json::json!({"#0": "uid2::local::3"})]
+            .iter()
+            .map(|record| record.as_object().cloned().unwrap())
+            .collect();
+        assert!(find_stored_deletions(vec![], &without_alias).is_err());
+    }
+
+    #[test]
+    fn test_find_deleted_compositions_all_deleted() {
+        let stored = vec!["uid1::local::1".to_string(), "uid2::local::3".to_string()];
+
+        let deleted = find_deleted_compositions(stored.clone(), &[]);
+
+        assert_eq!(deleted, stored);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_stops_composition_stream() {
+        let ehr_id = "7d44b88c-4199-4bad-97dc-d78268e01398";
+        let metadata: Vec<_> = (1..=10)
+            .map(|i| create_test_metadata(&format!("uid{i}::local::1"), "vital_signs.v1", ehr_id))
+            .collect();
+        let vendor = Arc::new(
+            MockOpenEhrVendor::new()
+                .with_compositions_metadata(metadata)
+                .with_compositions(vec![create_test_composition(
+                    "uid1::local::1",
+                    "vital_signs.v1",
+                    ehr_id,
+                )]),
+        );
+        let coordinator = create_test_coordinator(
+            create_test_config(1, "sqlite", "[sqlite]\npath = \"unused.db\""),
+            vendor.clone(),
+            Arc::new(MockDatabaseClient::new().with_insert_failure()),
+            Arc::new(MockStateStorage::new()),
+        )
+        .await;
+
+        // With one composition per chunk, the producer would block forever on
+        // the third chunk if the receiver outlived the failed write
+        let mut summary = ExportSummary::new();
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            coordinator.process_ehr_for_template(
+                &TemplateId::new("vital_signs.v1").unwrap(),
+                &EhrId::new(ehr_id).unwrap(),
+                &mut summary,
+            ),
+        )
+        .await
+        .expect("export should not hang after a failed write");
+
+        assert!(result.is_err());
+        assert!(vendor.fetch_count.load(Ordering::SeqCst) < 10);
+    }
+
+    #[tokio::test]
+    async fn test_export_to_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("atlas.db");
+        let ehr_id = "7d44b88c-4199-4bad-97dc-d78268e01398";
+        let uids = ["uid1::local::1", "uid2::local::1", "uid3::local::1"];
+
+        let vendor = Arc::new(
+            MockOpenEhrVendor::new()
+                .with_ehr_ids(vec![EhrId::new(ehr_id).unwrap()])
+                .with_compositions_metadata(
+                    uids.iter()
+                        .map(|uid| create_test_metadata(uid, "vital_signs.v1", ehr_id))
+                        .collect(),
+                )
+                .with_compositions(
+                    uids.iter()
+                        .map(|uid| create_test_composition(uid, "vital_signs.v1", ehr_id))
+                        .collect(),
+                ),
+        );
+        let mut config = create_test_config(
+            100,
+            "sqlite",
+            &format!("[sqlite]\npath = {:?}", path.display().to_string()),
+        );
+        config.openehr.templates.enabled = false;
+
+        let (database, storage) =
+            crate::adapters::database::factory::create_database_and_state(&config)
+                .await
+                .unwrap();
+        let coordinator = create_test_coordinator(config, vendor, database, storage).await;
+
+        let summary = coordinator.execute_export().await.unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+        assert_eq!(summary.successful_exports, 3);
+
+        // Rows and watermarks are read back from the database file
+        let client = crate::adapters::sqlite::SQLiteClient::new(crate::config::SQLiteConfig {
+            path: path.display().to_string(),
+            busy_timeout_seconds: 5,
+        })
+        .await
+        .unwrap();
+        let ids: Vec<String> = client
+            .call(|conn| {
+                conn.prepare("SELECT id FROM compositions ORDER BY id")?
+                    .query_map([], |row| row.get(0))?
+                    .collect()
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids, uids);
+
+        let storage = crate::adapters::sqlite::SQLiteAdapter::new(client);
+        let watermark = storage
+            .load_watermark(
+                &TemplateId::new("vital_signs.v1").unwrap(),
+                &EhrId::new(ehr_id).unwrap(),
+            )
+            .await
+            .unwrap()
+            .expect("watermark should be saved");
+        assert!(watermark.is_completed());
+        assert_eq!(
+            watermark.last_exported_composition_uid.unwrap().as_str(),
+            "uid3::local::1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_contribution_mode_propagates_deletion_between_runs() {
+        let ehr_id = "7d44b88c-4199-4bad-97dc-d78268e01398";
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+
+        // uid2 was exported by the previous run and deleted since
+        let vendor = Arc::new(
+            MockOpenEhrVendor::new()
+                .with_compositions_metadata(vec![create_test_metadata(
+                    "uid1::local::1",
+                    "vital_signs.v1",
+                    ehr_id,
+                )])
+                .with_compositions(vec![create_test_composition(
+                    "uid1::local::1",
+                    "vital_signs.v1",
+                    ehr_id,
+                )])
+                .with_deletion_records(vec![serde_json::json!({
+                    "ehr_id": ehr_id,
+                    "time_committed": "2025-03-02T10:00:00Z",
+                    "version_uid": "uid2::local::2",
+                })]),
+        );
+        let database = Arc::new(MockDatabaseClient::new().with_stored_ids(vec![
+            "uid1::local::1".to_string(),
+            "uid2::local::1".to_string(),
+        ]));
+        let storage = Arc::new(MockStateStorage::new());
+        let mut previous_run = QueryWatermark::new(CHANGE_FEED_WATERMARK);
+        previous_run.value = Some(serde_json::json!("2025-03-01T00:00:00Z"));
+        storage
+            .save_query_watermark(&previous_run, false)
+            .await
+            .unwrap();
+        let exported = WatermarkBuilder::new(template_id.clone(), EhrId::new(ehr_id).unwrap())
+            .last_exported_timestamp("2025-03-01T00:00:00Z".parse().unwrap())
+            .build();
+        storage.save_watermark(&exported, false).await.unwrap();
+
+        let mut config = create_test_config(10, "sqlite", "[sqlite]\npath = \"unused.db\"");
+        config.export.mode = "incremental".to_string();
+        config.export.change_detection = ChangeDetection::Contribution;
+        config.export.deletion_policy = DeletionPolicy::SoftDelete;
+        let coordinator =
+            create_test_coordinator(config, vendor.clone(), database.clone(), storage).await;
+
+        // The deletion alone marks the EHR as changed
+        let change_set = coordinator
+            .detect_changes(std::slice::from_ref(&template_id))
+            .await
+            .unwrap()
+            .expect("change feed watermark should enable change detection");
+        let ehr_ids = change_set.ehr_ids_for(&template_id);
+        assert_eq!(ehr_ids, vec![EhrId::new(ehr_id).unwrap()]);
+
+        let mut summary = ExportSummary::new();
+        coordinator
+            .process_ehr_for_template(&template_id, &ehr_ids[0], &mut summary)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.compositions_deleted, 1);
+        assert_eq!(
+            *database.deleted_ids.lock().unwrap(),
+            vec!["uid2::local::1".to_string()]
+        );
+
+        // The deletion is found without listing every composition again
+        assert_eq!(vendor.list_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_full_mode_compares_all_compositions_for_deletions() {
+        let ehr_id = EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap();
+        let template_id = TemplateId::new("vital_signs.v1").unwrap();
+
+        let vendor = Arc::new(MockOpenEhrVendor::new().with_compositions_metadata(vec![
+            create_test_metadata("uid1::local::1", "vital_signs.v1", ehr_id.as_str()),
+        ]));
+        let database = Arc::new(MockDatabaseClient::new().with_stored_ids(vec![
+            "uid1::local::1".to_string(),
+            "uid2::local::1".to_string(),
+        ]));
+        let storage = Arc::new(MockStateStorage::new());
+
+        let mut config = create_test_config(10, "sqlite", "[sqlite]\npath = \"unused.db\"");
+        config.export.mode = "full".to_string();
+        config.export.deletion_policy = DeletionPolicy::SoftDelete;
+        let coordinator =
+            create_test_coordinator(config, vendor.clone(), database.clone(), storage.clone())
+                .await;
+
+        // Nothing was stored before the first export of the pair
+        let mut summary = ExportSummary::new();
+        coordinator
+            .process_ehr_for_template(&template_id, &ehr_id, &mut summary)
+            .await
+            .unwrap();
+        assert_eq!(summary.compositions_deleted, 0);
+        assert_eq!(vendor.list_count.load(Ordering::SeqCst), 1);
+
+        let mut summary = ExportSummary::new();
+        coordinator
+            .process_ehr_for_template(&template_id, &ehr_id, &mut summary)
+            .await
+            .unwrap();
+        assert_eq!(summary.compositions_deleted, 1);
+        assert_eq!(vendor.list_count.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            *database.deleted_ids.lock().unwrap(),
+            vec!["uid2::local::1".to_string()]
+        );
+    }
 }
@@ -3,12 +3,18 @@
 //! This module provides the core export logic for Atlas, including:
 //! - Batch processing of compositions
 //! - Export coordination and orchestration
+//! - Contribution-based change detection
+//! - Export of named AQL query result sets
 //! - Summary and reporting
 
 pub mod batch;
+pub mod changes;
 pub mod coordinator;
+pub mod query;
 pub mod summary;
 
 pub use batch::{BatchConfig, BatchProcessor, BatchResult};
+pub use changes::{ChangeSet, CHANGE_FEED_WATERMARK};
 pub use coordinator::ExportCoordinator;
+pub use query::{QueryExportResult, QueryExporter};
 pub use summary::{ExportError, ExportErrorType, ExportSummary, ExportedCompositionInfo};
@@ -0,0 +1,261 @@
+//! Export of named AQL query result sets
+//!
+//! This module runs the queries configured in `[[export.aql_queries]]` and
+//! stores their rows in a container/table named after each query. Queries
+//! with a watermark column are exported incrementally: the highest value of
+//! that column is saved as a query watermark and bound to the `$watermark`
+//! parameter of the next run.
+
+use crate::adapters::database::traits::{BulkInsertFailure, DatabaseClient, QueryRow};
+use crate::adapters::openehr::aql::{AqlQuery, AqlRecord};
+use crate::adapters::openehr::vendor::OpenEhrVendor;
+use crate::config::AqlQueryConfig;
+use crate::core::state::{QueryWatermark, StateManager};
+use crate::domain::{AtlasError, Result};
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+
+/// Result of exporting one AQL query
+#[derive(Debug, Default)]
+pub struct QueryExportResult {
+    /// Number of rows stored
+    pub rows_exported: usize,
+
+    /// Rows that could not be stored
+    pub failures: Vec<BulkInsertFailure>,
+
+    /// Whether the export stopped early because shutdown was requested
+    pub interrupted: bool,
+}
+
+/// Exports the result sets of named AQL queries
+pub struct QueryExporter<'a> {
+    vendor: &'a dyn OpenEhrVendor,
+    database: &'a dyn DatabaseClient,
+    state_manager: &'a StateManager,
+    shutdown_signal: &'a watch::Receiver<bool>,
+    batch_size: usize,
+    incremental: bool,
+    dry_run: bool,
+}
+
+impl<'a> QueryExporter<'a> {
+    /// Create a new query exporter
+    ///
+    /// # Arguments
+    ///
+    /// * `vendor` - openEHR vendor to run the queries on
+    /// * `database` - Database to store the rows in
+    /// * `state_manager` - State manager for the query watermarks
+    /// * `shutdown_signal` - Receiver for shutdown signal, checked between batches
+    pub fn new(
+        vendor: &'a dyn OpenEhrVendor,
+        database: &'a dyn DatabaseClient,
+        state_manager: &'a StateManager,
+        shutdown_signal: &'a watch::Receiver<bool>,
+    ) -> Self {
+        Self {
+            vendor,
+            database,
+            state_manager,
+            shutdown_signal,
+            batch_size: 1000,
+            incremental: true,
+            dry_run: false,
+        }
+    }
+
+    /// Set the number of rows stored per batch
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Resume from the saved watermark (incremental) or from `watermark_start` (full)
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Skip database writes
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Export the result set of a query
+    ///
+    /// The watermark is only advanced when every row was stored, so rows that
+    /// failed are selected again by the next run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container/table cannot be created, the query
+    /// fails, a row lacks a key column, or the watermark cannot be loaded or
+    /// saved.
+    pub async fn export(&self, query: &AqlQueryConfig) -> Result<QueryExportResult> {
+        self.database
+            .ensure_query_container_exists(&query.name)
+            .await?;
+
+        let mut watermark = self
+            .state_manager
+            .load_query_watermark(&query.name)
+            .await?
+            .unwrap_or_else(|| QueryWatermark::new(&query.name));
+
+        let mut aql = AqlQuery::new(query.query.as_str());
+        if query.watermark_column.is_some() {
+            let start = match (&watermark.value, self.incremental) {
+                (Some(value), true) => value.clone(),
+                _ => serde_json::Value::String(query.watermark_start.clone()),
+            };
+            tracing::info!(query = %query.name, watermark = %start, "Exporting AQL query");
+            aql = aql.with_parameter("watermark", start);
+        } else {
+            tracing::info!(query = %query.name, "Exporting AQL query");
+        }
+
+        watermark.mark_started();
+        let mut next_watermark = watermark.clone();
+        let mut result = QueryExportResult::default();
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut records = self.vendor.query_records(aql);
+
+        while let Some(record) = records.next().await {
+            let record = record?;
+
+            if let Some(ref column) = query.watermark_column {
+                if let Some(value) = record.get(column) {
+                    next_watermark.advance(value);
+                }
+            }
+
+            batch.push(QueryRow {
+                id: row_id(query, &record)?,
+                data: record,
+            });
+
+            if batch.len() >= self.batch_size {
+                self.store(query, std::mem::take(&mut batch), &mut result)
+                    .await?;
+
+                if *self.shutdown_signal.borrow() {
+                    result.interrupted = true;
+                    break;
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            self.store(query, batch, &mut result).await?;
+        }
+
+        watermark.rows_exported_count += result.rows_exported as u64;
+        if result.interrupted {
+            watermark.mark_interrupted();
+        } else if result.failures.is_empty() {
+            watermark.value = next_watermark.value;
+            watermark.mark_completed();
+        } else {
+            watermark.mark_failed();
+        }
+
+        self.state_manager
+            .save_query_watermark(&watermark, self.dry_run)
+            .await?;
+
+        tracing::info!(
+            query = %query.name,
+            rows_exported = result.rows_exported,
+            failed = result.failures.len(),
+            "Exported AQL query"
+        );
+
+        Ok(result)
+    }
+
+    async fn store(
+        &self,
+        query: &AqlQueryConfig,
+        rows: Vec<QueryRow>,
+        result: &mut QueryExportResult,
+    ) -> Result<()> {
+        let stored = self
+            .database
+            .upsert_query_rows(&query.name, rows, self.dry_run)
+            .await?;
+
+        result.rows_exported += stored.success_count;
+        result.failures.extend(stored.failures);
+        Ok(())
+    }
+}
+
+/// Document/row ID of a result-set row
+///
+/// The ID is the hex-encoded SHA-256 of the key column values (or of the whole
+/// row without key columns), so it is stable across runs and safe to use as a
+/// Cosmos DB document ID whatever the values contain.
+fn row_id(query: &AqlQueryConfig, record: &AqlRecord) -> Result<String> {
+    let key = if query.key_columns.is_empty() {
+        serde_json::to_string(record)
+    } else {
+        let values = query
+            .key_columns
+            .iter()
+            .map(|column| {
+                record.get(column).ok_or_else(|| {
+                    AtlasError::Configuration(format!(
+                        "export.aql_queries '{}' key column '{column}' is not in the result set",
+                        query.name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        serde_json::to_string(&values)
+    }?;
+
+    Ok(Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(key_columns: &[&str]) -> AqlQueryConfig {
+        toml::from_str(&format!(
+            "name = \"latest_hba1c\"\nquery = \"SELECT 1\"\nkey_columns = {key_columns:?}"
+        ))
+        .unwrap()
+    }
+
+    fn record(value: serde_json::Value) -> AqlRecord {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_row_id() {
+        let by_ehr = query(&["ehr_id"]);
+        let a = record(json!({"ehr_id": "ehr-1", "hba1c": 48}));
+        let b = record(json!({"ehr_id": "ehr-1", "hba1c": 52}));
+
+        // Rows with the same key share an ID, which is safe for Cosmos DB
+        let id = row_id(&by_ehr, &a).unwrap();
+        assert_eq!(id, row_id(&by_ehr, &b).unwrap());
+        assert_eq!(id.len(), 64);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Without key columns the whole row is the key
+        let by_row = query(&[]);
+        assert_ne!(row_id(&by_row, &a).unwrap(), row_id(&by_row, &b).unwrap());
+
+        let missing = query(&["subject_id"]);
+        assert!(row_id(&missing, &a).is_err());
+    }
+}
@@ -48,6 +48,16 @@ pub struct ExportSummary {
     /// Number of duplicates skipped
     pub duplicates_skipped: usize,
 
+    /// Number of exported compositions deleted or marked as deleted because
+    /// they were deleted in openEHR
+    pub compositions_deleted: usize,
+
+    /// Number of EHRs whose metadata and EHR_STATUS were exported
+    pub ehr_statuses_exported: usize,
+
+    /// Number of result-set rows exported by the named AQL queries
+    pub query_rows_exported: usize,
+
     /// Duration of the export
     pub duration: Duration,
 
@@ -79,6 +89,9 @@ impl ExportSummary {
             successful_exports: 0,
             failed_exports: 0,
             duplicates_skipped: 0,
+            compositions_deleted: 0,
+            ehr_statuses_exported: 0,
+            query_rows_exported: 0,
             duration: Duration::from_secs(0),
             errors: Vec::new(),
             exported_compositions: Vec::new(),
@@ -115,6 +128,24 @@ impl ExportSummary {
             ));
     }
 
+    /// Merge another summary into this one
+    ///
+    /// Used to combine the results of independently processed units of work
+    /// (e.g., EHRs exported concurrently) into the overall export summary.
+    pub fn merge(&mut self, other: ExportSummary) {
+        self.total_ehrs += other.total_ehrs;
+        self.total_compositions += other.total_compositions;
+        self.successful_exports += other.successful_exports;
+        self.failed_exports += other.failed_exports;
+        self.duplicates_skipped += other.duplicates_skipped;
+        self.compositions_deleted += other.compositions_deleted;
+        self.ehr_statuses_exported += other.ehr_statuses_exported;
+        self.query_rows_exported += other.query_rows_exported;
+        self.errors.extend(other.errors);
+        self.exported_compositions
+            .extend(other.exported_compositions);
+    }
+
     /// Set the verification report
     pub fn set_verification_report(&mut self, report: VerificationReport) {
         self.verification_report = Some(report);
@@ -147,6 +178,9 @@ impl ExportSummary {
                 successful = self.successful_exports,
                 failed = self.failed_exports,
                 duplicates_skipped = self.duplicates_skipped,
+                compositions_deleted = self.compositions_deleted,
+                ehr_statuses_exported = self.ehr_statuses_exported,
+                query_rows_exported = self.query_rows_exported,
                 duration_secs = self.duration.as_secs(),
                 success_rate = format!("{:.2}%", self.success_rate()),
                 shutdown_reason = self.shutdown_reason.as_deref().unwrap_or("Unknown"),
@@ -160,6 +194,9 @@ impl ExportSummary {
                 successful = self.successful_exports,
                 failed = self.failed_exports,
                 duplicates_skipped = self.duplicates_skipped,
+                compositions_deleted = self.compositions_deleted,
+                ehr_statuses_exported = self.ehr_statuses_exported,
+                query_rows_exported = self.query_rows_exported,
                 duration_secs = self.duration.as_secs(),
                 success_rate = format!("{:.2}%", self.success_rate()),
                 dry_run = self.dry_run,
@@ -348,6 +385,47 @@ mod tests {
         assert_eq!(summary.exported_compositions[0].template_id, template_id);
     }
 
+    #[test]
+    fn test_export_summary_merge() {
+        use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+
+        let mut summary = ExportSummary::new();
+        summary.total_ehrs = 2;
+        summary.total_compositions = 10;
+        summary.successful_exports = 10;
+
+        let mut unit = ExportSummary::new();
+        unit.total_compositions = 5;
+        unit.successful_exports = 4;
+        unit.failed_exports = 1;
+        unit.duplicates_skipped = 2;
+        unit.compositions_deleted = 3;
+        unit.ehr_statuses_exported = 1;
+        unit.query_rows_exported = 4;
+        unit.add_error(ExportError::new(
+            ExportErrorType::Storage,
+            "Failed to write".to_string(),
+        ));
+        unit.add_exported_composition(
+            CompositionUid::from_str("84d7c3f5::local.ehrbase.org::1").unwrap(),
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+            TemplateId::from_str("vital_signs.v1").unwrap(),
+        );
+
+        summary.merge(unit);
+
+        assert_eq!(summary.total_ehrs, 2);
+        assert_eq!(summary.total_compositions, 15);
+        assert_eq!(summary.successful_exports, 14);
+        assert_eq!(summary.failed_exports, 1);
+        assert_eq!(summary.duplicates_skipped, 2);
+        assert_eq!(summary.compositions_deleted, 3);
+        assert_eq!(summary.ehr_statuses_exported, 1);
+        assert_eq!(summary.query_rows_exported, 4);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.exported_compositions.len(), 1);
+    }
+
     #[test]
     fn test_exported_composition_info_creation() {
         use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
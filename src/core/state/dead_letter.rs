@@ -0,0 +1,159 @@
+//! Dead-letter model for compositions that failed to export
+//!
+//! A composition that cannot be fetched from openEHR or stored in the target
+//! is recorded as a dead letter instead of being skipped. The watermark still
+//! advances past it, so dead letters are the only record of the failure;
+//! `atlas retry-failed` reprocesses them and removes those that succeed.
+
+use crate::adapters::openehr::vendor::CompositionMetadata;
+use crate::domain::composition::CompositionVersion;
+use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Step of the export at which a composition failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterStage {
+    /// Listing the version history of the composition failed
+    VersionHistory,
+    /// Fetching the composition from openEHR failed
+    Fetch,
+    /// Storing the composition in the target failed
+    Insert,
+}
+
+impl fmt::Display for DeadLetterStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionHistory => write!(f, "version_history"),
+            Self::Fetch => write!(f, "fetch"),
+            Self::Insert => write!(f, "insert"),
+        }
+    }
+}
+
+/// Composition that failed to export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Unique identifier for this dead-letter document
+    /// Format: "dead_letter_{composition_uid}"
+    pub id: String,
+
+    /// Composition UID (including version)
+    pub composition_uid: CompositionUid,
+
+    /// Template ID of the composition
+    pub template_id: TemplateId,
+
+    /// EHR ID of the composition
+    pub ehr_id: EhrId,
+
+    /// Timestamp when the composition was committed
+    pub time_committed: DateTime<Utc>,
+
+    /// Version information (only set for entries of a version history)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<CompositionVersion>,
+
+    /// Step at which the last attempt failed
+    pub stage: DeadLetterStage,
+
+    /// Error of the last attempt
+    pub error: String,
+
+    /// Number of failed attempts, including the original export
+    pub attempt_count: u32,
+
+    /// Timestamp of the first failure
+    pub first_failed_at: DateTime<Utc>,
+
+    /// Timestamp of the last failure
+    pub last_failed_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    /// Create a dead letter for the first failure of a composition
+    pub fn new(
+        metadata: &CompositionMetadata,
+        stage: DeadLetterStage,
+        error: impl Into<String>,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Self::generate_id(&metadata.uid),
+            composition_uid: metadata.uid.clone(),
+            template_id: metadata.template_id.clone(),
+            ehr_id: metadata.ehr_id.clone(),
+            time_committed: metadata.time_committed,
+            version: metadata.version.clone(),
+            stage,
+            error: error.into(),
+            attempt_count: 1,
+            first_failed_at: now,
+            last_failed_at: now,
+        }
+    }
+
+    /// Generate the document ID for a dead letter
+    ///
+    /// # Returns
+    ///
+    /// A string in the format "dead_letter_{composition_uid}"
+    pub fn generate_id(composition_uid: &CompositionUid) -> String {
+        format!("dead_letter_{}", composition_uid.as_str())
+    }
+
+    /// Record another failed attempt
+    pub fn record_failure(&mut self, stage: DeadLetterStage, error: impl Into<String>) {
+        self.stage = stage;
+        self.error = error.into();
+        self.attempt_count += 1;
+        self.last_failed_at = Utc::now();
+    }
+
+    /// Metadata to fetch the composition again with
+    pub fn metadata(&self) -> CompositionMetadata {
+        let mut metadata = CompositionMetadata::new(
+            self.composition_uid.clone(),
+            self.template_id.clone(),
+            self.ehr_id.clone(),
+            self.time_committed,
+        );
+        metadata.version = self.version.clone();
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_dead_letter_attempts() {
+        let metadata = CompositionMetadata::new(
+            CompositionUid::from_str("84d7c3f5::local.ehrbase.org::2").unwrap(),
+            TemplateId::from_str("vital_signs.v1").unwrap(),
+            EhrId::from_str("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap(),
+            Utc::now(),
+        );
+
+        let mut dead_letter = DeadLetter::new(&metadata, DeadLetterStage::Fetch, "timeout");
+        assert_eq!(dead_letter.id, "dead_letter_84d7c3f5::local.ehrbase.org::2");
+        assert_eq!(dead_letter.attempt_count, 1);
+        assert_eq!(dead_letter.first_failed_at, dead_letter.last_failed_at);
+
+        dead_letter.record_failure(DeadLetterStage::Insert, "conflict");
+        assert_eq!(dead_letter.attempt_count, 2);
+        assert_eq!(dead_letter.stage, DeadLetterStage::Insert);
+        assert_eq!(dead_letter.error, "conflict");
+
+        let retry = dead_letter.metadata();
+        assert_eq!(retry.uid, metadata.uid);
+        assert_eq!(retry.template_id, metadata.template_id);
+        assert_eq!(retry.ehr_id, metadata.ehr_id);
+    }
+}
@@ -4,7 +4,9 @@
 //! to the database backend.
 
 use crate::adapters::database::traits::StateStorage;
-use crate::core::state::watermark::Watermark;
+use crate::adapters::openehr::vendor::CompositionMetadata;
+use crate::core::state::dead_letter::{DeadLetter, DeadLetterStage};
+use crate::core::state::watermark::{QueryWatermark, Watermark};
 use crate::domain::ids::{EhrId, TemplateId};
 use crate::domain::Result;
 use std::sync::Arc;
@@ -102,6 +104,80 @@ impl StateManager {
 
         self.save_watermark(watermark, dry_run).await
     }
+
+    /// Load the watermark of a named AQL query
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails for reasons other than "not found".
+    pub async fn load_query_watermark(&self, query_name: &str) -> Result<Option<QueryWatermark>> {
+        self.storage.load_query_watermark(query_name).await
+    }
+
+    /// Save the watermark of a named AQL query
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upsert operation fails.
+    pub async fn save_query_watermark(
+        &self,
+        watermark: &QueryWatermark,
+        dry_run: bool,
+    ) -> Result<()> {
+        self.storage.save_query_watermark(watermark, dry_run).await
+    }
+
+    /// Record a failed export attempt of a composition as a dead letter
+    ///
+    /// Creates the dead letter on the first failure and increments the
+    /// attempt count of an existing one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dead letter cannot be loaded or saved.
+    pub async fn record_dead_letter(
+        &self,
+        metadata: &CompositionMetadata,
+        stage: DeadLetterStage,
+        error: impl Into<String>,
+        dry_run: bool,
+    ) -> Result<DeadLetter> {
+        let dead_letter = match self.storage.load_dead_letter(&metadata.uid).await? {
+            Some(mut dead_letter) => {
+                dead_letter.record_failure(stage, error);
+                dead_letter
+            }
+            None => DeadLetter::new(metadata, stage, error),
+        };
+
+        tracing::debug!(
+            composition_uid = %dead_letter.composition_uid,
+            stage = %dead_letter.stage,
+            attempt_count = dead_letter.attempt_count,
+            "Recording dead letter"
+        );
+
+        self.storage.save_dead_letter(&dead_letter, dry_run).await?;
+        Ok(dead_letter)
+    }
+
+    /// Delete a dead letter after its composition was exported
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delete operation fails.
+    pub async fn delete_dead_letter(&self, dead_letter: &DeadLetter, dry_run: bool) -> Result<()> {
+        self.storage.delete_dead_letter(dead_letter, dry_run).await
+    }
+
+    /// List all dead letters, oldest failure first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        self.storage.list_dead_letters().await
+    }
 }
 
 #[cfg(test)]
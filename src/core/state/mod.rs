@@ -1,7 +1,9 @@
 // State management and watermark tracking
 
+pub mod dead_letter;
 pub mod manager;
 pub mod watermark;
 
+pub use dead_letter::{DeadLetter, DeadLetterStage};
 pub use manager::StateManager;
-pub use watermark::{ExportStatus, Watermark, WatermarkBuilder};
+pub use watermark::{ExportStatus, QueryWatermark, Watermark, WatermarkBuilder};
@@ -1,11 +1,13 @@
 //! Watermark model for tracking export state
 //!
 //! This module defines the watermark structure used to track the state of
-//! incremental exports per {template_id, ehr_id} combination.
+//! incremental exports per {template_id, ehr_id} combination, and the query
+//! watermark tracking the exports of each named AQL query.
 
 use crate::domain::ids::{CompositionUid, EhrId, TemplateId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 
 /// Export status enumeration
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -144,6 +146,11 @@ impl Watermark {
 
     /// Update the watermark after successfully exporting a composition
     ///
+    /// The watermark only moves forward: a composition committed before the
+    /// last exported timestamp leaves the timestamp and UID unchanged. A
+    /// watermark without an exported composition takes any timestamp, as a
+    /// new watermark starts at its creation time.
+    ///
     /// # Arguments
     ///
     /// * `composition_uid` - UID of the exported composition
@@ -153,8 +160,11 @@ impl Watermark {
         composition_uid: CompositionUid,
         timestamp: DateTime<Utc>,
     ) {
-        self.last_exported_composition_uid = Some(composition_uid);
-        self.last_exported_timestamp = timestamp;
+        if self.last_exported_composition_uid.is_none() || timestamp >= self.last_exported_timestamp
+        {
+            self.last_exported_composition_uid = Some(composition_uid);
+            self.last_exported_timestamp = timestamp;
+        }
         self.compositions_exported_count += 1;
     }
 }
@@ -246,6 +256,126 @@ impl WatermarkBuilder {
     }
 }
 
+/// Watermark for tracking the export state of a named AQL query
+///
+/// The watermark value is the highest value of the query's watermark column
+/// seen in the last successful export. It is bound to the `$watermark`
+/// parameter of the next incremental run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryWatermark {
+    /// Unique identifier for this watermark document
+    /// Format: "query_{query_name}"
+    pub id: String,
+
+    /// Name of the AQL query this watermark tracks
+    pub query_name: String,
+
+    /// Highest watermark column value exported (None before the first export)
+    pub value: Option<serde_json::Value>,
+
+    /// Total count of rows exported for this query
+    pub rows_exported_count: u64,
+
+    /// Timestamp when the last export started
+    pub last_export_started_at: DateTime<Utc>,
+
+    /// Timestamp when the last export completed (None if still in progress)
+    pub last_export_completed_at: Option<DateTime<Utc>>,
+
+    /// Status of the last export operation
+    pub last_export_status: ExportStatus,
+}
+
+impl QueryWatermark {
+    /// Create a watermark for a query that has not been exported yet
+    pub fn new(query_name: impl Into<String>) -> Self {
+        let query_name = query_name.into();
+
+        Self {
+            id: Self::generate_id(&query_name),
+            query_name,
+            value: None,
+            rows_exported_count: 0,
+            last_export_started_at: Utc::now(),
+            last_export_completed_at: None,
+            last_export_status: ExportStatus::NotStarted,
+        }
+    }
+
+    /// Generate the document ID for a query watermark
+    ///
+    /// # Returns
+    ///
+    /// A string in the format "query_{query_name}"
+    pub fn generate_id(query_name: &str) -> String {
+        format!("query_{query_name}")
+    }
+
+    /// Mark the export as started
+    pub fn mark_started(&mut self) {
+        self.last_export_started_at = Utc::now();
+        self.last_export_status = ExportStatus::InProgress;
+        self.last_export_completed_at = None;
+    }
+
+    /// Mark the export as completed
+    pub fn mark_completed(&mut self) {
+        self.last_export_completed_at = Some(Utc::now());
+        self.last_export_status = ExportStatus::Completed;
+    }
+
+    /// Mark the export as failed
+    pub fn mark_failed(&mut self) {
+        self.last_export_completed_at = Some(Utc::now());
+        self.last_export_status = ExportStatus::Failed;
+    }
+
+    /// Mark the export as interrupted by user signal (SIGTERM/SIGINT)
+    pub fn mark_interrupted(&mut self) {
+        self.last_export_completed_at = Some(Utc::now());
+        self.last_export_status = ExportStatus::Interrupted;
+    }
+
+    /// Raise the watermark to `value` if it is higher than the current one
+    ///
+    /// Numbers are compared numerically, RFC 3339 timestamps chronologically
+    /// and other strings lexicographically. Nulls and values that cannot be
+    /// compared with the current watermark are ignored.
+    pub fn advance(&mut self, value: &serde_json::Value) {
+        if value.is_null() {
+            return;
+        }
+
+        let higher = match &self.value {
+            None => true,
+            Some(current) => compare_values(value, current) == Some(Ordering::Greater),
+        };
+
+        if higher {
+            self.value = Some(value.clone());
+        }
+    }
+}
+
+/// Compare two watermark column values
+fn compare_values(a: &serde_json::Value, b: &serde_json::Value) -> Option<Ordering> {
+    use serde_json::Value;
+
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => {
+            match (
+                DateTime::parse_from_rfc3339(a),
+                DateTime::parse_from_rfc3339(b),
+            ) {
+                (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+                _ => Some(a.cmp(b)),
+            }
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,6 +467,19 @@ mod tests {
             Some(composition_uid)
         );
         assert_eq!(watermark.last_exported_timestamp, timestamp);
+
+        // An older composition doesn't move the watermark backwards
+        let older = CompositionUid::from_str("1f1a0d3c::local.ehrbase.org::2").unwrap();
+        watermark.update_after_export(older, timestamp - chrono::Duration::hours(1));
+        assert_eq!(watermark.compositions_exported_count, initial_count + 2);
+        assert_eq!(watermark.last_exported_timestamp, timestamp);
+        assert_eq!(
+            watermark
+                .last_exported_composition_uid
+                .as_ref()
+                .map(|uid| uid.as_str()),
+            Some("84d7c3f5::local.ehrbase.org::1")
+        );
     }
 
     #[test]
@@ -379,4 +522,33 @@ mod tests {
         assert_eq!(deserialized.compositions_exported_count, 100);
         assert_eq!(deserialized.last_export_status, ExportStatus::Completed);
     }
+
+    #[test]
+    fn test_query_watermark_advance() {
+        use serde_json::json;
+
+        let mut watermark = QueryWatermark::new("latest_hba1c");
+        assert_eq!(watermark.id, "query_latest_hba1c");
+
+        watermark.advance(&json!(null));
+        assert!(watermark.value.is_none());
+
+        // Timestamps compare chronologically, not by their text
+        watermark.advance(&json!("2025-03-01T10:00:00+01:00"));
+        watermark.advance(&json!("2025-03-01T09:30:00Z"));
+        assert_eq!(watermark.value, Some(json!("2025-03-01T09:30:00Z")));
+
+        watermark.advance(&json!("2025-02-01T00:00:00Z"));
+        assert_eq!(watermark.value, Some(json!("2025-03-01T09:30:00Z")));
+
+        // Values of another type are ignored
+        watermark.advance(&json!(42));
+        assert_eq!(watermark.value, Some(json!("2025-03-01T09:30:00Z")));
+
+        let mut watermark = QueryWatermark::new("counts");
+        watermark.advance(&json!(9));
+        watermark.advance(&json!(10.5));
+        watermark.advance(&json!(10));
+        assert_eq!(watermark.value, Some(json!(10.5)));
+    }
 }
@@ -53,7 +53,8 @@ pub fn flatten_composition(composition: Composition, export_mode: String) -> Res
     let fields = flatten_content(&composition.content)?;
 
     // Create metadata
-    let atlas_metadata = AtlasMetadata::new(composition.template_id, export_mode);
+    let atlas_metadata = AtlasMetadata::new(composition.template_id, export_mode)
+        .with_composition_format(composition.content_format);
 
     // Create the flattened composition
     let cosmos_comp = CosmosCompositionFlattened {
@@ -62,6 +63,7 @@ pub fn flatten_composition(composition: Composition, export_mode: String) -> Res
         composition_uid,
         template_id,
         time_committed,
+        version: composition.version,
         fields,
         atlas_metadata,
     };
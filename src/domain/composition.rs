@@ -6,10 +6,11 @@ use super::ids::{CompositionUid, EhrId, TemplateId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// Represents an openEHR composition in FLAT format
+/// Represents an openEHR composition
 ///
 /// A composition is a clinical document containing structured health data.
-/// This type holds the composition metadata and content in FLAT JSON format.
+/// This type holds the composition metadata and content in the format it was
+/// retrieved in (FLAT JSON unless configured otherwise).
 ///
 /// # Examples
 ///
@@ -42,8 +43,18 @@ pub struct Composition {
     /// Timestamp when the composition was committed
     pub time_committed: DateTime<Utc>,
 
-    /// Composition content in FLAT JSON format
+    /// Composition content in the format given by `content_format`
+    ///
+    /// Canonical XML content is held as a JSON string.
     pub content: serde_json::Value,
+
+    /// Format of `content`
+    #[serde(default)]
+    pub content_format: ContentFormat,
+
+    /// Version history information (only set when exporting all versions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<CompositionVersion>,
 }
 
 impl Composition {
@@ -53,6 +64,71 @@ impl Composition {
     }
 }
 
+/// Serialization format of composition content retrieved from openEHR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFormat {
+    /// Web template FLAT JSON (path/value pairs)
+    #[default]
+    Flat,
+    /// Web template STRUCTURED JSON
+    Structured,
+    /// Canonical openEHR RM JSON
+    CanonicalJson,
+    /// Canonical openEHR RM XML
+    CanonicalXml,
+}
+
+impl ContentFormat {
+    /// Get the configuration name of the format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Structured => "structured",
+            Self::CanonicalJson => "canonical_json",
+            Self::CanonicalXml => "canonical_xml",
+        }
+    }
+}
+
+impl std::fmt::Display for ContentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for ContentFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flat" => Ok(Self::Flat),
+            "structured" => Ok(Self::Structured),
+            "canonical_json" => Ok(Self::CanonicalJson),
+            "canonical_xml" => Ok(Self::CanonicalXml),
+            _ => Err(format!(
+                "Invalid composition format '{s}'. Must be one of: flat, structured, canonical_json, canonical_xml"
+            )),
+        }
+    }
+}
+
+/// Version information of a single composition version
+///
+/// Populated from the VERSIONED_COMPOSITION revision history when
+/// `export.include_versions` is enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompositionVersion {
+    /// Version number within the versioned composition (starting at 1)
+    pub version_number: u32,
+
+    /// Lifecycle state of the version (e.g., "complete", "incomplete", "deleted")
+    pub lifecycle_state: Option<String>,
+
+    /// Change type from the commit audit (e.g., "creation", "amendment", "deleted")
+    pub change_type: Option<String>,
+}
+
 /// Builder for constructing Composition instances
 ///
 /// Follows the builder pattern (TR-6.2) for ergonomic construction of complex types.
@@ -63,6 +139,8 @@ pub struct CompositionBuilder {
     template_id: Option<TemplateId>,
     time_committed: Option<DateTime<Utc>>,
     content: Option<serde_json::Value>,
+    content_format: ContentFormat,
+    version: Option<CompositionVersion>,
 }
 
 impl CompositionBuilder {
@@ -101,6 +179,18 @@ impl CompositionBuilder {
         self
     }
 
+    /// Sets the format of the content (defaults to FLAT)
+    pub fn content_format(mut self, content_format: ContentFormat) -> Self {
+        self.content_format = content_format;
+        self
+    }
+
+    /// Sets the version information
+    pub fn version(mut self, version: CompositionVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
     /// Builds the Composition
     ///
     /// # Errors
@@ -113,6 +203,8 @@ impl CompositionBuilder {
             template_id: self.template_id.ok_or("template_id is required")?,
             time_committed: self.time_committed.ok_or("time_committed is required")?,
             content: self.content.ok_or("content is required")?,
+            content_format: self.content_format,
+            version: self.version,
         })
     }
 }
@@ -159,6 +251,8 @@ impl CompositionMetadata {
             template_id: self.template_id,
             time_committed: self.time_committed,
             content,
+            content_format: ContentFormat::default(),
+            version: None,
         }
     }
 }
@@ -224,6 +318,58 @@ mod tests {
         assert_eq!(composition.content, json!({"test": "data"}));
     }
 
+    #[test]
+    fn test_composition_version_serialization() {
+        let composition = CompositionBuilder::new()
+            .uid(CompositionUid::new("84d7c3f5::local.ehrbase.org::2").unwrap())
+            .ehr_id(EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::new("IDCR - Lab Report.v1").unwrap())
+            .time_committed(Utc::now())
+            .content(json!({"test": "data"}))
+            .version(CompositionVersion {
+                version_number: 2,
+                lifecycle_state: Some("complete".to_string()),
+                change_type: Some("amendment".to_string()),
+            })
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_value(&composition).unwrap();
+        assert_eq!(json["version"]["version_number"], 2);
+        assert_eq!(json["version"]["change_type"], "amendment");
+
+        let deserialized: Composition = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.version, composition.version);
+    }
+
+    #[test]
+    fn test_composition_content_format() {
+        let composition = CompositionBuilder::new()
+            .uid(CompositionUid::new("84d7c3f5::local.ehrbase.org::1").unwrap())
+            .ehr_id(EhrId::new("7d44b88c-4199-4bad-97dc-d78268e01398").unwrap())
+            .template_id(TemplateId::new("IDCR - Lab Report.v1").unwrap())
+            .time_committed(Utc::now())
+            .content(json!("<composition/>"))
+            .content_format(ContentFormat::CanonicalXml)
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_value(&composition).unwrap();
+        assert_eq!(json["content_format"], "canonical_xml");
+
+        // Compositions serialized before the format was recorded are FLAT
+        let mut legacy = json;
+        legacy.as_object_mut().unwrap().remove("content_format");
+        let deserialized: Composition = serde_json::from_value(legacy).unwrap();
+        assert_eq!(deserialized.content_format, ContentFormat::Flat);
+
+        assert_eq!(
+            "Canonical_JSON".parse::<ContentFormat>().unwrap(),
+            ContentFormat::CanonicalJson
+        );
+        assert!("xml".parse::<ContentFormat>().is_err());
+    }
+
     #[test]
     fn test_composition_builder_default() {
         let builder = Composition::builder();
@@ -1,6 +1,7 @@
 //! EHR domain model
 //!
-//! This module defines the EHR (Electronic Health Record) type.
+//! This module defines the EHR (Electronic Health Record) type and its
+//! EHR_STATUS.
 
 use super::ids::EhrId;
 use chrono::{DateTime, Utc};
@@ -33,6 +34,48 @@ pub struct Ehr {
 
     /// System ID where the EHR was created
     pub system_id: Option<String>,
+
+    /// Current EHR_STATUS of the EHR, if retrieved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<EhrStatus>,
+}
+
+/// EHR_STATUS of an EHR
+///
+/// Holds the subject the EHR belongs to and whether the EHR may be queried
+/// and modified.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EhrStatus {
+    /// Version UID of the EHR_STATUS
+    pub uid: Option<String>,
+
+    /// Reference to the subject in an external demographic service (e.g. an MPI)
+    pub subject: Option<PartyRef>,
+
+    /// Whether the EHR is included in population queries
+    pub is_queryable: bool,
+
+    /// Whether the EHR may be modified
+    pub is_modifiable: bool,
+
+    /// Archetyped other details of the EHR_STATUS, as canonical JSON
+    pub other_details: Option<serde_json::Value>,
+}
+
+/// External reference to a party (PARTY_REF)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PartyRef {
+    /// Identifier of the party in the external system
+    pub id: String,
+
+    /// Identification scheme of `id` (e.g. a national patient identifier)
+    pub scheme: Option<String>,
+
+    /// Namespace of the external system
+    pub namespace: Option<String>,
+
+    /// Type of the referenced party (e.g. `PERSON`)
+    pub party_type: Option<String>,
 }
 
 impl Ehr {
@@ -60,6 +103,7 @@ impl Ehr {
             id,
             time_created,
             system_id: None,
+            status: None,
         }
     }
 
@@ -69,6 +113,7 @@ impl Ehr {
             id,
             time_created,
             system_id: Some(system_id),
+            status: None,
         }
     }
 
@@ -76,6 +121,13 @@ impl Ehr {
     pub fn builder() -> EhrBuilder {
         EhrBuilder::default()
     }
+
+    /// Returns the subject reference of the EHR_STATUS, if any
+    pub fn subject(&self) -> Option<&PartyRef> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.subject.as_ref())
+    }
 }
 
 /// Builder for constructing EHR instances
@@ -86,6 +138,7 @@ pub struct EhrBuilder {
     id: Option<EhrId>,
     time_created: Option<DateTime<Utc>>,
     system_id: Option<String>,
+    status: Option<EhrStatus>,
 }
 
 impl EhrBuilder {
@@ -112,6 +165,12 @@ impl EhrBuilder {
         self
     }
 
+    /// Sets the EHR_STATUS
+    pub fn status(mut self, status: EhrStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
     /// Builds the EHR
     ///
     /// # Errors
@@ -122,6 +181,7 @@ impl EhrBuilder {
             id: self.id.ok_or("id is required")?,
             time_created: self.time_created.ok_or("time_created is required")?,
             system_id: self.system_id,
+            status: self.status,
         })
     }
 }
@@ -135,6 +195,7 @@ impl Default for Ehr {
             id: EhrId::new("default-ehr-id").unwrap(),
             time_created: Utc::now(),
             system_id: None,
+            status: None,
         }
     }
 }
@@ -199,6 +260,32 @@ mod tests {
         assert_eq!(ehr.id, deserialized.id);
     }
 
+    #[test]
+    fn test_ehr_status_subject() {
+        let status = EhrStatus {
+            uid: Some("8849182c-82ad-4088-a07f-48ead4180515::local.ehrbase.org::1".to_string()),
+            subject: Some(PartyRef {
+                id: "9999999000".to_string(),
+                scheme: Some("uk.nhs.nhs_number".to_string()),
+                namespace: Some("EHR.NETWORK".to_string()),
+                party_type: Some("PERSON".to_string()),
+            }),
+            is_queryable: true,
+            is_modifiable: true,
+            other_details: None,
+        };
+
+        let ehr = Ehr::builder()
+            .id(EhrId::new("test-id").unwrap())
+            .time_created(Utc::now())
+            .status(status)
+            .build()
+            .unwrap();
+
+        assert_eq!(ehr.subject().unwrap().id, "9999999000");
+        assert!(Ehr::default().subject().is_none());
+    }
+
     #[test]
     fn test_ehr_default() {
         let ehr = Ehr::default();
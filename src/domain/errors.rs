@@ -3,6 +3,8 @@
 //! This module defines the error hierarchy for Atlas following TR-6.4, TR-6.5, and TR-6.6.
 //! All errors are domain-specific and don't expose third-party types.
 
+use chrono::Utc;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Main Atlas error type
@@ -64,6 +66,49 @@ pub enum AtlasError {
     Other(String),
 }
 
+impl AtlasError {
+    /// Whether the error is transient and the operation may succeed if retried
+    ///
+    /// Connection failures, timeouts, throttling and server-side (5xx) errors
+    /// are retryable. Errors caused by the request itself, such as a missing
+    /// composition or a rejected query, are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AtlasError::OpenEhr(err) => matches!(
+                err,
+                OpenEhrError::ConnectionFailed(_)
+                    | OpenEhrError::RateLimitExceeded(_)
+                    | OpenEhrError::Timeout(_)
+                    | OpenEhrError::ServerError {
+                        status: 500 | 502 | 503 | 504,
+                        ..
+                    }
+                    | OpenEhrError::ClientError {
+                        status: 408 | 429,
+                        ..
+                    }
+            ),
+            AtlasError::CosmosDb(err) => matches!(
+                err,
+                CosmosDbError::ConnectionFailed(_)
+                    | CosmosDbError::Throttled(_)
+                    | CosmosDbError::Timeout(_)
+            ),
+            AtlasError::Connection(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Delay the server asked for before the next attempt (Retry-After)
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AtlasError::OpenEhr(OpenEhrError::RateLimitExceeded(value))
+            | AtlasError::CosmosDb(CosmosDbError::Throttled(value)) => parse_retry_after(value),
+            _ => None,
+        }
+    }
+}
+
 /// openEHR-specific errors
 ///
 /// Errors that occur when interacting with openEHR servers.
@@ -165,6 +210,10 @@ pub enum CosmosDbError {
     #[error("Failed to update document: {0}")]
     UpdateFailed(String),
 
+    /// Failed to delete document
+    #[error("Failed to delete document: {0}")]
+    DeleteFailed(String),
+
     /// Failed to query documents
     #[error("Failed to query documents: {0}")]
     QueryFailed(String),
@@ -258,6 +307,30 @@ impl ExportErrorDetail {
         self.retryable = true;
         self
     }
+
+    /// Creates an export error detail from an error, classifying whether it is retryable
+    pub fn from_error(error: &AtlasError) -> Self {
+        Self {
+            retryable: error.is_retryable(),
+            ..Self::new(error.to_string())
+        }
+    }
+}
+
+/// Parse a Retry-After value, given either in seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
 }
 
 // Conversion from std::io::Error
@@ -319,6 +392,49 @@ mod tests {
         assert!(detail.retryable);
     }
 
+    #[test]
+    fn test_retryable_classification() {
+        assert!(AtlasError::from(OpenEhrError::Timeout("30s".to_string())).is_retryable());
+        assert!(AtlasError::from(OpenEhrError::ServerError {
+            status: 503,
+            message: "Service Unavailable".to_string()
+        })
+        .is_retryable());
+        assert!(!AtlasError::from(OpenEhrError::ServerError {
+            status: 501,
+            message: "Not Implemented".to_string()
+        })
+        .is_retryable());
+        assert!(
+            !AtlasError::from(OpenEhrError::CompositionNotFound("uid".to_string())).is_retryable()
+        );
+        assert!(AtlasError::from(CosmosDbError::Throttled("5".to_string())).is_retryable());
+        assert!(!AtlasError::from(CosmosDbError::Conflict("doc".to_string())).is_retryable());
+        assert!(AtlasError::Connection("reset".to_string()).is_retryable());
+        assert!(!AtlasError::Database("syntax error".to_string()).is_retryable());
+
+        let detail = ExportErrorDetail::from_error(&AtlasError::Connection("reset".to_string()));
+        assert!(detail.retryable);
+        assert_eq!(detail.message, "Connection error: reset");
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let seconds = AtlasError::from(OpenEhrError::RateLimitExceeded("120".to_string()));
+        assert_eq!(seconds.retry_after(), Some(Duration::from_secs(120)));
+
+        let fractional = AtlasError::from(CosmosDbError::Throttled("0.25".to_string()));
+        assert_eq!(fractional.retry_after(), Some(Duration::from_millis(250)));
+
+        let date = AtlasError::from(OpenEhrError::RateLimitExceeded(
+            "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        ));
+        assert_eq!(date.retry_after(), Some(Duration::ZERO));
+
+        let unspecified = AtlasError::from(CosmosDbError::Throttled("5 seconds".to_string()));
+        assert_eq!(unspecified.retry_after(), None);
+    }
+
     #[test]
     fn test_io_error_conversion() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "File not found");
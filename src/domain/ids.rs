@@ -227,33 +227,41 @@ impl TemplateId {
     ///
     /// Replaces spaces and special characters with underscores and converts to lowercase
     pub fn to_container_name(&self, prefix: &str) -> String {
-        let sanitized = self
-            .0
-            .to_lowercase()
-            .replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
-
-        // Remove consecutive underscores
-        let mut result = String::new();
-        let mut last_was_underscore = false;
-        for c in sanitized.chars() {
-            if c == '_' {
-                if !last_was_underscore {
-                    result.push(c);
-                    last_was_underscore = true;
-                }
-            } else {
+        container_name(prefix, &self.0)
+    }
+}
+
+/// Generates a sanitized container name with a prefix
+///
+/// Format: `{prefix}_{name}`, or `{name}` with an empty prefix. The name is
+/// lowercased, and runs of other characters than letters, digits and
+/// underscores become a single underscore.
+pub fn container_name(prefix: &str, name: &str) -> String {
+    let sanitized = name
+        .to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+
+    // Remove consecutive underscores
+    let mut result = String::new();
+    let mut last_was_underscore = false;
+    for c in sanitized.chars() {
+        if c == '_' {
+            if !last_was_underscore {
                 result.push(c);
-                last_was_underscore = false;
+                last_was_underscore = true;
             }
+        } else {
+            result.push(c);
+            last_was_underscore = false;
         }
+    }
 
-        let result = result.trim_matches('_').to_string();
+    let result = result.trim_matches('_').to_string();
 
-        if prefix.is_empty() {
-            result
-        } else {
-            format!("{prefix}_{result}")
-        }
+    if prefix.is_empty() {
+        result
+    } else {
+        format!("{prefix}_{result}")
     }
 }
 
@@ -351,6 +359,11 @@ mod tests {
 
         let id2 = TemplateId::new("IDCR - Vital Signs.v1").unwrap();
         assert_eq!(id2.to_container_name(""), "idcr_vital_signs_v1");
+
+        assert_eq!(
+            container_name("compositions", "query_Vitals__Latest"),
+            "compositions_query_vitals_latest"
+        );
     }
 
     #[test]
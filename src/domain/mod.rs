@@ -73,10 +73,12 @@ pub mod result;
 pub mod template;
 
 // Re-export commonly used types for convenience
-pub use composition::{Composition, CompositionBuilder, CompositionMetadata};
+pub use composition::{
+    Composition, CompositionBuilder, CompositionMetadata, CompositionVersion, ContentFormat,
+};
 pub use context::ResultExt;
-pub use ehr::{Ehr, EhrBuilder};
+pub use ehr::{Ehr, EhrBuilder, EhrStatus, PartyRef};
 pub use errors::{AtlasError, CosmosDbError, ExportErrorDetail, OpenEhrError};
 pub use ids::{CompositionUid, EhrId, TemplateId};
 pub use result::Result;
-pub use template::{Template, TemplateBuilder};
+pub use template::{Template, TemplateBuilder, TemplateDefinition, TemplateFormat, TemplateNode};
@@ -1,9 +1,12 @@
 //! Template domain model
 //!
-//! This module defines the Template type representing openEHR operational templates.
+//! This module defines the Template type representing openEHR operational templates,
+//! and the downloaded template definitions (OPT or web template) with their node tree.
 
 use super::ids::TemplateId;
+use super::AtlasError;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Represents an openEHR operational template
 ///
@@ -159,10 +162,279 @@ impl Default for Template {
     }
 }
 
+/// Representation a template definition is downloaded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateFormat {
+    /// Web template (JSON), which describes the node tree of the template
+    #[default]
+    WebTemplate,
+    /// Operational template (ADL 1.4 OPT, XML)
+    Opt,
+}
+
+impl TemplateFormat {
+    /// Identifier of the format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WebTemplate => "web_template",
+            Self::Opt => "opt",
+        }
+    }
+
+    /// File extension used for cached definitions
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::WebTemplate => "json",
+            Self::Opt => "opt",
+        }
+    }
+}
+
+impl std::fmt::Display for TemplateFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for TemplateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "web_template" => Ok(Self::WebTemplate),
+            "opt" => Ok(Self::Opt),
+            _ => Err(format!(
+                "Invalid template format '{s}'. Must be 'web_template' or 'opt'"
+            )),
+        }
+    }
+}
+
+/// Template definition downloaded from the openEHR server
+///
+/// The definition is kept verbatim; `hash` is the SHA-256 of the content and
+/// identifies the revision of the template on the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateDefinition {
+    /// Template the definition belongs to
+    pub template_id: TemplateId,
+
+    /// Representation of `content`
+    pub format: TemplateFormat,
+
+    /// Definition as returned by the server
+    pub content: String,
+
+    /// Hex-encoded SHA-256 of `content`
+    pub hash: String,
+}
+
+impl TemplateDefinition {
+    /// Creates a template definition and computes its hash
+    pub fn new(template_id: TemplateId, format: TemplateFormat, content: String) -> Self {
+        let hash = Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Self {
+            template_id,
+            format,
+            content,
+            hash,
+        }
+    }
+
+    /// Lists the nodes of the template, depth first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for OPT definitions, which are not parsed, or if the
+    /// web template is not valid JSON or has no `tree`.
+    pub fn nodes(&self) -> super::Result<Vec<TemplateNode>> {
+        if self.format != TemplateFormat::WebTemplate {
+            return Err(AtlasError::Validation(format!(
+                "Node paths of template '{}' require the web_template format, got '{}'",
+                self.template_id, self.format
+            )));
+        }
+
+        let web_template: serde_json::Value = serde_json::from_str(&self.content)
+            .map_err(|e| AtlasError::Serialization(format!("Invalid web template: {e}")))?;
+
+        let tree = web_template.get("tree").ok_or_else(|| {
+            AtlasError::Serialization(format!(
+                "Web template of '{}' has no tree",
+                self.template_id
+            ))
+        })?;
+
+        let mut nodes = Vec::new();
+        collect_nodes(tree, &mut nodes);
+        Ok(nodes)
+    }
+}
+
+/// Node of a template: an attribute or archetype constrained by the template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateNode {
+    /// AQL path of the node from the composition root
+    pub path: String,
+
+    /// Web template ID of the node (used in FLAT paths)
+    pub id: String,
+
+    /// Name of the node (optional)
+    pub name: Option<String>,
+
+    /// Reference model type, e.g. `OBSERVATION` or `DV_QUANTITY`
+    pub rm_type: String,
+
+    /// Archetype or at-code node ID (optional)
+    pub node_id: Option<String>,
+
+    /// Minimum number of occurrences
+    pub min: u32,
+
+    /// Maximum number of occurrences (`None` = unbounded)
+    pub max: Option<u32>,
+}
+
+impl TemplateNode {
+    /// Whether the node must occur
+    pub fn is_required(&self) -> bool {
+        self.min > 0
+    }
+
+    /// Whether the node may occur more than once
+    pub fn is_repeating(&self) -> bool {
+        self.max.is_none_or(|max| max > 1)
+    }
+}
+
+/// Appends a web template node and its descendants to `nodes`
+fn collect_nodes(node: &serde_json::Value, nodes: &mut Vec<TemplateNode>) {
+    let text = |key: &str| node.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    nodes.push(TemplateNode {
+        path: text("aqlPath").unwrap_or_default(),
+        id: text("id").unwrap_or_default(),
+        name: text("name"),
+        rm_type: text("rmType").unwrap_or_default(),
+        node_id: text("nodeId"),
+        min: node
+            .get("min")
+            .and_then(|v| v.as_u64())
+            .map_or(0, |min| min as u32),
+        // A negative maximum means unbounded
+        max: node
+            .get("max")
+            .and_then(|v| v.as_u64())
+            .map(|max| max as u32),
+    });
+
+    if let Some(children) = node.get("children").and_then(|v| v.as_array()) {
+        for child in children {
+            collect_nodes(child, nodes);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn web_template_definition() -> TemplateDefinition {
+        let content = serde_json::json!({
+            "templateId": "IDCR - Vital Signs.v1",
+            "tree": {
+                "id": "vital_signs",
+                "name": "Vital Signs",
+                "rmType": "COMPOSITION",
+                "nodeId": "openEHR-EHR-COMPOSITION.encounter.v1",
+                "min": 1,
+                "max": 1,
+                "aqlPath": "",
+                "children": [{
+                    "id": "pulse",
+                    "name": "Pulse",
+                    "rmType": "OBSERVATION",
+                    "nodeId": "openEHR-EHR-OBSERVATION.pulse.v1",
+                    "min": 0,
+                    "max": -1,
+                    "aqlPath": "/content[openEHR-EHR-OBSERVATION.pulse.v1]",
+                    "children": [{
+                        "id": "rate",
+                        "name": "Rate",
+                        "rmType": "DV_QUANTITY",
+                        "nodeId": "at0004",
+                        "min": 1,
+                        "max": 1,
+                        "aqlPath": "/content[openEHR-EHR-OBSERVATION.pulse.v1]/data[at0002]/events[at0003]/data[at0001]/items[at0004]/value"
+                    }]
+                }]
+            }
+        });
+
+        TemplateDefinition::new(
+            TemplateId::new("IDCR - Vital Signs.v1").unwrap(),
+            TemplateFormat::WebTemplate,
+            content.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_template_definition_nodes() {
+        let definition = web_template_definition();
+        let nodes = definition.nodes().unwrap();
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].rm_type, "COMPOSITION");
+        assert!(nodes[0].is_required());
+
+        let pulse = &nodes[1];
+        assert_eq!(pulse.path, "/content[openEHR-EHR-OBSERVATION.pulse.v1]");
+        assert_eq!(pulse.max, None);
+        assert!(!pulse.is_required());
+        assert!(pulse.is_repeating());
+
+        let rate = &nodes[2];
+        assert_eq!(rate.rm_type, "DV_QUANTITY");
+        assert_eq!(rate.node_id.as_deref(), Some("at0004"));
+        assert!(!rate.is_repeating());
+    }
+
+    #[test]
+    fn test_template_definition_hash() {
+        let definition = web_template_definition();
+        assert_eq!(definition.hash.len(), 64);
+        assert_eq!(definition.hash, web_template_definition().hash);
+
+        let other = TemplateDefinition::new(
+            definition.template_id.clone(),
+            TemplateFormat::WebTemplate,
+            "{}".to_string(),
+        );
+        assert_ne!(definition.hash, other.hash);
+    }
+
+    #[test]
+    fn test_opt_definition_has_no_nodes() {
+        let definition = TemplateDefinition::new(
+            TemplateId::new("IDCR - Vital Signs.v1").unwrap(),
+            TemplateFormat::Opt,
+            "<template/>".to_string(),
+        );
+
+        assert!(definition.nodes().is_err());
+        assert_eq!(
+            "opt".parse::<TemplateFormat>().unwrap(),
+            TemplateFormat::Opt
+        );
+        assert!("adl2".parse::<TemplateFormat>().is_err());
+    }
+
     #[test]
     fn test_template_creation() {
         let template_id = TemplateId::new("IDCR - Lab Report.v1").unwrap();
@@ -102,6 +102,7 @@ async fn execute_command(cli: &Cli, shutdown_signal: watch::Receiver<bool>) -> a
         Commands::Export(args) => args.execute(&cli.config, shutdown_signal).await,
         Commands::ValidateConfig(args) => args.execute(&cli.config).await,
         Commands::Status(args) => args.execute(&cli.config).await,
+        Commands::RetryFailed(args) => args.execute(&cli.config, shutdown_signal).await,
         Commands::Init(args) => args.execute().await,
     }
 }
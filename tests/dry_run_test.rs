@@ -3,7 +3,7 @@
 //! These tests verify that the --dry-run flag prevents all database writes
 //! while allowing the export process to run normally.
 
-use atlas::config::schema::ExportConfig;
+use atlas::config::schema::{DeletionPolicy, ExportConfig};
 use atlas::core::export::batch::{BatchConfig, BatchResult};
 use atlas::core::export::summary::ExportSummary;
 use atlas::core::state::watermark::{ExportStatus, WatermarkBuilder};
@@ -34,6 +34,12 @@ fn test_export_config_dry_run_default() {
         max_retries: 3,
         retry_backoff_ms: vec![1000, 2000, 4000],
         shutdown_timeout_secs: 30,
+        incremental_overlap_secs: 300,
+        include_versions: false,
+        include_ehr_status: false,
+        aql_queries: Vec::new(),
+        change_detection: Default::default(),
+        deletion_policy: DeletionPolicy::Ignore,
         dry_run: false,
     };
 
@@ -48,6 +54,12 @@ fn test_export_config_dry_run_enabled() {
         max_retries: 3,
         retry_backoff_ms: vec![1000, 2000, 4000],
         shutdown_timeout_secs: 30,
+        incremental_overlap_secs: 300,
+        include_versions: false,
+        include_ehr_status: false,
+        aql_queries: Vec::new(),
+        change_detection: Default::default(),
+        deletion_policy: DeletionPolicy::Ignore,
         dry_run: true,
     };
 